@@ -0,0 +1,28 @@
+-- ====================================
+-- 令牌撤销列表
+-- ====================================
+
+-- 已撤销的访问令牌（按JWT的jti记录，过期后可清理）
+CREATE TABLE revoked_tokens (
+    id SERIAL PRIMARY KEY,
+    jti VARCHAR(64) UNIQUE NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason VARCHAR(50) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL, -- 令牌原本的过期时间
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 用户级撤销：签发时间不晚于 revoked_before 的令牌全部失效
+CREATE TABLE user_token_revocations (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    revoked_before TIMESTAMPTZ NOT NULL,
+    reason VARCHAR(50) NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_revoked_tokens_user_id ON revoked_tokens(user_id);
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
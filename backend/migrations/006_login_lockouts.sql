@@ -0,0 +1,34 @@
+-- ====================================
+-- 登录日志与登录锁定
+-- ====================================
+
+-- 每次登录尝试（成功或失败）都会记录一条日志
+CREATE TABLE login_logs (
+    id SERIAL PRIMARY KEY,
+    username VARCHAR(255) NOT NULL, -- 尝试登录时提交的用户名
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    ip_address VARCHAR(45),
+    user_agent TEXT,
+    success BOOLEAN NOT NULL,
+    failure_reason VARCHAR(50),
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 失败次数计数与锁定状态，scope 为 user（按用户名）或 ip（按客户端IP）
+CREATE TABLE login_lockouts (
+    id SERIAL PRIMARY KEY,
+    scope VARCHAR(20) NOT NULL,
+    lock_key VARCHAR(255) NOT NULL,
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    window_started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(), -- 当前计数窗口的开始时间
+    locked_until TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE(scope, lock_key)
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_login_logs_user_id ON login_logs(user_id);
+CREATE INDEX idx_login_logs_created_at ON login_logs(created_at);
@@ -0,0 +1,25 @@
+-- ====================================
+-- 登录日志：区分登录与令牌刷新
+-- ====================================
+
+-- event_type: login（用户名密码登录）或 refresh（刷新令牌）
+ALTER TABLE login_logs ADD COLUMN event_type VARCHAR(20) NOT NULL DEFAULT 'login';
+
+-- 使用无效刷新令牌时无法确定用户名
+ALTER TABLE login_logs ALTER COLUMN username DROP NOT NULL;
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_login_logs_username ON login_logs(username);
+
+-- ====================================
+-- 登录日志查看权限
+-- ====================================
+INSERT INTO permissions (name, description, resource, action) VALUES
+('登录日志查看', '查看登录日志', 'login_log', 'read');
+
+-- 为超级管理员角色分配登录日志查看权限
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT 1, id FROM permissions WHERE resource = 'login_log' AND action = 'read';
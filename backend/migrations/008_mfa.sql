@@ -0,0 +1,31 @@
+-- ====================================
+-- 二次验证（TOTP）
+-- ====================================
+
+-- 用户的TOTP密钥；enabled 为 false 表示已发起绑定但尚未验证激活
+CREATE TABLE user_mfa (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL, -- Base32 编码的共享密钥
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    last_used_step BIGINT, -- 最近一次使用的时间步，防止验证码重放
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    enabled_at TIMESTAMPTZ
+);
+
+-- 恢复码，只保存SHA-256摘要，每个只能使用一次
+CREATE TABLE mfa_recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 角色是否要求成员启用二次验证
+ALTER TABLE roles ADD COLUMN mfa_required BOOLEAN NOT NULL DEFAULT false;
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_mfa_recovery_codes_user_id ON mfa_recovery_codes(user_id);
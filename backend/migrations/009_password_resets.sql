@@ -0,0 +1,20 @@
+-- ====================================
+-- 找回密码
+-- ====================================
+
+-- 重置密码令牌（只保存令牌的SHA-256摘要，每个只能使用一次）
+CREATE TABLE password_reset_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ, -- 已使用或被新令牌作废的时间
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_password_reset_tokens_user_id ON password_reset_tokens(user_id);
+CREATE INDEX idx_password_reset_tokens_expires_at ON password_reset_tokens(expires_at);
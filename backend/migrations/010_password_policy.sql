@@ -0,0 +1,20 @@
+-- ====================================
+-- 密码策略
+-- ====================================
+
+-- 密码最后修改时间，用于密码有效期检查
+ALTER TABLE users ADD COLUMN password_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- 历史密码哈希，防止重复使用最近用过的密码
+CREATE TABLE password_history (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    password_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_password_history_user_id ON password_history(user_id);
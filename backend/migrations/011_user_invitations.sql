@@ -0,0 +1,25 @@
+-- ====================================
+-- 用户邀请
+-- ====================================
+
+-- 管理员设置初始密码后，用户首次登录必须先修改密码
+ALTER TABLE users ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT false;
+
+-- 邀请令牌（只保存令牌的SHA-256摘要），用户通过邀请链接设置自己的密码
+CREATE TABLE user_invitations (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    invited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ, -- 重新发送邀请时作废旧令牌
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_user_invitations_user_id ON user_invitations(user_id);
+CREATE INDEX idx_user_invitations_expires_at ON user_invitations(expires_at);
@@ -0,0 +1,27 @@
+-- ====================================
+-- 邮箱验证
+-- ====================================
+
+-- 邮箱验证时间，为空表示尚未验证
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- 已有用户视为已验证
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
+
+-- 邮箱验证令牌（只保存令牌的SHA-256摘要）；记录发送时的邮箱，修改邮箱后旧令牌失效
+CREATE TABLE email_verification_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ, -- 已使用或被新令牌作废的时间
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
+CREATE INDEX idx_email_verification_tokens_expires_at ON email_verification_tokens(expires_at);
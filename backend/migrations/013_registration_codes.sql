@@ -0,0 +1,25 @@
+-- ====================================
+-- 注册邀请码（REGISTRATION_MODE=invite_only 时注册必须提供）
+-- ====================================
+
+-- 只保存邀请码的SHA-256摘要；max_uses 为空表示不限次数
+CREATE TABLE registration_codes (
+    id SERIAL PRIMARY KEY,
+    code_hash VARCHAR(64) UNIQUE NOT NULL,
+    description VARCHAR(255),
+    max_uses INTEGER,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 记录用户注册时使用的邀请码
+ALTER TABLE users ADD COLUMN registration_code_id INTEGER REFERENCES registration_codes(id) ON DELETE SET NULL;
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_users_registration_code_id ON users(registration_code_id);
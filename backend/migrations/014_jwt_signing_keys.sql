@@ -0,0 +1,23 @@
+-- ====================================
+-- JWT签名密钥（JWT_ALGORITHM=EdDSA/RS256）
+-- ====================================
+
+-- 私钥由服务自动生成并定期轮换：Ed25519 为 PKCS#8 DER，RSA 为 PKCS#1 DER
+-- 新密钥在 activates_at 之前只用于发布（JWKS），之后开始签名；
+-- 被新密钥取代后，旧密钥在 expires_at 之前仍用于验签
+CREATE TABLE jwt_signing_keys (
+    id SERIAL PRIMARY KEY,
+    kid VARCHAR(64) UNIQUE NOT NULL,
+    algorithm VARCHAR(10) NOT NULL,
+    private_key BYTEA NOT NULL,
+    activates_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_jwt_signing_keys_activates_at ON jwt_signing_keys(activates_at);
+CREATE INDEX idx_jwt_signing_keys_expires_at ON jwt_signing_keys(expires_at);
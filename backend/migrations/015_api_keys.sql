@@ -0,0 +1,24 @@
+-- ====================================
+-- API密钥（个人访问令牌），供脚本等自动化场景代替密码登录
+-- ====================================
+
+-- 只保存密钥的SHA-256摘要，key_prefix 为密钥开头几位，用于在列表中辨认
+-- permissions 为空表示与所有者的权限相同，否则只能使用其中的权限
+CREATE TABLE api_keys (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(64) UNIQUE NOT NULL,
+    permissions TEXT[],
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
@@ -0,0 +1,26 @@
+-- ====================================
+-- 服务账户：非自然人的账户，不能使用密码登录，只能通过客户端凭据或API密钥认证
+-- ====================================
+
+ALTER TABLE users ADD COLUMN is_service_account BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- 服务账户的客户端密钥，client_id 为服务账户的用户名
+-- 只保存密钥的SHA-256摘要，secret_prefix 为密钥开头几位，用于在列表中辨认
+CREATE TABLE service_account_secrets (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    description VARCHAR(255),
+    secret_prefix VARCHAR(16) NOT NULL,
+    secret_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_users_is_service_account ON users(is_service_account);
+CREATE INDEX idx_service_account_secrets_user_id ON service_account_secrets(user_id);
@@ -0,0 +1,35 @@
+-- ====================================
+-- OpenID Connect 登录：通过外部身份提供方（IdP）登录
+-- ====================================
+
+-- 发起登录时保存的状态，回调时按 state 取回并删除，保证只能使用一次
+-- 只保存 state 的SHA-256摘要；nonce 和 PKCE 校验码只在服务端保存，不经过浏览器
+CREATE TABLE oidc_login_states (
+    id SERIAL PRIMARY KEY,
+    state_hash VARCHAR(64) UNIQUE NOT NULL,
+    provider VARCHAR(50) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    code_verifier VARCHAR(128) NOT NULL,
+    redirect_uri VARCHAR(500) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 本地用户与外部身份的关联，subject 为身份提供方的 sub 声明
+CREATE TABLE user_external_identities (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    last_login_at TIMESTAMPTZ,
+    UNIQUE(provider, subject)
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_oidc_login_states_expires_at ON oidc_login_states(expires_at);
+CREATE INDEX idx_user_external_identities_user_id ON user_external_identities(user_id);
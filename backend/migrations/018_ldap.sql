@@ -0,0 +1,21 @@
+-- ====================================
+-- LDAP / Active Directory 认证
+-- ====================================
+
+-- 目录用户通过 user_external_identities 关联本地用户，provider 为 'ldap'，subject 为小写的目录用户名
+
+-- 目录组与角色的映射，group_dn 为规范化（小写、去掉逗号两侧空白）的组DN
+-- 登录和定期同步时，按映射为目录用户增加或移除角色；没有出现在映射中的角色不受影响
+CREATE TABLE ldap_group_mappings (
+    id SERIAL PRIMARY KEY,
+    group_dn VARCHAR(500) NOT NULL,
+    role_id INTEGER NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE(group_dn, role_id)
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_ldap_group_mappings_role_id ON ldap_group_mappings(role_id);
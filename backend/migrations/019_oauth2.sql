@@ -0,0 +1,70 @@
+-- ====================================
+-- OAuth2 授权服务：内部应用通过本系统登录和获取用户的角色、权限
+-- ====================================
+
+-- 接入应用（OAuth2客户端）
+-- 只保存客户端密钥的SHA-256摘要，secret_hash 为空表示公开客户端（如单页应用），只能使用授权码 + PKCE
+-- service_account_id 为使用 client_credentials 授权时令牌代表的服务账户
+-- skip_consent 为受信任的内部应用，用户授权时不需要确认
+CREATE TABLE oauth_clients (
+    id SERIAL PRIMARY KEY,
+    client_id VARCHAR(64) UNIQUE NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    description VARCHAR(255),
+    secret_prefix VARCHAR(16),
+    secret_hash VARCHAR(64) UNIQUE,
+    redirect_uris TEXT[] NOT NULL DEFAULT '{}',
+    allowed_scopes TEXT[] NOT NULL DEFAULT '{}',
+    grant_types TEXT[] NOT NULL DEFAULT '{}',
+    service_account_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    skip_consent BOOLEAN NOT NULL DEFAULT FALSE,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 授权码，只保存SHA-256摘要，换取令牌时删除，保证只能使用一次
+CREATE TABLE oauth_authorization_codes (
+    id SERIAL PRIMARY KEY,
+    code_hash VARCHAR(64) UNIQUE NOT NULL,
+    client_id INTEGER NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    redirect_uri VARCHAR(500) NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    code_challenge VARCHAR(128) NOT NULL,
+    nonce VARCHAR(255),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 用户已同意授予应用的范围，再次授权相同或更小的范围时不需要重新确认
+CREATE TABLE oauth_consents (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    client_id INTEGER NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE(user_id, client_id)
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_oauth_authorization_codes_expires_at ON oauth_authorization_codes(expires_at);
+CREATE INDEX idx_oauth_consents_client_id ON oauth_consents(client_id);
+
+-- ====================================
+-- 接入应用管理权限
+-- ====================================
+INSERT INTO permissions (name, description, resource, action) VALUES
+('接入应用查看', '查看OAuth2接入应用', 'oauth_client', 'read'),
+('接入应用创建', '创建OAuth2接入应用', 'oauth_client', 'create'),
+('接入应用更新', '更新OAuth2接入应用和重置客户端密钥', 'oauth_client', 'update'),
+('接入应用删除', '删除OAuth2接入应用', 'oauth_client', 'delete');
+
+-- 为超级管理员角色分配接入应用管理权限
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT 1, id FROM permissions WHERE resource = 'oauth_client';
@@ -0,0 +1,23 @@
+-- ====================================
+-- 模拟登录：管理员以其他用户的身份登录，用于复现用户看到的界面
+-- ====================================
+
+-- 模拟登录的开始、结束以及被拒绝的尝试记录在登录日志中
+-- user_id / username 为被模拟的用户，impersonator_id 为实际操作的管理员
+ALTER TABLE login_logs ADD COLUMN impersonator_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_login_logs_impersonator_id ON login_logs(impersonator_id);
+
+-- ====================================
+-- 模拟登录权限
+-- ====================================
+INSERT INTO permissions (name, description, resource, action) VALUES
+('用户模拟登录', '以其他用户的身份登录，不能模拟权限高于自己的用户', 'user', 'impersonate');
+
+-- 为超级管理员角色分配模拟登录权限
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT 1, id FROM permissions WHERE resource = 'user' AND action = 'impersonate';
@@ -0,0 +1,26 @@
+-- ====================================
+-- 邮件链接登录（无密码登录）
+-- ====================================
+
+-- 登录链接令牌（只保存令牌的SHA-256摘要，每个只能使用一次）
+CREATE TABLE magic_link_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ, -- 已使用或被新令牌作废的时间
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- 角色的成员是否可以使用邮件链接登录；任一有效角色不允许时该用户不能使用
+ALTER TABLE roles ADD COLUMN magic_link_allowed BOOLEAN NOT NULL DEFAULT true;
+
+-- 超级管理员不能使用邮件链接登录
+UPDATE roles SET magic_link_allowed = false WHERE id = 1;
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE INDEX idx_magic_link_tokens_user_id ON magic_link_tokens(user_id);
+CREATE INDEX idx_magic_link_tokens_expires_at ON magic_link_tokens(expires_at);
@@ -0,0 +1,22 @@
+-- ====================================
+-- IP白名单：限制用户只能从指定网络登录和访问
+-- ====================================
+
+-- 每条记录属于一个角色或一个用户；用户及其有效角色都没有记录时不限制
+CREATE TABLE ip_allowlist_entries (
+    id SERIAL PRIMARY KEY,
+    role_id INTEGER REFERENCES roles(id) ON DELETE CASCADE,
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    cidr VARCHAR(50) NOT NULL, -- 规范化后的CIDR，如 192.168.1.0/24、2001:db8::/32
+    description VARCHAR(255),
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    CHECK ((role_id IS NULL) <> (user_id IS NULL))
+);
+
+-- ====================================
+-- 创建索引
+-- ====================================
+
+CREATE UNIQUE INDEX idx_ip_allowlist_entries_role_cidr ON ip_allowlist_entries(role_id, cidr) WHERE role_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_ip_allowlist_entries_user_cidr ON ip_allowlist_entries(user_id, cidr) WHERE user_id IS NOT NULL;
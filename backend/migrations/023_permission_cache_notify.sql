@@ -0,0 +1,38 @@
+-- ====================================
+-- 权限缓存失效通知：权限相关的表变化时通过 NOTIFY 通知所有应用实例
+-- ====================================
+
+-- 通知内容为 user:<用户ID>（只影响单个用户）或 all（影响所有用户）
+-- 同一事务中内容相同的通知只会送达一次
+CREATE OR REPLACE FUNCTION notify_permission_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'user_roles' THEN
+        IF TG_OP IN ('UPDATE', 'DELETE') THEN
+            PERFORM pg_notify('permission_changes', 'user:' || OLD.user_id);
+        END IF;
+        IF TG_OP IN ('INSERT', 'UPDATE') THEN
+            PERFORM pg_notify('permission_changes', 'user:' || NEW.user_id);
+        END IF;
+    ELSE
+        PERFORM pg_notify('permission_changes', 'all');
+    END IF;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+-- ====================================
+-- 创建触发器
+-- ====================================
+
+CREATE TRIGGER notify_user_roles_change AFTER INSERT OR UPDATE OR DELETE ON user_roles
+    FOR EACH ROW EXECUTE FUNCTION notify_permission_change();
+
+CREATE TRIGGER notify_role_permissions_change AFTER INSERT OR UPDATE OR DELETE ON role_permissions
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_permission_change();
+
+CREATE TRIGGER notify_roles_change AFTER UPDATE OF is_active OR DELETE ON roles
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_permission_change();
+
+CREATE TRIGGER notify_permissions_change AFTER UPDATE OF is_active, resource, action OR DELETE ON permissions
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_permission_change();
@@ -0,0 +1,9 @@
+-- ====================================
+-- JWT签名私钥加密保存
+-- ====================================
+
+-- 私钥改为使用 JWT_KEY_ENCRYPTION_KEY（AES-256-GCM）加密后保存：随机 nonce 加密文，kid 作为附加数据。
+-- 之前以明文保存的私钥全部删除，服务启动时重新生成；使用旧密钥签名的令牌随之失效，用户需要重新登录。
+DELETE FROM jwt_signing_keys;
+
+ALTER TABLE jwt_signing_keys RENAME COLUMN private_key TO encrypted_private_key;
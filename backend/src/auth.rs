@@ -1,14 +1,19 @@
-use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::jwt_keys::{self, KeyError};
+use crate::password_hash::{self, Argon2Config, PasswordHashError};
 
 #[derive(Error, Debug)]
 pub enum AuthError {
     #[error("密码加密失败: {0}")]
-    PasswordHashError(#[from] bcrypt::BcryptError),
+    PasswordHashError(#[from] PasswordHashError),
     #[error("密码验证失败")]
     PasswordVerifyError,
     #[error("JWT生成失败: {0}")]
@@ -17,6 +22,33 @@ pub enum AuthError {
     InvalidToken,
     #[error("令牌已过期")]
     TokenExpired,
+    #[error("签名密钥不可用: {0}")]
+    KeyError(#[from] KeyError),
+}
+
+/// 令牌用途
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenUse {
+    /// 正式的访问令牌
+    #[default]
+    Access,
+    /// 已通过密码验证、等待二次验证，只能在 /api/auth/mfa/verify 换取正式令牌
+    MfaPending,
+    /// 角色要求二次验证但用户尚未绑定，只能用于绑定二次验证
+    MfaSetup,
+    /// 密码已过期，只能用于修改密码
+    PasswordChange,
+    /// 邮箱尚未验证，只能访问当前用户信息和退出登录
+    EmailUnverified,
+    /// 通过OAuth2授权签发给接入应用的令牌，不能访问本系统的接口
+    OAuth,
+}
+
+impl TokenUse {
+    pub fn is_access(&self) -> bool {
+        *self == TokenUse::Access
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,6 +57,72 @@ pub struct Claims {
     pub username: String,
     pub exp: u64, // 过期时间
     pub iat: u64, // 签发时间
+    pub jti: String, // 令牌唯一标识，用于撤销
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>, // 所属登录会话
+    #[serde(default, rename = "use", skip_serializing_if = "TokenUse::is_access")]
+    pub token_use: TokenUse, // 令牌用途，缺省为访问令牌
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>, // 签发给的OAuth2客户端
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // OAuth2授权范围，空格分隔
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>, // 模拟登录时实际操作的管理员，sub 为被模拟的用户
+    #[serde(skip)]
+    pub api_key: Option<ApiKeyGrant>, // 使用API密钥认证时的密钥信息，不会出现在JWT中
+}
+
+/// 模拟登录令牌中实际操作的管理员（RFC 8693 act 声明）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Actor {
+    pub sub: i32,
+    pub username: String,
+}
+
+/// 通过API密钥认证的请求所使用的密钥
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyGrant {
+    pub id: i32,
+    /// 密钥限定的权限，None 表示与所有者的权限相同
+    pub permissions: Option<Vec<String>>,
+}
+
+impl ApiKeyGrant {
+    /// 密钥的权限范围是否包含指定权限（仍需所有者本身具有该权限）
+    pub fn allows(&self, permission: &str) -> bool {
+        self.permissions
+            .as_ref()
+            .is_none_or(|permissions| permissions.iter().any(|p| p == permission))
+    }
+}
+
+impl Claims {
+    /// 构造从当前时间起有效期为ttl秒的claims
+    pub fn new(user_id: i32, username: &str, ttl: u64) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        Self {
+            sub: user_id,
+            username: username.to_string(),
+            exp: now + ttl,
+            iat: now,
+            jti: Uuid::new_v4().to_string(),
+            sid: None,
+            token_use: TokenUse::Access,
+            client_id: None,
+            scope: None,
+            act: None,
+            api_key: None,
+        }
+    }
+
+    /// 是否为管理员模拟其他用户登录时签发的令牌
+    pub fn is_impersonating(&self) -> bool {
+        self.act.is_some()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,64 +130,153 @@ pub struct AuthResponse {
     pub token: String,
     pub token_type: String,
     pub expires_in: u64,
+    pub refresh_token: String,
+    pub refresh_expires_in: u64,
 }
 
 pub struct AuthService;
 
 impl AuthService {
+    /// 使用 Argon2id 计算密码哈希，参数由 ARGON2_* 环境变量配置
     pub fn hash_password(password: &str) -> Result<String, AuthError> {
-        let hashed = hash(password, DEFAULT_COST)?;
-        Ok(hashed)
+        Ok(Argon2Config::from_env().hash(password)?)
     }
 
+    /// 校验密码，同时支持 Argon2id 和旧版本的 bcrypt 哈希
     pub fn verify_password(password: &str, hash: &str) -> Result<bool, AuthError> {
-        let is_valid = verify(password, hash)?;
-        if !is_valid {
+        if !password_hash::verify(password, hash) {
             return Err(AuthError::PasswordVerifyError);
         }
         Ok(true)
     }
 
+    /// 密码哈希是否使用旧算法或旧参数，需要在验证通过后重新计算
+    pub fn password_needs_rehash(hash: &str) -> bool {
+        Argon2Config::from_env().needs_rehash(hash)
+    }
+
+    /// 访问令牌有效期（秒），默认15分钟
+    pub fn access_token_ttl() -> u64 {
+        env::var("JWT_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(900)
+    }
+
+    /// 刷新令牌有效期（秒），默认30天
+    pub fn refresh_token_ttl() -> u64 {
+        env::var("REFRESH_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(2_592_000)
+    }
+
+    /// 本服务签发的JWT中最长的有效期（秒），轮换后的旧签名密钥至少保留这么久
+    pub fn max_token_ttl() -> u64 {
+        Self::access_token_ttl()
+            .max(Self::mfa_token_ttl())
+            .max(Self::impersonation_token_ttl())
+    }
+
+    /// 二次验证过程中使用的临时令牌有效期（秒），默认5分钟
+    pub fn mfa_token_ttl() -> u64 {
+        env::var("MFA_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(300)
+    }
+
+    /// 模拟登录令牌有效期（秒），默认30分钟，不能续期
+    pub fn impersonation_token_ttl() -> u64 {
+        env::var("IMPERSONATION_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(1800)
+    }
+
+    /// 生成二次验证过程中使用的临时令牌（MfaPending 或 MfaSetup）
+    pub fn generate_mfa_token(
+        user_id: i32,
+        username: &str,
+        token_use: TokenUse,
+    ) -> Result<String, AuthError> {
+        let mut claims = Claims::new(user_id, username, Self::mfa_token_ttl());
+        claims.token_use = token_use;
+        Self::encode_claims(&claims)
+    }
+
+    /// 生成密码过期后只能用于修改密码的临时令牌，有效期与二次验证临时令牌相同
+    pub fn generate_password_change_token(user_id: i32, username: &str) -> Result<String, AuthError> {
+        let mut claims = Claims::new(user_id, username, Self::mfa_token_ttl());
+        claims.token_use = TokenUse::PasswordChange;
+        Self::encode_claims(&claims)
+    }
+
+    /// 生成不绑定会话的访问令牌
     pub fn generate_token(user_id: i32, username: &str) -> Result<String, AuthError> {
-        let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "default-secret".to_string());
-        let expiration = env::var("JWT_EXPIRATION")
-            .unwrap_or_else(|_| "24h".to_string())
-            .parse::<u64>()
-            .unwrap_or(86400); // 默认24小时
+        let claims = Claims::new(user_id, username, Self::access_token_ttl());
+        Self::encode_claims(&claims)
+    }
 
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    /// 生成绑定到登录会话的访问令牌，邮箱未验证时签发受限令牌（EmailUnverified）
+    pub fn generate_session_token(
+        user_id: i32,
+        username: &str,
+        session_id: Uuid,
+        token_use: TokenUse,
+    ) -> Result<String, AuthError> {
+        let mut claims = Claims::new(user_id, username, Self::access_token_ttl());
+        claims.sid = Some(session_id);
+        claims.token_use = token_use;
+        Self::encode_claims(&claims)
+    }
 
-        let claims = Claims {
-            sub: user_id,
-            username: username.to_string(),
-            exp: now + expiration,
-            iat: now,
-        };
+    /// 使用当前签名密钥签发令牌，令牌头部带有密钥的 kid
+    pub fn encode_claims<T: Serialize>(claims: &T) -> Result<String, AuthError> {
+        let key_ring = jwt_keys::current()?;
+        let key = key_ring.signing_key();
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret.as_ref()),
-        )?;
+        let mut header = Header::new(key.algorithm.jwt_algorithm());
+        header.kid = key.kid.clone();
+
+        let token = encode(&header, claims, key.encoding_key())?;
 
         Ok(token)
     }
 
+    /// 按令牌头部的 kid 选择验签密钥，只接受该密钥对应的算法
     pub fn verify_token(token: &str) -> Result<Claims, AuthError> {
-        let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "default-secret".to_string());
-        
+        let key_ring = jwt_keys::current()?;
+        let header = decode_header(token)?;
+        let key = key_ring
+            .find(header.kid.as_deref())
+            .ok_or(AuthError::InvalidToken)?;
+
         let token_data = decode::<Claims>(
             token,
-            &DecodingKey::from_secret(secret.as_ref()),
-            &Validation::default(),
-        )?;
+            key.decoding_key(),
+            &Validation::new(key.algorithm.jwt_algorithm()),
+        )
+        .map_err(|e| match e.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::TokenExpired,
+            _ => AuthError::JwtError(e),
+        })?;
 
         Ok(token_data.claims)
     }
 
+    /// 生成不透明的刷新令牌（32字节随机数的十六进制表示）
+    pub fn generate_refresh_token() -> String {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        hex::encode(bytes)
+    }
+
+    /// 计算令牌的SHA-256摘要，数据库中只保存摘要
+    pub fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
     pub fn extract_token_from_header(auth_header: &str) -> Result<String, AuthError> {
         if !auth_header.starts_with("Bearer ") {
             return Err(AuthError::InvalidToken);
@@ -107,6 +294,7 @@ impl AuthService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use jsonwebtoken::DecodingKey;
     use std::env;
 
     #[test]
@@ -169,6 +357,9 @@ mod tests {
         
         // 业务需求：令牌必须有有效的时间戳
         assert!(claims.exp > claims.iat);
+
+        // 业务需求：每个令牌都有唯一标识，用于撤销
+        assert!(!claims.jti.is_empty());
         
         // 业务需求：过期时间应该是1小时后
         let expected_duration = 3600; // 1小时
@@ -196,6 +387,33 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_refresh_token_generation_business_rules() {
+        // 业务规则：刷新令牌必须是不可预测且互不相同的
+        let token1 = AuthService::generate_refresh_token();
+        let token2 = AuthService::generate_refresh_token();
+
+        assert_ne!(token1, token2);
+        assert_eq!(token1.len(), 64);
+        assert!(token1.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn test_refresh_token_hashing_business_rules() {
+        // 业务规则：同一令牌的摘要必须稳定，且不能等于原令牌
+        let token = AuthService::generate_refresh_token();
+        let hash1 = AuthService::hash_token(&token);
+        let hash2 = AuthService::hash_token(&token);
+
+        assert_eq!(hash1, hash2);
+        assert_ne!(hash1, token);
+        assert_eq!(hash1.len(), 64);
+
+        // 不同令牌的摘要必须不同
+        let other = AuthService::generate_refresh_token();
+        assert_ne!(AuthService::hash_token(&other), hash1);
+    }
+
     #[test]
     fn test_authorization_header_parsing_business_logic() {
         // 业务逻辑：正确格式的Authorization头必须能解析出令牌
@@ -290,4 +508,108 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_token_id_uniqueness_business_rules() {
+        // 业务规则：同一用户同一秒内签发的令牌也必须可以单独撤销
+        let token1 = AuthService::generate_token(1, "admin").unwrap();
+        let token2 = AuthService::generate_token(1, "admin").unwrap();
+
+        let claims1 = decode_claims_unverified(&token1);
+        let claims2 = decode_claims_unverified(&token2);
+
+        assert_ne!(claims1.jti, claims2.jti);
+        assert!(Uuid::parse_str(&claims1.jti).is_ok(), "jti应该是UUID格式");
+    }
+
+    #[test]
+    fn test_session_token_business_rules() {
+        // 业务规则：会话令牌必须携带会话ID，普通令牌不携带
+        let session_id = Uuid::new_v4();
+        let token =
+            AuthService::generate_session_token(7, "session_user", session_id, TokenUse::Access)
+                .unwrap();
+        let claims = decode_claims_unverified(&token);
+
+        assert_eq!(claims.sub, 7);
+        assert_eq!(claims.sid, Some(session_id));
+        assert_eq!(claims.token_use, TokenUse::Access);
+
+        // 邮箱未验证的用户同样绑定会话，但令牌用途受限
+        let restricted = AuthService::generate_session_token(
+            7,
+            "session_user",
+            session_id,
+            TokenUse::EmailUnverified,
+        )
+        .unwrap();
+        let claims = decode_claims_unverified(&restricted);
+        assert_eq!(claims.sid, Some(session_id));
+        assert_eq!(serde_json::to_value(&claims).unwrap()["use"], "email_unverified");
+
+        let plain = AuthService::generate_token(7, "session_user").unwrap();
+        assert_eq!(decode_claims_unverified(&plain).sid, None);
+    }
+
+    #[test]
+    fn test_mfa_token_business_rules() {
+        // 业务规则：二次验证临时令牌必须标明用途，且不绑定会话
+        let token = AuthService::generate_mfa_token(7, "mfa_user", TokenUse::MfaPending).unwrap();
+        let claims = decode_claims_unverified(&token);
+        assert_eq!(claims.token_use, TokenUse::MfaPending);
+        assert_eq!(claims.sid, None);
+        assert!(claims.exp - claims.iat <= AuthService::mfa_token_ttl());
+
+        // 访问令牌不写入用途字段，旧令牌缺少该字段时视为访问令牌
+        let access = AuthService::generate_token(7, "mfa_user").unwrap();
+        assert_eq!(decode_claims_unverified(&access).token_use, TokenUse::Access);
+        let json = serde_json::to_value(Claims::new(7, "mfa_user", 60)).unwrap();
+        assert!(json.get("use").is_none());
+
+        let mut setup = Claims::new(7, "mfa_user", 60);
+        setup.token_use = TokenUse::MfaSetup;
+        assert_eq!(serde_json::to_value(&setup).unwrap()["use"], "mfa_setup");
+    }
+
+    #[test]
+    fn test_password_change_token_business_rules() {
+        // 业务规则：密码过期后签发的临时令牌只能用于修改密码，且不绑定会话
+        let token = AuthService::generate_password_change_token(7, "expired_user").unwrap();
+        let claims = decode_claims_unverified(&token);
+        assert_eq!(claims.token_use, TokenUse::PasswordChange);
+        assert_eq!(claims.sid, None);
+        assert_eq!(serde_json::to_value(&claims).unwrap()["use"], "password_change");
+    }
+
+    #[test]
+    fn test_impersonation_token_business_rules() {
+        // 业务规则：模拟登录令牌的 sub 为被模拟的用户，act 为实际操作的管理员
+        let mut claims = Claims::new(42, "alice", AuthService::impersonation_token_ttl());
+        claims.act = Some(Actor {
+            sub: 1,
+            username: "admin".to_string(),
+        });
+        assert!(claims.is_impersonating());
+
+        let token = AuthService::encode_claims(&claims).unwrap();
+        let decoded = decode_claims_unverified(&token);
+        assert_eq!(decoded.sub, 42);
+        assert_eq!(decoded.act, claims.act);
+        assert_eq!(decoded.token_use, TokenUse::Access);
+        assert!(AuthService::max_token_ttl() >= AuthService::impersonation_token_ttl());
+
+        // 普通令牌不写入 act 字段
+        let json = serde_json::to_value(Claims::new(7, "plain_user", 60)).unwrap();
+        assert!(json.get("act").is_none());
+        assert!(!Claims::new(7, "plain_user", 60).is_impersonating());
+    }
+
+    // 测试辅助函数：不校验签名直接解析claims，避免测试间共享JWT_SECRET造成干扰
+    fn decode_claims_unverified(token: &str) -> Claims {
+        let mut validation = Validation::default();
+        validation.insecure_disable_signature_validation();
+        decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+            .unwrap()
+            .claims
+    }
 }
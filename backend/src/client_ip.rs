@@ -0,0 +1,168 @@
+use axum::http::{HeaderMap, HeaderName};
+use ipnet::IpNet;
+use std::env;
+use std::net::IpAddr;
+
+/// 默认从该请求头读取经过代理转发的客户端地址
+const DEFAULT_CLIENT_IP_HEADER: &str = "x-forwarded-for";
+
+/// 解析CIDR，单个IP地址视为只包含该地址的网段；主机位会被清零
+pub fn parse_network(value: &str) -> Option<IpNet> {
+    let value = value.trim();
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .ok()
+        .map(|net| net.trunc())
+}
+
+/// 受信任的反向代理，只有来自这些地址的请求才读取转发请求头中的客户端地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedProxies {
+    pub networks: Vec<IpNet>,
+    pub header: HeaderName,
+}
+
+impl Default for TrustedProxies {
+    fn default() -> Self {
+        Self {
+            networks: Vec::new(),
+            header: HeaderName::from_static(DEFAULT_CLIENT_IP_HEADER),
+        }
+    }
+}
+
+impl TrustedProxies {
+    /// 从环境变量读取配置，未配置 TRUSTED_PROXIES 时不信任任何转发请求头
+    pub fn from_env() -> Self {
+        let networks = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .filter_map(|item| {
+                let network = parse_network(item);
+                if network.is_none() {
+                    tracing::warn!("TRUSTED_PROXIES 中的地址无效，已忽略: {}", item.trim());
+                }
+                network
+            })
+            .collect();
+
+        let header = env::var("CLIENT_IP_HEADER")
+            .ok()
+            .and_then(|name| HeaderName::try_from(name.trim().to_ascii_lowercase()).ok())
+            .unwrap_or_else(|| HeaderName::from_static(DEFAULT_CLIENT_IP_HEADER));
+
+        Self { networks, header }
+    }
+
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// 确定客户端地址：从直接连接的地址开始，只要当前一跳是受信任的代理，
+    /// 就继续取转发请求头中由它追加的上一跳（从右向左），避免客户端伪造请求头
+    pub fn client_ip(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut client = peer?.to_canonical();
+
+        let forwarded: Vec<&str> = headers
+            .get_all(&self.header)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+
+        for hop in forwarded.iter().rev() {
+            if !self.is_trusted(&client) {
+                break;
+            }
+            match hop.parse::<IpAddr>() {
+                Ok(ip) => client = ip.to_canonical(),
+                Err(_) => break,
+            }
+        }
+
+        Some(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn proxies(networks: &[&str]) -> TrustedProxies {
+        TrustedProxies {
+            networks: networks.iter().map(|n| parse_network(n).unwrap()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_network() {
+        assert_eq!(parse_network("10.0.0.0/8"), Some("10.0.0.0/8".parse().unwrap()));
+        // 单个地址视为 /32 或 /128
+        assert_eq!(parse_network(" 192.168.1.10 "), Some("192.168.1.10/32".parse().unwrap()));
+        assert_eq!(parse_network("2001:db8::1"), Some("2001:db8::1/128".parse().unwrap()));
+        // 主机位清零
+        assert_eq!(parse_network("192.168.1.10/24"), Some("192.168.1.0/24".parse().unwrap()));
+
+        assert_eq!(parse_network("192.168.1.0/33"), None);
+        assert_eq!(parse_network("office"), None);
+        assert_eq!(parse_network(""), None);
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_header() {
+        // 未配置受信任的代理时，客户端可以随意伪造转发请求头，必须忽略
+        let config = TrustedProxies::default();
+        let headers = forwarded(&["10.1.2.3"]);
+        assert_eq!(config.client_ip(Some(ip("203.0.113.5")), &headers), Some(ip("203.0.113.5")));
+
+        let config = proxies(&["10.0.0.0/8"]);
+        assert_eq!(config.client_ip(Some(ip("203.0.113.5")), &headers), Some(ip("203.0.113.5")));
+        assert_eq!(config.client_ip(None, &headers), None);
+    }
+
+    #[test]
+    fn test_trusted_proxy_chain() {
+        let config = proxies(&["10.0.0.0/8"]);
+
+        // 经过一层代理
+        let headers = forwarded(&["198.51.100.7"]);
+        assert_eq!(config.client_ip(Some(ip("10.0.0.2")), &headers), Some(ip("198.51.100.7")));
+
+        // 经过两层代理，客户端伪造的最左侧地址被忽略
+        let headers = forwarded(&["1.1.1.1, 198.51.100.7, 10.0.0.3"]);
+        assert_eq!(config.client_ip(Some(ip("10.0.0.2")), &headers), Some(ip("198.51.100.7")));
+
+        // 多个同名请求头按顺序拼接
+        let headers = forwarded(&["1.1.1.1, 198.51.100.7", "10.0.0.3"]);
+        assert_eq!(config.client_ip(Some(ip("10.0.0.2")), &headers), Some(ip("198.51.100.7")));
+
+        // 没有转发请求头或内容无效时使用最后一个可信的地址
+        assert_eq!(config.client_ip(Some(ip("10.0.0.2")), &HeaderMap::new()), Some(ip("10.0.0.2")));
+        let headers = forwarded(&["unknown, 10.0.0.3"]);
+        assert_eq!(config.client_ip(Some(ip("10.0.0.2")), &headers), Some(ip("10.0.0.3")));
+    }
+
+    #[test]
+    fn test_ipv4_mapped_address() {
+        let config = proxies(&["10.0.0.0/8"]);
+        let headers = forwarded(&["::ffff:198.51.100.7"]);
+        assert_eq!(config.client_ip(Some(ip("::ffff:10.0.0.2")), &headers), Some(ip("198.51.100.7")));
+    }
+}
@@ -1,12 +1,15 @@
 use axum::{
     async_trait,
-    extract::FromRequestParts,
-    http::request::Parts,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, Extensions, HeaderMap},
     response::{IntoResponse, Response},
 };
 use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
 
-use crate::auth::Claims;
+use crate::auth::{Actor, Claims};
+use crate::client_ip::TrustedProxies;
 
 #[derive(Debug, Clone)]
 pub struct AuthUser(pub Claims);
@@ -41,7 +44,10 @@ where
 #[derive(Debug, Clone)]
 pub struct RequireAuth {
     pub user_id: i32,
+    #[allow(dead_code)]
     pub username: String,
+    /// 模拟登录时实际操作的管理员
+    pub impersonator: Option<Actor>,
 }
 
 #[async_trait]
@@ -69,8 +75,49 @@ where
         Ok(RequireAuth {
             user_id: claims.sub,
             username: claims.username,
+            impersonator: claims.act,
         })
     }
 }
 
+/// 客户端信息：IP地址和User-Agent，用于会话和登录审计
+///
+/// 请求来自 TRUSTED_PROXIES 配置的反向代理时，IP地址取自转发请求头。
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_http(extensions: &Extensions, headers: &HeaderMap) -> Self {
+        let peer = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip_address = TrustedProxies::from_env()
+            .client_ip(peer, headers)
+            .map(|ip| ip.to_string());
+
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(512).collect());
+
+        ClientInfo {
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
 
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientInfo::from_http(&parts.extensions, &parts.headers))
+    }
+}
@@ -0,0 +1,454 @@
+use data_encoding::{BASE64, BASE64URL_NOPAD};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey};
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::sync::{Arc, OnceLock, RwLock};
+use thiserror::Error;
+
+/// HS256 密钥的最小长度（字节）
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// 示例配置和旧版本默认值中出现过的密钥，视为未配置
+const PLACEHOLDER_SECRETS: &[&str] = &[
+    "default-secret",
+    "your-strong-jwt-secret-key",
+    "your-super-secret-jwt-key",
+    "your_jwt_secret",
+    "dev-jwt-secret-key-change-in-production",
+];
+
+/// 自动生成的RSA密钥长度
+const RSA_KEY_BITS: usize = 2048;
+
+#[derive(Error, Debug)]
+pub enum KeyError {
+    #[error("未配置JWT_SECRET")]
+    MissingSecret,
+    #[error("JWT_SECRET不安全：长度至少为{MIN_SECRET_LENGTH}字节，且不能使用示例值")]
+    InsecureSecret,
+    #[error("不支持的JWT签名算法: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("签名密钥无效: {0}")]
+    InvalidKey(String),
+    #[error("生成签名密钥失败: {0}")]
+    GenerateError(String),
+    #[error("没有可用的签名密钥")]
+    NoSigningKey,
+    #[error("使用EdDSA/RS256时必须配置JWT_KEY_ENCRYPTION_KEY，用于加密保存在数据库中的签名私钥")]
+    MissingEncryptionKey,
+    #[error("JWT_KEY_ENCRYPTION_KEY无效：必须是32字节随机数的Base64编码")]
+    InvalidEncryptionKey,
+    #[error("解密签名密钥失败，JWT_KEY_ENCRYPTION_KEY可能已更换")]
+    DecryptError,
+}
+
+/// JWT签名算法，通过 JWT_ALGORITHM 环境变量配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JwtAlgorithm {
+    /// Ed25519 签名（默认）
+    #[default]
+    EdDSA,
+    RS256,
+    /// 共享密钥签名，只用于兼容旧部署，不提供JWKS
+    HS256,
+}
+
+impl JwtAlgorithm {
+    pub fn from_env() -> Result<Self, KeyError> {
+        match env::var("JWT_ALGORITHM") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JwtAlgorithm::EdDSA => "EdDSA",
+            JwtAlgorithm::RS256 => "RS256",
+            JwtAlgorithm::HS256 => "HS256",
+        }
+    }
+
+    pub fn jwt_algorithm(&self) -> Algorithm {
+        match self {
+            JwtAlgorithm::EdDSA => Algorithm::EdDSA,
+            JwtAlgorithm::RS256 => Algorithm::RS256,
+            JwtAlgorithm::HS256 => Algorithm::HS256,
+        }
+    }
+
+    pub fn is_asymmetric(&self) -> bool {
+        *self != JwtAlgorithm::HS256
+    }
+}
+
+impl std::str::FromStr for JwtAlgorithm {
+    type Err = KeyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "EdDSA" => Ok(JwtAlgorithm::EdDSA),
+            "RS256" => Ok(JwtAlgorithm::RS256),
+            "HS256" => Ok(JwtAlgorithm::HS256),
+            other => Err(KeyError::UnsupportedAlgorithm(other.to_string())),
+        }
+    }
+}
+
+/// 一个签名/验签密钥
+#[derive(Clone)]
+pub struct JwtKey {
+    /// 密钥标识，写入令牌头部的 kid；HS256 密钥没有 kid
+    pub kid: Option<String>,
+    pub algorithm: JwtAlgorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    /// 公钥的JWK表示，HS256 密钥为空
+    jwk: Option<Value>,
+}
+
+impl JwtKey {
+    /// 使用共享密钥创建 HS256 密钥，拒绝过短或示例中的密钥
+    pub fn hmac(secret: &str) -> Result<Self, KeyError> {
+        if secret.len() < MIN_SECRET_LENGTH || PLACEHOLDER_SECRETS.contains(&secret) {
+            return Err(KeyError::InsecureSecret);
+        }
+
+        Ok(Self::hmac_unchecked(secret))
+    }
+
+    fn hmac_unchecked(secret: &str) -> Self {
+        Self {
+            kid: None,
+            algorithm: JwtAlgorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        }
+    }
+
+    /// 从私钥（Ed25519 为 PKCS#8 DER，RSA 为 PKCS#1 DER）加载密钥，kid 为公钥的 JWK 指纹（RFC 7638）
+    pub fn from_private_der(algorithm: JwtAlgorithm, der: &[u8]) -> Result<Self, KeyError> {
+        // 指纹按 RFC 7638 的规范格式计算：只包含必需成员，按字典序排列，无空白
+        let (encoding_key, decoding_key, mut jwk, canonical) = match algorithm {
+            JwtAlgorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der)
+                    .map_err(|e| KeyError::InvalidKey(e.to_string()))?;
+                let x = BASE64URL_NOPAD.encode(key_pair.public_key().as_ref());
+                (
+                    EncodingKey::from_ed_der(der),
+                    DecodingKey::from_ed_components(&x),
+                    json!({ "kty": "OKP", "crv": "Ed25519", "x": x }),
+                    format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x),
+                )
+            }
+            JwtAlgorithm::RS256 => {
+                let private_key = RsaPrivateKey::from_pkcs1_der(der)
+                    .map_err(|e| KeyError::InvalidKey(e.to_string()))?;
+                let n = BASE64URL_NOPAD.encode(&private_key.n().to_bytes_be());
+                let e = BASE64URL_NOPAD.encode(&private_key.e().to_bytes_be());
+                (
+                    EncodingKey::from_rsa_der(der),
+                    DecodingKey::from_rsa_components(&n, &e),
+                    json!({ "kty": "RSA", "n": n, "e": e }),
+                    format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n),
+                )
+            }
+            JwtAlgorithm::HS256 => {
+                return Err(KeyError::UnsupportedAlgorithm(algorithm.as_str().to_string()))
+            }
+        };
+        let decoding_key = decoding_key.map_err(|e| KeyError::InvalidKey(e.to_string()))?;
+
+        let kid = BASE64URL_NOPAD.encode(&Sha256::digest(canonical.as_bytes()));
+        jwk["kid"] = json!(kid);
+        jwk["alg"] = json!(algorithm.as_str());
+        jwk["use"] = json!("sig");
+
+        Ok(Self {
+            kid: Some(kid),
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+}
+
+/// 生成新的私钥，返回可由 [`JwtKey::from_private_der`] 加载的DER编码
+pub fn generate_private_key(algorithm: JwtAlgorithm) -> Result<Vec<u8>, KeyError> {
+    match algorithm {
+        JwtAlgorithm::EdDSA => {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                .map_err(|e| KeyError::GenerateError(e.to_string()))?;
+            Ok(pkcs8.as_ref().to_vec())
+        }
+        JwtAlgorithm::RS256 => {
+            let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), RSA_KEY_BITS)
+                .map_err(|e| KeyError::GenerateError(e.to_string()))?;
+            let der = private_key
+                .to_pkcs1_der()
+                .map_err(|e| KeyError::GenerateError(e.to_string()))?;
+            Ok(der.as_bytes().to_vec())
+        }
+        JwtAlgorithm::HS256 => Err(KeyError::UnsupportedAlgorithm(algorithm.as_str().to_string())),
+    }
+}
+
+/// 加密保存在数据库中的签名私钥（AES-256-GCM），主密钥由 JWT_KEY_ENCRYPTION_KEY 配置
+///
+/// 只能读取数据库的人拿不到私钥，无法伪造令牌。
+pub struct KeyEncryption {
+    key: LessSafeKey,
+}
+
+impl KeyEncryption {
+    /// 读取主密钥：32字节随机数的Base64编码，例如 `openssl rand -base64 32` 的输出
+    pub fn from_env() -> Result<Self, KeyError> {
+        let value = env::var("JWT_KEY_ENCRYPTION_KEY").map_err(|_| KeyError::MissingEncryptionKey)?;
+        let key = BASE64
+            .decode(value.trim().as_bytes())
+            .map_err(|_| KeyError::InvalidEncryptionKey)?;
+        Self::new(&key)
+    }
+
+    pub fn new(key: &[u8]) -> Result<Self, KeyError> {
+        let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| KeyError::InvalidEncryptionKey)?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// 加密私钥，结果为随机 nonce 加密文（含认证标签）；kid 作为附加数据，密文不能被挪用到其他记录
+    pub fn seal(&self, kid: &str, private_key: &[u8]) -> Result<Vec<u8>, KeyError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|e| KeyError::GenerateError(e.to_string()))?;
+
+        let mut in_out = private_key.to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(kid.as_bytes()), &mut in_out)
+            .map_err(|e| KeyError::GenerateError(e.to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&in_out);
+        Ok(sealed)
+    }
+
+    /// 解密 [`KeyEncryption::seal`] 的结果；主密钥或 kid 不匹配、密文被篡改时失败
+    pub fn open(&self, kid: &str, sealed: &[u8]) -> Result<Vec<u8>, KeyError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(KeyError::DecryptError);
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| KeyError::DecryptError)?;
+        let mut in_out = ciphertext.to_vec();
+        let private_key = self
+            .key
+            .open_in_place(nonce, Aad::from(kid.as_bytes()), &mut in_out)
+            .map_err(|_| KeyError::DecryptError)?;
+
+        Ok(private_key.to_vec())
+    }
+}
+
+/// 当前使用的密钥集合：一个签名密钥，以及轮换后仍在有效期内、用于验签的旧密钥
+pub struct KeyRing {
+    signing_key: JwtKey,
+    keys: Vec<JwtKey>,
+}
+
+impl KeyRing {
+    /// `keys` 中应包含签名密钥
+    pub fn new(signing_key: JwtKey, keys: Vec<JwtKey>) -> Self {
+        Self { signing_key, keys }
+    }
+
+    pub fn signing_key(&self) -> &JwtKey {
+        &self.signing_key
+    }
+
+    /// 按令牌头部的 kid 查找验签密钥
+    pub fn find(&self, kid: Option<&str>) -> Option<&JwtKey> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid.as_deref() == Some(kid)),
+            None => self.keys.iter().find(|k| k.kid.is_none()),
+        }
+    }
+
+    /// 公开的 JWK Set，供其他服务离线验证令牌
+    pub fn jwks(&self) -> Value {
+        let keys: Vec<&Value> = self.keys.iter().filter_map(|k| k.jwk.as_ref()).collect();
+        json!({ "keys": keys })
+    }
+}
+
+static KEY_RING: OnceLock<RwLock<Arc<KeyRing>>> = OnceLock::new();
+
+/// 安装（或在密钥轮换后替换）全局密钥集合
+pub fn install(key_ring: KeyRing) {
+    let key_ring = Arc::new(key_ring);
+    let lock = KEY_RING.get_or_init(|| RwLock::new(key_ring.clone()));
+    *lock.write().unwrap_or_else(|e| e.into_inner()) = key_ring;
+}
+
+/// 获取当前的密钥集合
+///
+/// 服务启动时会安装密钥集合；尚未安装时（例如单元测试）按 JWT_SECRET 使用 HS256。
+pub fn current() -> Result<Arc<KeyRing>, KeyError> {
+    if let Some(lock) = KEY_RING.get() {
+        return Ok(lock.read().unwrap_or_else(|e| e.into_inner()).clone());
+    }
+
+    let key = match env::var("JWT_SECRET") {
+        Ok(secret) => JwtKey::hmac_unchecked(&secret),
+        #[cfg(test)]
+        Err(_) => JwtKey::hmac_unchecked("unit-test-secret"),
+        #[cfg(not(test))]
+        Err(_) => return Err(KeyError::NoSigningKey),
+    };
+
+    Ok(Arc::new(KeyRing::new(key.clone(), vec![key])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_secret_business_rules() {
+        // 过短或示例中的密钥视为未配置，启动时直接失败
+        assert!(matches!(JwtKey::hmac("short"), Err(KeyError::InsecureSecret)));
+        assert!(matches!(JwtKey::hmac("default-secret"), Err(KeyError::InsecureSecret)));
+        assert!(matches!(
+            JwtKey::hmac("dev-jwt-secret-key-change-in-production"),
+            Err(KeyError::InsecureSecret)
+        ));
+
+        let key = JwtKey::hmac(&"k".repeat(MIN_SECRET_LENGTH)).unwrap();
+        assert_eq!(key.algorithm, JwtAlgorithm::HS256);
+        assert!(key.kid.is_none());
+    }
+
+    #[test]
+    fn test_algorithm_parsing() {
+        assert_eq!("EdDSA".parse::<JwtAlgorithm>().unwrap(), JwtAlgorithm::EdDSA);
+        assert_eq!("RS256".parse::<JwtAlgorithm>().unwrap(), JwtAlgorithm::RS256);
+        assert!(!"HS256".parse::<JwtAlgorithm>().unwrap().is_asymmetric());
+        assert!(matches!("none".parse::<JwtAlgorithm>(), Err(KeyError::UnsupportedAlgorithm(_))));
+    }
+
+    #[test]
+    fn test_ed25519_key_business_rules() {
+        let der = generate_private_key(JwtAlgorithm::EdDSA).unwrap();
+        let key = JwtKey::from_private_der(JwtAlgorithm::EdDSA, &der).unwrap();
+
+        // 同一私钥重新加载时 kid 不变，便于多实例共享
+        let reloaded = JwtKey::from_private_der(JwtAlgorithm::EdDSA, &der).unwrap();
+        assert_eq!(key.kid, reloaded.kid);
+
+        let jwk = key.jwk.as_ref().unwrap();
+        assert_eq!(jwk["kty"], "OKP");
+        assert_eq!(jwk["crv"], "Ed25519");
+        assert_eq!(jwk["alg"], "EdDSA");
+        assert_eq!(jwk["kid"], json!(key.kid));
+        assert!(jwk.get("d").is_none(), "JWK中不能包含私钥");
+
+        // 不同密钥的 kid 不同
+        let other = JwtKey::from_private_der(
+            JwtAlgorithm::EdDSA,
+            &generate_private_key(JwtAlgorithm::EdDSA).unwrap(),
+        )
+        .unwrap();
+        assert_ne!(key.kid, other.kid);
+    }
+
+    #[test]
+    fn test_rsa_key_business_rules() {
+        let der = generate_private_key(JwtAlgorithm::RS256).unwrap();
+        let key = JwtKey::from_private_der(JwtAlgorithm::RS256, &der).unwrap();
+
+        let jwk = key.jwk.as_ref().unwrap();
+        assert_eq!(jwk["kty"], "RSA");
+        assert_eq!(jwk["alg"], "RS256");
+        assert_eq!(jwk["e"], "AQAB");
+        assert!(jwk.get("d").is_none(), "JWK中不能包含私钥");
+
+        // 私钥与算法不匹配时拒绝加载
+        assert!(JwtKey::from_private_der(JwtAlgorithm::EdDSA, &der).is_err());
+    }
+
+    #[test]
+    fn test_private_key_encryption() {
+        let encryption = KeyEncryption::new(&[7u8; 32]).unwrap();
+        let der = generate_private_key(JwtAlgorithm::EdDSA).unwrap();
+
+        let sealed = encryption.seal("kid-1", &der).unwrap();
+        assert_ne!(&sealed[NONCE_LEN..NONCE_LEN + der.len()], der.as_slice(), "数据库中不能保存明文私钥");
+        assert_eq!(encryption.open("kid-1", &sealed).unwrap(), der);
+
+        // 每次加密使用不同的 nonce
+        assert_ne!(encryption.seal("kid-1", &der).unwrap(), sealed);
+
+        // 主密钥不同、密文挪用到其他 kid 或被篡改时无法解密
+        let other = KeyEncryption::new(&[8u8; 32]).unwrap();
+        assert!(matches!(other.open("kid-1", &sealed), Err(KeyError::DecryptError)));
+        assert!(matches!(encryption.open("kid-2", &sealed), Err(KeyError::DecryptError)));
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(encryption.open("kid-1", &tampered), Err(KeyError::DecryptError)));
+        assert!(matches!(encryption.open("kid-1", &sealed[..4]), Err(KeyError::DecryptError)));
+
+        // 主密钥必须是32字节
+        assert!(matches!(KeyEncryption::new(&[7u8; 16]), Err(KeyError::InvalidEncryptionKey)));
+    }
+
+    #[test]
+    fn test_key_ring_lookup_and_jwks() {
+        let old = JwtKey::from_private_der(
+            JwtAlgorithm::EdDSA,
+            &generate_private_key(JwtAlgorithm::EdDSA).unwrap(),
+        )
+        .unwrap();
+        let new = JwtKey::from_private_der(
+            JwtAlgorithm::EdDSA,
+            &generate_private_key(JwtAlgorithm::EdDSA).unwrap(),
+        )
+        .unwrap();
+
+        let ring = KeyRing::new(new.clone(), vec![new.clone(), old.clone()]);
+        assert_eq!(ring.signing_key().kid, new.kid);
+
+        // 轮换后旧密钥仍可用于验签
+        assert_eq!(ring.find(old.kid.as_deref()).unwrap().kid, old.kid);
+        assert!(ring.find(Some("unknown")).is_none());
+        // 非对称密钥集合不接受没有 kid 的令牌
+        assert!(ring.find(None).is_none());
+
+        let jwks = ring.jwks();
+        assert_eq!(jwks["keys"].as_array().unwrap().len(), 2);
+
+        // HS256 密钥不公开
+        let hmac = JwtKey::hmac(&"k".repeat(MIN_SECRET_LENGTH)).unwrap();
+        let ring = KeyRing::new(hmac.clone(), vec![hmac]);
+        assert!(ring.find(None).is_some());
+        assert_eq!(ring.jwks()["keys"].as_array().unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,434 @@
+use ldap3::adapters::{Adapter, EntriesOnly, PagedResults};
+use ldap3::{ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+use thiserror::Error;
+
+#[cfg(test)]
+pub mod stand_in;
+
+/// LDAP结果码：用户名或密码错误（RFC 4511）
+const RC_INVALID_CREDENTIALS: u32 = 49;
+
+/// 分页查询时每页的条目数，避免超出目录服务的单次查询上限
+const PAGE_SIZE: i32 = 500;
+
+#[derive(Error, Debug)]
+pub enum LdapError {
+    #[error("LDAP配置无效: {0}")]
+    InvalidConfig(String),
+    #[error("目录服务请求失败: {0}")]
+    Ldap(#[from] ldap3::LdapError),
+    #[error("目录服务请求超时")]
+    Timeout,
+}
+
+/// 通过 LDAP_* 环境变量配置的目录服务，未设置 LDAP_URL 时不启用
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    pub url: String,
+    pub starttls: bool,
+    /// 用于查找用户的账户，不配置时匿名查询
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    pub user_base_dn: String,
+    /// 用户条目的过滤条件
+    pub user_filter: String,
+    /// 作为用户名的属性，OpenLDAP 一般为 uid，Active Directory 为 sAMAccountName
+    pub username_attribute: String,
+    pub email_attribute: String,
+    /// 用户条目上列出所属组的属性（Active Directory 的 memberOf）
+    pub member_of_attribute: String,
+    /// 配置后从组条目读取成员关系（如 OpenLDAP 的 groupOfNames），不再使用用户的 memberOf
+    pub group_base_dn: Option<String>,
+    pub group_filter: String,
+    pub group_member_attribute: String,
+    pub timeout: Duration,
+}
+
+impl LdapConfig {
+    pub fn from_env() -> Result<Option<Self>, LdapError> {
+        let var = |key: &str| env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        let Some(url) = var("LDAP_URL") else {
+            return Ok(None);
+        };
+        let user_base_dn = var("LDAP_USER_BASE_DN")
+            .ok_or_else(|| LdapError::InvalidConfig("缺少 LDAP_USER_BASE_DN".to_string()))?;
+
+        Ok(Some(Self {
+            url,
+            starttls: var("LDAP_STARTTLS").is_some_and(|v| matches!(v.as_str(), "true" | "1" | "yes")),
+            bind_dn: var("LDAP_BIND_DN"),
+            bind_password: var("LDAP_BIND_PASSWORD"),
+            user_base_dn,
+            user_filter: var("LDAP_USER_FILTER").unwrap_or_else(|| "(objectClass=inetOrgPerson)".to_string()),
+            username_attribute: var("LDAP_USERNAME_ATTRIBUTE").unwrap_or_else(|| "uid".to_string()),
+            email_attribute: var("LDAP_EMAIL_ATTRIBUTE").unwrap_or_else(|| "mail".to_string()),
+            member_of_attribute: var("LDAP_MEMBER_OF_ATTRIBUTE").unwrap_or_else(|| "memberOf".to_string()),
+            group_base_dn: var("LDAP_GROUP_BASE_DN"),
+            group_filter: var("LDAP_GROUP_FILTER").unwrap_or_else(|| "(objectClass=groupOfNames)".to_string()),
+            group_member_attribute: var("LDAP_GROUP_MEMBER_ATTRIBUTE").unwrap_or_else(|| "member".to_string()),
+            timeout: Duration::from_secs(
+                var("LDAP_TIMEOUT").and_then(|v| v.parse().ok()).unwrap_or(10),
+            ),
+        }))
+    }
+}
+
+/// 目录中的用户
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryUser {
+    pub dn: String,
+    pub username: String,
+    pub email: Option<String>,
+    /// 所属组的DN，已规范化（见 [`normalize_dn`]）
+    pub groups: Vec<String>,
+}
+
+/// 规范化DN以便比较：去掉各部分前后的空白并转为小写
+pub fn normalize_dn(dn: &str) -> String {
+    dn.split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(",")
+        .to_lowercase()
+}
+
+pub struct LdapDirectory {
+    config: LdapConfig,
+}
+
+impl LdapDirectory {
+    pub fn new(config: LdapConfig) -> Self {
+        Self { config }
+    }
+
+    /// 以用户身份绑定来验证密码，成功时返回目录中的用户信息；用户不存在或密码错误时返回 None
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<Option<DirectoryUser>, LdapError> {
+        // 空密码的简单绑定会被当作匿名绑定而"成功"，必须拒绝
+        if username.is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+
+        self.with_timeout(async {
+            let mut ldap = self.connect().await?;
+
+            let filter = format!(
+                "(&{}({}={}))",
+                self.config.user_filter,
+                self.config.username_attribute,
+                ldap_escape(username)
+            );
+            let (entries, _) = ldap
+                .search(&self.config.user_base_dn, Scope::Subtree, &filter, self.user_attributes())
+                .await?
+                .success()?;
+
+            // 不存在或不唯一时都视为认证失败
+            let entry = match <[_; 1]>::try_from(entries) {
+                Ok([entry]) => SearchEntry::construct(entry),
+                Err(_) => {
+                    ldap.unbind().await.ok();
+                    return Ok(None);
+                }
+            };
+
+            // 以查询账户的身份读取组，用户本身不一定有读取组的权限
+            let groups = match &self.config.group_base_dn {
+                Some(_) => Some(self.groups_of(&mut ldap, &entry.dn).await?),
+                None => None,
+            };
+
+            let bind = ldap.simple_bind(&entry.dn, password).await?;
+            ldap.unbind().await.ok();
+            if bind.rc == RC_INVALID_CREDENTIALS {
+                return Ok(None);
+            }
+            bind.success()?;
+
+            Ok(self.directory_user(entry, groups))
+        })
+        .await
+    }
+
+    /// 目录中的所有用户，用于定期同步
+    pub async fn list_users(&self) -> Result<Vec<DirectoryUser>, LdapError> {
+        self.with_timeout(async {
+            let mut ldap = self.connect().await?;
+
+            let memberships = match &self.config.group_base_dn {
+                Some(group_base_dn) => Some(self.group_memberships(&mut ldap, group_base_dn).await?),
+                None => None,
+            };
+
+            let entries = Self::paged_search(
+                &mut ldap,
+                &self.config.user_base_dn,
+                &self.config.user_filter,
+                self.user_attributes(),
+            )
+            .await?;
+            ldap.unbind().await.ok();
+
+            Ok(entries
+                .into_iter()
+                .filter_map(|entry| {
+                    let groups = memberships
+                        .as_ref()
+                        .map(|m| m.get(&normalize_dn(&entry.dn)).cloned().unwrap_or_default());
+                    self.directory_user(entry, groups)
+                })
+                .collect())
+        })
+        .await
+    }
+
+    async fn with_timeout<T>(&self, fut: impl Future<Output = Result<T, LdapError>>) -> Result<T, LdapError> {
+        tokio::time::timeout(self.config.timeout, fut)
+            .await
+            .map_err(|_| LdapError::Timeout)?
+    }
+
+    /// 建立连接，配置了查询账户时先绑定
+    async fn connect(&self) -> Result<Ldap, LdapError> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.config.timeout)
+            .set_starttls(self.config.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.config.url).await?;
+        ldap3::drive!(conn);
+
+        if let Some(bind_dn) = &self.config.bind_dn {
+            ldap.simple_bind(bind_dn, self.config.bind_password.as_deref().unwrap_or_default())
+                .await?
+                .success()?;
+        }
+
+        Ok(ldap)
+    }
+
+    fn user_attributes(&self) -> Vec<String> {
+        vec![
+            self.config.username_attribute.clone(),
+            self.config.email_attribute.clone(),
+            self.config.member_of_attribute.clone(),
+        ]
+    }
+
+    async fn groups_of(&self, ldap: &mut Ldap, user_dn: &str) -> Result<Vec<String>, LdapError> {
+        let Some(group_base_dn) = &self.config.group_base_dn else {
+            return Ok(Vec::new());
+        };
+
+        let filter = format!(
+            "(&{}({}={}))",
+            self.config.group_filter,
+            self.config.group_member_attribute,
+            ldap_escape(user_dn)
+        );
+        let (entries, _) = ldap
+            .search(group_base_dn, Scope::Subtree, &filter, vec!["1.1"])
+            .await?
+            .success()?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| normalize_dn(&SearchEntry::construct(entry).dn))
+            .collect())
+    }
+
+    /// 所有组的成员关系：规范化的成员DN -> 所属组DN
+    async fn group_memberships(
+        &self,
+        ldap: &mut Ldap,
+        group_base_dn: &str,
+    ) -> Result<HashMap<String, Vec<String>>, LdapError> {
+        let groups = Self::paged_search(
+            ldap,
+            group_base_dn,
+            &self.config.group_filter,
+            vec![self.config.group_member_attribute.clone()],
+        )
+        .await?;
+
+        let mut memberships: HashMap<String, Vec<String>> = HashMap::new();
+        for group in groups {
+            let group_dn = normalize_dn(&group.dn);
+            for member in attribute_values(&group, &self.config.group_member_attribute) {
+                memberships.entry(normalize_dn(member)).or_default().push(group_dn.clone());
+            }
+        }
+
+        Ok(memberships)
+    }
+
+    async fn paged_search(
+        ldap: &mut Ldap,
+        base: &str,
+        filter: &str,
+        attributes: Vec<String>,
+    ) -> Result<Vec<SearchEntry>, LdapError> {
+        let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+            Box::new(EntriesOnly::new()),
+            Box::new(PagedResults::new(PAGE_SIZE)),
+        ];
+        let mut stream = ldap
+            .streaming_search_with(adapters, base, Scope::Subtree, filter, attributes)
+            .await?;
+
+        let mut entries = Vec::new();
+        while let Some(entry) = stream.next().await? {
+            entries.push(SearchEntry::construct(entry));
+        }
+        stream.finish().await.success()?;
+
+        Ok(entries)
+    }
+
+    /// 转换为目录用户，没有用户名属性的条目会被忽略
+    fn directory_user(&self, entry: SearchEntry, groups: Option<Vec<String>>) -> Option<DirectoryUser> {
+        let username = attribute_values(&entry, &self.config.username_attribute).first()?.to_string();
+        let email = attribute_values(&entry, &self.config.email_attribute)
+            .first()
+            .map(|email| email.to_string());
+        let groups = groups.unwrap_or_else(|| {
+            attribute_values(&entry, &self.config.member_of_attribute)
+                .iter()
+                .map(|dn| normalize_dn(dn))
+                .collect()
+        });
+
+        Some(DirectoryUser {
+            dn: entry.dn,
+            username,
+            email,
+            groups,
+        })
+    }
+}
+
+/// 属性名不区分大小写
+fn attribute_values<'a>(entry: &'a SearchEntry, name: &str) -> &'a [String] {
+    entry
+        .attrs
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, values)| values.as_slice())
+        .unwrap_or_default()
+}
+
+static DIRECTORY: OnceLock<Option<LdapDirectory>> = OnceLock::new();
+
+/// 获取配置的目录服务，未启用LDAP时为 None
+///
+/// 配置无效时服务在启动时就会拒绝启动，这里不再报错。
+pub fn directory() -> Option<&'static LdapDirectory> {
+    DIRECTORY
+        .get_or_init(|| LdapConfig::from_env().ok().flatten().map(LdapDirectory::new))
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(url: &str) -> LdapConfig {
+        LdapConfig {
+            url: url.to_string(),
+            starttls: false,
+            bind_dn: Some(stand_in::ADMIN_DN.to_string()),
+            bind_password: Some(stand_in::ADMIN_PASSWORD.to_string()),
+            user_base_dn: "ou=users,dc=example,dc=org".to_string(),
+            user_filter: "(objectClass=inetOrgPerson)".to_string(),
+            username_attribute: "uid".to_string(),
+            email_attribute: "mail".to_string(),
+            member_of_attribute: "memberOf".to_string(),
+            group_base_dn: None,
+            group_filter: "(objectClass=groupOfNames)".to_string(),
+            group_member_attribute: "member".to_string(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_normalize_dn() {
+        assert_eq!(
+            normalize_dn("CN=Admins, OU=Groups,DC=Example, DC=org"),
+            "cn=admins,ou=groups,dc=example,dc=org"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_against_stand_in() {
+        let server = stand_in::LdapStandIn::start().await;
+        let directory = LdapDirectory::new(config(&server.url));
+
+        let alice = directory
+            .authenticate("alice", "alice-password")
+            .await
+            .expect("目录服务请求失败")
+            .expect("应认证成功");
+        assert_eq!(alice.dn, "uid=alice,ou=users,dc=example,dc=org");
+        assert_eq!(alice.username, "alice");
+        assert_eq!(alice.email.as_deref(), Some("alice@example.org"));
+        assert_eq!(alice.groups, vec!["cn=admins,ou=groups,dc=example,dc=org".to_string()]);
+
+        // 密码错误、用户不存在、空密码
+        assert!(directory.authenticate("alice", "wrong").await.unwrap().is_none());
+        assert!(directory.authenticate("nobody", "alice-password").await.unwrap().is_none());
+        assert!(directory.authenticate("alice", "").await.unwrap().is_none());
+
+        // 过滤条件中的特殊字符会被转义，不能用通配符匹配其他用户
+        assert!(directory.authenticate("a*", "alice-password").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_groups_from_group_entries() {
+        let server = stand_in::LdapStandIn::start().await;
+        let directory = LdapDirectory::new(LdapConfig {
+            group_base_dn: Some("ou=groups,dc=example,dc=org".to_string()),
+            ..config(&server.url)
+        });
+
+        let bob = directory
+            .authenticate("bob", "bob-password")
+            .await
+            .unwrap()
+            .expect("应认证成功");
+        assert_eq!(bob.groups, vec!["cn=developers,ou=groups,dc=example,dc=org".to_string()]);
+
+        let mut users = directory.list_users().await.expect("目录服务请求失败");
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        let summary: Vec<(&str, usize)> = users.iter().map(|u| (u.username.as_str(), u.groups.len())).collect();
+        assert_eq!(summary, vec![("alice", 1), ("bob", 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_with_member_of() {
+        let server = stand_in::LdapStandIn::start().await;
+        let directory = LdapDirectory::new(config(&server.url));
+
+        let users = directory.list_users().await.expect("目录服务请求失败");
+        let alice = users.iter().find(|u| u.username == "alice").expect("应包含alice");
+        assert_eq!(alice.groups, vec!["cn=admins,ou=groups,dc=example,dc=org".to_string()]);
+        // 组条目不是用户
+        assert_eq!(users.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_service_account_password() {
+        let server = stand_in::LdapStandIn::start().await;
+        let directory = LdapDirectory::new(LdapConfig {
+            bind_password: Some("wrong".to_string()),
+            ..config(&server.url)
+        });
+
+        assert!(matches!(
+            directory.authenticate("alice", "alice-password").await,
+            Err(LdapError::Ldap(_))
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+//! 测试用的进程内LDAP服务器，只实现简单绑定、查询和解绑，数据为固定的示例目录
+
+use bytes::BytesMut;
+use lber::common::TagClass;
+use lber::parse::parse_tag;
+use lber::structure::{StructureTag, PL};
+use lber::write::encode_into;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::normalize_dn;
+
+pub const ADMIN_DN: &str = "cn=admin,dc=example,dc=org";
+pub const ADMIN_PASSWORD: &str = "admin-password";
+
+// 协议操作的标签号（RFC 4511）
+const OP_BIND_REQUEST: u64 = 0;
+const OP_BIND_RESPONSE: u64 = 1;
+const OP_UNBIND_REQUEST: u64 = 2;
+const OP_SEARCH_REQUEST: u64 = 3;
+const OP_SEARCH_RESULT_ENTRY: u64 = 4;
+const OP_SEARCH_RESULT_DONE: u64 = 5;
+
+const RC_SUCCESS: u8 = 0;
+const RC_PROTOCOL_ERROR: u8 = 2;
+const RC_INVALID_CREDENTIALS: u8 = 49;
+
+struct Entry {
+    dn: &'static str,
+    password: Option<&'static str>,
+    attributes: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+impl Entry {
+    fn values(&self, name: &str) -> &[&'static str] {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+fn directory() -> Vec<Entry> {
+    vec![
+        Entry {
+            dn: ADMIN_DN,
+            password: Some(ADMIN_PASSWORD),
+            attributes: vec![("objectClass", vec!["organizationalRole"]), ("cn", vec!["admin"])],
+        },
+        Entry {
+            dn: "uid=alice,ou=users,dc=example,dc=org",
+            password: Some("alice-password"),
+            attributes: vec![
+                ("objectClass", vec!["top", "inetOrgPerson"]),
+                ("uid", vec!["alice"]),
+                ("mail", vec!["alice@example.org"]),
+                ("memberOf", vec!["cn=admins,ou=groups,dc=example,dc=org"]),
+            ],
+        },
+        Entry {
+            dn: "uid=bob,ou=users,dc=example,dc=org",
+            password: Some("bob-password"),
+            attributes: vec![
+                ("objectClass", vec!["top", "inetOrgPerson"]),
+                ("uid", vec!["bob"]),
+                ("mail", vec!["bob@example.org"]),
+                ("memberOf", vec!["CN=Developers, OU=Groups,DC=example,DC=org"]),
+            ],
+        },
+        Entry {
+            dn: "cn=admins,ou=groups,dc=example,dc=org",
+            password: None,
+            attributes: vec![
+                ("objectClass", vec!["top", "groupOfNames"]),
+                ("member", vec!["uid=alice,ou=users,dc=example,dc=org"]),
+            ],
+        },
+        Entry {
+            dn: "cn=developers,ou=groups,dc=example,dc=org",
+            password: None,
+            attributes: vec![
+                ("objectClass", vec!["top", "groupOfNames"]),
+                ("member", vec!["UID=bob, OU=users,dc=example,dc=org"]),
+            ],
+        },
+    ]
+}
+
+pub struct LdapStandIn {
+    pub url: String,
+}
+
+impl LdapStandIn {
+    /// 在随机端口上启动
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("绑定端口失败");
+        let url = format!("ldap://{}", listener.local_addr().expect("获取端口失败"));
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream));
+            }
+        });
+
+        Self { url }
+    }
+}
+
+async fn serve(mut stream: TcpStream) {
+    let entries = directory();
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let (consumed, message) = match parse_tag(&buffer) {
+            Ok((rest, message)) => (buffer.len() - rest.len(), message),
+            Err(lber::Err::Incomplete(_)) => match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => {
+                    buffer.extend_from_slice(&chunk[..n]);
+                    continue;
+                }
+            },
+            Err(_) => return,
+        };
+        buffer.drain(..consumed);
+
+        let Some(mut parts) = message.expect_constructed() else {
+            return;
+        };
+        if parts.len() < 2 {
+            return;
+        }
+        let message_id = parts.remove(0);
+        let op = parts.remove(0);
+
+        let responses = match (op.class, op.id) {
+            (TagClass::Application, OP_BIND_REQUEST) => vec![bind(&entries, op)],
+            (TagClass::Application, OP_SEARCH_REQUEST) => search(&entries, op),
+            (TagClass::Application, OP_UNBIND_REQUEST) => return,
+            _ => return,
+        };
+
+        let mut out = BytesMut::new();
+        for response in responses {
+            let message = constructed(TagClass::Universal, 16, vec![message_id.clone(), response]);
+            encode_into(&mut out, message).expect("编码失败");
+        }
+        if stream.write_all(&out).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn bind(entries: &[Entry], op: StructureTag) -> StructureTag {
+    let parts = op.expect_constructed().unwrap_or_default();
+    let (Some(name), Some(password)) = (parts.get(1).and_then(text), parts.get(2).and_then(text)) else {
+        return ldap_result(OP_BIND_RESPONSE, RC_PROTOCOL_ERROR);
+    };
+
+    // 匿名绑定
+    if name.is_empty() && password.is_empty() {
+        return ldap_result(OP_BIND_RESPONSE, RC_SUCCESS);
+    }
+
+    let valid = entries.iter().any(|entry| {
+        normalize_dn(entry.dn) == normalize_dn(&name) && entry.password.is_some_and(|p| p == password)
+    });
+    let rc = if valid { RC_SUCCESS } else { RC_INVALID_CREDENTIALS };
+    ldap_result(OP_BIND_RESPONSE, rc)
+}
+
+fn search(entries: &[Entry], op: StructureTag) -> Vec<StructureTag> {
+    let parts = op.expect_constructed().unwrap_or_default();
+    if parts.len() < 8 {
+        return vec![ldap_result(OP_SEARCH_RESULT_DONE, RC_PROTOCOL_ERROR)];
+    }
+
+    let base = normalize_dn(&text(&parts[0]).unwrap_or_default());
+    let filter = &parts[6];
+    let requested: Vec<String> = parts[7]
+        .clone()
+        .expect_constructed()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(text)
+        .collect();
+
+    let mut responses: Vec<StructureTag> = entries
+        .iter()
+        .filter(|entry| normalize_dn(entry.dn).ends_with(&base) && matches(entry, filter))
+        .map(|entry| {
+            let attributes = entry
+                .attributes
+                .iter()
+                .filter(|(key, _)| {
+                    requested.is_empty()
+                        || requested.iter().any(|r| r == "*" || r.eq_ignore_ascii_case(key))
+                })
+                .map(|(key, values)| {
+                    let values = values.iter().map(|v| octet_string(v)).collect();
+                    constructed(
+                        TagClass::Universal,
+                        16,
+                        vec![octet_string(key), constructed(TagClass::Universal, 17, values)],
+                    )
+                })
+                .collect();
+
+            constructed(
+                TagClass::Application,
+                OP_SEARCH_RESULT_ENTRY,
+                vec![octet_string(entry.dn), constructed(TagClass::Universal, 16, attributes)],
+            )
+        })
+        .collect();
+
+    responses.push(ldap_result(OP_SEARCH_RESULT_DONE, RC_SUCCESS));
+    responses
+}
+
+/// 只支持与、或、非、相等和存在性过滤条件，相等比较不区分大小写
+fn matches(entry: &Entry, filter: &StructureTag) -> bool {
+    let children = || match &filter.payload {
+        PL::C(children) => children.clone(),
+        PL::P(_) => Vec::new(),
+    };
+
+    match (filter.class, filter.id) {
+        (TagClass::Context, 0) => children().iter().all(|f| matches(entry, f)),
+        (TagClass::Context, 1) => children().iter().any(|f| matches(entry, f)),
+        (TagClass::Context, 2) => !children().first().is_some_and(|f| matches(entry, f)),
+        (TagClass::Context, 3) => {
+            let children = children();
+            let (Some(attribute), Some(value)) = (children.first().and_then(text), children.get(1).and_then(text))
+            else {
+                return false;
+            };
+            entry
+                .values(&attribute)
+                .iter()
+                .any(|v| normalize_dn(v) == normalize_dn(&value))
+        }
+        (TagClass::Context, 7) => match &filter.payload {
+            PL::P(attribute) => !entry.values(&String::from_utf8_lossy(attribute)).is_empty(),
+            PL::C(_) => false,
+        },
+        _ => false,
+    }
+}
+
+fn text(tag: &StructureTag) -> Option<String> {
+    match &tag.payload {
+        PL::P(bytes) => String::from_utf8(bytes.clone()).ok(),
+        PL::C(_) => None,
+    }
+}
+
+fn octet_string(value: &str) -> StructureTag {
+    StructureTag {
+        class: TagClass::Universal,
+        id: 4,
+        payload: PL::P(value.as_bytes().to_vec()),
+    }
+}
+
+fn constructed(class: TagClass, id: u64, children: Vec<StructureTag>) -> StructureTag {
+    StructureTag {
+        class,
+        id,
+        payload: PL::C(children),
+    }
+}
+
+fn ldap_result(op: u64, rc: u8) -> StructureTag {
+    let enumerated = StructureTag {
+        class: TagClass::Universal,
+        id: 10,
+        payload: PL::P(vec![rc]),
+    };
+    constructed(TagClass::Application, op, vec![enumerated, octet_string(""), octet_string("")])
+}
@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum MailError {
+    #[error("邮件地址无效: {0}")]
+    InvalidAddress(String),
+    #[error("构建邮件失败: {0}")]
+    BuildError(#[from] lettre::error::Error),
+    #[error("SMTP发送失败: {0}")]
+    SmtpError(#[from] lettre::transport::smtp::Error),
+    #[error("写入邮件文件失败: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// 邮件发送方式，通过 MAIL_TRANSPORT 环境变量选择
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError>;
+}
+
+/// 通过SMTP服务器发送
+pub struct SmtpMailTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailTransport {
+    /// 从环境变量读取SMTP配置
+    ///
+    /// SMTP_TLS 可选 none（明文，用于本地测试用的SMTP服务器）、starttls（默认）、tls。
+    pub fn from_env() -> Result<Self, MailError> {
+        let host = env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let tls = env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+
+        let mut builder = match tls.as_str() {
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+        };
+
+        if let Some(port) = env::var("SMTP_PORT").ok().and_then(|v| v.parse::<u16>().ok()) {
+            builder = builder.port(port);
+        }
+
+        if let Ok(username) = env::var("SMTP_USERNAME") {
+            let password = env::var("SMTP_PASSWORD").unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: sender()?,
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpMailTransport {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError> {
+        let email = build_message(&self.from, message)?;
+        self.transport.send(email).await?;
+        Ok(())
+    }
+}
+
+/// 将邮件写入目录中的 .eml 文件，用于本地开发和测试
+pub struct FileMailTransport {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, MailError> {
+        Ok(Self {
+            dir: dir.into(),
+            from: sender()?,
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileMailTransport {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError> {
+        let email = build_message(&self.from, message)?;
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!("{}.eml", Uuid::new_v4()));
+        tokio::fs::write(&path, email.formatted()).await?;
+
+        tracing::info!("邮件已写入 {}", path.display());
+        Ok(())
+    }
+}
+
+/// 只把邮件内容打印到日志，不实际发送（默认）
+pub struct LogMailTransport;
+
+#[async_trait]
+impl MailTransport for LogMailTransport {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError> {
+        tracing::info!(
+            "邮件未实际发送 (MAIL_TRANSPORT=log)\n收件人: {}\n主题: {}\n\n{}",
+            message.to,
+            message.subject,
+            message.body
+        );
+        Ok(())
+    }
+}
+
+static MAILER: OnceLock<Arc<dyn MailTransport>> = OnceLock::new();
+
+/// 获取全局邮件发送器
+pub fn mailer() -> Arc<dyn MailTransport> {
+    MAILER.get_or_init(transport_from_env).clone()
+}
+
+fn transport_from_env() -> Arc<dyn MailTransport> {
+    let kind = env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "log".to_string());
+
+    let transport: Result<Arc<dyn MailTransport>, MailError> = match kind.as_str() {
+        "smtp" => SmtpMailTransport::from_env().map(|t| Arc::new(t) as Arc<dyn MailTransport>),
+        "file" => {
+            let dir = env::var("MAIL_FILE_DIR").unwrap_or_else(|_| "./mail".to_string());
+            FileMailTransport::new(dir).map(|t| Arc::new(t) as Arc<dyn MailTransport>)
+        }
+        _ => Ok(Arc::new(LogMailTransport)),
+    };
+
+    transport.unwrap_or_else(|e| {
+        tracing::error!("邮件发送配置无效，改为只记录日志: {}", e);
+        Arc::new(LogMailTransport)
+    })
+}
+
+/// 在后台发送邮件，发送失败只记录日志
+///
+/// 接口不等待发送完成，响应时间不因邮箱是否存在而不同。
+pub fn send_in_background(message: MailMessage) {
+    tokio::spawn(async move {
+        if let Err(e) = mailer().send(&message).await {
+            tracing::error!("发送邮件「{}」失败: {}", message.subject, e);
+        }
+    });
+}
+
+/// 生成指向前端页面的链接，前端地址由 APP_BASE_URL 配置
+pub fn app_link(path: &str, token: &str) -> String {
+    let base_url = env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:4200".to_string());
+    format!("{}{}?token={}", base_url.trim_end_matches('/'), path, token)
+}
+
+fn sender() -> Result<Mailbox, MailError> {
+    let from = env::var("MAIL_FROM").unwrap_or_else(|_| "Web Admin <noreply@example.com>".to_string());
+    from.parse().map_err(|_| MailError::InvalidAddress(from))
+}
+
+fn build_message(from: &Mailbox, message: &MailMessage) -> Result<Message, MailError> {
+    let to: Mailbox = message
+        .to
+        .parse()
+        .map_err(|_| MailError::InvalidAddress(message.to.clone()))?;
+
+    let email = Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(message.subject.clone())
+        .header(ContentType::TEXT_PLAIN)
+        .body(message.body.clone())?;
+
+    Ok(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_message() -> MailMessage {
+        MailMessage {
+            to: "user@example.com".to_string(),
+            subject: "重置密码".to_string(),
+            body: "请点击链接重置密码".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_message_business_rules() {
+        let from: Mailbox = "Web Admin <noreply@example.com>".parse().unwrap();
+
+        let email = build_message(&from, &test_message()).unwrap();
+        let formatted = String::from_utf8(email.formatted()).unwrap();
+        assert!(formatted.contains("To: user@example.com"));
+        assert!(formatted.contains("noreply@example.com"));
+
+        // 无效的收件人地址直接报错
+        let invalid = MailMessage {
+            to: "not-an-email".to_string(),
+            ..test_message()
+        };
+        assert!(matches!(
+            build_message(&from, &invalid),
+            Err(MailError::InvalidAddress(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_transport_writes_eml() {
+        let dir = env::temp_dir().join(format!("web-admin-mail-{}", Uuid::new_v4()));
+        let transport = FileMailTransport::new(&dir).unwrap();
+
+        transport.send(&test_message()).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let content = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(content.contains("To: user@example.com"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_log_transport_never_fails() {
+        assert!(LogMailTransport.send(&test_message()).await.is_ok());
+    }
+}
@@ -1,8 +1,15 @@
 mod auth;
+mod client_ip;
 mod database;
 mod extractors;
+mod jwt_keys;
+mod ldap;
+mod mail;
 mod middleware;
 mod models;
+mod oidc;
+mod password_hash;
+mod permission_cache;
 mod rbac;
 mod routes;
 mod services;
@@ -13,6 +20,7 @@ use axum::{
     routing::Router,
 };
 use std::net::SocketAddr;
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use tokio::net::TcpListener;
@@ -20,7 +28,10 @@ use tokio::net::TcpListener;
 use crate::{
     database::establish_connection,
     middleware::auth_middleware,
-    routes::{auth_routes, user_routes, role_routes, permission_routes, department_routes, user_department_routes},
+    ldap::LdapConfig,
+    password_hash::Argon2Config,
+    routes::{auth_routes, user_routes, role_routes, permission_routes, department_routes, user_department_routes, login_log_routes, registration_code_routes, well_known_routes, api_key_routes, service_account_routes, ldap_routes, oauth_client_routes, oauth_routes},
+    services::{JwtKeyService, LdapService, PasswordPolicyService, RegistrationPolicy, RevocationService, KEY_REFRESH_INTERVAL},
 };
 
 #[tokio::main]
@@ -41,6 +52,83 @@ async fn main() {
         .await
         .expect("数据库连接失败");
 
+    // 加载JWT签名密钥，没有安全的密钥时拒绝启动
+    if let Err(e) = JwtKeyService::init(&db).await {
+        panic!("JWT签名密钥配置无效: {}", e);
+    }
+
+    // 检查注册策略，拼写错误的配置不能被当作开放注册
+    if let Err(e) = RegistrationPolicy::from_env() {
+        panic!("{}", e);
+    }
+
+    // 检查密码哈希参数，并提示仍在使用旧哈希的账户数量
+    if let Err(e) = Argon2Config::from_env().params() {
+        panic!("密码哈希配置无效: {}", e);
+    }
+    match PasswordPolicyService::hash_stats(&db).await {
+        Ok(stats) if stats.bcrypt + stats.argon2id_outdated > 0 => tracing::info!(
+            "{} 个账户仍使用 bcrypt 密码哈希，{} 个账户的 Argon2id 参数与当前配置不同，将在下次登录时自动升级",
+            stats.bcrypt, stats.argon2id_outdated
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("统计密码哈希失败: {}", e),
+    }
+
+    // 定期轮换签名密钥，并加载其他实例生成的新密钥
+    let key_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(KEY_REFRESH_INTERVAL));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = JwtKeyService::refresh(&key_db).await {
+                tracing::warn!("刷新JWT签名密钥失败: {}", e);
+            }
+        }
+    });
+
+    // 接收权限变更通知，使本实例的权限缓存失效
+    tokio::spawn(permission_cache::listen());
+
+    // 定期清理已过期的令牌撤销记录
+    let cleanup_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = RevocationService::purge_expired(&cleanup_db).await {
+                tracing::warn!("清理令牌撤销记录失败: {}", e);
+            }
+        }
+    });
+
+    // 检查LDAP配置，启用后定期同步目录用户
+    let ldap_config = match LdapConfig::from_env() {
+        Ok(config) => config,
+        Err(e) => panic!("无法启用LDAP认证: {}", e),
+    };
+    let ldap_sync_interval = std::env::var("LDAP_SYNC_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(3600);
+    if ldap_config.is_some() && ldap_sync_interval > 0 {
+        let ldap_db = db.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(ldap_sync_interval));
+            loop {
+                interval.tick().await;
+                match LdapService::sync(&ldap_db).await {
+                    Ok(report) => tracing::info!(
+                        "目录同步完成: 目录用户 {}，更新 {}，停用 {}",
+                        report.directory_users, report.updated, report.deactivated
+                    ),
+                    Err(e) => tracing::warn!("目录同步失败: {}", e),
+                }
+            }
+        });
+    }
+
     // 设置CORS
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -49,6 +137,7 @@ async fn main() {
 
     // 创建应用路由
     let app = Router::new()
+        .nest("/.well-known", well_known_routes())
         .nest("/api/auth", auth_routes())
         .nest("/api/users",
             user_routes()
@@ -70,6 +159,31 @@ async fn main() {
             user_department_routes()
                 .layer(from_fn(auth_middleware))
         )
+        .nest("/api/login-logs",
+            login_log_routes()
+                .layer(from_fn(auth_middleware))
+        )
+        .nest("/api/registration-codes",
+            registration_code_routes()
+                .layer(from_fn(auth_middleware))
+        )
+        .nest("/api/api-keys",
+            api_key_routes()
+                .layer(from_fn(auth_middleware))
+        )
+        .nest("/api/service-accounts",
+            service_account_routes()
+                .layer(from_fn(auth_middleware))
+        )
+        .nest("/api/ldap",
+            ldap_routes()
+                .layer(from_fn(auth_middleware))
+        )
+        .nest("/api/oauth-clients",
+            oauth_client_routes()
+                .layer(from_fn(auth_middleware))
+        )
+        .nest("/api/oauth", oauth_routes())
         .layer(cors)
         .with_state(db);
 
@@ -78,7 +192,7 @@ async fn main() {
     tracing::info!("服务器启动在 http://{}", addr);
 
     let listener = TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
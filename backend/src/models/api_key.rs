@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "api_keys")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub permissions: Option<Vec<String>>,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub last_used_at: Option<DateTimeWithTimeZone>,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateApiKeyDto {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// 限定的权限（如 "user:read"），不提供时与所有者的权限相同
+    #[validate(length(min = 1))]
+    pub permissions: Option<Vec<String>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_create_api_key_dto_validation() {
+        let valid_dto = CreateApiKeyDto {
+            name: "部署脚本".to_string(),
+            permissions: Some(vec!["user:read".to_string()]),
+            expires_at: None,
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 不限定权限
+        let unscoped_dto = CreateApiKeyDto {
+            name: "部署脚本".to_string(),
+            permissions: None,
+            expires_at: None,
+        };
+        assert!(unscoped_dto.validate().is_ok());
+
+        let empty_name_dto = CreateApiKeyDto {
+            name: "".to_string(),
+            permissions: None,
+            expires_at: None,
+        };
+        assert!(empty_name_dto.validate().is_err());
+
+        // 限定为空的权限列表没有意义
+        let empty_permissions_dto = CreateApiKeyDto {
+            name: "部署脚本".to_string(),
+            permissions: Some(Vec::new()),
+            expires_at: None,
+        };
+        assert!(empty_permissions_dto.validate().is_err());
+    }
+}
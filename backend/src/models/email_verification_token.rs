@@ -0,0 +1,79 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "email_verification_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTimeWithTimeZone,
+    pub used_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct VerifyEmailDto {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResendVerificationDto {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_verify_email_dto_validation() {
+        let valid_dto = VerifyEmailDto {
+            token: "a".repeat(64),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let empty_dto = VerifyEmailDto {
+            token: "".to_string(),
+        };
+        assert!(empty_dto.validate().is_err());
+    }
+
+    #[test]
+    fn test_resend_verification_dto_validation() {
+        let valid_dto = ResendVerificationDto {
+            email: "user@example.com".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let invalid_dto = ResendVerificationDto {
+            email: "not-an-email".to_string(),
+        };
+        assert!(invalid_dto.validate().is_err());
+    }
+}
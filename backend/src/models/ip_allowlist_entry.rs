@@ -0,0 +1,85 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "ip_allowlist_entries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// 所属角色，与 user_id 二选一
+    pub role_id: Option<i32>,
+    /// 所属用户，与 role_id 二选一
+    pub user_id: Option<i32>,
+    pub cidr: String,
+    pub description: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::role::Entity",
+        from = "Column::RoleId",
+        to = "super::role::Column::Id"
+    )]
+    Role,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateIpAllowlistEntryDto {
+    /// CIDR或单个IP地址，如 192.168.1.0/24、10.0.0.8、2001:db8::/32
+    #[validate(length(min = 1, max = 50))]
+    pub cidr: String,
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_create_ip_allowlist_entry_dto_validation() {
+        let valid_dto = CreateIpAllowlistEntryDto {
+            cidr: "192.168.1.0/24".to_string(),
+            description: Some("办公室网络".to_string()),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let empty_dto = CreateIpAllowlistEntryDto {
+            cidr: "".to_string(),
+            description: None,
+        };
+        assert!(empty_dto.validate().is_err());
+
+        let long_description = CreateIpAllowlistEntryDto {
+            cidr: "10.0.0.0/8".to_string(),
+            description: Some("a".repeat(256)),
+        };
+        assert!(long_description.validate().is_err());
+    }
+}
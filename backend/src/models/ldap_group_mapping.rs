@@ -0,0 +1,70 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "ldap_group_mappings")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub group_dn: String,
+    pub role_id: i32,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::role::Entity",
+        from = "Column::RoleId",
+        to = "super::role::Column::Id"
+    )]
+    Role,
+}
+
+impl Related<super::role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateLdapGroupMappingDto {
+    /// 目录组的DN，如 cn=admins,ou=groups,dc=example,dc=org
+    #[validate(length(min = 3, max = 500))]
+    pub group_dn: String,
+    pub role_id: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LdapGroupMappingResponse {
+    pub id: i32,
+    pub group_dn: String,
+    pub role_id: i32,
+    pub role_name: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_create_ldap_group_mapping_dto_validation() {
+        let valid_dto = CreateLdapGroupMappingDto {
+            group_dn: "cn=admins,ou=groups,dc=example,dc=org".to_string(),
+            role_id: 1,
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let empty_dn_dto = CreateLdapGroupMappingDto {
+            group_dn: "".to_string(),
+            role_id: 1,
+        };
+        assert!(empty_dn_dto.validate().is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "login_lockouts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub scope: String,
+    pub lock_key: String,
+    pub failed_count: i32,
+    pub window_started_at: DateTimeWithTimeZone,
+    pub locked_until: Option<DateTimeWithTimeZone>,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
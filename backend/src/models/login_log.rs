@@ -0,0 +1,84 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "login_logs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub event_type: String,
+    pub username: Option<String>,
+    pub user_id: Option<i32>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub success: bool,
+    pub failure_reason: Option<String>,
+    /// 模拟登录事件中实际操作的管理员
+    pub impersonator_id: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LoginLogFilter {
+    /// 用户名（模糊匹配）
+    pub username: Option<String>,
+    pub user_id: Option<i32>,
+    /// 模拟登录的管理员
+    pub impersonator_id: Option<i32>,
+    pub ip_address: Option<String>,
+    /// login、mfa 或 refresh
+    pub event_type: Option<String>,
+    pub success: Option<bool>,
+    pub start_time: Option<DateTimeWithTimeZone>,
+    pub end_time: Option<DateTimeWithTimeZone>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+
+    #[test]
+    fn test_login_log_filter_from_query_string() {
+        let uri: axum::http::Uri =
+            "/api/login-logs?page=2&username=admin&user_id=1&success=false&event_type=login&start_time=2024-01-01T00:00:00Z"
+                .parse()
+                .unwrap();
+        let Query(filter) = Query::<LoginLogFilter>::try_from_uri(&uri).unwrap();
+
+        assert_eq!(filter.username.as_deref(), Some("admin"));
+        assert_eq!(filter.user_id, Some(1));
+        assert_eq!(filter.success, Some(false));
+        assert_eq!(filter.event_type.as_deref(), Some("login"));
+        assert!(filter.start_time.is_some());
+        assert!(filter.end_time.is_none());
+        assert!(filter.ip_address.is_none());
+    }
+
+    #[test]
+    fn test_login_log_filter_empty_query() {
+        let uri: axum::http::Uri = "/api/login-logs".parse().unwrap();
+        let Query(filter) = Query::<LoginLogFilter>::try_from_uri(&uri).unwrap();
+        assert!(filter.username.is_none());
+        assert!(filter.success.is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "magic_link_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: DateTimeWithTimeZone,
+    pub used_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MagicLinkRequestDto {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MagicLinkLoginDto {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_magic_link_dto_validation() {
+        let valid_request = MagicLinkRequestDto {
+            email: "user@example.com".to_string(),
+        };
+        assert!(valid_request.validate().is_ok());
+
+        let invalid_request = MagicLinkRequestDto {
+            email: "not-an-email".to_string(),
+        };
+        assert!(invalid_request.validate().is_err());
+
+        let valid_login = MagicLinkLoginDto {
+            token: "a".repeat(64),
+        };
+        assert!(valid_login.validate().is_ok());
+
+        // 空令牌必须被拒绝
+        let empty_token = MagicLinkLoginDto {
+            token: "".to_string(),
+        };
+        assert!(empty_token.validate().is_err());
+    }
+}
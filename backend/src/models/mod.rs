@@ -5,13 +5,59 @@ pub mod user_role;
 pub mod role_permission;
 pub mod department;
 pub mod user_department;
+pub mod refresh_token;
+pub mod revoked_token;
+pub mod user_token_revocation;
+pub mod user_session;
+pub mod login_log;
+pub mod login_lockout;
+pub mod user_mfa;
+pub mod mfa_recovery_code;
+pub mod password_reset_token;
+pub mod magic_link_token;
+pub mod password_history;
+pub mod user_invitation;
+pub mod email_verification_token;
+pub mod registration_code;
+pub mod jwt_signing_key;
+pub mod api_key;
+pub mod service_account_secret;
+pub mod oidc_login_state;
+pub mod user_external_identity;
+pub mod ldap_group_mapping;
+pub mod oauth_client;
+pub mod oauth_authorization_code;
+pub mod oauth_consent;
+pub mod ip_allowlist_entry;
 pub mod common;
 
 pub use user::*;
 pub use role::*;
 pub use permission::*;
+#[allow(unused_imports)]
 pub use user_role::*;
+#[allow(unused_imports)]
 pub use role_permission::*;
+#[allow(unused_imports)]
 pub use department::*;
+#[allow(unused_imports)]
 pub use user_department::*;
+pub use refresh_token::*;
+pub use revoked_token::*;
+pub use user_session::*;
+pub use login_log::*;
+pub use user_mfa::*;
+pub use password_reset_token::*;
+pub use magic_link_token::*;
+pub use user_invitation::*;
+pub use email_verification_token::*;
+pub use registration_code::*;
+pub use api_key::*;
+pub use service_account_secret::*;
+pub use oidc_login_state::*;
+pub use ldap_group_mapping::*;
+pub use oauth_client::*;
+pub use oauth_authorization_code::*;
+pub use oauth_consent::*;
+pub use ip_allowlist_entry::*;
 pub use common::*;
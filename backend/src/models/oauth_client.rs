@@ -0,0 +1,128 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "oauth_clients")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub client_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub secret_prefix: Option<String>,
+    #[serde(skip_serializing)]
+    pub secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub service_account_id: Option<i32>,
+    pub skip_consent: bool,
+    pub is_active: bool,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::ServiceAccountId",
+        to = "super::user::Column::Id"
+    )]
+    ServiceAccount,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// 公开客户端没有客户端密钥
+    pub fn is_confidential(&self) -> bool {
+        self.secret_hash.is_some()
+    }
+
+    pub fn allows_grant(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|g| g == grant_type)
+    }
+}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateOAuthClientDto {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[validate(length(min = 1))]
+    pub allowed_scopes: Vec<String>,
+    #[validate(length(min = 1))]
+    pub grant_types: Vec<String>,
+    /// 是否为机密客户端（有客户端密钥），单页应用等公开客户端设为 false
+    #[serde(default = "default_confidential")]
+    pub confidential: bool,
+    pub service_account_id: Option<i32>,
+    #[serde(default)]
+    pub skip_consent: bool,
+}
+
+fn default_confidential() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateOAuthClientDto {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    pub redirect_uris: Option<Vec<String>>,
+    #[validate(length(min = 1))]
+    pub allowed_scopes: Option<Vec<String>>,
+    #[validate(length(min = 1))]
+    pub grant_types: Option<Vec<String>>,
+    pub service_account_id: Option<i32>,
+    pub skip_consent: Option<bool>,
+    pub is_active: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_create_oauth_client_dto_validation() {
+        let dto: CreateOAuthClientDto = serde_json::from_value(serde_json::json!({
+            "name": "工单系统",
+            "redirect_uris": ["https://tickets.example.com/callback"],
+            "allowed_scopes": ["openid", "profile"],
+            "grant_types": ["authorization_code"]
+        }))
+        .unwrap();
+        assert!(dto.validate().is_ok());
+        // 默认为机密客户端，需要用户确认授权
+        assert!(dto.confidential);
+        assert!(!dto.skip_consent);
+
+        let no_scopes_dto = CreateOAuthClientDto {
+            allowed_scopes: Vec::new(),
+            ..dto
+        };
+        assert!(no_scopes_dto.validate().is_err());
+
+        let update_dto = UpdateOAuthClientDto {
+            name: Some("".to_string()),
+            description: None,
+            redirect_uris: None,
+            allowed_scopes: None,
+            grant_types: None,
+            service_account_id: None,
+            skip_consent: None,
+            is_active: None,
+        };
+        assert!(update_dto.validate().is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "oidc_login_states")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub state_hash: String,
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub redirect_uri: String,
+    pub expires_at: DateTimeWithTimeZone,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+/// 身份提供方回调前端后，前端提交的授权码和 state
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct OidcCallbackDto {
+    #[validate(length(min = 1, max = 2048))]
+    pub code: String,
+    #[validate(length(min = 1, max = 256))]
+    pub state: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_oidc_callback_dto_validation() {
+        let valid_dto = OidcCallbackDto {
+            code: "authorization-code".to_string(),
+            state: "state".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let empty_code_dto = OidcCallbackDto {
+            code: "".to_string(),
+            state: "state".to_string(),
+        };
+        assert!(empty_code_dto.validate().is_err());
+
+        let empty_state_dto = OidcCallbackDto {
+            code: "authorization-code".to_string(),
+            state: "".to_string(),
+        };
+        assert!(empty_state_dto.validate().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "password_reset_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: DateTimeWithTimeZone,
+    pub used_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ForgotPasswordDto {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResetPasswordDto {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 6))]
+    pub new_password: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_forgot_password_dto_validation() {
+        let valid_dto = ForgotPasswordDto {
+            email: "user@example.com".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let invalid_dto = ForgotPasswordDto {
+            email: "not-an-email".to_string(),
+        };
+        assert!(invalid_dto.validate().is_err());
+    }
+
+    #[test]
+    fn test_reset_password_dto_validation() {
+        let valid_dto = ResetPasswordDto {
+            token: "a".repeat(64),
+            new_password: "new_password".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 空令牌必须被拒绝
+        let empty_token = ResetPasswordDto {
+            token: "".to_string(),
+            new_password: "new_password".to_string(),
+        };
+        assert!(empty_token.validate().is_err());
+
+        // 新密码与注册时的长度要求一致
+        let short_password = ResetPasswordDto {
+            token: "a".repeat(64),
+            new_password: "12345".to_string(),
+        };
+        assert!(short_password.validate().is_err());
+    }
+}
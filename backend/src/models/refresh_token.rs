@@ -0,0 +1,74 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "refresh_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub family_id: Uuid,
+    pub parent_id: Option<i32>,
+    pub expires_at: DateTimeWithTimeZone,
+    pub used_at: Option<DateTimeWithTimeZone>,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct RefreshTokenDto {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_refresh_token_dto_validation() {
+        let valid_dto = RefreshTokenDto {
+            refresh_token: "a".repeat(64),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 空令牌必须被拒绝
+        let empty_dto = RefreshTokenDto {
+            refresh_token: "".to_string(),
+        };
+        assert!(empty_dto.validate().is_err());
+    }
+
+    #[test]
+    fn test_refresh_token_dto_deserialization() {
+        let dto: RefreshTokenDto =
+            serde_json::from_str(r#"{"refresh_token":"opaque_token"}"#).unwrap();
+        assert_eq!(dto.refresh_token, "opaque_token");
+
+        // 缺少字段时反序列化失败
+        let result = serde_json::from_str::<RefreshTokenDto>("{}");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "registration_codes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub code_hash: String,
+    pub description: Option<String>,
+    pub max_uses: Option<i32>,
+    pub used_count: i32,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::CreatedBy",
+        to = "super::user::Column::Id"
+    )]
+    Creator,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateRegistrationCodeDto {
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    #[validate(range(min = 1))]
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_create_registration_code_dto_validation() {
+        let valid_dto = CreateRegistrationCodeDto {
+            description: Some("2024届校招".to_string()),
+            max_uses: Some(50),
+            expires_at: None,
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 不限次数、永不过期
+        let unlimited_dto = CreateRegistrationCodeDto {
+            description: None,
+            max_uses: None,
+            expires_at: None,
+        };
+        assert!(unlimited_dto.validate().is_ok());
+
+        let zero_uses_dto = CreateRegistrationCodeDto {
+            description: None,
+            max_uses: Some(0),
+            expires_at: None,
+        };
+        assert!(zero_uses_dto.validate().is_err());
+    }
+}
@@ -10,6 +10,8 @@ pub struct Model {
     pub name: String,
     pub description: Option<String>,
     pub is_active: bool,
+    pub mfa_required: bool,
+    pub magic_link_allowed: bool,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -50,6 +52,8 @@ pub struct RoleResponse {
     pub name: String,
     pub description: Option<String>,
     pub is_active: bool,
+    pub mfa_required: bool,
+    pub magic_link_allowed: bool,
     pub permissions: Vec<String>,
 }
 
@@ -141,6 +145,8 @@ mod tests {
             name: "admin".to_string(),
             description: Some("系统管理员".to_string()),
             is_active: true,
+            mfa_required: false,
+            magic_link_allowed: false,
             permissions: vec![
                 "users:read".to_string(),
                 "users:write".to_string(),
@@ -234,6 +240,8 @@ mod tests {
                 name: role_name.to_string(),
                 description: Some(format!("{}角色", role_name)),
                 is_active: true,
+                mfa_required: false,
+                magic_link_allowed: true,
                 permissions: permissions.iter().map(|p| p.to_string()).collect(),
             };
 
@@ -241,7 +249,7 @@ mod tests {
             match role_name {
                 "admin" => assert!(role_response.permissions.len() >= 3, "管理员应该有多个权限"),
                 "guest" => assert!(role_response.permissions.len() <= 2, "访客权限应该有限"),
-                _ => assert!(role_response.permissions.len() >= 1, "所有角色都应该有至少一个权限"),
+                _ => assert!(!role_response.permissions.is_empty(), "所有角色都应该有至少一个权限"),
             }
 
             // 业务验证：所有角色都应该有读取权限
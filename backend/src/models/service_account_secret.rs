@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "service_account_secrets")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub description: Option<String>,
+    pub secret_prefix: String,
+    #[serde(skip_serializing)]
+    pub secret_hash: String,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub last_used_at: Option<DateTimeWithTimeZone>,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateServiceAccountDto {
+    #[validate(length(min = 3, max = 50))]
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateServiceAccountSecretDto {
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 服务账户使用客户端凭据换取访问令牌（OAuth 2.0 client_credentials）
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ClientCredentialsDto {
+    #[validate(length(min = 1))]
+    pub grant_type: String,
+    #[validate(length(min = 1))]
+    pub client_id: String,
+    #[validate(length(min = 1))]
+    pub client_secret: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_service_account_dto_validation() {
+        let valid_dto = CreateServiceAccountDto {
+            username: "ci-deployer".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let short_dto = CreateServiceAccountDto {
+            username: "ci".to_string(),
+        };
+        assert!(short_dto.validate().is_err());
+
+        let secret_dto = CreateServiceAccountSecretDto {
+            description: Some("a".repeat(256)),
+            expires_at: None,
+        };
+        assert!(secret_dto.validate().is_err());
+    }
+
+    #[test]
+    fn test_client_credentials_dto_validation() {
+        let valid_dto = ClientCredentialsDto {
+            grant_type: "client_credentials".to_string(),
+            client_id: "ci-deployer".to_string(),
+            client_secret: "sas_secret".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        let missing_secret_dto = ClientCredentialsDto {
+            grant_type: "client_credentials".to_string(),
+            client_id: "ci-deployer".to_string(),
+            client_secret: "".to_string(),
+        };
+        assert!(missing_secret_dto.validate().is_err());
+    }
+}
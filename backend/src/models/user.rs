@@ -13,6 +13,11 @@ pub struct Model {
     pub is_active: bool,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub password_changed_at: DateTimeWithTimeZone,
+    pub must_change_password: bool,
+    pub email_verified_at: Option<DateTimeWithTimeZone>,
+    pub registration_code_id: Option<i32>,
+    pub is_service_account: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,6 +34,28 @@ impl Related<super::user_role::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+#[cfg(test)]
+impl Model {
+    /// 测试用的已激活普通用户，邮箱为 <用户名>@example.com、尚未验证；其他字段按需用结构体更新语法覆盖
+    pub fn for_test(id: i32, username: &str) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id,
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password_hash: "hash".to_string(),
+            is_active: true,
+            created_at: now.into(),
+            updated_at: now.into(),
+            password_changed_at: now.into(),
+            must_change_password: false,
+            email_verified_at: None,
+            registration_code_id: None,
+            is_service_account: false,
+        }
+    }
+}
+
 // 自定义邮箱验证函数
 fn validate_email_format(email: &str) -> Result<(), ValidationError> {
     // 检查是否包含连续的点号
@@ -60,6 +87,22 @@ pub struct CreateUserDto {
     pub email: String,
     #[validate(length(min = 6))]
     pub password: String,
+    /// 注册邀请码，REGISTRATION_MODE=invite_only 时必填
+    #[serde(default)]
+    pub invitation_code: Option<String>,
+}
+
+/// 管理员创建用户：提供初始密码，或不提供密码而向用户发送邀请链接
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct AdminCreateUserDto {
+    #[validate(length(min = 3, max = 50))]
+    pub username: String,
+    #[validate(custom = "validate_email_format")]
+    pub email: String,
+    #[validate(length(min = 6))]
+    pub password: Option<String>,
+    /// 使用初始密码时，是否要求首次登录修改密码，默认是
+    pub must_change_password: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -70,12 +113,50 @@ pub struct LoginDto {
     pub password: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateProfileDto {
+    #[validate(custom = "validate_email_format")]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordDto {
+    #[validate(length(min = 1))]
+    pub current_password: String,
+    #[validate(length(min = 6))]
+    pub new_password: String,
+}
+
+/// 用户列表按账户类型筛选，默认只列出自然人用户
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountTypeFilter {
+    #[default]
+    Human,
+    Service,
+    All,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserListQuery {
+    #[serde(default)]
+    pub account_type: AccountTypeFilter,
+}
+
+/// 转发认证要求的权限（resource:action），也可以通过 X-Required-Permission 请求头指定
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ForwardAuthQuery {
+    pub permission: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponse {
     pub id: i32,
     pub username: String,
     pub email: String,
     pub is_active: bool,
+    pub email_verified: bool,
+    pub is_service_account: bool,
     pub roles: Vec<String>,
     pub permissions: Vec<String>,
 }
@@ -92,6 +173,7 @@ mod tests {
             username: "testuser".to_string(),
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
+            invitation_code: None,
         };
         assert!(valid_dto.validate().is_ok());
 
@@ -100,6 +182,7 @@ mod tests {
             username: "ab".to_string(), // 少于3个字符
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
+            invitation_code: None,
         };
         assert!(invalid_username_dto.validate().is_err());
 
@@ -108,6 +191,7 @@ mod tests {
             username: "a".repeat(51), // 超过50个字符
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
+            invitation_code: None,
         };
         assert!(long_username_dto.validate().is_err());
 
@@ -116,6 +200,7 @@ mod tests {
             username: "testuser".to_string(),
             email: "invalid-email".to_string(),
             password: "password123".to_string(),
+            invitation_code: None,
         };
         assert!(invalid_email_dto.validate().is_err());
 
@@ -123,6 +208,7 @@ mod tests {
         let short_password_dto = CreateUserDto {
             username: "testuser".to_string(),
             email: "test@example.com".to_string(),
+            invitation_code: None,
             password: "12345".to_string(), // 少于6个字符
         };
         assert!(short_password_dto.validate().is_err());
@@ -132,6 +218,7 @@ mod tests {
             username: "testuser".to_string(),
             email: "".to_string(),
             password: "password123".to_string(),
+            invitation_code: None,
         };
         assert!(empty_email_dto.validate().is_err());
     }
@@ -167,6 +254,71 @@ mod tests {
         assert!(all_empty_dto.validate().is_err());
     }
 
+    #[test]
+    fn test_admin_create_user_dto_validation() {
+        // 提供初始密码
+        let with_password: AdminCreateUserDto = serde_json::from_str(
+            r#"{"username":"newuser","email":"new@example.com","password":"Secur3#Pass"}"#,
+        )
+        .unwrap();
+        assert!(with_password.validate().is_ok());
+        assert_eq!(with_password.must_change_password, None);
+
+        // 不提供密码表示发送邀请
+        let invitation: AdminCreateUserDto =
+            serde_json::from_str(r#"{"username":"newuser","email":"new@example.com"}"#).unwrap();
+        assert!(invitation.validate().is_ok());
+        assert!(invitation.password.is_none());
+
+        let short_password = AdminCreateUserDto {
+            username: "newuser".to_string(),
+            email: "new@example.com".to_string(),
+            password: Some("12345".to_string()),
+            must_change_password: None,
+        };
+        assert!(short_password.validate().is_err());
+    }
+
+    #[test]
+    fn test_update_profile_dto_validation() {
+        let valid_dto = UpdateProfileDto {
+            email: Some("new@example.com".to_string()),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 不修改任何字段也是合法的
+        let empty_dto = UpdateProfileDto { email: None };
+        assert!(empty_dto.validate().is_ok());
+
+        let invalid_email_dto = UpdateProfileDto {
+            email: Some("invalid-email".to_string()),
+        };
+        assert!(invalid_email_dto.validate().is_err());
+    }
+
+    #[test]
+    fn test_change_password_dto_validation() {
+        let valid_dto = ChangePasswordDto {
+            current_password: "old_password".to_string(),
+            new_password: "new_password".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 必须提供当前密码
+        let missing_current_dto = ChangePasswordDto {
+            current_password: "".to_string(),
+            new_password: "new_password".to_string(),
+        };
+        assert!(missing_current_dto.validate().is_err());
+
+        // 新密码与注册时的长度要求一致
+        let short_password_dto = ChangePasswordDto {
+            current_password: "old_password".to_string(),
+            new_password: "12345".to_string(),
+        };
+        assert!(short_password_dto.validate().is_err());
+    }
+
     #[test]
     fn test_user_model_fields() {
         let user = Model {
@@ -177,6 +329,11 @@ mod tests {
             is_active: true,
             created_at: chrono::Utc::now().into(),
             updated_at: chrono::Utc::now().into(),
+            password_changed_at: chrono::Utc::now().into(),
+            must_change_password: false,
+            email_verified_at: None,
+            registration_code_id: None,
+            is_service_account: false,
         };
 
         assert_eq!(user.id, 1);
@@ -193,6 +350,8 @@ mod tests {
             username: "testuser".to_string(),
             email: "test@example.com".to_string(),
             is_active: true,
+            email_verified: true,
+            is_service_account: false,
             roles: vec!["admin".to_string(), "user".to_string()],
             permissions: vec!["read".to_string(), "write".to_string()],
         };
@@ -228,6 +387,7 @@ mod tests {
                 username: "testuser".to_string(),
                 email: email.to_string(),
                 password: "password123".to_string(),
+                invitation_code: None,
             };
             
             if should_be_valid {
@@ -255,6 +415,7 @@ mod tests {
                 username: username.to_string(),
                 email: "test@example.com".to_string(),
                 password: "password123".to_string(),
+                invitation_code: None,
             };
             
             if should_be_valid {
@@ -279,6 +440,7 @@ mod tests {
                 username: "testuser".to_string(),
                 email: "test@example.com".to_string(),
                 password: password.to_string(),
+                invitation_code: None,
             };
             
             if should_be_valid {
@@ -0,0 +1,72 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user_invitations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub invited_by: Option<i32>,
+    pub expires_at: DateTimeWithTimeZone,
+    pub accepted_at: Option<DateTimeWithTimeZone>,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct AcceptInvitationDto {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 6))]
+    pub password: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_accept_invitation_dto_validation() {
+        let valid_dto = AcceptInvitationDto {
+            token: "a".repeat(64),
+            password: "Secur3#Pass".to_string(),
+        };
+        assert!(valid_dto.validate().is_ok());
+
+        // 空令牌必须被拒绝
+        let empty_token = AcceptInvitationDto {
+            token: "".to_string(),
+            password: "Secur3#Pass".to_string(),
+        };
+        assert!(empty_token.validate().is_err());
+
+        let short_password = AcceptInvitationDto {
+            token: "a".repeat(64),
+            password: "12345".to_string(),
+        };
+        assert!(short_password.validate().is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user_mfa")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: i32,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub enabled: bool,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTimeWithTimeZone,
+    pub enabled_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MfaCodeDto {
+    /// 6位TOTP验证码
+    #[validate(length(min = 6, max = 6))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MfaVerifyDto {
+    /// 登录接口返回的 mfa_token
+    #[validate(length(min = 1))]
+    pub mfa_token: String,
+    /// TOTP验证码或恢复码
+    #[validate(length(min = 6, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[test]
+    fn test_mfa_dto_validation() {
+        assert!(MfaCodeDto { code: "123456".to_string() }.validate().is_ok());
+        assert!(MfaCodeDto { code: "12345".to_string() }.validate().is_err());
+
+        // 恢复码比TOTP验证码长
+        let recovery = MfaVerifyDto {
+            mfa_token: "token".to_string(),
+            code: "abcd-efgh-2345".to_string(),
+        };
+        assert!(recovery.validate().is_ok());
+
+        let missing_token = MfaVerifyDto {
+            mfa_token: "".to_string(),
+            code: "123456".to_string(),
+        };
+        assert!(missing_token.validate().is_err());
+    }
+
+    #[test]
+    fn test_mfa_secret_is_never_serialized() {
+        let mfa = Model {
+            user_id: 1,
+            secret: "JBSWY3DPEHPK3PXP".to_string(),
+            enabled: true,
+            last_used_step: None,
+            created_at: chrono::Utc::now().into(),
+            enabled_at: None,
+        };
+
+        let json = serde_json::to_value(&mfa).unwrap();
+        assert!(json.get("secret").is_none());
+        assert_eq!(json["enabled"], true);
+    }
+}
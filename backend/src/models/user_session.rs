@@ -0,0 +1,96 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user_sessions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: i32,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub last_seen_at: DateTimeWithTimeZone,
+    pub expires_at: DateTimeWithTimeZone,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
+    pub revoked_reason: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub last_seen_at: DateTimeWithTimeZone,
+    pub expires_at: DateTimeWithTimeZone,
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn from_model(session: Model, current_session: Option<Uuid>) -> Self {
+        Self {
+            current: current_session == Some(session.id),
+            id: session.id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_model(id: Uuid) -> Model {
+        Model {
+            id,
+            user_id: 1,
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ip_address: Some("192.168.1.10".to_string()),
+            created_at: chrono::Utc::now().into(),
+            last_seen_at: chrono::Utc::now().into(),
+            expires_at: chrono::Utc::now().into(),
+            revoked_at: None,
+            revoked_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_session_response_marks_current_session() {
+        let id = Uuid::new_v4();
+
+        let current = SessionResponse::from_model(session_model(id), Some(id));
+        assert!(current.current);
+        assert_eq!(current.id, id);
+        assert_eq!(current.ip_address.as_deref(), Some("192.168.1.10"));
+
+        let other = SessionResponse::from_model(session_model(id), Some(Uuid::new_v4()));
+        assert!(!other.current);
+
+        let no_session = SessionResponse::from_model(session_model(id), None);
+        assert!(!no_session.current);
+    }
+}
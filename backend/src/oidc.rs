@@ -0,0 +1,556 @@
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[cfg(test)]
+pub mod mock_idp;
+
+/// 发现文档和JWKS的缓存时间
+const METADATA_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// 请求身份提供方的超时时间
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum OidcError {
+    #[error("未配置OIDC身份提供方 {0}")]
+    UnknownProvider(String),
+    #[error("OIDC配置无效: {0}")]
+    InvalidConfig(String),
+    #[error("请求身份提供方失败: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("身份提供方返回错误: {0}")]
+    ProviderError(String),
+    #[error("ID令牌无效: {0}")]
+    InvalidIdToken(String),
+}
+
+/// 通过环境变量配置的OIDC身份提供方
+///
+/// OIDC_PROVIDERS 列出启用的提供方名称（逗号分隔），每个提供方的配置为 OIDC_<名称>_*。
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    pub name: String,
+    pub display_name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+    /// 是否按已验证的邮箱关联已有账户，默认是
+    pub link_by_email: bool,
+    /// 没有可关联的账户时是否自动创建，默认否
+    pub jit_provisioning: bool,
+    /// 自动创建的用户获得的角色（角色名称）
+    pub default_role: Option<String>,
+    /// 自动创建的用户加入的部门（部门编码）
+    pub default_department: Option<String>,
+    /// ID令牌表明已在身份提供方完成多因素认证时，是否免除本地二次验证，默认否
+    pub trust_idp_mfa: bool,
+    /// 表示多因素认证的 acr 取值；为空时只根据 amr 中的 mfa 判断
+    pub mfa_acr_values: Vec<String>,
+}
+
+impl OidcProvider {
+    /// 所有启用的提供方
+    pub fn all_from_env() -> Result<Vec<Self>, OidcError> {
+        Self::enabled_names()
+            .iter()
+            .map(|name| Self::from_env(name))
+            .collect()
+    }
+
+    /// 按名称查找启用的提供方
+    pub fn find(name: &str) -> Result<Self, OidcError> {
+        if !Self::enabled_names().iter().any(|n| n == name) {
+            return Err(OidcError::UnknownProvider(name.to_string()));
+        }
+        Self::from_env(name)
+    }
+
+    fn enabled_names() -> Vec<String> {
+        env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    fn from_env(name: &str) -> Result<Self, OidcError> {
+        let var = |key: &str| {
+            env::var(Self::env_key(name, key))
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let flag = |key: &str, default: bool| {
+            var(key).map(|v| matches!(v.as_str(), "true" | "1" | "yes")).unwrap_or(default)
+        };
+
+        let issuer = var("ISSUER")
+            .ok_or_else(|| OidcError::InvalidConfig(format!("缺少 {}", Self::env_key(name, "ISSUER"))))?;
+        let client_id = var("CLIENT_ID")
+            .ok_or_else(|| OidcError::InvalidConfig(format!("缺少 {}", Self::env_key(name, "CLIENT_ID"))))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            display_name: var("DISPLAY_NAME").unwrap_or_else(|| name.to_string()),
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id,
+            client_secret: var("CLIENT_SECRET"),
+            scopes: var("SCOPES")
+                .unwrap_or_else(|| "openid email profile".to_string())
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            link_by_email: flag("LINK_BY_EMAIL", true),
+            jit_provisioning: flag("JIT_PROVISIONING", false),
+            default_role: var("DEFAULT_ROLE"),
+            default_department: var("DEFAULT_DEPARTMENT"),
+            trust_idp_mfa: flag("TRUST_IDP_MFA", false),
+            mfa_acr_values: var("MFA_ACR_VALUES")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// 是否信任身份提供方完成的多因素认证：需显式开启，且ID令牌的 amr 包含 mfa（RFC 8176）或 acr 为配置的取值
+    pub fn trusts_mfa(&self, claims: &IdTokenClaims) -> bool {
+        if !self.trust_idp_mfa {
+            return false;
+        }
+
+        claims.amr.iter().any(|method| method == "mfa")
+            || claims
+                .acr
+                .as_ref()
+                .is_some_and(|acr| self.mfa_acr_values.iter().any(|value| value == acr))
+    }
+
+    fn env_key(name: &str, key: &str) -> String {
+        format!("OIDC_{}_{}", name.to_uppercase().replace('-', "_"), key)
+    }
+}
+
+/// 身份提供方回调的前端地址，默认为 APP_BASE_URL 下的 /oidc/callback
+pub fn redirect_uri() -> String {
+    env::var("OIDC_REDIRECT_URI").unwrap_or_else(|_| {
+        let base_url = env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:4200".to_string());
+        format!("{}/oidc/callback", base_url.trim_end_matches('/'))
+    })
+}
+
+/// OpenID Connect 发现文档中用到的字段
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// PKCE（RFC 7636）校验码，使用 S256 方式
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn generate() -> Self {
+        let verifier = random_token();
+        let challenge = Self::challenge_for(&verifier);
+        Self { verifier, challenge }
+    }
+
+    pub fn challenge_for(verifier: &str) -> String {
+        BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
+    }
+}
+
+/// 生成用于 state、nonce 和 PKCE 的随机字符串
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE64URL_NOPAD.encode(&bytes)
+}
+
+/// ID令牌中用到的声明
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+    /// 身份提供方使用的认证方式（RFC 8176）
+    #[serde(default)]
+    pub amr: Vec<String>,
+    /// 身份提供方的认证等级
+    #[serde(default)]
+    pub acr: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+type Cache<T> = OnceLock<Mutex<HashMap<String, (Instant, T)>>>;
+
+static METADATA_CACHE: Cache<ProviderMetadata> = OnceLock::new();
+static JWKS_CACHE: Cache<JwkSet> = OnceLock::new();
+
+fn cached<T: Clone>(cache: &Cache<T>, key: &str) -> Option<T> {
+    let cache = cache.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(key)
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < METADATA_CACHE_TTL)
+        .map(|(_, value)| value.clone())
+}
+
+fn store<T>(cache: &Cache<T>, key: &str, value: T) {
+    cache
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key.to_string(), (Instant::now(), value));
+}
+
+fn http_client() -> Result<reqwest::Client, OidcError> {
+    Ok(reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?)
+}
+
+/// OIDC 依赖方客户端：授权码 + PKCE 流程
+pub struct OidcClient;
+
+impl OidcClient {
+    /// 获取提供方的发现文档，文档中的 issuer 必须与配置一致
+    pub async fn discover(provider: &OidcProvider) -> Result<ProviderMetadata, OidcError> {
+        if let Some(metadata) = cached(&METADATA_CACHE, &provider.issuer) {
+            return Ok(metadata);
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+        let metadata: ProviderMetadata = http_client()?
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if metadata.issuer.trim_end_matches('/') != provider.issuer {
+            return Err(OidcError::InvalidConfig(format!(
+                "发现文档的 issuer {} 与配置不一致",
+                metadata.issuer
+            )));
+        }
+
+        store(&METADATA_CACHE, &provider.issuer, metadata.clone());
+        Ok(metadata)
+    }
+
+    /// 构造跳转到身份提供方的授权地址
+    pub fn authorization_url(
+        metadata: &ProviderMetadata,
+        provider: &OidcProvider,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+        redirect_uri: &str,
+    ) -> String {
+        let params = [
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", &provider.scopes.join(" ")),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+        let separator = if metadata.authorization_endpoint.contains('?') { '&' } else { '?' };
+        format!("{}{}{}", metadata.authorization_endpoint, separator, params)
+    }
+
+    /// 使用授权码换取ID令牌
+    pub async fn exchange_code(
+        metadata: &ProviderMetadata,
+        provider: &OidcProvider,
+        code: &str,
+        code_verifier: &str,
+        redirect_uri: &str,
+    ) -> Result<String, OidcError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &provider.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = http_client()?
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(OidcError::ProviderError(format!(
+                "{} {}",
+                status,
+                body.chars().take(200).collect::<String>()
+            )));
+        }
+
+        let token: TokenResponse = response.json().await?;
+        token
+            .id_token
+            .ok_or_else(|| OidcError::ProviderError("令牌响应中没有 id_token".to_string()))
+    }
+
+    /// 校验ID令牌的签名、issuer、audience、有效期和 nonce
+    pub async fn verify_id_token(
+        metadata: &ProviderMetadata,
+        provider: &OidcProvider,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let header = decode_header(id_token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+        // 只接受非对称签名，防止以客户端密钥伪造的 HS256 令牌
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(OidcError::InvalidIdToken("不支持的签名算法".to_string()));
+        }
+
+        let decoding_key = Self::find_key(metadata, header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(|e| OidcError::InvalidIdToken(e.to_string()))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OidcError::InvalidIdToken("nonce 不匹配".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    /// 按 kid 查找身份提供方的公钥，找不到时重新获取一次JWKS（提供方可能已轮换密钥）
+    async fn find_key(metadata: &ProviderMetadata, kid: Option<&str>) -> Result<DecodingKey, OidcError> {
+        let lookup = |jwks: &JwkSet| match kid {
+            Some(kid) => jwks.find(kid).cloned(),
+            None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+            None => None,
+        };
+
+        if let Some(jwk) = cached(&JWKS_CACHE, &metadata.jwks_uri).as_ref().and_then(lookup) {
+            return DecodingKey::from_jwk(&jwk).map_err(|e| OidcError::InvalidIdToken(e.to_string()));
+        }
+
+        let jwks: JwkSet = http_client()?
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwk = lookup(&jwks);
+        store(&JWKS_CACHE, &metadata.jwks_uri, jwks);
+
+        let jwk = jwk.ok_or_else(|| OidcError::InvalidIdToken("找不到签名公钥".to_string()))?;
+        DecodingKey::from_jwk(&jwk).map_err(|e| OidcError::InvalidIdToken(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(issuer: &str) -> OidcProvider {
+        OidcProvider {
+            name: "company".to_string(),
+            display_name: "公司账号".to_string(),
+            issuer: issuer.to_string(),
+            client_id: "web-admin".to_string(),
+            client_secret: Some("secret".to_string()),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            link_by_email: true,
+            jit_provisioning: false,
+            default_role: None,
+            default_department: None,
+            trust_idp_mfa: false,
+            mfa_acr_values: Vec::new(),
+        }
+    }
+
+    fn claims(amr: &[&str], acr: Option<&str>) -> IdTokenClaims {
+        IdTokenClaims {
+            sub: "employee-42".to_string(),
+            nonce: None,
+            email: None,
+            email_verified: false,
+            preferred_username: None,
+            amr: amr.iter().map(|m| m.to_string()).collect(),
+            acr: acr.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_trusts_mfa() {
+        let mfa = claims(&["pwd", "mfa", "otp"], None);
+        let password_only = claims(&["pwd"], Some("urn:example:loa:1"));
+        let high_acr = claims(&["pwd"], Some("urn:example:loa:2"));
+
+        // 默认不信任身份提供方的多因素认证
+        let provider = provider("https://idp.example.com");
+        assert!(!provider.trusts_mfa(&mfa));
+
+        let trusted = OidcProvider {
+            trust_idp_mfa: true,
+            ..provider.clone()
+        };
+        assert!(trusted.trusts_mfa(&mfa));
+        assert!(!trusted.trusts_mfa(&password_only));
+        // 未配置 acr 取值时只看 amr
+        assert!(!trusted.trusts_mfa(&high_acr));
+
+        let with_acr = OidcProvider {
+            mfa_acr_values: vec!["urn:example:loa:2".to_string()],
+            ..trusted
+        };
+        assert!(with_acr.trusts_mfa(&high_acr));
+        assert!(!with_acr.trusts_mfa(&password_only));
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // S256: BASE64URL(SHA256(verifier))
+        assert_eq!(
+            Pkce::challenge_for("dBjftJeZ4CVP-mB92K27uhbUJU1p1r7wW1gFWFOEjXk"),
+            "bwWFMyPfdG9qreDhH2lmftFx_dFeLDalzcT1gb_j68g"
+        );
+
+        let pkce = Pkce::generate();
+        assert_eq!(pkce.verifier.len(), 43);
+        assert_eq!(pkce.challenge, Pkce::challenge_for(&pkce.verifier));
+    }
+
+    #[test]
+    fn test_authorization_url() {
+        let metadata = ProviderMetadata {
+            issuer: "https://idp.example.com".to_string(),
+            authorization_endpoint: "https://idp.example.com/authorize".to_string(),
+            token_endpoint: "https://idp.example.com/token".to_string(),
+            jwks_uri: "https://idp.example.com/jwks".to_string(),
+        };
+
+        let url = OidcClient::authorization_url(
+            &metadata,
+            &provider("https://idp.example.com"),
+            "state1",
+            "nonce1",
+            "challenge1",
+            "http://localhost:4200/oidc/callback",
+        );
+
+        assert!(url.starts_with("https://idp.example.com/authorize?response_type=code&"));
+        assert!(url.contains("client_id=web-admin"));
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A4200%2Foidc%2Fcallback"));
+        assert!(url.contains("scope=openid%20email"));
+        assert!(url.contains("state=state1"));
+        assert!(url.contains("nonce=nonce1"));
+        assert!(url.contains("code_challenge=challenge1&code_challenge_method=S256"));
+    }
+
+    #[tokio::test]
+    async fn test_code_flow_against_mock_idp() {
+        let idp = mock_idp::MockIdp::start().await;
+        let provider = provider(&idp.issuer);
+        let redirect_uri = "http://localhost:4200/oidc/callback";
+
+        let metadata = OidcClient::discover(&provider).await.expect("发现文档获取失败");
+        assert_eq!(metadata.issuer, idp.issuer);
+
+        let pkce = Pkce::generate();
+        let code = idp.authorize(
+            &pkce.challenge,
+            json!({
+                "sub": "employee-42",
+                "nonce": "nonce1",
+                "email": "alice@example.com",
+                "email_verified": true,
+                "preferred_username": "alice"
+            }),
+        );
+
+        // 错误的PKCE校验码会被身份提供方拒绝
+        let rejected = OidcClient::exchange_code(&metadata, &provider, &code, "wrong-verifier", redirect_uri).await;
+        assert!(matches!(rejected, Err(OidcError::ProviderError(_))));
+
+        let code = idp.authorize(
+            &pkce.challenge,
+            json!({
+                "sub": "employee-42",
+                "nonce": "nonce1",
+                "email": "alice@example.com",
+                "email_verified": true,
+                "preferred_username": "alice"
+            }),
+        );
+        let id_token = OidcClient::exchange_code(&metadata, &provider, &code, &pkce.verifier, redirect_uri)
+            .await
+            .expect("换取令牌失败");
+
+        let claims = OidcClient::verify_id_token(&metadata, &provider, &id_token, "nonce1")
+            .await
+            .expect("ID令牌校验失败");
+        assert_eq!(claims.sub, "employee-42");
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+        assert!(claims.email_verified);
+        assert_eq!(claims.preferred_username.as_deref(), Some("alice"));
+
+        // nonce 不匹配
+        let replayed = OidcClient::verify_id_token(&metadata, &provider, &id_token, "other-nonce").await;
+        assert!(matches!(replayed, Err(OidcError::InvalidIdToken(_))));
+
+        // 签发给其他客户端的令牌
+        let other_client = OidcProvider {
+            client_id: "other-app".to_string(),
+            ..provider.clone()
+        };
+        let wrong_audience = OidcClient::verify_id_token(&metadata, &other_client, &id_token, "nonce1").await;
+        assert!(matches!(wrong_audience, Err(OidcError::InvalidIdToken(_))));
+    }
+}
@@ -0,0 +1,130 @@
+//! 测试用的本地OIDC身份提供方：发现文档、JWKS 和支持 PKCE 的令牌端点
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Form, Router,
+};
+use chrono::Utc;
+use jsonwebtoken::{encode, Header};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+use super::Pkce;
+use crate::jwt_keys::{generate_private_key, JwtAlgorithm, JwtKey, KeyRing};
+
+/// 模拟身份提供方签发的ID令牌的 audience
+pub const CLIENT_ID: &str = "web-admin";
+
+struct PendingCode {
+    challenge: String,
+    claims: Value,
+}
+
+#[derive(Clone)]
+struct IdpState {
+    issuer: String,
+    key: Arc<JwtKey>,
+    codes: Arc<Mutex<HashMap<String, PendingCode>>>,
+}
+
+pub struct MockIdp {
+    pub issuer: String,
+    state: IdpState,
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    code: String,
+    code_verifier: String,
+}
+
+impl MockIdp {
+    /// 在随机端口上启动
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("绑定端口失败");
+        let issuer = format!("http://{}", listener.local_addr().expect("获取端口失败"));
+
+        let der = generate_private_key(JwtAlgorithm::EdDSA).expect("生成密钥失败");
+        let key = JwtKey::from_private_der(JwtAlgorithm::EdDSA, &der).expect("加载密钥失败");
+
+        let state = IdpState {
+            issuer: issuer.clone(),
+            key: Arc::new(key),
+            codes: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(state.clone());
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        Self { issuer, state }
+    }
+
+    /// 模拟用户在身份提供方完成登录，返回授权码；`claims` 会原样写入ID令牌
+    pub fn authorize(&self, code_challenge: &str, claims: Value) -> String {
+        let code = super::random_token();
+        self.state.codes.lock().unwrap().insert(
+            code.clone(),
+            PendingCode {
+                challenge: code_challenge.to_string(),
+                claims,
+            },
+        );
+        code
+    }
+}
+
+async fn discovery(State(state): State<IdpState>) -> Json<Value> {
+    Json(json!({
+        "issuer": state.issuer,
+        "authorization_endpoint": format!("{}/authorize", state.issuer),
+        "token_endpoint": format!("{}/token", state.issuer),
+        "jwks_uri": format!("{}/jwks", state.issuer),
+    }))
+}
+
+async fn jwks(State(state): State<IdpState>) -> Json<Value> {
+    let key = (*state.key).clone();
+    Json(KeyRing::new(key.clone(), vec![key]).jwks())
+}
+
+async fn token(
+    State(state): State<IdpState>,
+    Form(form): Form<TokenForm>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 授权码只能使用一次
+    let pending = state.codes.lock().unwrap().remove(&form.code);
+    let pending = pending
+        .filter(|p| Pkce::challenge_for(&form.code_verifier) == p.challenge)
+        .ok_or((StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" }))))?;
+
+    let now = Utc::now().timestamp();
+    let mut claims = pending.claims;
+    claims["iss"] = json!(state.issuer);
+    claims["aud"] = json!(CLIENT_ID);
+    claims["iat"] = json!(now);
+    claims["exp"] = json!(now + 300);
+
+    let mut header = Header::new(state.key.algorithm.jwt_algorithm());
+    header.kid = state.key.kid.clone();
+    let id_token = encode(&header, &claims, state.key.encoding_key())
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "server_error" }))))?;
+
+    Ok(Json(json!({
+        "id_token": id_token,
+        "access_token": super::random_token(),
+        "token_type": "Bearer"
+    })))
+}
@@ -0,0 +1,200 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use std::env;
+use thiserror::Error;
+
+/// 盐值长度（字节）
+const SALT_LENGTH: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum PasswordHashError {
+    #[error("Argon2参数无效: {0}")]
+    InvalidParams(String),
+    #[error("{0}")]
+    HashFailed(String),
+}
+
+/// 密码哈希算法，按哈希字符串的前缀识别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashScheme {
+    /// 当前使用的算法
+    Argon2id,
+    /// 旧版本使用的算法，用户下次登录时升级为 Argon2id
+    Bcrypt,
+}
+
+impl HashScheme {
+    /// 识别哈希使用的算法，没有密码（空字符串）或无法识别时返回None
+    pub fn detect(hash: &str) -> Option<Self> {
+        if hash.starts_with("$argon2id$") {
+            Some(HashScheme::Argon2id)
+        } else if ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix)) {
+            Some(HashScheme::Bcrypt)
+        } else {
+            None
+        }
+    }
+}
+
+/// Argon2id 参数，默认值为 OWASP 推荐的最低配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Config {
+    /// 内存开销（KiB）
+    pub memory_cost: u32,
+    /// 迭代次数
+    pub time_cost: u32,
+    /// 并行度
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_cost: 19_456,
+            time_cost: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2Config {
+    /// 从环境变量读取参数，未配置的项使用默认值
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let read = |name: &str, default: u32| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            memory_cost: read("ARGON2_MEMORY_COST", default.memory_cost),
+            time_cost: read("ARGON2_TIME_COST", default.time_cost),
+            parallelism: read("ARGON2_PARALLELISM", default.parallelism),
+        }
+    }
+
+    pub fn params(&self) -> Result<Params, PasswordHashError> {
+        Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+            .map_err(|e| PasswordHashError::InvalidParams(e.to_string()))
+    }
+
+    /// 按当前参数生成的哈希都以此开头，参数不同的哈希需要重新计算
+    pub fn hash_prefix(&self) -> String {
+        format!(
+            "$argon2id$v={}$m={},t={},p={}$",
+            Version::V0x13 as u32,
+            self.memory_cost,
+            self.time_cost,
+            self.parallelism
+        )
+    }
+
+    /// 使用 Argon2id 计算密码哈希，结果为 PHC 字符串格式
+    pub fn hash(&self, password: &str) -> Result<String, PasswordHashError> {
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params()?);
+
+        let mut salt = [0u8; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt)
+            .map_err(|e| PasswordHashError::HashFailed(e.to_string()))?;
+
+        argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| PasswordHashError::HashFailed(e.to_string()))
+    }
+
+    /// 哈希是否使用旧算法或与当前不同的参数，没有密码的账户不需要重新计算
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        HashScheme::detect(hash).is_some() && !hash.starts_with(&self.hash_prefix())
+    }
+}
+
+/// 校验密码，按哈希自身记录的算法和参数计算；哈希无法识别时视为不匹配
+pub fn verify(password: &str, hash: &str) -> bool {
+    match HashScheme::detect(hash) {
+        Some(HashScheme::Argon2id) => PasswordHash::new(hash)
+            .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+            .unwrap_or(false),
+        Some(HashScheme::Bcrypt) => bcrypt::verify(password, hash).unwrap_or(false),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试使用较小的参数，避免拖慢测试
+    fn config() -> Argon2Config {
+        Argon2Config {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_detect_scheme() {
+        let argon2 = config().hash("password").unwrap();
+        assert_eq!(HashScheme::detect(&argon2), Some(HashScheme::Argon2id));
+
+        let bcrypt = bcrypt::hash("password", 4).unwrap();
+        assert_eq!(HashScheme::detect(&bcrypt), Some(HashScheme::Bcrypt));
+
+        assert_eq!(HashScheme::detect(""), None);
+        assert_eq!(HashScheme::detect("$argon2i$v=19$m=1024,t=1,p=1$c2FsdA$aGFzaA"), None);
+    }
+
+    #[test]
+    fn test_verify_both_schemes() {
+        let argon2 = config().hash("correct horse").unwrap();
+        assert!(verify("correct horse", &argon2));
+        assert!(!verify("wrong horse", &argon2));
+
+        // 旧的 bcrypt 哈希仍然可以验证
+        let bcrypt = bcrypt::hash("correct horse", 4).unwrap();
+        assert!(verify("correct horse", &bcrypt));
+        assert!(!verify("wrong horse", &bcrypt));
+
+        // 没有密码的账户（服务账户、目录用户）不能通过验证
+        assert!(!verify("", ""));
+        assert!(!verify("anything", "not-a-hash"));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let current = config();
+        let hash = current.hash("password").unwrap();
+        assert!(hash.starts_with(&current.hash_prefix()));
+        assert!(!current.needs_rehash(&hash));
+
+        // 旧算法需要升级
+        assert!(current.needs_rehash(&bcrypt::hash("password", 4).unwrap()));
+
+        // 调整参数后旧参数的哈希需要重新计算，但仍然可以验证
+        let stronger = Argon2Config {
+            time_cost: 2,
+            ..current
+        };
+        assert!(stronger.needs_rehash(&hash));
+        assert!(verify("password", &hash));
+
+        // 没有密码的账户不需要重新计算
+        assert!(!current.needs_rehash(""));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let invalid = Argon2Config {
+            memory_cost: 1,
+            ..config()
+        };
+        assert!(invalid.params().is_err());
+        assert!(invalid.hash("password").is_err());
+        assert!(config().params().is_ok());
+    }
+}
@@ -0,0 +1,267 @@
+use sqlx::postgres::PgListener;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::database::database_url;
+
+/// 权限变更通知的频道，由数据库触发器（迁移 023）在 user_roles、role_permissions、roles、permissions 变化时发送
+pub const PERMISSION_CHANNEL: &str = "permission_changes";
+
+/// 监听连接断开后重新连接的间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// 权限缓存的失效范围，对应通知的内容：`user:<用户ID>` 或 `all`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invalidation {
+    User(i32),
+    All,
+}
+
+impl Invalidation {
+    /// 解析通知内容，无法识别时按全部失效处理
+    pub fn parse(payload: &str) -> Self {
+        payload
+            .strip_prefix("user:")
+            .and_then(|id| id.parse::<i32>().ok())
+            .map(Invalidation::User)
+            .unwrap_or(Invalidation::All)
+    }
+}
+
+struct CachedPermissions {
+    permissions: Arc<HashSet<String>>,
+    loaded_at: Instant,
+}
+
+/// 进程内的用户权限缓存，按用户ID保存
+///
+/// 每次失效都会增加版本号；从数据库加载权限前记下版本号，加载期间发生过失效时不写入缓存，
+/// 避免把失效前读到的旧权限重新放回缓存。
+/// 尚未订阅变更通知（或订阅已断开）时收不到其他实例的失效通知，此时不使用缓存。
+pub struct PermissionCache {
+    ttl: Duration,
+    entries: RwLock<HashMap<i32, CachedPermissions>>,
+    generation: AtomicU64,
+    subscribed: AtomicBool,
+}
+
+impl PermissionCache {
+    /// ttl 为 0 表示不缓存
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+            subscribed: AtomicBool::new(false),
+        }
+    }
+
+    /// 缓存有效期（秒），默认300，作为错过失效通知时的兜底；0 表示不缓存
+    pub fn ttl_from_env() -> Duration {
+        let seconds = env::var("PERMISSION_CACHE_TTL")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(300);
+        Duration::from_secs(seconds)
+    }
+
+    /// 配置了有效期且已订阅变更通知时才使用缓存
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.subscribed.load(Ordering::Acquire)
+    }
+
+    /// 记录变更通知的订阅状态；状态变化时清空缓存，断开期间可能错过了通知
+    pub fn set_subscribed(&self, subscribed: bool) {
+        self.invalidate(Invalidation::All);
+        self.subscribed.store(subscribed, Ordering::Release);
+    }
+
+    /// 当前版本号，加载权限前获取，写入缓存时传回
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub fn get(&self, user_id: i32) -> Option<Arc<HashSet<String>>> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(&user_id)
+            .filter(|entry| entry.loaded_at.elapsed() < self.ttl)
+            .map(|entry| entry.permissions.clone())
+    }
+
+    /// 写入缓存；generation 之后发生过失效时放弃写入
+    pub fn insert(&self, user_id: i32, permissions: Arc<HashSet<String>>, generation: u64) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        // 持有写锁时检查，失效操作同样需要写锁，两者不会交错
+        if self.generation() != generation {
+            return;
+        }
+        entries.insert(
+            user_id,
+            CachedPermissions {
+                permissions,
+                loaded_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn invalidate(&self, invalidation: Invalidation) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        match invalidation {
+            Invalidation::User(user_id) => {
+                entries.remove(&user_id);
+            }
+            Invalidation::All => entries.clear(),
+        }
+    }
+}
+
+static CACHE: OnceLock<PermissionCache> = OnceLock::new();
+
+/// 全局权限缓存，有效期由 PERMISSION_CACHE_TTL 配置
+pub fn cache() -> &'static PermissionCache {
+    CACHE.get_or_init(|| PermissionCache::new(PermissionCache::ttl_from_env()))
+}
+
+/// 监听其他实例（以及本实例）的权限变更通知并使缓存失效
+///
+/// 使用单独的数据库连接；只有订阅成功后才启用缓存，连接断开时停用并清空，重新订阅后再启用。
+pub async fn listen() {
+    let url = database_url();
+
+    loop {
+        let mut listener = match PgListener::connect(&url).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::warn!("连接权限变更通知失败: {}", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+
+        if let Err(e) = listener.listen(PERMISSION_CHANNEL).await {
+            tracing::warn!("订阅权限变更通知失败: {}", e);
+            tokio::time::sleep(RECONNECT_DELAY).await;
+            continue;
+        }
+
+        cache().set_subscribed(true);
+        tracing::info!("已订阅权限变更通知");
+
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(notification)) => cache().invalidate(Invalidation::parse(notification.payload())),
+                Ok(None) => {
+                    tracing::warn!("权限变更通知连接已断开，正在重新连接");
+                    cache().set_subscribed(false);
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!("接收权限变更通知失败: {}", e);
+                    cache().set_subscribed(false);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(items: &[&str]) -> Arc<HashSet<String>> {
+        Arc::new(items.iter().map(|p| p.to_string()).collect())
+    }
+
+    fn subscribed_cache(ttl: Duration) -> PermissionCache {
+        let cache = PermissionCache::new(ttl);
+        cache.set_subscribed(true);
+        cache
+    }
+
+    #[test]
+    fn test_parse_invalidation() {
+        assert_eq!(Invalidation::parse("user:42"), Invalidation::User(42));
+        assert_eq!(Invalidation::parse("all"), Invalidation::All);
+        // 无法识别的通知按全部失效处理
+        assert_eq!(Invalidation::parse("user:abc"), Invalidation::All);
+        assert_eq!(Invalidation::parse(""), Invalidation::All);
+    }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let cache = subscribed_cache(Duration::from_secs(60));
+
+        cache.insert(1, permissions(&["user:read"]), cache.generation());
+        cache.insert(2, permissions(&["role:read"]), cache.generation());
+        assert!(cache.get(1).unwrap().contains("user:read"));
+
+        // 只使指定用户失效
+        cache.invalidate(Invalidation::User(1));
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(2).is_some());
+
+        cache.invalidate(Invalidation::All);
+        assert!(cache.get(2).is_none());
+    }
+
+    #[test]
+    fn test_stale_load_is_discarded() {
+        let cache = subscribed_cache(Duration::from_secs(60));
+
+        // 加载期间发生失效，加载结果不能写入缓存
+        let generation = cache.generation();
+        cache.invalidate(Invalidation::User(2));
+        cache.insert(1, permissions(&["user:delete"]), generation);
+        assert!(cache.get(1).is_none());
+
+        cache.insert(1, permissions(&["user:read"]), cache.generation());
+        assert!(cache.get(1).is_some());
+    }
+
+    #[test]
+    fn test_disabled_and_expired_cache() {
+        let disabled = subscribed_cache(Duration::ZERO);
+        disabled.insert(1, permissions(&["user:read"]), disabled.generation());
+        assert!(disabled.get(1).is_none());
+
+        let expiring = subscribed_cache(Duration::from_millis(1));
+        expiring.insert(1, permissions(&["user:read"]), expiring.generation());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(expiring.get(1).is_none());
+    }
+
+    #[test]
+    fn test_cache_bypassed_until_subscribed() {
+        // 尚未订阅变更通知时不使用缓存
+        let cache = PermissionCache::new(Duration::from_secs(60));
+        cache.insert(1, permissions(&["user:read"]), cache.generation());
+        assert!(cache.get(1).is_none());
+
+        cache.set_subscribed(true);
+        cache.insert(1, permissions(&["user:read"]), cache.generation());
+        assert!(cache.get(1).is_some());
+
+        // 订阅断开后停用并清空，重新订阅后不会读到断开前的记录
+        let generation = cache.generation();
+        cache.set_subscribed(false);
+        assert!(cache.get(1).is_none());
+        cache.insert(1, permissions(&["user:delete"]), generation);
+        cache.set_subscribed(true);
+        assert!(cache.get(1).is_none());
+    }
+}
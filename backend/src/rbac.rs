@@ -1,7 +1,9 @@
 use crate::auth::Claims;
 use crate::models::{permission, role, user, user_role, role_permission};
+use crate::permission_cache::{self, Invalidation};
 use sea_orm::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -16,6 +18,13 @@ pub enum RbacError {
     RoleNotFound,
 }
 
+/// 用户的有效角色和权限
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserAccess {
+    pub roles: Vec<String>,
+    pub permissions: HashSet<String>,
+}
+
 pub struct RbacService;
 
 impl RbacService {
@@ -32,62 +41,184 @@ impl RbacService {
         Ok(user_permissions.contains(&required_permission))
     }
 
-    /// 获取用户所有权限
+    /// 检查请求者是否有指定权限：使用API密钥时还需在密钥的权限范围内
+    pub async fn check_claims_permission(
+        db: &DatabaseConnection,
+        claims: &Claims,
+        resource: &str,
+        action: &str,
+    ) -> Result<bool, RbacError> {
+        let required_permission = format!("{}:{}", resource, action);
+        if let Some(grant) = &claims.api_key {
+            if !grant.allows(&required_permission) {
+                return Ok(false);
+            }
+        }
+
+        Self::check_permission(db, claims.sub, resource, action).await
+    }
+
+    /// 获取用户所有权限，优先使用进程内缓存
     pub async fn get_user_permissions(
         db: &DatabaseConnection,
         user_id: i32,
     ) -> Result<HashSet<String>, RbacError> {
-        let user_roles = user_role::Entity::find()
-            .filter(user_role::Column::UserId.eq(user_id))
-            .find_also_related(role::Entity)
-            .all(db)
-            .await?;
-
-        let mut permissions = HashSet::new();
+        let mut permissions = Self::get_users_permissions(db, &[user_id]).await?;
+        Ok(permissions.remove(&user_id).unwrap_or_default())
+    }
 
-        for (_user_role, role) in user_roles {
-            if let Some(role) = role {
-                if role.is_active {
-                    let role_permissions = role_permission::Entity::find()
-                        .filter(role_permission::Column::RoleId.eq(role.id))
-                        .find_also_related(permission::Entity)
-                        .all(db)
-                        .await?;
-
-                    for (_, permission) in role_permissions {
-                        if let Some(permission) = permission {
-                            if permission.is_active {
-                                let permission_string = format!("{}:{}", permission.resource, permission.action);
-                                permissions.insert(permission_string);
-                            }
-                        }
-                    }
+    /// 批量获取多个用户的所有权限，缓存中没有的用户一次查询加载
+    pub async fn get_users_permissions(
+        db: &DatabaseConnection,
+        user_ids: &[i32],
+    ) -> Result<HashMap<i32, HashSet<String>>, RbacError> {
+        let cache = permission_cache::cache();
+        let mut permissions = HashMap::new();
+        let mut missing = Vec::new();
+
+        for &user_id in user_ids {
+            match cache.get(user_id) {
+                Some(cached) => {
+                    permissions.insert(user_id, (*cached).clone());
                 }
+                None => missing.push(user_id),
             }
         }
 
+        if missing.is_empty() {
+            return Ok(permissions);
+        }
+
+        // 先记下版本号，加载期间权限发生变化时不写入缓存
+        let generation = cache.generation();
+        let rows = Self::load_permission_rows(db, &missing).await?;
+        for (user_id, loaded) in group_by_user::<HashSet<String>>(&missing, rows) {
+            cache.insert(user_id, Arc::new(loaded.clone()), generation);
+            permissions.insert(user_id, loaded);
+        }
+
         Ok(permissions)
     }
 
-    /// 获取用户所有角色
+    /// 获取用户所有有效角色
     pub async fn get_user_roles(
         db: &DatabaseConnection,
         user_id: i32,
     ) -> Result<Vec<String>, RbacError> {
-        let user_roles = user_role::Entity::find()
-            .filter(user_role::Column::UserId.eq(user_id))
-            .find_also_related(role::Entity)
+        let mut roles = Self::get_users_roles(db, &[user_id]).await?;
+        Ok(roles.remove(&user_id).unwrap_or_default())
+    }
+
+    /// 批量获取多个用户的有效角色，按分配顺序排列
+    pub async fn get_users_roles(
+        db: &DatabaseConnection,
+        user_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<String>>, RbacError> {
+        if user_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<(i32, String)> = user_role::Entity::find()
+            .select_only()
+            .column(user_role::Column::UserId)
+            .column(role::Column::Name)
+            .inner_join(role::Entity)
+            .filter(user_role::Column::UserId.is_in(user_ids.to_vec()))
+            .filter(role::Column::IsActive.eq(true))
+            .order_by_asc(user_role::Column::Id)
+            .into_tuple()
             .all(db)
             .await?;
 
-        let roles: Vec<String> = user_roles
-            .into_iter()
-            .filter_map(|(_, role)| {
-                role.filter(|r| r.is_active).map(|r| r.name)
+        Ok(group_by_user(user_ids, rows))
+    }
+
+    /// 批量获取多个用户的角色和权限，供用户列表、用户详情等接口使用
+    pub async fn get_users_access(
+        db: &DatabaseConnection,
+        user_ids: &[i32],
+    ) -> Result<HashMap<i32, UserAccess>, RbacError> {
+        let mut roles = Self::get_users_roles(db, user_ids).await?;
+        let mut permissions = Self::get_users_permissions(db, user_ids).await?;
+
+        Ok(user_ids
+            .iter()
+            .map(|&user_id| {
+                let access = UserAccess {
+                    roles: roles.remove(&user_id).unwrap_or_default(),
+                    permissions: permissions.remove(&user_id).unwrap_or_default(),
+                };
+                (user_id, access)
             })
-            .collect();
+            .collect())
+    }
+
+    /// 获取单个用户的角色和权限
+    pub async fn get_user_access(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<UserAccess, RbacError> {
+        let mut access = Self::get_users_access(db, &[user_id]).await?;
+        Ok(access.remove(&user_id).unwrap_or_default())
+    }
+
+    /// 一次查询加载用户通过有效角色获得的有效权限（用户ID，权限字符串）
+    async fn load_permission_rows(
+        db: &DatabaseConnection,
+        user_ids: &[i32],
+    ) -> Result<Vec<(i32, String)>, RbacError> {
+        let rows: Vec<(i32, String, String)> = role_permission::Entity::find()
+            .select_only()
+            .column(user_role::Column::UserId)
+            .column(permission::Column::Resource)
+            .column(permission::Column::Action)
+            .distinct()
+            .join(JoinType::InnerJoin, role_permission::Relation::Permission.def())
+            .join(JoinType::InnerJoin, role_permission::Relation::Role.def())
+            .join(JoinType::InnerJoin, role::Relation::UserRole.def())
+            .filter(user_role::Column::UserId.is_in(user_ids.to_vec()))
+            .filter(role::Column::IsActive.eq(true))
+            .filter(permission::Column::IsActive.eq(true))
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user_id, resource, action)| (user_id, format!("{}:{}", resource, action)))
+            .collect())
+    }
+
+    /// 用户的任一有效角色是否要求启用二次验证
+    pub async fn is_mfa_required(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<bool, RbacError> {
+        let count = user_role::Entity::find()
+            .filter(user_role::Column::UserId.eq(user_id))
+            .inner_join(role::Entity)
+            .filter(role::Column::IsActive.eq(true))
+            .filter(role::Column::MfaRequired.eq(true))
+            .count(db)
+            .await?;
+
+        Ok(count > 0)
+    }
+
+    /// 用户的有效角色是否都允许使用邮件链接登录，没有角色的用户允许
+    pub async fn is_magic_link_allowed(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<bool, RbacError> {
+        let count = user_role::Entity::find()
+            .filter(user_role::Column::UserId.eq(user_id))
+            .inner_join(role::Entity)
+            .filter(role::Column::IsActive.eq(true))
+            .filter(role::Column::MagicLinkAllowed.eq(false))
+            .count(db)
+            .await?;
 
-        Ok(roles)
+        Ok(count == 0)
     }
 
     /// 为用户分配角色
@@ -126,9 +257,32 @@ impl RbacService {
         };
 
         user_role::Entity::insert(user_role).exec(db).await?;
+        // 数据库触发器会通知所有实例，这里先使本实例的缓存失效，保证随后的请求立即生效
+        permission_cache::cache().invalidate(Invalidation::User(user_id));
         Ok(())
     }
 
+    /// 按名称为用户分配启用中的角色，角色不存在或已禁用时返回 false
+    pub async fn assign_role_by_name(
+        db: &DatabaseConnection,
+        user_id: i32,
+        role_name: &str,
+    ) -> Result<bool, RbacError> {
+        let role = role::Entity::find()
+            .filter(role::Column::Name.eq(role_name))
+            .filter(role::Column::IsActive.eq(true))
+            .one(db)
+            .await?;
+
+        match role {
+            Some(role) => {
+                Self::assign_role_to_user(db, user_id, role.id).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     /// 获取角色的所有权限
     pub async fn get_role_permissions(
         db: &DatabaseConnection,
@@ -190,6 +344,7 @@ impl RbacService {
         };
 
         role_permission::Entity::insert(role_permission).exec(db).await?;
+        permission_cache::cache().invalidate(Invalidation::All);
         Ok(())
     }
 
@@ -219,20 +374,36 @@ impl RbacService {
 
         if let Some(role_permission) = role_permission {
             role_permission::Entity::delete_by_id(role_permission.id).exec(db).await?;
+            permission_cache::cache().invalidate(Invalidation::All);
         }
 
         Ok(())
     }
 }
 
+/// 按用户分组查询结果；每个请求的用户都有一项，没有任何记录的用户为空集合
+fn group_by_user<C>(user_ids: &[i32], rows: Vec<(i32, String)>) -> HashMap<i32, C>
+where
+    C: Default + Extend<String>,
+{
+    let mut grouped: HashMap<i32, C> = user_ids.iter().map(|&id| (id, C::default())).collect();
+    for (user_id, value) in rows {
+        if let Some(values) = grouped.get_mut(&user_id) {
+            values.extend(std::iter::once(value));
+        }
+    }
+    grouped
+}
+
 /// 权限检查中间件
+#[allow(dead_code)]
 pub async fn require_permission(
     claims: Claims,
     db: DatabaseConnection,
     resource: &str,
     action: &str,
 ) -> Result<Claims, RbacError> {
-    let has_permission = RbacService::check_permission(&db, claims.sub, resource, action).await?;
+    let has_permission = RbacService::check_claims_permission(&db, &claims, resource, action).await?;
 
     if !has_permission {
         return Err(RbacError::InsufficientPermissions);
@@ -260,4 +431,27 @@ mod tests {
                 "错误消息应该包含业务上下文: '{}'", expected_context);
         }
     }
+
+    #[test]
+    fn test_group_by_user() {
+        let rows = vec![
+            (1, "admin".to_string()),
+            (2, "user".to_string()),
+            (1, "user".to_string()),
+            // 未请求的用户不应出现在结果中
+            (9, "admin".to_string()),
+        ];
+
+        let roles: HashMap<i32, Vec<String>> = group_by_user(&[1, 2, 3], rows);
+        assert_eq!(roles.len(), 3);
+        // 保持查询结果的顺序
+        assert_eq!(roles[&1], vec!["admin", "user"]);
+        assert_eq!(roles[&2], vec!["user"]);
+        // 没有角色的用户得到空列表
+        assert!(roles[&3].is_empty());
+
+        let permissions: HashMap<i32, HashSet<String>> =
+            group_by_user(&[1], vec![(1, "user:read".to_string()), (1, "user:read".to_string())]);
+        assert_eq!(permissions[&1].len(), 1);
+    }
 }
@@ -0,0 +1,126 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    auth::Claims,
+    models::{user, CreateApiKeyDto},
+    extractors::AuthUser,
+    routes::utils::{api_key_error_response, forbid_impersonation},
+    services::ApiKeyService,
+};
+use sea_orm::{DatabaseConnection, EntityTrait};
+
+// 当前用户管理自己的API密钥，权限不超过用户本身的权限，因此不需要额外的权限
+pub fn api_key_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/", get(list_api_keys).post(create_api_key))
+        .route("/:id", delete(revoke_api_key))
+}
+
+// API密钥不能用来管理API密钥，避免泄露的密钥为自己续期或创建更大权限的密钥；
+// 服务账户的API密钥由管理员通过 /api/service-accounts 管理
+async fn ensure_key_management_allowed(
+    db: &DatabaseConnection,
+    claims: &Claims,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    if claims.api_key.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "不能使用API密钥管理API密钥，请登录后操作"
+            })),
+        ));
+    }
+
+    let owner = user::Entity::find_by_id(claims.sub)
+        .one(db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if owner.is_some_and(|owner| owner.is_service_account) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "服务账户的API密钥由管理员管理"
+            })),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn list_api_keys(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_key_management_allowed(&db, &claims).await?;
+
+    let keys = ApiKeyService::list(&db, claims.sub)
+        .await
+        .map_err(api_key_error_response)?;
+
+    Ok(Json(json!({
+        "data": keys
+    })))
+}
+
+async fn create_api_key(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateApiKeyDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_key_management_allowed(&db, &claims).await?;
+    forbid_impersonation(&claims)?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let (record, key) = ApiKeyService::create(&db, claims.sub, payload)
+        .await
+        .map_err(api_key_error_response)?;
+
+    Ok(Json(json!({
+        "message": "API密钥创建成功，请妥善保存，密钥只显示这一次",
+        "key": key,
+        "api_key": record
+    })))
+}
+
+async fn revoke_api_key(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    ensure_key_management_allowed(&db, &claims).await?;
+
+    ApiKeyService::revoke(&db, claims.sub, id)
+        .await
+        .map_err(api_key_error_response)?;
+
+    Ok(Json(json!({
+        "message": "API密钥已撤销"
+    })))
+}
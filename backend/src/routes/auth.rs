@@ -1,30 +1,73 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::Json,
-    routing::{post, get},
+    routing::{delete, post, get},
     Router,
     middleware::from_fn,
 };
+use chrono::{DateTime, Utc};
 use sea_orm::*;
 use serde_json::{json, Value};
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::{AuthService, AuthResponse},
-    models::{user, CreateUserDto, LoginDto, UserResponse},
+    auth::{AuthService, TokenUse},
+    models::{
+        refresh_token, user, AcceptInvitationDto, ChangePasswordDto, CreateUserDto, ForgotPasswordDto, ForwardAuthQuery, LoginDto, LogoutDto, MagicLinkLoginDto, MagicLinkRequestDto, MfaCodeDto, MfaEnrollmentResponse, MfaVerifyDto, OidcCallbackDto,
+        RefreshTokenDto, ResendVerificationDto, ClientCredentialsDto, ResetPasswordDto, SessionResponse, UpdateProfileDto, UserResponse, VerifyEmailDto,
+    },
     rbac::RbacService,
-    extractors::RequireAuth,
-    middleware::auth_middleware,
+    extractors::{AuthUser, ClientInfo, RequireAuth},
+    middleware::{auth_middleware, check_client_ip, IP_NOT_ALLOWED, email_unverified_middleware, mfa_setup_middleware, password_change_middleware, verify_access_token},
+    oidc::OidcProvider,
+    routes::utils::{check_permission, forbid_impersonation, impersonation_error_response, invitation_error_response, ldap_error_response, magic_link_error_response, oidc_login_error_response, password_policy_error, registration_error_response},
+    services::{
+        EmailVerificationError, EmailVerificationMode, EmailVerificationService, InvitationService, LockoutPolicy,
+        RegistrationMode, RegistrationPolicy, RegistrationService, LockoutService, LoginEvent, LoginLogService, MfaError, MfaService, PasswordResetError,
+        PasswordPolicy, PasswordPolicyService, PasswordResetService, RevocationService,
+        SessionService, TokenError, TokenService, LOGIN_FAILURE_ACCOUNT_LOCKED, LOGIN_FAILURE_INVALID_MFA_CODE,
+        LOGIN_FAILURE_EMAIL_UNVERIFIED, LOGIN_FAILURE_INVALID_MFA_TOKEN, LOGIN_FAILURE_INVALID_PASSWORD, LOGIN_FAILURE_INVALID_REQUEST,
+        LOGIN_FAILURE_MFA_PENDING, LOGIN_FAILURE_MFA_SETUP_REQUIRED, LOGIN_FAILURE_MUST_CHANGE_PASSWORD,
+        LOGIN_FAILURE_PASSWORD_EXPIRED, LOGIN_FAILURE_USER_DISABLED,
+        LOGIN_FAILURE_USER_NOT_FOUND, LOGIN_FAILURE_SERVICE_ACCOUNT, LOGIN_FAILURE_INVALID_CLIENT,
+        LOGIN_FAILURE_OIDC_INVALID_STATE, LOGIN_FAILURE_OIDC_NOT_LINKED, LOGIN_FAILURE_OIDC_PROVIDER_ERROR,
+        LOGIN_FAILURE_DIRECTORY_UNAVAILABLE, LOGIN_FAILURE_MAGIC_LINK_INVALID, LOGIN_FAILURE_MAGIC_LINK_NOT_ALLOWED,
+        ImpersonationService, IpAllowlistService, MagicLinkError, MagicLinkService, LOGIN_FAILURE_IP_NOT_ALLOWED, LdapAuthOutcome, LdapService, LdapServiceError, OidcLoginError, OidcService, ServiceAccountError, ServiceAccountService,
+    },
 };
 use sea_orm::DatabaseConnection;
 
 pub fn auth_routes() -> Router<DatabaseConnection> {
     Router::new()
         .route("/register", post(register))
+        .route("/registration-policy", get(registration_policy))
         .route("/login", post(login))
-        .route("/refresh", post(refresh_token).layer(from_fn(auth_middleware)))
-        .route("/me", get(get_current_user).layer(from_fn(auth_middleware)))
+        .route("/token", post(client_credentials_token))
+        .route("/oidc/providers", get(oidc_providers))
+        .route("/oidc/:provider/authorize", get(oidc_authorize))
+        .route("/oidc/callback", post(oidc_callback))
+        .route("/magic-link", post(request_magic_link))
+        .route("/magic-link/login", post(magic_link_login))
+        .route("/refresh", post(refresh_token))
+        .route("/logout", post(logout).layer(from_fn(email_unverified_middleware)))
+        .route("/logout-all", post(logout_all).layer(from_fn(email_unverified_middleware)))
+        .route("/sessions", get(list_sessions).layer(from_fn(auth_middleware)))
+        .route("/sessions/:id", delete(revoke_session).layer(from_fn(auth_middleware)))
+        .route("/mfa", get(mfa_status).layer(from_fn(auth_middleware)))
+        .route("/mfa/enroll", post(mfa_enroll).layer(from_fn(mfa_setup_middleware)))
+        .route("/mfa/activate", post(mfa_activate).layer(from_fn(mfa_setup_middleware)))
+        .route("/mfa/verify", post(mfa_verify))
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
+        .route("/accept-invitation", post(accept_invitation))
+        .route("/verify-email", post(verify_email))
+        .route("/verify-email/resend", post(resend_verification))
+        .route("/impersonation/stop", post(stop_impersonation).layer(from_fn(auth_middleware)))
+        .route("/verify", get(forward_auth))
+        .route("/me", get(get_current_user).put(update_profile).layer(from_fn(email_unverified_middleware)))
+        .route("/me/password", post(change_password).layer(from_fn(password_change_middleware)))
 }
 
 async fn register(
@@ -42,6 +85,21 @@ async fn register(
         ));
     }
 
+    // 检查注册策略：是否开放注册、邀请码、邮箱域名
+    let registration_policy = RegistrationPolicy::from_env().map_err(registration_error_response)?;
+    registration_policy
+        .check(&payload.email, payload.invitation_code.as_deref())
+        .map_err(registration_error_response)?;
+
+    let registration_code = match (&registration_policy.mode, &payload.invitation_code) {
+        (RegistrationMode::InviteOnly, Some(code)) => Some(
+            RegistrationService::find_usable_code(&db, code)
+                .await
+                .map_err(registration_error_response)?,
+        ),
+        _ => None,
+    };
+
     // 检查用户名是否已存在
     let existing_user = user::Entity::find()
         .filter(user::Column::Username.eq(&payload.username))
@@ -90,6 +148,19 @@ async fn register(
         ));
     }
 
+    // 检查密码策略
+    PasswordPolicyService::validate_new_password(
+        &db,
+        &PasswordPolicy::from_env(),
+        "password",
+        &payload.password,
+        &payload.username,
+        &payload.email,
+        None,
+    )
+    .await
+    .map_err(password_policy_error)?;
+
     // 加密密码
     let password_hash = AuthService::hash_password(&payload.password)
         .map_err(|e| {
@@ -102,41 +173,77 @@ async fn register(
             )
         })?;
 
-    // 创建用户
+    // 创建用户，使用邀请码时在同一事务中消耗一次使用次数
+    let db_error = |e: DbErr| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "创建用户失败",
+                "message": e.to_string()
+            })),
+        )
+    };
+
+    let txn = db.begin().await.map_err(db_error)?;
+
+    if let Some(code) = &registration_code {
+        RegistrationService::claim_code(&txn, code.id)
+            .await
+            .map_err(registration_error_response)?;
+    }
+
     let user = user::ActiveModel {
         username: Set(payload.username),
         email: Set(payload.email),
         password_hash: Set(password_hash),
         is_active: Set(true),
+        registration_code_id: Set(registration_code.as_ref().map(|code| code.id)),
         ..Default::default()
     };
 
-    let user = user.insert(&db).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "创建用户失败",
-                "message": e.to_string()
-            })),
-        )
-    })?;
+    let user = user.insert(&txn).await.map_err(db_error)?;
+    txn.commit().await.map_err(db_error)?;
+
+    // 分配默认角色，失败不影响注册结果，管理员可以稍后手动分配
+    if let Err(e) = RegistrationService::assign_default_role(&db, &registration_policy, user.id).await {
+        tracing::error!("为新用户 {} 分配默认角色失败: {}", user.username, e);
+    }
+
+    // 发送邮箱验证邮件，发送失败不影响注册结果，用户可以稍后重新发送
+    if let Err(e) = EmailVerificationService::send_verification(&db, &user).await {
+        tracing::error!("发送邮箱验证邮件失败: {}", e);
+    }
 
     Ok(Json(json!({
-        "message": "用户注册成功",
+        "message": "用户注册成功，验证邮件已发送至您的邮箱",
         "user": {
             "id": user.id,
             "username": user.username,
-            "email": user.email
+            "email": user.email,
+            "email_verified": false
         }
     })))
 }
 
+async fn registration_policy() -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let policy = RegistrationPolicy::from_env().map_err(registration_error_response)?;
+
+    Ok(Json(json!({
+        "mode": policy.mode.as_str(),
+        "enabled": policy.mode != RegistrationMode::Disabled,
+        "invitation_code_required": policy.mode == RegistrationMode::InviteOnly,
+        "allowed_domains": if policy.mode == RegistrationMode::DomainRestricted { policy.allowed_domains } else { Vec::new() }
+    })))
+}
+
 async fn login(
     State(db): State<DatabaseConnection>,
+    client: ClientInfo,
     Json(payload): Json<LoginDto>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 验证输入
     if let Err(errors) = payload.validate() {
+        record_login(&db, LoginEvent::Login, Some(&payload.username), None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
         return Err((
             StatusCode::BAD_REQUEST,
             Json(json!({
@@ -146,10 +253,11 @@ async fn login(
         ));
     }
 
-    // 查找用户
-    let user = user::Entity::find()
-        .filter(user::Column::Username.eq(&payload.username))
-        .one(&db)
+    let policy = LockoutPolicy::from_env();
+    let ip_address = client.ip_address.as_deref();
+
+    // 检查账户或IP是否已被锁定
+    let locked_until = LockoutService::check_lockout(&db, &payload.username, ip_address)
         .await
         .map_err(|e| {
             (
@@ -161,26 +269,65 @@ async fn login(
             )
         })?;
 
-    let user = user.ok_or((
-        StatusCode::UNAUTHORIZED,
-        Json(json!({
-            "error": "用户名或密码错误"
-        })),
-    ))?;
+    if let Some(locked_until) = locked_until {
+        record_login(&db, LoginEvent::Login, Some(&payload.username), None, &client, Some(LOGIN_FAILURE_ACCOUNT_LOCKED)).await;
+        return Err(locked_response(locked_until));
+    }
 
-    // 验证密码
-    AuthService::verify_password(&payload.password, &user.password_hash)
-        .map_err(|_| {
+    // 查找用户
+    let user = user::Entity::find()
+        .filter(user::Column::Username.eq(&payload.username))
+        .one(&db)
+        .await
+        .map_err(|e| {
             (
-                StatusCode::UNAUTHORIZED,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "用户名或密码错误"
+                    "error": "数据库错误",
+                    "message": e.to_string()
                 })),
             )
         })?;
 
+    // 目录用户使用目录密码验证，本地不存在的目录用户在这里自动创建
+    let ldap_outcome = match LdapService::authenticate(&db, &payload.username, &payload.password, user.as_ref()).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            if matches!(e, LdapServiceError::Ldap(_)) {
+                tracing::warn!("目录认证失败: {}", e);
+                let user_id = user.as_ref().map(|user| user.id);
+                record_login(&db, LoginEvent::Login, Some(&payload.username), user_id, &client, Some(LOGIN_FAILURE_DIRECTORY_UNAVAILABLE)).await;
+            }
+            return Err(ldap_error_response(e));
+        }
+    };
+
+    // 验证密码，服务账户不能使用密码登录
+    let user = match (ldap_outcome, user) {
+        (LdapAuthOutcome::Authenticated(user), _) => user,
+        (LdapAuthOutcome::InvalidCredentials, user) => {
+            let (user_id, reason) = match user {
+                Some(user) => (Some(user.id), LOGIN_FAILURE_INVALID_PASSWORD),
+                None => (None, LOGIN_FAILURE_USER_NOT_FOUND),
+            };
+            return Err(login_failed(&db, &policy, LoginEvent::Login, &payload.username, user_id, &client, reason).await);
+        }
+        (LdapAuthOutcome::NotDirectoryUser, Some(user)) if !user.is_service_account && AuthService::verify_password(&payload.password, &user.password_hash).is_ok() => {
+            upgrade_password_hash(&db, user, &payload.password).await
+        }
+        (LdapAuthOutcome::NotDirectoryUser, user) => {
+            let (user_id, reason) = match user {
+                Some(user) if user.is_service_account => (Some(user.id), LOGIN_FAILURE_SERVICE_ACCOUNT),
+                Some(user) => (Some(user.id), LOGIN_FAILURE_INVALID_PASSWORD),
+                None => (None, LOGIN_FAILURE_USER_NOT_FOUND),
+            };
+            return Err(login_failed(&db, &policy, LoginEvent::Login, &payload.username, user_id, &client, reason).await);
+        }
+    };
+
     // 检查用户是否激活
     if !user.is_active {
+        record_login(&db, LoginEvent::Login, Some(&payload.username), Some(user.id), &client, Some(LOGIN_FAILURE_USER_DISABLED)).await;
         return Err((
             StatusCode::FORBIDDEN,
             Json(json!({
@@ -189,105 +336,183 @@ async fn login(
         ));
     }
 
-    // 生成JWT令牌
-    let token = AuthService::generate_token(user.id, &user.username)
-        .map_err(|e| {
-            (
+    check_ip_allowlist(&db, &user, &client, LoginEvent::Login).await?;
+
+    // 配置为必须验证邮箱时，未验证的用户不能登录
+    if EmailVerificationMode::from_env().blocks_login(&user) {
+        record_login(&db, LoginEvent::Login, Some(&payload.username), Some(user.id), &client, Some(LOGIN_FAILURE_EMAIL_UNVERIFIED)).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "邮箱尚未验证，请先查收验证邮件",
+                "email_verification_required": true
+            })),
+        ));
+    }
+
+    // 已启用二次验证或所属角色要求二次验证：返回临时令牌
+    if let Some(response) = second_factor_challenge(&db, &user, &client, LoginEvent::Login).await? {
+        return Ok(response);
+    }
+
+    complete_login(&db, &user, &client, LoginEvent::Login).await
+}
+
+// 服务账户使用客户端凭据换取访问令牌，不签发刷新令牌，也不创建登录会话
+async fn client_credentials_token(
+    State(db): State<DatabaseConnection>,
+    client: ClientInfo,
+    Json(payload): Json<ClientCredentialsDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        record_login(&db, LoginEvent::ClientCredentials, Some(&payload.client_id), None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    if payload.grant_type != "client_credentials" {
+        record_login(&db, LoginEvent::ClientCredentials, Some(&payload.client_id), None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "不支持的授权类型",
+                "grant_type": payload.grant_type
+            })),
+        ));
+    }
+
+    let account = match ServiceAccountService::authenticate_client(&db, &payload.client_id, &payload.client_secret).await {
+        Ok(account) => account,
+        Err(ServiceAccountError::InvalidClient) => {
+            record_login(&db, LoginEvent::ClientCredentials, Some(&payload.client_id), None, &client, Some(LOGIN_FAILURE_INVALID_CLIENT)).await;
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(json!({
+                    "error": ServiceAccountError::InvalidClient.to_string()
+                })),
+            ));
+        }
+        Err(e) => {
+            return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "生成令牌失败",
+                    "error": "数据库错误",
                     "message": e.to_string()
                 })),
-            )
-        })?;
-
-    let auth_response = AuthResponse {
-        token,
-        token_type: "Bearer".to_string(),
-        expires_in: 86400, // 24小时
+            ));
+        }
     };
 
-    Ok(Json(json!({
-        "message": "登录成功",
-        "auth": auth_response
-    })))
-}
+    check_ip_allowlist(&db, &account, &client, LoginEvent::ClientCredentials).await?;
 
-async fn get_current_user(
-    State(db): State<DatabaseConnection>,
-    auth: RequireAuth,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 查找用户
-    let user = user::Entity::find_by_id(auth.user_id)
-        .one(&db)
-        .await
+    let access_token = AuthService::generate_token(account.id, &account.username)
         .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "数据库错误",
+                    "error": "生成令牌失败",
                     "message": e.to_string()
                 })),
             )
         })?;
 
-    let user = user.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(json!({
-            "error": "用户不存在"
-        })),
-    ))?;
+    record_login(&db, LoginEvent::ClientCredentials, Some(&account.username), Some(account.id), &client, None).await;
 
-    // 获取用户角色和权限
-    let roles = RbacService::get_user_roles(&db, user.id)
-        .await
-        .unwrap_or_default();
+    Ok(Json(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": AuthService::access_token_ttl()
+    })))
+}
 
-    let permissions = RbacService::get_user_permissions(&db, user.id)
-        .await
-        .unwrap_or_default();
+// 可用的外部身份提供方，供登录页显示登录按钮
+async fn oidc_providers() -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let providers = OidcProvider::all_from_env().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "OIDC配置无效",
+                "message": e.to_string()
+            })),
+        )
+    })?;
 
-    let user_response = UserResponse {
-        id: user.id,
-        username: user.username,
-        email: user.email,
-        is_active: user.is_active,
-        roles,
-        permissions: permissions.into_iter().collect(),
-    };
+    let providers: Vec<Value> = providers
+        .iter()
+        .map(|provider| {
+            json!({
+                "name": provider.name,
+                "display_name": provider.display_name
+            })
+        })
+        .collect();
 
     Ok(Json(json!({
-        "user": user_response
+        "data": providers
     })))
 }
 
-async fn refresh_token(
+// 发起OIDC登录，前端跳转到返回的授权地址
+async fn oidc_authorize(
     State(db): State<DatabaseConnection>,
-    auth: RequireAuth,
+    Path(provider): Path<String>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 查找用户以确保用户仍然存在且激活
-    let user = user::Entity::find_by_id(auth.user_id)
-        .one(&db)
+    let authorization_url = OidcService::begin(&db, &provider)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "数据库错误",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
+        .map_err(oidc_login_error_response)?;
 
-    let user = user.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(json!({
-            "error": "用户不存在"
-        })),
-    ))?;
+    Ok(Json(json!({
+        "authorization_url": authorization_url
+    })))
+}
+
+// 身份提供方回调前端后，前端提交授权码完成登录
+// 身份验证（包括二次验证）由身份提供方负责，不检查本地密码是否过期
+async fn oidc_callback(
+    State(db): State<DatabaseConnection>,
+    client: ClientInfo,
+    Json(payload): Json<OidcCallbackDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        record_login(&db, LoginEvent::Oidc, None, None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
 
-    // 检查用户是否仍然激活
+    let login = match OidcService::complete(&db, payload).await {
+        Ok(login) => login,
+        Err(e) => {
+            let reason = match e {
+                OidcLoginError::InvalidState => Some(LOGIN_FAILURE_OIDC_INVALID_STATE),
+                OidcLoginError::Oidc(_) => Some(LOGIN_FAILURE_OIDC_PROVIDER_ERROR),
+                OidcLoginError::AccountNotLinked | OidcLoginError::EmailRequired | OidcLoginError::EmailTaken => {
+                    Some(LOGIN_FAILURE_OIDC_NOT_LINKED)
+                }
+                OidcLoginError::DatabaseError(_) => None,
+            };
+            if let Some(reason) = reason {
+                record_login(&db, LoginEvent::Oidc, None, None, &client, Some(reason)).await;
+            }
+            return Err(oidc_login_error_response(e));
+        }
+    };
+    let user = login.user;
+
+    // 检查用户是否激活
     if !user.is_active {
+        record_login(&db, LoginEvent::Oidc, Some(&user.username), Some(user.id), &client, Some(LOGIN_FAILURE_USER_DISABLED)).await;
         return Err((
             StatusCode::FORBIDDEN,
             Json(json!({
@@ -296,26 +521,1489 @@ async fn refresh_token(
         ));
     }
 
-    // 生成新的JWT令牌
-    let new_token = AuthService::generate_token(user.id, &user.username)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "生成令牌失败",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
+    check_ip_allowlist(&db, &user, &client, LoginEvent::Oidc).await?;
 
-    let auth_response = AuthResponse {
-        token: new_token,
-        token_type: "Bearer".to_string(),
-        expires_in: 86400, // 24小时
-    };
+    // 配置为必须验证邮箱时，未验证的用户不能登录
+    if EmailVerificationMode::from_env().blocks_login(&user) {
+        record_login(&db, LoginEvent::Oidc, Some(&user.username), Some(user.id), &client, Some(LOGIN_FAILURE_EMAIL_UNVERIFIED)).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "邮箱尚未验证，请先查收验证邮件",
+                "email_verification_required": true
+            })),
+        ));
+    }
+
+    // 本地二次验证照常生效，除非该提供方配置为信任身份提供方的多因素认证且ID令牌表明已完成
+    if !login.mfa_satisfied {
+        if let Some(response) = second_factor_challenge(&db, &user, &client, LoginEvent::Oidc).await? {
+            return Ok(response);
+        }
+    }
+
+    issue_login_tokens(&db, &user, &client, LoginEvent::Oidc).await
+}
+
+// 无论邮箱是否注册、所属角色是否允许都返回相同的响应
+async fn request_magic_link(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<MagicLinkRequestDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    match MagicLinkService::request_link(&db, &payload.email).await {
+        Ok(()) => {}
+        Err(MagicLinkError::Disabled) => return Err(magic_link_error_response(MagicLinkError::Disabled)),
+        Err(e) => tracing::error!("处理登录链接请求失败: {}", e),
+    }
 
     Ok(Json(json!({
-        "message": "令牌刷新成功",
-        "auth": auth_response
+        "message": "如果该邮箱已注册且允许使用邮件链接登录，您将收到一封包含登录链接的邮件",
+        "expires_in": MagicLinkService::token_ttl()
     })))
 }
+
+// 使用邮件中的登录链接登录，与密码登录一样检查二次验证
+async fn magic_link_login(
+    State(db): State<DatabaseConnection>,
+    client: ClientInfo,
+    Json(payload): Json<MagicLinkLoginDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        record_login(&db, LoginEvent::MagicLink, None, None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let user = match MagicLinkService::consume(&db, &payload.token).await {
+        Ok(user) => user,
+        Err(e) => {
+            if matches!(e, MagicLinkError::InvalidToken) {
+                record_login(&db, LoginEvent::MagicLink, None, None, &client, Some(LOGIN_FAILURE_MAGIC_LINK_INVALID)).await;
+            }
+            return Err(magic_link_error_response(e));
+        }
+    };
+
+    // 检查用户是否激活
+    if !user.is_active || user.is_service_account {
+        let reason = if user.is_service_account { LOGIN_FAILURE_SERVICE_ACCOUNT } else { LOGIN_FAILURE_USER_DISABLED };
+        record_login(&db, LoginEvent::MagicLink, Some(&user.username), Some(user.id), &client, Some(reason)).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "账户已被禁用"
+            })),
+        ));
+    }
+
+    check_ip_allowlist(&db, &user, &client, LoginEvent::MagicLink).await?;
+
+    // 发送链接后角色可能已被调整，使用时再检查一次
+    let allowed = RbacService::is_magic_link_allowed(&db, user.id)
+        .await
+        .map_err(|e| magic_link_error_response(e.into()))?;
+
+    if !allowed {
+        record_login(&db, LoginEvent::MagicLink, Some(&user.username), Some(user.id), &client, Some(LOGIN_FAILURE_MAGIC_LINK_NOT_ALLOWED)).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "所属角色不允许使用邮件链接登录，请使用密码登录"
+            })),
+        ));
+    }
+
+    // 配置为必须验证邮箱时，未验证的用户不能登录
+    if EmailVerificationMode::from_env().blocks_login(&user) {
+        record_login(&db, LoginEvent::MagicLink, Some(&user.username), Some(user.id), &client, Some(LOGIN_FAILURE_EMAIL_UNVERIFIED)).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "邮箱尚未验证，请先查收验证邮件",
+                "email_verification_required": true
+            })),
+        ));
+    }
+
+    if let Some(response) = second_factor_challenge(&db, &user, &client, LoginEvent::MagicLink).await? {
+        return Ok(response);
+    }
+
+    complete_login(&db, &user, &client, LoginEvent::MagicLink).await
+}
+
+async fn mfa_status(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let enabled = MfaService::is_enabled(&db, claims.sub)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let required = RbacService::is_mfa_required(&db, claims.sub)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "权限检查失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "enabled": enabled,
+        "required": required
+    })))
+}
+
+async fn mfa_enroll(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    forbid_impersonation(&claims)?;
+
+    let (secret, otpauth_uri) = MfaService::start_enrollment(&db, claims.sub, &claims.username)
+        .await
+        .map_err(mfa_error_response)?;
+
+    Ok(Json(json!({
+        "message": "请使用认证器App扫描二维码，并提交验证码完成绑定",
+        "enrollment": MfaEnrollmentResponse { secret, otpauth_uri }
+    })))
+}
+
+async fn mfa_activate(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+    Json(payload): Json<MfaCodeDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    forbid_impersonation(&claims)?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let recovery_codes = MfaService::activate(&db, claims.sub, &payload.code)
+        .await
+        .map_err(mfa_error_response)?;
+
+    // 使用访问令牌绑定：直接返回恢复码
+    if claims.token_use.is_access() {
+        return Ok(Json(json!({
+            "message": "二次验证已启用，请妥善保存恢复码",
+            "recovery_codes": recovery_codes
+        })));
+    }
+
+    // 登录时被要求绑定：绑定完成即视为通过二次验证，作废临时令牌并签发正式令牌
+    RevocationService::revoke_token(&db, &claims, "mfa_setup_completed")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = find_active_user(&db, claims.sub).await?;
+    let Json(mut response) = complete_login(&db, &user, &client, LoginEvent::Mfa).await?;
+    response["message"] = json!("二次验证已启用，请妥善保存恢复码");
+    response["recovery_codes"] = json!(recovery_codes);
+
+    Ok(Json(response))
+}
+
+async fn mfa_verify(
+    State(db): State<DatabaseConnection>,
+    client: ClientInfo,
+    Json(payload): Json<MfaVerifyDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        record_login(&db, LoginEvent::Mfa, None, None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let invalid_token = (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({
+            "error": "二次验证令牌无效或已过期，请重新登录"
+        })),
+    );
+
+    // 只接受登录接口签发的 MfaPending 临时令牌，且每个只能使用一次
+    let claims = match AuthService::verify_token(&payload.mfa_token) {
+        Ok(claims) if claims.token_use == TokenUse::MfaPending => claims,
+        _ => {
+            record_login(&db, LoginEvent::Mfa, None, None, &client, Some(LOGIN_FAILURE_INVALID_MFA_TOKEN)).await;
+            return Err(invalid_token);
+        }
+    };
+
+    let revoked = RevocationService::is_revoked(&db, &claims)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if revoked {
+        record_login(&db, LoginEvent::Mfa, Some(&claims.username), Some(claims.sub), &client, Some(LOGIN_FAILURE_INVALID_MFA_TOKEN)).await;
+        return Err(invalid_token);
+    }
+
+    // 验证码错误同样计入登录失败次数，防止暴力破解
+    let policy = LockoutPolicy::from_env();
+    let locked_until = LockoutService::check_lockout(&db, &claims.username, client.ip_address.as_deref())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if let Some(locked_until) = locked_until {
+        record_login(&db, LoginEvent::Mfa, Some(&claims.username), Some(claims.sub), &client, Some(LOGIN_FAILURE_ACCOUNT_LOCKED)).await;
+        return Err(locked_response(locked_until));
+    }
+
+    let user = find_active_user(&db, claims.sub).await?;
+
+    match MfaService::verify_code(&db, user.id, &payload.code).await {
+        Ok(()) => {}
+        Err(MfaError::DatabaseError(e)) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            ));
+        }
+        Err(_) => {
+            return Err(login_failed(&db, &policy, LoginEvent::Mfa, &user.username, Some(user.id), &client, LOGIN_FAILURE_INVALID_MFA_CODE).await);
+        }
+    }
+
+    RevocationService::revoke_token(&db, &claims, "mfa_verified")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    complete_login(&db, &user, &client, LoginEvent::Mfa).await
+}
+
+async fn forgot_password(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<ForgotPasswordDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    // 无论邮箱是否注册都返回相同的响应
+    if let Err(e) = PasswordResetService::request_reset(&db, &payload.email).await {
+        tracing::error!("处理找回密码请求失败: {}", e);
+    }
+
+    Ok(Json(json!({
+        "message": "如果该邮箱已注册，您将收到一封重置密码的邮件"
+    })))
+}
+
+async fn reset_password(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<ResetPasswordDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    PasswordResetService::reset_password(&db, &payload.token, &payload.new_password)
+        .await
+        .map_err(|e| match e {
+            PasswordResetError::InvalidToken => (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": e.to_string()
+                })),
+            ),
+            PasswordResetError::PasswordPolicy(e) => password_policy_error(e),
+            PasswordResetError::DatabaseError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "重置密码失败",
+                    "message": e.to_string()
+                })),
+            ),
+        })?;
+
+    Ok(Json(json!({
+        "message": "密码已重置，请使用新密码登录"
+    })))
+}
+
+async fn accept_invitation(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<AcceptInvitationDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let user = InvitationService::accept(&db, &payload.token, &payload.password)
+        .await
+        .map_err(invitation_error_response)?;
+
+    Ok(Json(json!({
+        "message": "账户已激活，请使用新密码登录",
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email
+        }
+    })))
+}
+
+async fn verify_email(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<VerifyEmailDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let user = EmailVerificationService::verify(&db, &payload.token)
+        .await
+        .map_err(|e| match e {
+            EmailVerificationError::InvalidToken => (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": e.to_string()
+                })),
+            ),
+            EmailVerificationError::DatabaseError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "验证邮箱失败",
+                    "message": e.to_string()
+                })),
+            ),
+        })?;
+
+    Ok(Json(json!({
+        "message": "邮箱验证成功",
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "email_verified": true
+        }
+    })))
+}
+
+async fn resend_verification(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<ResendVerificationDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    // 无论邮箱是否注册、是否已验证都返回相同的响应
+    if let Err(e) = EmailVerificationService::request_resend(&db, &payload.email).await {
+        tracing::error!("处理重新发送验证邮件请求失败: {}", e);
+    }
+
+    Ok(Json(json!({
+        "message": "如果该邮箱已注册且尚未验证，您将收到一封验证邮件"
+    })))
+}
+
+async fn get_current_user(
+    State(db): State<DatabaseConnection>,
+    auth: RequireAuth,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 查找用户
+    let user = user::Entity::find_by_id(auth.user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = user.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "用户不存在"
+        })),
+    ))?;
+
+    // 获取用户角色和权限
+    let access = RbacService::get_user_access(&db, user.id)
+        .await
+        .unwrap_or_default();
+
+    let user_response = UserResponse {
+        id: user.id,
+        username: user.username,
+        email: user.email,
+        is_active: user.is_active,
+        email_verified: user.email_verified_at.is_some(),
+        is_service_account: user.is_service_account,
+        roles: access.roles,
+        permissions: access.permissions.into_iter().collect(),
+    };
+
+    // 模拟登录时返回实际操作的管理员，前端据此提示正在模拟登录
+    let impersonator = auth.impersonator.map(|actor| {
+        json!({
+            "id": actor.sub,
+            "username": actor.username
+        })
+    });
+
+    Ok(Json(json!({
+        "user": user_response,
+        "impersonator": impersonator
+    })))
+}
+
+// 结束模拟登录：撤销当前的模拟登录令牌，管理员恢复使用自己的令牌
+async fn stop_impersonation(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let actor = ImpersonationService::stop(&db, &claims)
+        .await
+        .map_err(impersonation_error_response)?;
+
+    if let Err(e) = LoginLogService::record_impersonation(
+        &db,
+        LoginEvent::ImpersonationStop,
+        actor.sub,
+        Some(&claims.username),
+        Some(claims.sub),
+        &client,
+        None,
+    )
+    .await
+    {
+        tracing::warn!("记录登录日志失败: {}", e);
+    }
+
+    Ok(Json(json!({
+        "message": "已结束模拟登录"
+    })))
+}
+
+// 反向代理转发认证（nginx auth_request、Traefik ForwardAuth）：令牌有效且具有要求的权限时返回200，
+// 并通过响应头返回用户信息；用户名和角色名按URL编码，多个角色以逗号分隔
+async fn forward_auth(
+    State(db): State<DatabaseConnection>,
+    client: ClientInfo,
+    headers: HeaderMap,
+    Query(query): Query<ForwardAuthQuery>,
+) -> Result<(HeaderMap, Json<Value>), (StatusCode, Json<Value>)> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| AuthService::extract_token_from_header(value).ok())
+        .or_else(|| cookie_value(&headers, &forward_auth_cookie()))
+        .ok_or((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "认证失败，请提供有效的JWT令牌"
+            })),
+        ))?;
+
+    let claims = verify_access_token(&token)
+        .await
+        .map_err(|(status, error)| (status, Json(json!({ "error": error }))))?;
+
+    // 客户端地址取自代理转发的请求头，代理需要配置在 TRUSTED_PROXIES 中
+    check_client_ip(&claims, &client)
+        .await
+        .map_err(|(status, error)| (status, Json(json!({ "error": error }))))?;
+
+    // 请求头优先于查询参数
+    let required = headers
+        .get(REQUIRED_PERMISSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or(query.permission);
+
+    if let Some(required) = required {
+        let (resource, action) = parse_permission(&required).ok_or((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "权限格式无效，应为 resource:action"
+            })),
+        ))?;
+        check_permission(&db, &claims, resource, action).await?;
+    }
+
+    let roles = RbacService::get_user_roles(&db, claims.sub)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let mut response_headers = HeaderMap::new();
+    let encoded_roles = roles
+        .iter()
+        .map(|role| urlencoding::encode(role).into_owned())
+        .collect::<Vec<_>>()
+        .join(",");
+    for (name, value) in [
+        (USER_ID_HEADER, claims.sub.to_string()),
+        (USER_NAME_HEADER, urlencoding::encode(&claims.username).into_owned()),
+        (USER_ROLES_HEADER, encoded_roles),
+    ] {
+        // URL编码后只包含可见ASCII字符，转换不会失败
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response_headers.insert(name, value);
+        }
+    }
+
+    Ok((
+        response_headers,
+        Json(json!({
+            "user_id": claims.sub,
+            "username": claims.username,
+            "roles": roles
+        })),
+    ))
+}
+
+const REQUIRED_PERMISSION_HEADER: &str = "x-required-permission";
+const USER_ID_HEADER: &str = "x-user-id";
+const USER_NAME_HEADER: &str = "x-user-name";
+const USER_ROLES_HEADER: &str = "x-user-roles";
+
+// 浏览器访问被代理的应用时不会携带Authorization头，从该Cookie中读取访问令牌
+fn forward_auth_cookie() -> String {
+    std::env::var("FORWARD_AUTH_COOKIE").unwrap_or_else(|_| "access_token".to_string())
+}
+
+fn cookie_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, value)| *key == name && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+fn parse_permission(permission: &str) -> Option<(&str, &str)> {
+    let (resource, action) = permission.trim().split_once(':')?;
+    if resource.is_empty() || action.is_empty() || action.contains(':') {
+        return None;
+    }
+    Some((resource, action))
+}
+
+async fn update_profile(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<UpdateProfileDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    forbid_impersonation(&claims)?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let user = find_active_user(&db, claims.sub).await?;
+    let mut user_model: user::ActiveModel = user.clone().into();
+    let mut email_changed = false;
+
+    if let Some(email) = payload.email.filter(|email| *email != user.email) {
+        // 检查邮箱是否已被其他用户使用
+        let existing_email = user::Entity::find()
+            .filter(user::Column::Email.eq(&email))
+            .one(&db)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({
+                        "error": "数据库错误",
+                        "message": e.to_string()
+                    })),
+                )
+            })?;
+
+        if existing_email.is_some() {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(json!({
+                    "error": "邮箱已存在"
+                })),
+            ));
+        }
+
+        // 新邮箱需要重新验证
+        user_model.email = Set(email);
+        user_model.email_verified_at = Set(None);
+        email_changed = true;
+    }
+
+    let user = if user_model.is_changed() {
+        user_model.updated_at = Set(Utc::now().into());
+        user_model.update(&db).await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "更新用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?
+    } else {
+        user
+    };
+
+    if email_changed {
+        if let Err(e) = EmailVerificationService::send_verification(&db, &user).await {
+            tracing::error!("发送邮箱验证邮件失败: {}", e);
+        }
+    }
+
+    Ok(Json(json!({
+        "message": "个人信息更新成功",
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "email_verified": EmailVerificationService::is_verified(&user),
+            "is_active": user.is_active
+        }
+    })))
+}
+
+async fn change_password(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+    Json(payload): Json<ChangePasswordDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    forbid_impersonation(&claims)?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let user = find_active_user(&db, claims.sub).await?;
+
+    // 当前密码错误与登录失败共用失败计数和锁定规则，防止借已登录的会话暴力破解密码
+    let lockout_policy = LockoutPolicy::from_env();
+    let locked_until = LockoutService::check_lockout(&db, &user.username, client.ip_address.as_deref())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if let Some(locked_until) = locked_until {
+        return Err(locked_response(locked_until));
+    }
+
+    if AuthService::verify_password(&payload.current_password, &user.password_hash).is_err() {
+        if let Some(locked_until) = record_credential_failure(&db, &lockout_policy, Some(&user.username), &client).await? {
+            return Err(locked_response(locked_until));
+        }
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "当前密码错误"
+            })),
+        ));
+    }
+
+    let policy = PasswordPolicy::from_env();
+    PasswordPolicyService::validate_new_password(
+        &db,
+        &policy,
+        "new_password",
+        &payload.new_password,
+        &user.username,
+        &user.email,
+        Some(&user),
+    )
+    .await
+    .map_err(password_policy_error)?;
+
+    let user = PasswordPolicyService::set_password(&db, &policy, user, &payload.new_password)
+        .await
+        .map_err(password_policy_error)?;
+
+    // 保留当前会话，使其他设备上的登录全部失效；密码过期时的临时令牌不属于任何会话
+    SessionService::revoke_other_sessions(&db, user.id, claims.sid, "password_changed")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "撤销会话失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if claims.token_use.is_access() {
+        return Ok(Json(json!({
+            "message": "密码修改成功，其他设备上的登录已失效"
+        })));
+    }
+
+    // 登录时密码已过期：修改完成即视为登录成功，作废临时令牌并签发正式令牌
+    RevocationService::revoke_token(&db, &claims, "password_changed")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let Json(mut response) = complete_login(&db, &user, &client, LoginEvent::Login).await?;
+    response["message"] = json!("密码修改成功");
+
+    Ok(Json(response))
+}
+
+async fn refresh_token(
+    State(db): State<DatabaseConnection>,
+    client: ClientInfo,
+    Json(payload): Json<RefreshTokenDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        record_login(&db, LoginEvent::Refresh, None, None, &client, Some(LOGIN_FAILURE_INVALID_REQUEST)).await;
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    // 在轮换之前检查IP白名单，被拒绝时刷新令牌仍然有效
+    let owner = TokenService::find_refresh_token_owner(&db, &payload.refresh_token)
+        .await
+        .unwrap_or_default();
+    if let Some(owner) = owner {
+        check_ip_allowlist(&db, &owner, &client, LoginEvent::Refresh).await?;
+    }
+
+    // 轮换刷新令牌：旧令牌作废，签发新的令牌对
+    let (user, auth_response) = match TokenService::rotate_refresh_token(&db, &payload.refresh_token).await {
+        Ok(result) => result,
+        Err(e) => {
+            // 尽量找出令牌所属用户，便于在登录日志中追查
+            let owner = TokenService::find_refresh_token_owner(&db, &payload.refresh_token)
+                .await
+                .unwrap_or_default();
+            record_login(
+                &db,
+                LoginEvent::Refresh,
+                owner.as_ref().map(|u| u.username.as_str()),
+                owner.as_ref().map(|u| u.id),
+                &client,
+                Some(e.log_reason()),
+            )
+            .await;
+            return Err(token_error_response(e));
+        }
+    };
+
+    record_login(&db, LoginEvent::Refresh, Some(&user.username), Some(user.id), &client, None).await;
+
+    Ok(Json(json!({
+        "message": "令牌刷新成功",
+        "auth": auth_response
+    })))
+}
+
+async fn logout(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    payload: Option<Json<LogoutDto>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    // 撤销当前访问令牌
+    RevocationService::revoke_token(&db, &claims, "logout")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "注销失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    // 撤销当前会话；不带会话ID的令牌则按刷新令牌查找会话（只允许撤销自己的会话）
+    let mut session_id = claims.sid;
+    if session_id.is_none() {
+        if let Some(raw_token) = payload.refresh_token {
+            session_id = refresh_token::Entity::find()
+                .filter(refresh_token::Column::TokenHash.eq(AuthService::hash_token(&raw_token)))
+                .filter(refresh_token::Column::UserId.eq(claims.sub))
+                .one(&db)
+                .await
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({
+                            "error": "数据库错误",
+                            "message": e.to_string()
+                        })),
+                    )
+                })?
+                .map(|stored| stored.family_id);
+        }
+    }
+
+    if let Some(session_id) = session_id {
+        SessionService::revoke_session(&db, session_id, "logout")
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({
+                        "error": "注销失败",
+                        "message": e.to_string()
+                    })),
+                )
+            })?;
+    }
+
+    Ok(Json(json!({
+        "message": "注销成功"
+    })))
+}
+
+async fn logout_all(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    forbid_impersonation(&claims)?;
+
+    RevocationService::revoke_all_for_user(&db, claims.sub, "logout_all")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "注销失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "message": "已注销所有会话"
+    })))
+}
+
+async fn list_sessions(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let sessions = SessionService::list_active_sessions(&db, claims.sub)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取会话列表失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let sessions: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::from_model(session, claims.sid))
+        .collect();
+
+    Ok(Json(json!({
+        "sessions": sessions
+    })))
+}
+
+async fn revoke_session(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    forbid_impersonation(&claims)?;
+
+    let session = SessionService::find_user_session(&db, claims.sub, session_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if session.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "会话不存在"
+            })),
+        ));
+    }
+
+    SessionService::revoke_session(&db, session_id, "user_revoked")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "撤销会话失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "message": "会话已撤销"
+    })))
+}
+
+// 帮助函数：检查客户端地址是否在用户或所属角色的IP白名单中，不在时记录登录日志并拒绝
+async fn check_ip_allowlist(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    client: &ClientInfo,
+    event: LoginEvent,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let allowed = IpAllowlistService::is_allowed(db, user.id, client.ip_address.as_deref())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if !allowed {
+        record_login(db, event, Some(&user.username), Some(user.id), client, Some(LOGIN_FAILURE_IP_NOT_ALLOWED)).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": IP_NOT_ALLOWED
+            })),
+        ));
+    }
+
+    Ok(())
+}
+
+// 帮助函数：身份验证通过后检查二次验证，需要二次验证时返回临时令牌的响应
+async fn second_factor_challenge(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    client: &ClientInfo,
+    event: LoginEvent,
+) -> Result<Option<Json<Value>>, (StatusCode, Json<Value>)> {
+    // 已启用二次验证：返回只能用于二次验证的临时令牌
+    let mfa_enabled = MfaService::is_enabled(db, user.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if mfa_enabled {
+        record_login(db, event, Some(&user.username), Some(user.id), client, Some(LOGIN_FAILURE_MFA_PENDING)).await;
+        let mfa_token = generate_mfa_token(user, TokenUse::MfaPending)?;
+
+        return Ok(Some(Json(json!({
+            "message": "请输入二次验证码",
+            "mfa_required": true,
+            "mfa_token": mfa_token,
+            "expires_in": AuthService::mfa_token_ttl()
+        }))));
+    }
+
+    // 所属角色要求二次验证但尚未绑定：返回只能用于绑定的临时令牌
+    let mfa_setup_required = RbacService::is_mfa_required(db, user.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "权限检查失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if mfa_setup_required {
+        record_login(db, event, Some(&user.username), Some(user.id), client, Some(LOGIN_FAILURE_MFA_SETUP_REQUIRED)).await;
+        let mfa_token = generate_mfa_token(user, TokenUse::MfaSetup)?;
+
+        return Ok(Some(Json(json!({
+            "message": "所属角色要求启用二次验证，请先完成绑定",
+            "mfa_setup_required": true,
+            "mfa_token": mfa_token,
+            "expires_in": AuthService::mfa_token_ttl()
+        }))));
+    }
+
+    Ok(None)
+}
+
+// 帮助函数：登录成功，清除失败计数、记录日志并签发令牌
+async fn complete_login(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    client: &ClientInfo,
+    event: LoginEvent,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 管理员设置的初始密码或密码已过期：返回只能用于修改密码的临时令牌
+    // 目录用户的密码由目录管理，没有本地密码，不检查过期
+    let policy = PasswordPolicy::from_env();
+    let password_expired = !user.password_hash.is_empty()
+        && policy.is_expired(&user.password_changed_at.with_timezone(&Utc), &Utc::now());
+    if user.must_change_password || password_expired {
+        let reason = if user.must_change_password {
+            LOGIN_FAILURE_MUST_CHANGE_PASSWORD
+        } else {
+            LOGIN_FAILURE_PASSWORD_EXPIRED
+        };
+        record_login(db, event, Some(&user.username), Some(user.id), client, Some(reason)).await;
+        let password_token = AuthService::generate_password_change_token(user.id, &user.username)
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({
+                        "error": "生成令牌失败",
+                        "message": e.to_string()
+                    })),
+                )
+            })?;
+
+        return Ok(Json(json!({
+            "message": if user.must_change_password { "首次登录请修改密码" } else { "密码已过期，请修改密码" },
+            "must_change_password": user.must_change_password,
+            "password_expired": password_expired,
+            "password_token": password_token,
+            "expires_in": AuthService::mfa_token_ttl()
+        })));
+    }
+
+    LockoutService::record_success(db, &user.username)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    issue_login_tokens(db, user, client, event).await
+}
+
+// 帮助函数：记录登录成功并签发访问令牌和刷新令牌
+async fn issue_login_tokens(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    client: &ClientInfo,
+    event: LoginEvent,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    record_login(db, event, Some(&user.username), Some(user.id), client, None).await;
+
+    // 生成访问令牌和刷新令牌
+    let auth_response = TokenService::issue_tokens(db, user, client)
+        .await
+        .map_err(token_error_response)?;
+
+    Ok(Json(json!({
+        "message": "登录成功",
+        "auth": auth_response
+    })))
+}
+
+// 帮助函数：查找仍处于激活状态的用户
+async fn find_active_user(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<user::Model, (StatusCode, Json<Value>)> {
+    let user = user::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    match user {
+        Some(user) if user.is_active => Ok(user),
+        Some(_) => Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "账户已被禁用"
+            })),
+        )),
+        None => Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "用户不存在"
+            })),
+        )),
+    }
+}
+
+// 帮助函数：生成二次验证过程中使用的临时令牌
+fn generate_mfa_token(
+    user: &user::Model,
+    token_use: TokenUse,
+) -> Result<String, (StatusCode, Json<Value>)> {
+    AuthService::generate_mfa_token(user.id, &user.username, token_use).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "生成令牌失败",
+                "message": e.to_string()
+            })),
+        )
+    })
+}
+
+// 帮助函数：记录登录日志，写入失败不影响登录流程
+async fn record_login(
+    db: &DatabaseConnection,
+    event: LoginEvent,
+    username: Option<&str>,
+    user_id: Option<i32>,
+    client: &ClientInfo,
+    failure_reason: Option<&str>,
+) {
+    if let Err(e) = LoginLogService::record(db, event, username, user_id, client, failure_reason).await {
+        tracing::warn!("记录登录日志失败: {}", e);
+    }
+}
+
+// 帮助函数：密码验证通过后将旧算法或旧参数的密码哈希重新计算，失败时不影响登录
+async fn upgrade_password_hash(db: &DatabaseConnection, user: user::Model, password: &str) -> user::Model {
+    let fallback = user.clone();
+    match PasswordPolicyService::rehash_if_needed(db, user, password).await {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::warn!("升级密码哈希失败: {}", e);
+            fallback
+        }
+    }
+}
+
+// 帮助函数：处理密码或二次验证码错误，累计失败次数并按失败次数延迟响应
+async fn login_failed(
+    db: &DatabaseConnection,
+    policy: &LockoutPolicy,
+    event: LoginEvent,
+    username: &str,
+    user_id: Option<i32>,
+    client: &ClientInfo,
+    reason: &str,
+) -> (StatusCode, Json<Value>) {
+    record_login(db, event, Some(username), user_id, client, Some(reason)).await;
+
+    // 用户名不存在时只按IP计数
+    let known_username = user_id.map(|_| username);
+    let locked_until = match record_credential_failure(db, policy, known_username, client).await {
+        Ok(locked_until) => locked_until,
+        Err(response) => return response,
+    };
+
+    match locked_until {
+        Some(locked_until) => locked_response(locked_until),
+        None => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": match event {
+                    LoginEvent::Mfa => "验证码无效",
+                    _ => "用户名或密码错误",
+                }
+            })),
+        ),
+    }
+}
+
+// 帮助函数：累计密码或验证码错误次数并按失败次数延迟，返回因本次失败而锁定的截止时间
+async fn record_credential_failure(
+    db: &DatabaseConnection,
+    policy: &LockoutPolicy,
+    username: Option<&str>,
+    client: &ClientInfo,
+) -> Result<Option<DateTime<Utc>>, (StatusCode, Json<Value>)> {
+    let outcome = LockoutService::record_failure(db, policy, username, client.ip_address.as_deref())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    tokio::time::sleep(policy.failure_delay(outcome.failed_count)).await;
+
+    Ok(outcome.locked_until)
+}
+
+// 帮助函数：账户锁定响应
+fn locked_response(locked_until: DateTime<Utc>) -> (StatusCode, Json<Value>) {
+    let retry_after = (locked_until - Utc::now()).num_seconds().max(0);
+
+    (
+        StatusCode::LOCKED,
+        Json(json!({
+            "error": "登录失败次数过多，账户已被临时锁定",
+            "locked_until": locked_until,
+            "retry_after": retry_after
+        })),
+    )
+}
+
+// 帮助函数：将二次验证错误转换为HTTP响应
+fn mfa_error_response(e: MfaError) -> (StatusCode, Json<Value>) {
+    match e {
+        MfaError::NotEnrolled | MfaError::InvalidCode => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        MfaError::AlreadyEnabled => (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        MfaError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "数据库错误",
+                "message": e.to_string()
+            })),
+        ),
+    }
+}
+
+// 帮助函数：将令牌错误转换为HTTP响应
+fn token_error_response(e: TokenError) -> (StatusCode, Json<Value>) {
+    match e {
+        TokenError::InvalidRefreshToken
+        | TokenError::RefreshTokenExpired
+        | TokenError::RefreshTokenReused
+        | TokenError::UserNotFound => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        TokenError::UserDisabled => (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        TokenError::DatabaseError(_) | TokenError::AuthError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "生成令牌失败",
+                "message": e.to_string()
+            })),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_value() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark; access_token=abc.def; empty="));
+
+        assert_eq!(cookie_value(&headers, "access_token").as_deref(), Some("abc.def"));
+        assert_eq!(cookie_value(&headers, "theme").as_deref(), Some("dark"));
+        assert!(cookie_value(&headers, "empty").is_none());
+        assert!(cookie_value(&headers, "missing").is_none());
+        assert!(cookie_value(&HeaderMap::new(), "access_token").is_none());
+    }
+
+    #[test]
+    fn test_parse_permission() {
+        assert_eq!(parse_permission("user:read"), Some(("user", "read")));
+        assert_eq!(parse_permission(" oauth_client:update "), Some(("oauth_client", "update")));
+        assert!(parse_permission("user").is_none());
+        assert!(parse_permission(":read").is_none());
+        assert!(parse_permission("user:").is_none());
+        assert!(parse_permission("user:read:all").is_none());
+    }
+}
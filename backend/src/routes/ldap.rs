@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    models::CreateLdapGroupMappingDto,
+    extractors::AuthUser,
+    routes::utils::{check_permission, ldap_error_response},
+    services::LdapService,
+};
+use sea_orm::DatabaseConnection;
+
+// 目录组到角色的映射决定了目录用户的角色，管理需要 role:update 权限
+pub fn ldap_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/group-mappings", get(list_group_mappings).post(create_group_mapping))
+        .route("/group-mappings/:id", delete(delete_group_mapping))
+        .route("/sync", post(sync_directory))
+}
+
+async fn list_group_mappings(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "role", "read").await?;
+
+    let mappings = LdapService::list_mappings(&db)
+        .await
+        .map_err(ldap_error_response)?;
+
+    Ok(Json(json!({
+        "data": mappings
+    })))
+}
+
+async fn create_group_mapping(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateLdapGroupMappingDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "role", "update").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let mapping = LdapService::create_mapping(&db, payload)
+        .await
+        .map_err(ldap_error_response)?;
+
+    Ok(Json(json!({
+        "message": "组映射创建成功，目录用户下次登录或同步时生效",
+        "mapping": mapping
+    })))
+}
+
+async fn delete_group_mapping(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "role", "update").await?;
+
+    LdapService::delete_mapping(&db, id)
+        .await
+        .map_err(ldap_error_response)?;
+
+    Ok(Json(json!({
+        "message": "组映射已删除，已分配的角色不会自动移除"
+    })))
+}
+
+// 立即同步目录：更新目录用户的邮箱和角色，停用已从目录中移除的用户
+async fn sync_directory(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let report = LdapService::sync(&db)
+        .await
+        .map_err(ldap_error_response)?;
+
+    Ok(Json(json!({
+        "message": "目录同步完成",
+        "report": report
+    })))
+}
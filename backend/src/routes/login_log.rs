@@ -0,0 +1,66 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    models::{LoginLogFilter, PaginationInfo, PaginationQuery, PaginationResponse},
+    extractors::AuthUser,
+    routes::utils::check_permission,
+    services::LoginLogService,
+};
+use sea_orm::DatabaseConnection;
+
+pub fn login_log_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/", get(list_login_logs))
+}
+
+async fn list_login_logs(
+    State(db): State<DatabaseConnection>,
+    Query(pagination): Query<PaginationQuery>,
+    Query(filter): Query<LoginLogFilter>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "login_log", "read").await?;
+
+    // 验证分页参数
+    if let Err(errors) = pagination.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "分页参数验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let page = pagination.page.unwrap_or(1);
+    let per_page = pagination.per_page.unwrap_or(20);
+
+    let (logs, total) = LoginLogService::list(&db, &filter, page, per_page)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取登录日志失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let pagination_info = PaginationInfo::new(page, per_page, total);
+    let response = PaginationResponse {
+        data: logs,
+        pagination: pagination_info,
+    };
+
+    Ok(Json(json!(response)))
+}
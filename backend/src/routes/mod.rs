@@ -4,6 +4,14 @@ pub mod role;
 pub mod permission;
 pub mod department;
 pub mod user_department;
+pub mod login_log;
+pub mod registration_code;
+pub mod well_known;
+pub mod api_key;
+pub mod service_account;
+pub mod ldap;
+pub mod oauth_client;
+pub mod oauth;
 pub mod utils;
 
 pub use auth::*;
@@ -12,3 +20,11 @@ pub use role::*;
 pub use permission::*;
 pub use department::*;
 pub use user_department::*;
+pub use login_log::*;
+pub use registration_code::*;
+pub use well_known::*;
+pub use api_key::*;
+pub use service_account::*;
+pub use ldap::*;
+pub use oauth_client::*;
+pub use oauth::*;
@@ -0,0 +1,388 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+    routing::{delete, get, post},
+    Form, Router,
+    middleware::from_fn,
+};
+use data_encoding::BASE64;
+use sea_orm::*;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    auth::Claims,
+    extractors::{AuthUser, ClientInfo},
+    middleware::auth_middleware,
+    models::{oauth_client, user, AuthorizationDecisionDto, AuthorizationRequest, TokenOperationRequest, TokenRequest},
+    routes::utils::{forbid_impersonation, oauth_error_response},
+    services::{
+        LoginEvent, LoginLogService, OAuthError, OAuthService, GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS,
+        LOGIN_FAILURE_INVALID_CLIENT, LOGIN_FAILURE_OAUTH_INVALID_GRANT, SUPPORTED_SCOPES,
+    },
+};
+use sea_orm::DatabaseConnection;
+
+// OAuth2 授权服务：授权页面数据和用户的授权记录需要登录，令牌端点通过客户端凭据认证
+pub fn oauth_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route(
+            "/authorize",
+            get(authorization_info)
+                .post(authorization_decision)
+                .layer(from_fn(auth_middleware)),
+        )
+        .route("/consents", get(list_consents).layer(from_fn(auth_middleware)))
+        .route("/consents/:client_id", delete(revoke_consent).layer(from_fn(auth_middleware)))
+        .route("/token", post(token))
+        .route("/introspect", post(introspect))
+        .route("/revoke", post(revoke))
+}
+
+// 授权页面需要展示的信息：应用、请求的授权范围、是否需要用户确认
+async fn authorization_info(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Query(request): Query<AuthorizationRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let (client, scopes) = prepare_authorization(&db, &claims, &request).await?;
+
+    let consent_required = OAuthService::consent_required(&db, &client, claims.sub, &scopes)
+        .await
+        .map_err(oauth_error_response)?;
+
+    let scopes: Vec<Value> = scopes
+        .iter()
+        .map(|scope| {
+            let description = SUPPORTED_SCOPES
+                .iter()
+                .find(|(name, _)| name == scope)
+                .map(|(_, description)| *description);
+            json!({ "name": scope, "description": description })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "client": {
+            "client_id": client.client_id,
+            "name": client.name,
+            "description": client.description
+        },
+        "scopes": scopes,
+        "consent_required": consent_required
+    })))
+}
+
+// 用户同意或拒绝授权，返回前端需要跳转的回调地址
+async fn authorization_decision(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<AuthorizationDecisionDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let request = payload.request;
+    let (client, scopes) = prepare_authorization(&db, &claims, &request).await?;
+
+    if !payload.approved {
+        let redirect_to = OAuthService::redirect_with(
+            &request.redirect_uri,
+            &[("error", "access_denied")],
+            request.state.as_deref(),
+        );
+        return Ok(Json(json!({
+            "redirect_to": redirect_to
+        })));
+    }
+
+    let redirect_to = OAuthService::approve(&db, &client, claims.sub, &request, scopes)
+        .await
+        .map_err(oauth_error_response)?;
+
+    Ok(Json(json!({
+        "redirect_to": redirect_to
+    })))
+}
+
+// 校验授权请求；应用和回调地址有效时，其他错误附带应用的错误回调地址
+async fn prepare_authorization(
+    db: &DatabaseConnection,
+    claims: &Claims,
+    request: &AuthorizationRequest,
+) -> Result<(oauth_client::Model, Vec<String>), (StatusCode, Json<Value>)> {
+    // 只有登录的用户本人可以授权，API密钥、服务账户和模拟登录不能授权
+    forbid_impersonation(claims)?;
+    let is_service_account = user::Entity::find_by_id(claims.sub)
+        .one(db)
+        .await
+        .map_err(|e| oauth_error_response(OAuthError::DatabaseError(e)))?
+        .is_none_or(|user| user.is_service_account);
+    if claims.api_key.is_some() || is_service_account {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "只有登录的用户可以授权接入应用"
+            })),
+        ));
+    }
+
+    // 验证输入
+    if let Err(errors) = request.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let client = OAuthService::find_authorization_client(db, request)
+        .await
+        .map_err(oauth_error_response)?;
+
+    match OAuthService::check_authorization_request(&client, request) {
+        Ok(scopes) => Ok((client, scopes)),
+        Err(e) => {
+            let description = e.to_string();
+            let redirect_to = OAuthService::redirect_with(
+                &request.redirect_uri,
+                &[("error", e.code()), ("error_description", &description)],
+                request.state.as_deref(),
+            );
+            Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": e.code(),
+                    "error_description": description,
+                    "redirect_to": redirect_to
+                })),
+            ))
+        }
+    }
+}
+
+async fn list_consents(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let consents = OAuthService::list_consents(&db, claims.sub)
+        .await
+        .map_err(oauth_error_response)?;
+
+    Ok(Json(json!({
+        "data": consents
+    })))
+}
+
+async fn revoke_consent(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(client_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    OAuthService::revoke_consent(&db, claims.sub, &client_id)
+        .await
+        .map_err(oauth_error_response)?;
+
+    Ok(Json(json!({
+        "message": "已取消对该应用的授权"
+    })))
+}
+
+// 令牌端点（RFC 6749 3.2），支持授权码和客户端凭据两种授权类型
+async fn token(
+    State(db): State<DatabaseConnection>,
+    client_info: ClientInfo,
+    headers: HeaderMap,
+    Form(form): Form<TokenRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    let (client_id, client_secret) = client_credentials(&headers, form.client_id.clone(), form.client_secret.clone())
+        .map_err(oauth_error_response)?;
+
+    let client = match OAuthService::authenticate_client(&db, &client_id, client_secret.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            if matches!(e, OAuthError::InvalidClient) {
+                record_token_request(&db, &client_id, None, &client_info, Some(LOGIN_FAILURE_INVALID_CLIENT)).await;
+            }
+            return Err(oauth_error_response(e));
+        }
+    };
+
+    let grant = match form.grant_type.as_str() {
+        GRANT_AUTHORIZATION_CODE => {
+            let (Some(code), Some(redirect_uri), Some(code_verifier)) = (&form.code, &form.redirect_uri, &form.code_verifier) else {
+                return Err(oauth_error_response(OAuthError::InvalidRequest("缺少 code、redirect_uri 或 code_verifier")));
+            };
+            OAuthService::exchange_code(&db, &client, code, redirect_uri, code_verifier).await
+        }
+        GRANT_CLIENT_CREDENTIALS => OAuthService::client_credentials(&db, &client, form.scope.as_deref()).await,
+        "" => Err(OAuthError::InvalidRequest("缺少 grant_type")),
+        _ => Err(OAuthError::UnsupportedGrantType),
+    };
+
+    let grant = match grant {
+        Ok(grant) => grant,
+        Err(e) => {
+            if matches!(e, OAuthError::InvalidGrant(_)) {
+                record_token_request(&db, &client_id, None, &client_info, Some(LOGIN_FAILURE_OAUTH_INVALID_GRANT)).await;
+            }
+            return Err(oauth_error_response(e));
+        }
+    };
+
+    record_token_request(&db, &grant.user.username, Some(grant.user.id), &client_info, None).await;
+
+    let mut body = json!({
+        "access_token": grant.access_token,
+        "token_type": "Bearer",
+        "expires_in": grant.expires_in,
+        "scope": grant.scope
+    });
+    if let Some(id_token) = grant.id_token {
+        body["id_token"] = json!(id_token);
+    }
+
+    // 令牌响应不能被缓存（RFC 6749 5.1）
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(body)))
+}
+
+// 令牌内省（RFC 7662），只有机密客户端可以调用
+async fn introspect(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Form(form): Form<TokenOperationRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let (client_id, client_secret) = client_credentials(&headers, form.client_id, form.client_secret)
+        .map_err(oauth_error_response)?;
+    if client_secret.is_none() {
+        return Err(oauth_error_response(OAuthError::InvalidClient));
+    }
+    OAuthService::authenticate_client(&db, &client_id, client_secret.as_deref())
+        .await
+        .map_err(oauth_error_response)?;
+
+    let claims = OAuthService::introspect(&db, &form.token)
+        .await
+        .map_err(oauth_error_response)?;
+
+    let Some(claims) = claims else {
+        return Ok(Json(json!({ "active": false })));
+    };
+
+    Ok(Json(json!({
+        "active": true,
+        "scope": claims.scope,
+        "client_id": claims.client_id,
+        "username": claims.username,
+        "sub": claims.sub.to_string(),
+        "token_type": "Bearer",
+        "exp": claims.exp,
+        "iat": claims.iat,
+        "jti": claims.jti,
+        "iss": OAuthService::issuer()
+    })))
+}
+
+// 令牌撤销（RFC 7009），令牌无效或不属于该应用时同样返回成功
+async fn revoke(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Form(form): Form<TokenOperationRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let (client_id, client_secret) = client_credentials(&headers, form.client_id, form.client_secret)
+        .map_err(oauth_error_response)?;
+    let client = OAuthService::authenticate_client(&db, &client_id, client_secret.as_deref())
+        .await
+        .map_err(oauth_error_response)?;
+
+    OAuthService::revoke(&db, &client, &form.token)
+        .await
+        .map_err(oauth_error_response)?;
+
+    Ok(Json(json!({})))
+}
+
+// 帮助函数：从 HTTP Basic 认证或表单参数中取出客户端凭据，不能同时使用两种方式（RFC 6749 2.3.1）
+fn client_credentials(
+    headers: &HeaderMap,
+    form_client_id: Option<String>,
+    form_client_secret: Option<String>,
+) -> Result<(String, Option<String>), OAuthError> {
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "));
+
+    match basic {
+        Some(_) if form_client_secret.is_some() => Err(OAuthError::InvalidRequest("不能同时使用多种客户端认证方式")),
+        Some(encoded) => {
+            let decoded = BASE64
+                .decode(encoded.trim().as_bytes())
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or(OAuthError::InvalidClient)?;
+            let (id, secret) = decoded.split_once(':').ok_or(OAuthError::InvalidClient)?;
+            let id = urlencoding::decode(id).map_err(|_| OAuthError::InvalidClient)?;
+            let secret = urlencoding::decode(secret).map_err(|_| OAuthError::InvalidClient)?;
+            Ok((id.into_owned(), Some(secret.into_owned())))
+        }
+        None => {
+            let client_id = form_client_id.ok_or(OAuthError::InvalidClient)?;
+            Ok((client_id, form_client_secret))
+        }
+    }
+}
+
+async fn record_token_request(
+    db: &DatabaseConnection,
+    username: &str,
+    user_id: Option<i32>,
+    client: &ClientInfo,
+    failure_reason: Option<&str>,
+) {
+    if let Err(e) = LoginLogService::record(db, LoginEvent::OAuth, Some(username), user_id, client, failure_reason).await {
+        tracing::warn!("记录登录日志失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn basic(credentials: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let value = format!("Basic {}", BASE64.encode(credentials.as_bytes()));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_client_credentials_from_basic_auth() {
+        let (id, secret) = client_credentials(&basic("client:ocs%3Asecret"), None, None).unwrap();
+        assert_eq!(id, "client");
+        // 凭据按 application/x-www-form-urlencoded 编码
+        assert_eq!(secret.as_deref(), Some("ocs:secret"));
+
+        // 不能同时在请求头和表单中提供密钥
+        assert!(client_credentials(&basic("client:secret"), None, Some("secret".to_string())).is_err());
+        assert!(client_credentials(&basic("no-colon"), None, None).is_err());
+    }
+
+    #[test]
+    fn test_client_credentials_from_form() {
+        let (id, secret) =
+            client_credentials(&HeaderMap::new(), Some("client".to_string()), Some("secret".to_string())).unwrap();
+        assert_eq!(id, "client");
+        assert_eq!(secret.as_deref(), Some("secret"));
+
+        // 公开客户端只提供 client_id
+        let (_, secret) = client_credentials(&HeaderMap::new(), Some("client".to_string()), None).unwrap();
+        assert!(secret.is_none());
+
+        assert!(matches!(
+            client_credentials(&HeaderMap::new(), None, None),
+            Err(OAuthError::InvalidClient)
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    models::{CreateOAuthClientDto, UpdateOAuthClientDto},
+    extractors::AuthUser,
+    routes::utils::{check_permission, oauth_client_error_response},
+    services::OAuthClientService,
+};
+use sea_orm::DatabaseConnection;
+
+// 接入应用（OAuth2客户端）管理
+pub fn oauth_client_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/", get(list_clients).post(create_client))
+        .route("/:id", get(get_client).put(update_client).delete(delete_client))
+        .route("/:id/secret", post(rotate_secret))
+}
+
+async fn list_clients(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "oauth_client", "read").await?;
+
+    let clients = OAuthClientService::list(&db)
+        .await
+        .map_err(oauth_client_error_response)?;
+
+    Ok(Json(json!({
+        "data": clients
+    })))
+}
+
+async fn create_client(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateOAuthClientDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "oauth_client", "create").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let (client, secret) = OAuthClientService::create(&db, payload, claims.sub)
+        .await
+        .map_err(oauth_client_error_response)?;
+
+    Ok(Json(json!({
+        "message": "接入应用创建成功，客户端密钥只显示这一次，请妥善保存",
+        "client": client,
+        "client_secret": secret
+    })))
+}
+
+async fn get_client(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "oauth_client", "read").await?;
+
+    let client = OAuthClientService::find(&db, id)
+        .await
+        .map_err(oauth_client_error_response)?;
+
+    Ok(Json(json!(client)))
+}
+
+async fn update_client(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateOAuthClientDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "oauth_client", "update").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let client = OAuthClientService::update(&db, id, payload)
+        .await
+        .map_err(oauth_client_error_response)?;
+
+    Ok(Json(json!({
+        "message": "接入应用更新成功",
+        "client": client
+    })))
+}
+
+async fn delete_client(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "oauth_client", "delete").await?;
+
+    OAuthClientService::delete(&db, id)
+        .await
+        .map_err(oauth_client_error_response)?;
+
+    Ok(Json(json!({
+        "message": "接入应用已删除"
+    })))
+}
+
+// 重新生成客户端密钥，旧密钥立即失效
+async fn rotate_secret(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "oauth_client", "update").await?;
+
+    let (client, secret) = OAuthClientService::rotate_secret(&db, id)
+        .await
+        .map_err(oauth_client_error_response)?;
+
+    Ok(Json(json!({
+        "message": "客户端密钥已重置，旧密钥立即失效；新密钥只显示这一次，请妥善保存",
+        "client": client,
+        "client_secret": secret
+    })))
+}
@@ -12,6 +12,7 @@ use validator::Validate;
 use crate::{
     models::{permission, CreatePermissionDto, PermissionResponse, PaginationQuery, PaginationResponse, PaginationInfo},
     extractors::AuthUser,
+    permission_cache::{self, Invalidation},
     routes::utils::check_permission,
 };
 use sea_orm::DatabaseConnection;
@@ -31,7 +32,7 @@ async fn list_permissions(
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "permission", "read").await?;
+    check_permission(&db, &claims, "permission", "read").await?;
 
     // 验证分页参数
     if let Err(errors) = pagination.validate() {
@@ -105,7 +106,7 @@ async fn get_permission(
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "permission", "read").await?;
+    check_permission(&db, &claims, "permission", "read").await?;
 
     let permission = permission::Entity::find_by_id(permission_id)
         .one(&db)
@@ -147,7 +148,7 @@ async fn create_permission(
     Json(payload): Json<CreatePermissionDto>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "permission", "create").await?;
+    check_permission(&db, &claims, "permission", "create").await?;
 
     // 验证输入
     if let Err(errors) = payload.validate() {
@@ -223,7 +224,7 @@ async fn update_permission(
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "permission", "update").await?;
+    check_permission(&db, &claims, "permission", "update").await?;
 
     let permission = permission::Entity::find_by_id(permission_id)
         .one(&db)
@@ -278,6 +279,9 @@ async fn update_permission(
         )
     })?;
 
+    // 其他实例由数据库触发器通知
+    permission_cache::cache().invalidate(Invalidation::All);
+
     Ok(Json(json!({
         "message": "权限更新成功",
         "permission": {
@@ -297,7 +301,7 @@ async fn delete_permission(
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "permission", "delete").await?;
+    check_permission(&db, &claims, "permission", "delete").await?;
 
     let permission = permission::Entity::find_by_id(permission_id)
         .one(&db)
@@ -333,6 +337,8 @@ async fn delete_permission(
         )
     })?;
 
+    permission_cache::cache().invalidate(Invalidation::All);
+
     Ok(Json(json!({
         "message": "权限删除成功"
     })))
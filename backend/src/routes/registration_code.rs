@@ -0,0 +1,87 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    models::CreateRegistrationCodeDto,
+    extractors::AuthUser,
+    routes::utils::{check_permission, registration_error_response},
+    services::RegistrationService,
+};
+use sea_orm::DatabaseConnection;
+
+// 注册邀请码可以用来创建账户，管理需要 user:create 权限
+pub fn registration_code_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/", get(list_registration_codes).post(create_registration_code))
+        .route("/:id", delete(revoke_registration_code))
+}
+
+async fn list_registration_codes(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "create").await?;
+
+    let codes = RegistrationService::list_codes(&db)
+        .await
+        .map_err(registration_error_response)?;
+
+    Ok(Json(json!({
+        "data": codes
+    })))
+}
+
+async fn create_registration_code(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateRegistrationCodeDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "create").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let (record, code) = RegistrationService::create_code(&db, payload, claims.sub)
+        .await
+        .map_err(registration_error_response)?;
+
+    Ok(Json(json!({
+        "message": "邀请码创建成功，请妥善保存，邀请码只显示这一次",
+        "code": code,
+        "registration_code": record
+    })))
+}
+
+async fn revoke_registration_code(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "create").await?;
+
+    RegistrationService::revoke_code(&db, id)
+        .await
+        .map_err(registration_error_response)?;
+
+    Ok(Json(json!({
+        "message": "邀请码已作废"
+    })))
+}
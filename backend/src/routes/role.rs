@@ -10,10 +10,12 @@ use serde_json::{json, Value};
 use validator::Validate;
 
 use crate::{
-    models::{role, CreateRoleDto, RoleResponse, PaginationQuery, PaginationResponse, PaginationInfo},
+    models::{role, CreateIpAllowlistEntryDto, CreateRoleDto, RoleResponse, PaginationQuery, PaginationResponse, PaginationInfo},
+    permission_cache::{self, Invalidation},
     rbac::RbacService,
-    extractors::AuthUser,
-    routes::utils::check_permission,
+    extractors::{AuthUser, ClientInfo},
+    routes::utils::{check_permission, ip_allowlist_error_response},
+    services::{AllowlistOwner, IpAllowlistService},
 };
 use sea_orm::DatabaseConnection;
 
@@ -26,6 +28,8 @@ pub fn role_routes() -> Router<DatabaseConnection> {
         .route("/:id", delete(delete_role))
         .route("/:id/permissions", post(assign_permission))
         .route("/:id/permissions", delete(remove_permission))
+        .route("/:id/ip-allowlist", get(list_role_ip_allowlist).post(add_role_ip_allowlist_entry))
+        .route("/:id/ip-allowlist/:entry_id", delete(remove_role_ip_allowlist_entry))
 }
 
 
@@ -37,7 +41,7 @@ async fn list_roles(
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "read").await?;
+    check_permission(&db, &claims, "role", "read").await?;
 
     // 验证分页参数
     if let Err(errors) = pagination.validate() {
@@ -97,6 +101,8 @@ async fn list_roles(
                     name: role.name,
                     description: role.description,
                     is_active: role.is_active,
+                    mfa_required: role.mfa_required,
+                    magic_link_allowed: role.magic_link_allowed,
                     permissions: permissions.into_iter().collect(),
                 }
             }
@@ -118,7 +124,7 @@ async fn get_role(
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "read").await?;
+    check_permission(&db, &claims, "role", "read").await?;
 
     let role = role::Entity::find_by_id(role_id)
         .one(&db)
@@ -149,6 +155,8 @@ async fn get_role(
         name: role.name,
         description: role.description,
         is_active: role.is_active,
+        mfa_required: role.mfa_required,
+        magic_link_allowed: role.magic_link_allowed,
         permissions: permissions.into_iter().collect(),
     };
 
@@ -163,7 +171,7 @@ async fn create_role(
     Json(payload): Json<CreateRoleDto>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "create").await?;
+    check_permission(&db, &claims, "role", "create").await?;
 
     // 验证输入
     if let Err(errors) = payload.validate() {
@@ -235,7 +243,7 @@ async fn update_role(
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "update").await?;
+    check_permission(&db, &claims, "role", "update").await?;
 
     let role = role::Entity::find_by_id(role_id)
         .one(&db)
@@ -272,6 +280,14 @@ async fn update_role(
         role_model.is_active = Set(is_active);
     }
 
+    if let Some(mfa_required) = payload.get("mfa_required").and_then(|v| v.as_bool()) {
+        role_model.mfa_required = Set(mfa_required);
+    }
+
+    if let Some(magic_link_allowed) = payload.get("magic_link_allowed").and_then(|v| v.as_bool()) {
+        role_model.magic_link_allowed = Set(magic_link_allowed);
+    }
+
     let role = role_model.update(&db).await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -282,13 +298,18 @@ async fn update_role(
         )
     })?;
 
+    // 其他实例由数据库触发器通知
+    permission_cache::cache().invalidate(Invalidation::All);
+
     Ok(Json(json!({
         "message": "角色更新成功",
         "role": {
             "id": role.id,
             "name": role.name,
             "description": role.description,
-            "is_active": role.is_active
+            "is_active": role.is_active,
+            "mfa_required": role.mfa_required,
+            "magic_link_allowed": role.magic_link_allowed
         }
     })))
 }
@@ -299,7 +320,7 @@ async fn delete_role(
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "delete").await?;
+    check_permission(&db, &claims, "role", "delete").await?;
 
     let role = role::Entity::find_by_id(role_id)
         .one(&db)
@@ -335,6 +356,8 @@ async fn delete_role(
         )
     })?;
 
+    permission_cache::cache().invalidate(Invalidation::All);
+
     Ok(Json(json!({
         "message": "角色删除成功"
     })))
@@ -347,7 +370,7 @@ async fn assign_permission(
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "update").await?;
+    check_permission(&db, &claims, "role", "update").await?;
 
     let permission_id = payload.get("permission_id")
         .and_then(|v| v.as_i64())
@@ -382,7 +405,7 @@ async fn remove_permission(
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "role", "update").await?;
+    check_permission(&db, &claims, "role", "update").await?;
 
     let permission_id = payload.get("permission_id")
         .and_then(|v| v.as_i64())
@@ -409,3 +432,70 @@ async fn remove_permission(
         "message": "权限移除成功"
     })))
 }
+
+async fn list_role_ip_allowlist(
+    State(db): State<DatabaseConnection>,
+    Path(role_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "role", "read").await?;
+
+    let entries = IpAllowlistService::list(&db, AllowlistOwner::Role(role_id))
+        .await
+        .map_err(ip_allowlist_error_response)?;
+
+    Ok(Json(json!({
+        "data": entries
+    })))
+}
+
+async fn add_role_ip_allowlist_entry(
+    State(db): State<DatabaseConnection>,
+    Path(role_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+    Json(payload): Json<CreateIpAllowlistEntryDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "role", "update").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    // 添加后操作者自己当前的网络不再被允许时拒绝
+    let entry = IpAllowlistService::add(&db, AllowlistOwner::Role(role_id), payload, claims.sub, client.ip_address.as_deref())
+        .await
+        .map_err(ip_allowlist_error_response)?;
+
+    Ok(Json(json!({
+        "message": "白名单记录已添加，该角色的成员只能从白名单中的网络登录和访问",
+        "entry": entry
+    })))
+}
+
+async fn remove_role_ip_allowlist_entry(
+    State(db): State<DatabaseConnection>,
+    Path((role_id, entry_id)): Path<(i32, i32)>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "role", "update").await?;
+
+    IpAllowlistService::remove(&db, AllowlistOwner::Role(role_id), entry_id, claims.sub, client.ip_address.as_deref())
+        .await
+        .map_err(ip_allowlist_error_response)?;
+
+    Ok(Json(json!({
+        "message": "白名单记录已删除"
+    })))
+}
@@ -0,0 +1,268 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    models::{user, CreateApiKeyDto, CreateServiceAccountDto, CreateServiceAccountSecretDto, UserResponse},
+    rbac::{RbacService, UserAccess},
+    extractors::AuthUser,
+    routes::utils::{api_key_error_response, check_permission, forbid_impersonation, service_account_error_response},
+    services::{ApiKeyService, ServiceAccountService},
+};
+use sea_orm::DatabaseConnection;
+
+// 服务账户的启用、禁用、删除和角色分配与普通用户相同，使用 /api/users/:id 的接口
+pub fn service_account_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/", get(list_service_accounts).post(create_service_account))
+        .route("/:id/secrets", get(list_secrets).post(create_secret))
+        .route("/:id/secrets/:secret_id", delete(revoke_secret))
+        .route("/:id/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/:id/api-keys/:key_id", delete(revoke_api_key))
+}
+
+async fn service_account_response(db: &DatabaseConnection, account: user::Model) -> UserResponse {
+    let access = RbacService::get_user_access(db, account.id)
+        .await
+        .unwrap_or_default();
+
+    account_response(account, access)
+}
+
+fn account_response(account: user::Model, access: UserAccess) -> UserResponse {
+    UserResponse {
+        id: account.id,
+        username: account.username,
+        email: account.email,
+        is_active: account.is_active,
+        email_verified: account.email_verified_at.is_some(),
+        is_service_account: account.is_service_account,
+        roles: access.roles,
+        permissions: access.permissions.into_iter().collect(),
+    }
+}
+
+async fn list_service_accounts(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let accounts = ServiceAccountService::list(&db)
+        .await
+        .map_err(service_account_error_response)?;
+
+    let account_ids: Vec<i32> = accounts.iter().map(|account| account.id).collect();
+    let mut access = RbacService::get_users_access(&db, &account_ids)
+        .await
+        .unwrap_or_default();
+
+    let accounts: Vec<UserResponse> = accounts
+        .into_iter()
+        .map(|account| {
+            let access = access.remove(&account.id).unwrap_or_default();
+            account_response(account, access)
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "data": accounts
+    })))
+}
+
+async fn create_service_account(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateServiceAccountDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "create").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let account = ServiceAccountService::create(&db, payload)
+        .await
+        .map_err(service_account_error_response)?;
+
+    tracing::info!("用户 {} 创建了服务账户 {}", claims.sub, account.username);
+
+    Ok(Json(json!({
+        "message": "服务账户创建成功",
+        "service_account": service_account_response(&db, account).await
+    })))
+}
+
+async fn list_secrets(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let account = ServiceAccountService::find(&db, id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    let secrets = ServiceAccountService::list_secrets(&db, account.id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    Ok(Json(json!({
+        "client_id": account.username,
+        "data": secrets
+    })))
+}
+
+async fn create_secret(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateServiceAccountSecretDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+    forbid_impersonation(&claims)?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let account = ServiceAccountService::find(&db, id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    let (record, secret) = ServiceAccountService::create_secret(&db, &account, payload)
+        .await
+        .map_err(service_account_error_response)?;
+
+    Ok(Json(json!({
+        "message": "客户端密钥创建成功，请妥善保存，密钥只显示这一次",
+        "client_id": account.username,
+        "client_secret": secret,
+        "secret": record
+    })))
+}
+
+async fn revoke_secret(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path((id, secret_id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let account = ServiceAccountService::find(&db, id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    ServiceAccountService::revoke_secret(&db, account.id, secret_id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    Ok(Json(json!({
+        "message": "客户端密钥已撤销"
+    })))
+}
+
+async fn list_api_keys(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let account = ServiceAccountService::find(&db, id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    let keys = ApiKeyService::list(&db, account.id)
+        .await
+        .map_err(api_key_error_response)?;
+
+    Ok(Json(json!({
+        "data": keys
+    })))
+}
+
+async fn create_api_key(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateApiKeyDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+    forbid_impersonation(&claims)?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    let account = ServiceAccountService::find(&db, id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    // 限定的权限必须在服务账户本身的权限范围内
+    let (record, key) = ApiKeyService::create(&db, account.id, payload)
+        .await
+        .map_err(api_key_error_response)?;
+
+    Ok(Json(json!({
+        "message": "API密钥创建成功，请妥善保存，密钥只显示这一次",
+        "key": key,
+        "api_key": record
+    })))
+}
+
+async fn revoke_api_key(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path((id, key_id)): Path<(i32, i32)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let account = ServiceAccountService::find(&db, id)
+        .await
+        .map_err(service_account_error_response)?;
+
+    ApiKeyService::revoke(&db, account.id, key_id)
+        .await
+        .map_err(api_key_error_response)?;
+
+    Ok(Json(json!({
+        "message": "API密钥已撤销"
+    })))
+}
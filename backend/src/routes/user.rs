@@ -5,26 +5,43 @@ use axum::{
     routing::{get, post, put, delete},
     Router,
 };
+use chrono::Utc;
 use sea_orm::*;
 use serde_json::{json, Value};
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    models::{user, CreateUserDto, UserResponse, PaginationQuery, PaginationResponse, PaginationInfo},
+    auth::AuthService,
+    models::{user, AccountTypeFilter, AdminCreateUserDto, CreateIpAllowlistEntryDto, UserListQuery, UserResponse, SessionResponse, PaginationQuery, PaginationResponse, PaginationInfo},
     rbac::RbacService,
-    extractors::AuthUser,
-    routes::utils::check_permission,
+    extractors::{AuthUser, ClientInfo},
+    routes::utils::{check_permission, forbid_impersonation, impersonation_error_response, invitation_error_response, ip_allowlist_error_response, password_policy_error},
+    services::{
+        AllowlistOwner, ImpersonationService, InvitationService, IpAllowlistService, LockoutService, LoginEvent, LoginLogService, MfaService, PasswordPolicy, PasswordPolicyService,
+        RevocationService, SessionService, LOGIN_FAILURE_IMPERSONATION_FORBIDDEN,
+    },
 };
 use sea_orm::DatabaseConnection;
 
 pub fn user_routes() -> Router<DatabaseConnection> {
     Router::new()
         .route("/", get(list_users))
+        .route("/password-hashes", get(password_hash_stats))
         .route("/:id", get(get_user))
         .route("/", post(create_user))
         .route("/:id", put(update_user))
         .route("/:id", delete(delete_user))
         .route("/:id/roles", post(assign_role))
+        .route("/:id/logout", post(logout_user))
+        .route("/:id/unlock", post(unlock_user))
+        .route("/:id/invitation", post(resend_invitation))
+        .route("/:id/mfa", delete(reset_user_mfa))
+        .route("/:id/sessions", get(list_user_sessions))
+        .route("/:id/sessions/:session_id", delete(revoke_user_session))
+        .route("/:id/impersonate", post(impersonate_user))
+        .route("/:id/ip-allowlist", get(list_user_ip_allowlist).post(add_user_ip_allowlist_entry))
+        .route("/:id/ip-allowlist/:entry_id", delete(remove_user_ip_allowlist_entry))
 }
 
 
@@ -32,10 +49,11 @@ pub fn user_routes() -> Router<DatabaseConnection> {
 async fn list_users(
     State(db): State<DatabaseConnection>,
     Query(pagination): Query<PaginationQuery>,
+    Query(filter): Query<UserListQuery>,
     AuthUser(claims): AuthUser,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     // 检查权限
-    check_permission(&db, claims.sub, "user", "read").await?;
+    check_permission(&db, &claims, "user", "read").await?;
 
     // 验证分页参数
     if let Err(errors) = pagination.validate() {
@@ -52,8 +70,16 @@ async fn list_users(
     let per_page = pagination.per_page.unwrap_or(20);
     let offset = (page - 1) * per_page;
 
+    // 默认只列出自然人用户，服务账户需要显式筛选
+    let account_condition = match filter.account_type {
+        AccountTypeFilter::Human => Condition::all().add(user::Column::IsServiceAccount.eq(false)),
+        AccountTypeFilter::Service => Condition::all().add(user::Column::IsServiceAccount.eq(true)),
+        AccountTypeFilter::All => Condition::all(),
+    };
+
     // 获取总数
     let total = user::Entity::find()
+        .filter(account_condition.clone())
         .count(&db)
         .await
         .map_err(|e| {
@@ -68,6 +94,7 @@ async fn list_users(
 
     // 获取分页数据
     let users = user::Entity::find()
+        .filter(account_condition)
         .limit(per_page as u64)
         .offset(offset as u64)
         .all(&db)
@@ -82,36 +109,797 @@ async fn list_users(
             )
         })?;
 
-    let user_responses: Vec<UserResponse> = futures::future::join_all(
-        users.into_iter().map(|user| {
-            let db = db.clone();
-            async move {
-                let roles = RbacService::get_user_roles(&db, user.id)
-                    .await
-                    .unwrap_or_default();
-                let permissions = RbacService::get_user_permissions(&db, user.id)
-                    .await
-                    .unwrap_or_default();
-
-                UserResponse {
-                    id: user.id,
-                    username: user.username,
-                    email: user.email,
-                    is_active: user.is_active,
-                    roles,
-                    permissions: permissions.into_iter().collect(),
-                }
+    // 一次查询所有用户的角色和权限，避免逐个用户查询
+    let user_ids: Vec<i32> = users.iter().map(|user| user.id).collect();
+    let mut access = RbacService::get_users_access(&db, &user_ids)
+        .await
+        .unwrap_or_default();
+
+    let user_responses: Vec<UserResponse> = users
+        .into_iter()
+        .map(|user| {
+            let access = access.remove(&user.id).unwrap_or_default();
+            UserResponse {
+                id: user.id,
+                username: user.username,
+                email: user.email,
+                is_active: user.is_active,
+                email_verified: user.email_verified_at.is_some(),
+                is_service_account: user.is_service_account,
+                roles: access.roles,
+                permissions: access.permissions.into_iter().collect(),
             }
         })
-    ).await;
+        .collect();
+
+    let pagination_info = PaginationInfo::new(page, per_page, total);
+    let response = PaginationResponse {
+        data: user_responses,
+        pagination: pagination_info,
+    };
+
+    Ok(Json(json!(response)))
+}
+
+async fn get_user(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = user.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "用户不存在"
+        })),
+    ))?;
+
+    let access = RbacService::get_user_access(&db, user.id)
+        .await
+        .unwrap_or_default();
+
+    let user_response = UserResponse {
+        id: user.id,
+        username: user.username,
+        email: user.email,
+        is_active: user.is_active,
+        email_verified: user.email_verified_at.is_some(),
+        is_service_account: user.is_service_account,
+        roles: access.roles,
+        permissions: access.permissions.into_iter().collect(),
+    };
+
+    Ok(Json(json!({
+        "user": user_response
+    })))
+}
+
+async fn create_user(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<AdminCreateUserDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "create").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    // 检查用户名或邮箱是否已存在
+    let existing_user = user::Entity::find()
+        .filter(
+            Condition::any()
+                .add(user::Column::Username.eq(&payload.username))
+                .add(user::Column::Email.eq(&payload.email)),
+        )
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if let Some(existing_user) = existing_user {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": if existing_user.username == payload.username { "用户名已存在" } else { "邮箱已存在" }
+            })),
+        ));
+    }
+
+    // 提供初始密码时直接设置；否则等待用户通过邀请链接设置密码
+    let (password_hash, must_change_password) = match &payload.password {
+        Some(password) => {
+            PasswordPolicyService::validate_new_password(
+                &db,
+                &PasswordPolicy::from_env(),
+                "password",
+                password,
+                &payload.username,
+                &payload.email,
+                None,
+            )
+            .await
+            .map_err(password_policy_error)?;
+
+            let password_hash = AuthService::hash_password(password)
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({
+                            "error": "密码加密失败",
+                            "message": e.to_string()
+                        })),
+                    )
+                })?;
+
+            (password_hash, payload.must_change_password.unwrap_or(true))
+        }
+        None => (String::new(), false),
+    };
+
+    // 创建用户
+    let user = user::ActiveModel {
+        username: Set(payload.username),
+        email: Set(payload.email),
+        password_hash: Set(password_hash),
+        is_active: Set(true),
+        must_change_password: Set(must_change_password),
+        // 管理员直接设置密码时视为邮箱已确认；邀请的用户在接受邀请时完成验证
+        email_verified_at: Set(if payload.password.is_some() { Some(Utc::now().into()) } else { None }),
+        ..Default::default()
+    };
+
+    let create_failed = |e: DbErr| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "创建用户失败",
+                "message": e.to_string()
+            })),
+        )
+    };
+
+    // 用户和邀请在同一事务中创建，邀请失败时不会留下没有密码也没有邀请的账户
+    let txn = db.begin().await.map_err(create_failed)?;
+
+    let user = user.insert(&txn).await.map_err(create_failed)?;
+
+    let invitation = if InvitationService::is_pending(&user) {
+        let invitation = InvitationService::create_invitation(&txn, &user, claims.sub)
+            .await
+            .map_err(invitation_error_response)?;
+        Some(invitation)
+    } else {
+        None
+    };
+
+    txn.commit().await.map_err(create_failed)?;
+
+    let invitation_expires_at = invitation.map(|invitation| invitation.send());
+
+    Ok(Json(json!({
+        "message": if invitation_expires_at.is_some() { "用户创建成功，邀请邮件已发送" } else { "用户创建成功" },
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "email_verified": user.email_verified_at.is_some(),
+            "must_change_password": user.must_change_password
+        },
+        "invitation_expires_at": invitation_expires_at
+    })))
+}
+
+async fn resend_invitation(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "create").await?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = user.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "用户不存在"
+        })),
+    ))?;
+
+    let expires_at = InvitationService::invite(&db, &user, claims.sub)
+        .await
+        .map_err(invitation_error_response)?;
+
+    Ok(Json(json!({
+        "message": "邀请邮件已重新发送，之前的邀请链接已失效",
+        "invitation_expires_at": expires_at
+    })))
+}
+
+async fn update_user(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = user.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "用户不存在"
+        })),
+    ))?;
+
+    // 更新用户信息
+    let email_verified_at = user.email_verified_at;
+    let mut user_model: user::ActiveModel = user.into();
+    
+    if let Some(email) = payload.get("email").and_then(|v| v.as_str()) {
+        user_model.email = Set(email.to_string());
+    }
+    
+    if let Some(is_active) = payload.get("is_active").and_then(|v| v.as_bool()) {
+        user_model.is_active = Set(is_active);
+    }
+
+    // 管理员可以手动标记邮箱验证状态
+    if let Some(email_verified) = payload.get("email_verified").and_then(|v| v.as_bool()) {
+        if email_verified != email_verified_at.is_some() {
+            user_model.email_verified_at = Set(email_verified.then(|| Utc::now().into()));
+        }
+    }
+
+    let user = user_model.update(&db).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "更新用户失败",
+                "message": e.to_string()
+            })),
+        )
+    })?;
+
+    // 禁用用户时撤销其所有令牌
+    if !user.is_active {
+        revoke_user_tokens(&db, user.id, "user_disabled").await?;
+    }
+
+    Ok(Json(json!({
+        "message": "用户更新成功",
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "is_active": user.is_active,
+            "email_verified": user.email_verified_at.is_some()
+        }
+    })))
+}
+
+async fn delete_user(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "delete").await?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = user.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "用户不存在"
+        })),
+    ))?;
+
+    // 软删除用户（设置为非激活状态）
+    let mut user_model: user::ActiveModel = user.into();
+    user_model.is_active = Set(false);
+
+    user_model.update(&db).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "删除用户失败",
+                "message": e.to_string()
+            })),
+        )
+    })?;
+
+    // 撤销被删除用户的所有令牌
+    revoke_user_tokens(&db, user_id, "user_deleted").await?;
+
+    Ok(Json(json!({
+        "message": "用户删除成功"
+    })))
+}
+
+async fn assign_role(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let role_id = payload.get("role_id")
+        .and_then(|v| v.as_i64())
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "缺少role_id参数"
+            })),
+        ))? as i32;
+
+    RbacService::assign_role_to_user(&db, user_id, role_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "分配角色失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "message": "角色分配成功"
+    })))
+}
+
+async fn logout_user(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if user.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "用户不存在"
+            })),
+        ));
+    }
+
+    revoke_user_tokens(&db, user_id, "admin_logout").await?;
+
+    Ok(Json(json!({
+        "message": "已注销该用户的所有会话"
+    })))
+}
+
+async fn unlock_user(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let user = user.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": "用户不存在"
+        })),
+    ))?;
+
+    let outcome = LockoutService::unlock_user(&db, &user.username)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "解锁用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "message": "用户已解锁",
+        "was_locked": outcome.was_locked,
+        "unlocked_ip": outcome.unlocked_ip
+    })))
+}
+
+async fn reset_user_mfa(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+    forbid_impersonation(&claims)?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取用户失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if user.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "用户不存在"
+            })),
+        ));
+    }
+
+    MfaService::reset(&db, user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "重置二次验证失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    tracing::info!("用户 {} 重置了用户 {} 的二次验证", claims.sub, user_id);
+
+    Ok(Json(json!({
+        "message": "已重置该用户的二次验证"
+    })))
+}
+
+async fn list_user_sessions(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let sessions = SessionService::list_active_sessions(&db, user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取会话列表失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    let sessions: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::from_model(session, claims.sid))
+        .collect();
+
+    Ok(Json(json!({
+        "sessions": sessions
+    })))
+}
+
+async fn revoke_user_session(
+    State(db): State<DatabaseConnection>,
+    Path((user_id, session_id)): Path<(i32, Uuid)>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    let session = SessionService::find_user_session(&db, user_id, session_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "获取会话失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    if session.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "会话不存在"
+            })),
+        ));
+    }
+
+    SessionService::revoke_session(&db, session_id, "admin_revoked")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "撤销会话失败",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "message": "会话已撤销"
+    })))
+}
+
+// 以指定用户的身份登录，用于复现用户看到的界面；开始和被拒绝的尝试都记录到登录日志
+async fn impersonate_user(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "impersonate").await?;
+
+    let impersonator_id = claims.act.as_ref().map_or(claims.sub, |actor| actor.sub);
+    let grant = match ImpersonationService::start(&db, &claims, user_id).await {
+        Ok(grant) => grant,
+        Err(e) => {
+            if e.is_forbidden() {
+                if let Err(log_error) = LoginLogService::record_impersonation(
+                    &db,
+                    LoginEvent::ImpersonationStart,
+                    impersonator_id,
+                    None,
+                    Some(user_id),
+                    &client,
+                    Some(LOGIN_FAILURE_IMPERSONATION_FORBIDDEN),
+                )
+                .await
+                {
+                    tracing::warn!("记录登录日志失败: {}", log_error);
+                }
+            }
+            return Err(impersonation_error_response(e));
+        }
+    };
+
+    if let Err(e) = LoginLogService::record_impersonation(
+        &db,
+        LoginEvent::ImpersonationStart,
+        grant.actor.sub,
+        Some(&grant.user.username),
+        Some(grant.user.id),
+        &client,
+        None,
+    )
+    .await
+    {
+        tracing::warn!("记录登录日志失败: {}", e);
+    }
+
+    Ok(Json(json!({
+        "message": "已开始模拟登录，结束后请恢复使用自己的令牌",
+        "token": grant.token,
+        "token_type": "Bearer",
+        "expires_in": grant.expires_in,
+        "user": {
+            "id": grant.user.id,
+            "username": grant.user.username
+        },
+        "impersonator": {
+            "id": grant.actor.sub,
+            "username": grant.actor.username
+        }
+    })))
+}
+
+// 统计各密码哈希算法的账户数量，旧算法的哈希在用户下次登录时自动升级
+async fn password_hash_stats(
+    State(db): State<DatabaseConnection>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let stats = PasswordPolicyService::hash_stats(&db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(json!({
+        "stats": stats
+    })))
+}
+
+// 帮助函数：撤销用户的所有令牌
+async fn revoke_user_tokens(
+    db: &DatabaseConnection,
+    user_id: i32,
+    reason: &str,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    RevocationService::revoke_all_for_user(db, user_id, reason)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "撤销用户令牌失败",
+                    "message": e.to_string()
+                })),
+            )
+        })
+}
+
+async fn list_user_ip_allowlist(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "read").await?;
+
+    let entries = IpAllowlistService::list(&db, AllowlistOwner::User(user_id))
+        .await
+        .map_err(ip_allowlist_error_response)?;
+
+    Ok(Json(json!({
+        "data": entries
+    })))
+}
+
+async fn add_user_ip_allowlist_entry(
+    State(db): State<DatabaseConnection>,
+    Path(user_id): Path<i32>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+    Json(payload): Json<CreateIpAllowlistEntryDto>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
+
+    // 验证输入
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ));
+    }
+
+    // 添加后操作者自己当前的网络不再被允许时拒绝
+    let entry = IpAllowlistService::add(&db, AllowlistOwner::User(user_id), payload, claims.sub, client.ip_address.as_deref())
+        .await
+        .map_err(ip_allowlist_error_response)?;
+
+    Ok(Json(json!({
+        "message": "白名单记录已添加，该用户只能从白名单中的网络登录和访问",
+        "entry": entry
+    })))
+}
+
+async fn remove_user_ip_allowlist_entry(
+    State(db): State<DatabaseConnection>,
+    Path((user_id, entry_id)): Path<(i32, i32)>,
+    AuthUser(claims): AuthUser,
+    client: ClientInfo,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // 检查权限
+    check_permission(&db, &claims, "user", "update").await?;
 
-    let pagination_info = PaginationInfo::new(page, per_page, total);
-    let response = PaginationResponse {
-        data: user_responses,
-        pagination: pagination_info,
-    };
+    IpAllowlistService::remove(&db, AllowlistOwner::User(user_id), entry_id, claims.sub, client.ip_address.as_deref())
+        .await
+        .map_err(ip_allowlist_error_response)?;
 
-    Ok(Json(json!(response)))
+    Ok(Json(json!({
+        "message": "白名单记录已删除"
+    })))
 }
 
 #[cfg(test)]
@@ -136,6 +924,7 @@ mod tests {
             username: "testuser".to_string(),
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
+            invitation_code: None,
         };
         assert!(valid_dto.validate().is_ok());
 
@@ -144,6 +933,7 @@ mod tests {
             username: "ab".to_string(), // 太短
             email: "invalid-email".to_string(), // 无效邮箱
             password: "123".to_string(), // 太短
+            invitation_code: None,
         };
         assert!(invalid_dto.validate().is_err());
     }
@@ -189,6 +979,8 @@ mod tests {
             username: "testuser".to_string(),
             email: "test@example.com".to_string(),
             is_active: true,
+            email_verified: true,
+            is_service_account: false,
             roles: vec!["admin".to_string(), "user".to_string()],
             permissions: vec!["read".to_string(), "write".to_string(), "delete".to_string()],
         };
@@ -211,6 +1003,8 @@ mod tests {
                 username: "user1".to_string(),
                 email: "user1@test.com".to_string(),
                 is_active: true,
+                email_verified: true,
+                is_service_account: false,
                 roles: vec!["user".to_string()],
                 permissions: vec!["read".to_string()],
             },
@@ -219,6 +1013,8 @@ mod tests {
                 username: "user2".to_string(),
                 email: "user2@test.com".to_string(),
                 is_active: false,
+                email_verified: false,
+                is_service_account: false,
                 roles: vec!["admin".to_string()],
                 permissions: vec!["read".to_string(), "write".to_string()],
             },
@@ -299,6 +1095,7 @@ mod tests {
                 username: username.to_string(),
                 email: email.to_string(),
                 password: password.to_string(),
+                invitation_code: None,
             };
 
             if should_be_valid {
@@ -340,6 +1137,8 @@ mod tests {
             username: "admin".to_string(),
             email: "admin@test.com".to_string(),
             is_active: true,
+            email_verified: true,
+            is_service_account: false,
             roles: vec!["admin".to_string(), "super_admin".to_string()],
             permissions: vec![
                 "user:create".to_string(),
@@ -355,6 +1154,8 @@ mod tests {
             username: "user".to_string(),
             email: "user@test.com".to_string(),
             is_active: true,
+            email_verified: true,
+            is_service_account: false,
             roles: vec!["user".to_string()],
             permissions: vec!["user:read".to_string()],
         };
@@ -385,6 +1186,7 @@ mod tests {
                 username: "testuser".to_string(),
                 email: email.to_string(),
                 password: "password123".to_string(),
+                invitation_code: None,
             };
 
             // 所有这些邮箱格式都应该是有效的
@@ -408,6 +1210,7 @@ mod tests {
                 username: "testuser".to_string(),
                 email: "test@example.com".to_string(),
                 password: password.to_string(),
+                invitation_code: None,
             };
 
             if should_be_valid {
@@ -418,267 +1221,3 @@ mod tests {
         }
     }
 }
-
-async fn get_user(
-    State(db): State<DatabaseConnection>,
-    Path(user_id): Path<i32>,
-    AuthUser(claims): AuthUser,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 检查权限
-    check_permission(&db, claims.sub, "user", "read").await?;
-
-    let user = user::Entity::find_by_id(user_id)
-        .one(&db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "获取用户失败",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
-
-    let user = user.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(json!({
-            "error": "用户不存在"
-        })),
-    ))?;
-
-    let roles = RbacService::get_user_roles(&db, user.id)
-        .await
-        .unwrap_or_default();
-    let permissions = RbacService::get_user_permissions(&db, user.id)
-        .await
-        .unwrap_or_default();
-
-    let user_response = UserResponse {
-        id: user.id,
-        username: user.username,
-        email: user.email,
-        is_active: user.is_active,
-        roles,
-        permissions: permissions.into_iter().collect(),
-    };
-
-    Ok(Json(json!({
-        "user": user_response
-    })))
-}
-
-async fn create_user(
-    State(db): State<DatabaseConnection>,
-    AuthUser(claims): AuthUser,
-    Json(payload): Json<CreateUserDto>,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 检查权限
-    check_permission(&db, claims.sub, "user", "create").await?;
-
-    // 验证输入
-    if let Err(errors) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "验证失败",
-                "details": errors
-            })),
-        ));
-    }
-
-    // 检查用户名是否已存在
-    let existing_user = user::Entity::find()
-        .filter(user::Column::Username.eq(&payload.username))
-        .one(&db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "数据库错误",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
-
-    if existing_user.is_some() {
-        return Err((
-            StatusCode::CONFLICT,
-            Json(json!({
-                "error": "用户名已存在"
-            })),
-        ));
-    }
-
-    // 创建用户
-    let user = user::ActiveModel {
-        username: Set(payload.username),
-        email: Set(payload.email),
-        password_hash: Set("".to_string()), // 这里应该加密密码
-        is_active: Set(true),
-        ..Default::default()
-    };
-
-    let user = user.insert(&db).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "创建用户失败",
-                "message": e.to_string()
-            })),
-        )
-    })?;
-
-    Ok(Json(json!({
-        "message": "用户创建成功",
-        "user": {
-            "id": user.id,
-            "username": user.username,
-            "email": user.email
-        }
-    })))
-}
-
-async fn update_user(
-    State(db): State<DatabaseConnection>,
-    AuthUser(claims): AuthUser,
-    Path(user_id): Path<i32>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 检查权限
-    check_permission(&db, claims.sub, "user", "update").await?;
-
-    let user = user::Entity::find_by_id(user_id)
-        .one(&db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "获取用户失败",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
-
-    let user = user.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(json!({
-            "error": "用户不存在"
-        })),
-    ))?;
-
-    // 更新用户信息
-    let mut user_model: user::ActiveModel = user.into();
-    
-    if let Some(email) = payload.get("email").and_then(|v| v.as_str()) {
-        user_model.email = Set(email.to_string());
-    }
-    
-    if let Some(is_active) = payload.get("is_active").and_then(|v| v.as_bool()) {
-        user_model.is_active = Set(is_active);
-    }
-
-    let user = user_model.update(&db).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "更新用户失败",
-                "message": e.to_string()
-            })),
-        )
-    })?;
-
-    Ok(Json(json!({
-        "message": "用户更新成功",
-        "user": {
-            "id": user.id,
-            "username": user.username,
-            "email": user.email,
-            "is_active": user.is_active
-        }
-    })))
-}
-
-async fn delete_user(
-    State(db): State<DatabaseConnection>,
-    Path(user_id): Path<i32>,
-    AuthUser(claims): AuthUser,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 检查权限
-    check_permission(&db, claims.sub, "user", "delete").await?;
-
-    let user = user::Entity::find_by_id(user_id)
-        .one(&db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "获取用户失败",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
-
-    let user = user.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(json!({
-            "error": "用户不存在"
-        })),
-    ))?;
-
-    // 软删除用户（设置为非激活状态）
-    let mut user_model: user::ActiveModel = user.into();
-    user_model.is_active = Set(false);
-
-    user_model.update(&db).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "删除用户失败",
-                "message": e.to_string()
-            })),
-        )
-    })?;
-
-    Ok(Json(json!({
-        "message": "用户删除成功"
-    })))
-}
-
-async fn assign_role(
-    State(db): State<DatabaseConnection>,
-    AuthUser(claims): AuthUser,
-    Path(user_id): Path<i32>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    // 检查权限
-    check_permission(&db, claims.sub, "user", "update").await?;
-
-    let role_id = payload.get("role_id")
-        .and_then(|v| v.as_i64())
-        .ok_or((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "缺少role_id参数"
-            })),
-        ))? as i32;
-
-    RbacService::assign_role_to_user(&db, user_id, role_id)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "分配角色失败",
-                    "message": e.to_string()
-                })),
-            )
-        })?;
-
-    Ok(Json(json!({
-        "message": "角色分配成功"
-    })))
-}
@@ -5,16 +5,19 @@ use axum::{
 use sea_orm::DatabaseConnection;
 use serde_json::{json, Value};
 
+use crate::auth::Claims;
 use crate::rbac::RbacService;
+use crate::oidc::OidcError;
+use crate::services::{ApiKeyError, ImpersonationError, InvitationError, IpAllowlistError, LdapServiceError, MagicLinkError, OAuthClientError, OAuthError, OidcLoginError, PasswordPolicyError, RegistrationError, ServiceAccountError};
 
 // 帮助函数：检查权限
 pub async fn check_permission(
     db: &DatabaseConnection,
-    user_id: i32,
+    claims: &Claims,
     resource: &str,
     action: &str,
 ) -> Result<(), (StatusCode, Json<Value>)> {
-    match RbacService::check_permission(db, user_id, resource, action).await {
+    match RbacService::check_claims_permission(db, claims, resource, action).await {
         Ok(true) => Ok(()),
         Ok(false) => Err((
             StatusCode::FORBIDDEN,
@@ -32,3 +35,378 @@ pub async fn check_permission(
         )),
     }
 }
+
+// 帮助函数：模拟登录期间禁止修改密码、二次验证、创建凭据等敏感操作
+pub fn forbid_impersonation(claims: &Claims) -> Result<(), (StatusCode, Json<Value>)> {
+    if claims.is_impersonating() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "模拟登录期间不能执行该操作"
+            })),
+        ));
+    }
+    Ok(())
+}
+
+// 帮助函数：将密码策略检查错误转换为HTTP响应，违反策略时与DTO验证失败的格式一致
+pub fn password_policy_error(e: PasswordPolicyError) -> (StatusCode, Json<Value>) {
+    match e {
+        PasswordPolicyError::Violation(errors) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "验证失败",
+                "details": errors
+            })),
+        ),
+        PasswordPolicyError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "数据库错误",
+                "message": e.to_string()
+            })),
+        ),
+        PasswordPolicyError::AuthError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "密码加密失败",
+                "message": e.to_string()
+            })),
+        ),
+    }
+}
+
+// 帮助函数：将邀请错误转换为HTTP响应
+pub fn invitation_error_response(e: InvitationError) -> (StatusCode, Json<Value>) {
+    match e {
+        InvitationError::InvalidToken | InvitationError::ServiceAccount => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        InvitationError::AlreadyAccepted => (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        InvitationError::PasswordPolicy(e) => password_policy_error(e),
+        InvitationError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "数据库错误",
+                "message": e.to_string()
+            })),
+        ),
+    }
+}
+
+// 帮助函数：将注册策略和邀请码错误转换为HTTP响应
+pub fn registration_error_response(e: RegistrationError) -> (StatusCode, Json<Value>) {
+    match e {
+        RegistrationError::Disabled | RegistrationError::EmailDomainNotAllowed => (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        RegistrationError::InvitationCodeRequired | RegistrationError::InvalidInvitationCode => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        RegistrationError::CodeNotFound => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        RegistrationError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "数据库错误",
+                "message": e.to_string()
+            })),
+        ),
+        RegistrationError::InvalidConfig(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+    }
+}
+
+// 帮助函数：将API密钥错误转换为HTTP响应
+pub fn api_key_error_response(e: ApiKeyError) -> (StatusCode, Json<Value>) {
+    match e {
+        ApiKeyError::PermissionsNotGranted(ref permissions) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": e.to_string(),
+                "permissions": permissions
+            })),
+        ),
+        ApiKeyError::InvalidExpiry => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ApiKeyError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ApiKeyError::InvalidKey => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ApiKeyError::DatabaseError(_) | ApiKeyError::RbacError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "数据库错误",
+                "message": e.to_string()
+            })),
+        ),
+    }
+}
+
+// 帮助函数：将服务账户错误转换为HTTP响应
+pub fn service_account_error_response(e: ServiceAccountError) -> (StatusCode, Json<Value>) {
+    match e {
+        ServiceAccountError::UsernameTaken => (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ServiceAccountError::NotFound | ServiceAccountError::SecretNotFound => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ServiceAccountError::InvalidExpiry => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ServiceAccountError::InvalidClient => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": e.to_string()
+            })),
+        ),
+        ServiceAccountError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "数据库错误",
+                "message": e.to_string()
+            })),
+        ),
+    }
+}
+
+// 帮助函数：将OIDC登录错误转换为HTTP响应
+pub fn oidc_login_error_response(e: OidcLoginError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        OidcLoginError::Oidc(OidcError::UnknownProvider(_)) => StatusCode::NOT_FOUND,
+        OidcLoginError::Oidc(OidcError::Http(_)) | OidcLoginError::Oidc(OidcError::ProviderError(_)) => {
+            StatusCode::BAD_GATEWAY
+        }
+        OidcLoginError::Oidc(OidcError::InvalidIdToken(_)) => StatusCode::UNAUTHORIZED,
+        OidcLoginError::InvalidState => StatusCode::BAD_REQUEST,
+        OidcLoginError::AccountNotLinked | OidcLoginError::EmailRequired => StatusCode::FORBIDDEN,
+        OidcLoginError::EmailTaken => StatusCode::CONFLICT,
+        OidcLoginError::Oidc(OidcError::InvalidConfig(_)) | OidcLoginError::DatabaseError(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "OIDC登录失败",
+                    "message": e.to_string()
+                })),
+            );
+        }
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.to_string()
+        })),
+    )
+}
+
+pub fn ldap_error_response(e: LdapServiceError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        LdapServiceError::Ldap(_) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({
+                    "error": "目录服务暂时不可用",
+                    "message": e.to_string()
+                })),
+            );
+        }
+        LdapServiceError::NotConfigured => StatusCode::BAD_REQUEST,
+        LdapServiceError::RoleNotFound | LdapServiceError::MappingNotFound => StatusCode::NOT_FOUND,
+        LdapServiceError::UsernameTaken(_) | LdapServiceError::EmailTaken(_) | LdapServiceError::MappingExists => {
+            StatusCode::CONFLICT
+        }
+        LdapServiceError::DatabaseError(_) | LdapServiceError::RbacError(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "目录操作失败",
+                    "message": e.to_string()
+                })),
+            );
+        }
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.to_string()
+        })),
+    )
+}
+
+pub fn oauth_client_error_response(e: OAuthClientError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        OAuthClientError::NotFound | OAuthClientError::ServiceAccountNotFound => StatusCode::NOT_FOUND,
+        OAuthClientError::DatabaseError(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "数据库错误",
+                    "message": e.to_string()
+                })),
+            );
+        }
+        _ => StatusCode::BAD_REQUEST,
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.to_string()
+        })),
+    )
+}
+
+// OAuth2 协议端点按 RFC 6749 5.2 返回错误码和说明
+pub fn oauth_error_response(e: OAuthError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        OAuthError::InvalidClient => StatusCode::UNAUTHORIZED,
+        OAuthError::ConsentNotFound => StatusCode::NOT_FOUND,
+        OAuthError::DatabaseError(_) | OAuthError::AuthError(_) | OAuthError::RbacError(_) => {
+            tracing::error!("OAuth2请求处理失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": e.code(),
+                    "error_description": "服务器内部错误"
+                })),
+            );
+        }
+        _ => StatusCode::BAD_REQUEST,
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.code(),
+            "error_description": e.to_string()
+        })),
+    )
+}
+
+pub fn impersonation_error_response(e: ImpersonationError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        ImpersonationError::UserNotFound => StatusCode::NOT_FOUND,
+        ImpersonationError::HigherPrivileges(missing) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": e.to_string(),
+                    "missing_permissions": missing
+                })),
+            );
+        }
+        ImpersonationError::ApiKey | ImpersonationError::AlreadyImpersonating => StatusCode::FORBIDDEN,
+        ImpersonationError::DatabaseError(_) | ImpersonationError::RbacError(_) | ImpersonationError::TokenError(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "服务器内部错误",
+                    "message": e.to_string()
+                })),
+            );
+        }
+        _ => StatusCode::BAD_REQUEST,
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.to_string()
+        })),
+    )
+}
+
+// 帮助函数：将邮件链接登录错误转换为HTTP响应
+pub fn magic_link_error_response(e: MagicLinkError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        MagicLinkError::Disabled => StatusCode::FORBIDDEN,
+        MagicLinkError::InvalidToken => StatusCode::UNAUTHORIZED,
+        MagicLinkError::DatabaseError(_) | MagicLinkError::RbacError(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "服务器内部错误",
+                    "message": e.to_string()
+                })),
+            );
+        }
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.to_string()
+        })),
+    )
+}
+
+// 帮助函数：将IP白名单错误转换为HTTP响应
+pub fn ip_allowlist_error_response(e: IpAllowlistError) -> (StatusCode, Json<Value>) {
+    let status = match &e {
+        IpAllowlistError::RoleNotFound | IpAllowlistError::UserNotFound | IpAllowlistError::EntryNotFound => StatusCode::NOT_FOUND,
+        IpAllowlistError::InvalidCidr(_) => StatusCode::BAD_REQUEST,
+        IpAllowlistError::Duplicate | IpAllowlistError::WouldLockOut => StatusCode::CONFLICT,
+        IpAllowlistError::DatabaseError(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "服务器内部错误",
+                    "message": e.to_string()
+                })),
+            );
+        }
+    };
+
+    (
+        status,
+        Json(json!({
+            "error": e.to_string()
+        })),
+    )
+}
@@ -0,0 +1,66 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+    routing::get,
+    Router,
+};
+use serde_json::json;
+
+use crate::jwt_keys;
+use crate::services::{OAuthService, GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, SUPPORTED_SCOPES};
+use sea_orm::DatabaseConnection;
+
+// 公开端点，供其他服务获取验证访问令牌所需的公钥
+pub fn well_known_routes() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/jwks.json", get(jwks))
+        .route("/openid-configuration", get(openid_configuration))
+}
+
+// 作为OAuth2授权服务的元数据（OpenID Connect Discovery），授权页面由前端提供
+async fn openid_configuration() -> impl IntoResponse {
+    let issuer = OAuthService::issuer();
+    let signing_algorithm = jwt_keys::current()
+        .map(|ring| ring.signing_key().algorithm.as_str())
+        .ok();
+
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/oauth/authorize", issuer),
+            "token_endpoint": format!("{}/api/oauth/token", issuer),
+            "introspection_endpoint": format!("{}/api/oauth/introspect", issuer),
+            "revocation_endpoint": format!("{}/api/oauth/revoke", issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", issuer),
+            "response_types_supported": ["code"],
+            "grant_types_supported": [GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": signing_algorithm.into_iter().collect::<Vec<_>>(),
+            "scopes_supported": SUPPORTED_SCOPES.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+            "code_challenge_methods_supported": ["S256"],
+            "claims_supported": ["iss", "sub", "aud", "exp", "iat", "nonce", "preferred_username", "email", "email_verified", "roles", "permissions"]
+        })),
+    )
+}
+
+async fn jwks() -> impl IntoResponse {
+    match jwt_keys::current() {
+        Ok(ring) => (
+            StatusCode::OK,
+            [(header::CACHE_CONTROL, "public, max-age=300")],
+            Json(ring.jwks()),
+        ),
+        Err(e) => {
+            tracing::error!("获取JWT签名密钥失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(header::CACHE_CONTROL, "no-store")],
+                Json(json!({
+                    "error": "签名密钥不可用"
+                })),
+            )
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::auth::{ApiKeyGrant, AuthService, Claims, TokenUse};
+use crate::models::{api_key, user, CreateApiKeyDto};
+use crate::rbac::{RbacError, RbacService};
+
+/// API密钥的固定前缀，认证时据此区分API密钥和JWT
+pub const API_KEY_PREFIX: &str = "wa_";
+
+/// 列表中显示的密钥开头长度（包含前缀）
+const KEY_PREFIX_DISPLAY_LEN: usize = 11;
+
+/// 两次记录最近使用时间的最小间隔（秒），避免每个请求都写数据库
+const LAST_USED_UPDATE_INTERVAL: i64 = 60;
+
+#[derive(Error, Debug)]
+pub enum ApiKeyError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("权限检查失败: {0}")]
+    RbacError(#[from] RbacError),
+    #[error("API密钥的权限不能超出您拥有的权限")]
+    PermissionsNotGranted(Vec<String>),
+    #[error("过期时间必须晚于当前时间")]
+    InvalidExpiry,
+    #[error("API密钥不存在")]
+    NotFound,
+    #[error("API密钥无效、已过期或已被撤销")]
+    InvalidKey,
+}
+
+pub struct ApiKeyService;
+
+impl ApiKeyService {
+    /// 令牌是否为API密钥（而不是JWT）
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(API_KEY_PREFIX)
+    }
+
+    /// 为用户创建API密钥，返回密钥记录和明文密钥（明文只在创建时返回一次）
+    ///
+    /// 限定的权限必须是用户当前拥有权限的子集。
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: i32,
+        dto: CreateApiKeyDto,
+    ) -> Result<(api_key::Model, String), ApiKeyError> {
+        let now = Utc::now();
+
+        if dto.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(ApiKeyError::InvalidExpiry);
+        }
+
+        let permissions = match dto.permissions {
+            Some(requested) => {
+                let granted = RbacService::get_user_permissions(db, user_id).await?;
+                let permissions = Self::normalize_permissions(requested);
+
+                let missing: Vec<String> = permissions
+                    .iter()
+                    .filter(|p| !granted.contains(*p))
+                    .cloned()
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(ApiKeyError::PermissionsNotGranted(missing));
+                }
+
+                Some(permissions)
+            }
+            None => None,
+        };
+
+        let key = Self::generate_key();
+        let record = api_key::ActiveModel {
+            user_id: Set(user_id),
+            name: Set(dto.name),
+            key_prefix: Set(key[..KEY_PREFIX_DISPLAY_LEN].to_string()),
+            key_hash: Set(AuthService::hash_token(&key)),
+            permissions: Set(permissions),
+            expires_at: Set(dto.expires_at.map(Into::into)),
+            last_used_at: Set(None),
+            revoked_at: Set(None),
+            created_at: Set(now.into()),
+            ..Default::default()
+        };
+
+        let record = record.insert(db).await?;
+        Ok((record, key))
+    }
+
+    /// 用户自己的API密钥，包括已撤销和已过期的
+    pub async fn list(db: &DatabaseConnection, user_id: i32) -> Result<Vec<api_key::Model>, ApiKeyError> {
+        let keys = api_key::Entity::find()
+            .filter(api_key::Column::UserId.eq(user_id))
+            .order_by_desc(api_key::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        Ok(keys)
+    }
+
+    /// 撤销用户自己的API密钥，撤销后立即失效
+    pub async fn revoke(db: &DatabaseConnection, user_id: i32, id: i32) -> Result<(), ApiKeyError> {
+        let record = api_key::Entity::find_by_id(id)
+            .filter(api_key::Column::UserId.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or(ApiKeyError::NotFound)?;
+
+        if record.revoked_at.is_none() {
+            let mut active_record: api_key::ActiveModel = record.into();
+            active_record.revoked_at = Set(Some(Utc::now().into()));
+            active_record.update(db).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 使用API密钥认证，返回代表密钥所有者的claims
+    pub async fn authenticate(db: &DatabaseConnection, key: &str) -> Result<Claims, ApiKeyError> {
+        let now = Utc::now();
+
+        let (record, owner) = api_key::Entity::find()
+            .filter(api_key::Column::KeyHash.eq(AuthService::hash_token(key)))
+            .find_also_related(user::Entity)
+            .one(db)
+            .await?
+            .ok_or(ApiKeyError::InvalidKey)?;
+
+        let owner = owner
+            .filter(|u| u.is_active)
+            .ok_or(ApiKeyError::InvalidKey)?;
+
+        if !Self::is_usable(&record, &now) {
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        if Self::last_used_stale(&record, &now) {
+            api_key::Entity::update_many()
+                .col_expr(
+                    api_key::Column::LastUsedAt,
+                    Expr::value(Some(DateTimeWithTimeZone::from(now))),
+                )
+                .filter(api_key::Column::Id.eq(record.id))
+                .exec(db)
+                .await?;
+        }
+
+        Ok(Self::claims_for(&record, &owner, &now))
+    }
+
+    /// 密钥是否仍可使用：未撤销且未过期
+    fn is_usable(record: &api_key::Model, now: &DateTime<Utc>) -> bool {
+        record.revoked_at.is_none()
+            && record
+                .expires_at
+                .is_none_or(|expires_at| expires_at.with_timezone(&Utc) > *now)
+    }
+
+    fn last_used_stale(record: &api_key::Model, now: &DateTime<Utc>) -> bool {
+        record.last_used_at.is_none_or(|last_used_at| {
+            last_used_at.with_timezone(&Utc) + Duration::seconds(LAST_USED_UPDATE_INTERVAL) <= *now
+        })
+    }
+
+    /// 为当前请求构造claims，有效期与普通访问令牌相同，但不超过密钥的过期时间
+    fn claims_for(record: &api_key::Model, owner: &user::Model, now: &DateTime<Utc>) -> Claims {
+        let mut claims = Claims::new(owner.id, &owner.username, AuthService::access_token_ttl());
+        if let Some(expires_at) = record.expires_at {
+            claims.exp = claims.exp.min(expires_at.timestamp().max(now.timestamp()) as u64);
+        }
+        claims.jti = format!("api-key-{}-{}", record.id, Uuid::new_v4());
+        claims.token_use = TokenUse::Access;
+        claims.api_key = Some(ApiKeyGrant {
+            id: record.id,
+            permissions: record.permissions.clone(),
+        });
+        claims
+    }
+
+    fn normalize_permissions(permissions: Vec<String>) -> Vec<String> {
+        let mut permissions: Vec<String> = permissions
+            .into_iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        permissions.sort();
+        permissions.dedup();
+        permissions
+    }
+
+    fn generate_key() -> String {
+        format!("{}{}", API_KEY_PREFIX, AuthService::generate_refresh_token())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_record(
+        expires_at: Option<DateTime<Utc>>,
+        revoked_at: Option<DateTime<Utc>>,
+        permissions: Option<Vec<String>>,
+    ) -> api_key::Model {
+        api_key::Model {
+            id: 7,
+            user_id: 1,
+            name: "部署脚本".to_string(),
+            key_prefix: "wa_12345678".to_string(),
+            key_hash: AuthService::hash_token("wa_key"),
+            permissions,
+            expires_at: expires_at.map(Into::into),
+            last_used_at: None,
+            revoked_at: revoked_at.map(Into::into),
+            created_at: Utc::now().into(),
+        }
+    }
+
+    fn owner() -> user::Model {
+        user::Model {
+            email_verified_at: Some(Utc::now().into()),
+            ..user::Model::for_test(1, "alice")
+        }
+    }
+
+    #[test]
+    fn test_generate_key_format() {
+        let key = ApiKeyService::generate_key();
+
+        assert!(ApiKeyService::is_api_key(&key));
+        assert_eq!(key.len(), API_KEY_PREFIX.len() + 64);
+        assert_ne!(key, ApiKeyService::generate_key());
+
+        // JWT 不会被当作API密钥
+        assert!(!ApiKeyService::is_api_key("eyJhbGciOiJFZERTQSJ9.e30.sig"));
+    }
+
+    #[test]
+    fn test_api_key_usable_business_rules() {
+        let now = Utc::now();
+
+        assert!(ApiKeyService::is_usable(&key_record(None, None, None), &now));
+        assert!(ApiKeyService::is_usable(&key_record(Some(now + Duration::days(1)), None, None), &now));
+        // 已过期
+        assert!(!ApiKeyService::is_usable(&key_record(Some(now - Duration::seconds(1)), None, None), &now));
+        // 已撤销
+        assert!(!ApiKeyService::is_usable(&key_record(None, Some(now), None), &now));
+    }
+
+    #[test]
+    fn test_last_used_update_throttled() {
+        let now = Utc::now();
+        let mut record = key_record(None, None, None);
+
+        assert!(ApiKeyService::last_used_stale(&record, &now));
+
+        record.last_used_at = Some((now - Duration::seconds(10)).into());
+        assert!(!ApiKeyService::last_used_stale(&record, &now));
+
+        record.last_used_at = Some((now - Duration::seconds(LAST_USED_UPDATE_INTERVAL)).into());
+        assert!(ApiKeyService::last_used_stale(&record, &now));
+    }
+
+    #[test]
+    fn test_claims_carry_key_scope() {
+        let now = Utc::now();
+        let record = key_record(
+            Some(now + Duration::seconds(30)),
+            None,
+            Some(vec!["user:read".to_string()]),
+        );
+
+        let claims = ApiKeyService::claims_for(&record, &owner(), &now);
+        assert_eq!(claims.sub, 1);
+        assert_eq!(claims.sid, None);
+        // 有效期不超过密钥的过期时间
+        assert!(claims.exp <= (now + Duration::seconds(30)).timestamp() as u64);
+
+        let grant = claims.api_key.expect("应包含API密钥信息");
+        assert_eq!(grant.id, 7);
+        assert!(grant.allows("user:read"));
+        assert!(!grant.allows("user:delete"));
+
+        // 不限定权限的密钥不额外限制
+        let unscoped = ApiKeyService::claims_for(&key_record(None, None, None), &owner(), &now);
+        assert!(unscoped.api_key.expect("应包含API密钥信息").allows("user:delete"));
+    }
+
+    #[test]
+    fn test_normalize_permissions() {
+        let permissions = ApiKeyService::normalize_permissions(vec![
+            "user:read".to_string(),
+            " role:read ".to_string(),
+            "user:read".to_string(),
+            "".to_string(),
+        ]);
+
+        assert_eq!(permissions, vec!["role:read".to_string(), "user:read".to_string()]);
+    }
+}
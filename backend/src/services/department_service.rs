@@ -266,7 +266,7 @@ impl DepartmentService {
     }
 
     /// 递归排序部门树
-    fn sort_department_tree(tree: &mut Vec<DepartmentTreeDto>) {
+    fn sort_department_tree(tree: &mut [DepartmentTreeDto]) {
         tree.sort_by(|a, b| a.sort_order.cmp(&b.sort_order).then(a.id.cmp(&b.id)));
 
         for child in tree.iter_mut() {
@@ -275,7 +275,7 @@ impl DepartmentService {
     }
 
     /// 在树中递归查找父部门
-    fn find_parent_in_tree(tree: &mut Vec<DepartmentTreeDto>, parent_id: i32) -> Option<&mut DepartmentTreeDto> {
+    fn find_parent_in_tree(tree: &mut [DepartmentTreeDto], parent_id: i32) -> Option<&mut DepartmentTreeDto> {
         for node in tree.iter_mut() {
             if node.id == parent_id {
                 return Some(node);
@@ -571,7 +571,7 @@ mod tests {
 
     #[test]
     fn test_department_sorting_criteria() {
-        let mut departments = vec![
+        let mut departments = [
             DepartmentTreeDto {
                 id: 3,
                 name: "C部门".to_string(),
@@ -0,0 +1,214 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+
+use crate::auth::AuthService;
+use crate::models::{email_verification_token, magic_link_token, password_reset_token};
+
+/// 同一用户两次发送令牌邮件的最小间隔（秒）
+const TOKEN_REQUEST_INTERVAL: i64 = 60;
+
+/// 通过邮件发送的一次性令牌表（找回密码、邮箱验证、邮件链接登录）
+///
+/// 这些表都有 user_id、token_hash、expires_at、used_at、created_at 列，只保存令牌的SHA-256摘要。
+pub trait EmailTokenEntity: EntityTrait {
+    const USER_ID: Self::Column;
+    const TOKEN_HASH: Self::Column;
+    const EXPIRES_AT: Self::Column;
+    const USED_AT: Self::Column;
+    const CREATED_AT: Self::Column;
+
+    fn expires_at(record: &Self::Model) -> DateTimeWithTimeZone;
+    fn used_at(record: &Self::Model) -> Option<DateTimeWithTimeZone>;
+}
+
+/// 新签发的令牌，明文只用于发送邮件
+#[derive(Debug, Clone)]
+pub struct IssuedToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct EmailTokenService;
+
+impl EmailTokenService {
+    /// 为用户签发新令牌，之前尚未使用的令牌全部作废
+    ///
+    /// scope 为令牌的附加列（例如邮箱验证令牌对应的邮箱），写入新令牌并用于判断发送频率；
+    /// 同一用户（及 scope）在最小间隔内已签发过时不签发，返回 None。
+    pub async fn issue<A>(
+        db: &DatabaseConnection,
+        user_id: i32,
+        scope: Vec<(<A::Entity as EntityTrait>::Column, Value)>,
+        ttl_seconds: i64,
+    ) -> Result<Option<IssuedToken>, DbErr>
+    where
+        A: ActiveModelTrait + ActiveModelBehavior + Send,
+        A::Entity: EmailTokenEntity,
+        <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    {
+        let now = Utc::now();
+
+        let mut recent = A::Entity::find()
+            .filter(<A::Entity as EmailTokenEntity>::USER_ID.eq(user_id))
+            .filter(
+                <A::Entity as EmailTokenEntity>::CREATED_AT.gt(now - Duration::seconds(TOKEN_REQUEST_INTERVAL)),
+            );
+        for (column, value) in &scope {
+            recent = recent.filter(column.eq(value.clone()));
+        }
+        if recent.one(db).await?.is_some() {
+            return Ok(None);
+        }
+
+        let token = AuthService::generate_refresh_token();
+        let expires_at = now + Duration::seconds(ttl_seconds);
+        let now: DateTimeWithTimeZone = now.into();
+
+        let txn = db.begin().await?;
+
+        A::Entity::update_many()
+            .col_expr(<A::Entity as EmailTokenEntity>::USED_AT, Expr::value(Some(now)))
+            .filter(<A::Entity as EmailTokenEntity>::USER_ID.eq(user_id))
+            .filter(<A::Entity as EmailTokenEntity>::USED_AT.is_null())
+            .exec(&txn)
+            .await?;
+
+        let mut record = A::new();
+        record.set(<A::Entity as EmailTokenEntity>::USER_ID, user_id.into());
+        record.set(<A::Entity as EmailTokenEntity>::TOKEN_HASH, AuthService::hash_token(&token).into());
+        record.set(<A::Entity as EmailTokenEntity>::EXPIRES_AT, DateTimeWithTimeZone::from(expires_at).into());
+        record.set(<A::Entity as EmailTokenEntity>::CREATED_AT, now.into());
+        for (column, value) in scope {
+            record.set(column, value);
+        }
+        A::Entity::insert(record).exec_without_returning(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(Some(IssuedToken { token, expires_at }))
+    }
+
+    /// 查找仍可使用的令牌（不使用）
+    pub async fn find_usable<E: EmailTokenEntity>(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> Result<Option<E::Model>, DbErr> {
+        let record = E::find()
+            .filter(E::TOKEN_HASH.eq(AuthService::hash_token(token)))
+            .one(db)
+            .await?;
+
+        let now = Utc::now();
+        Ok(record.filter(|record| Self::is_usable(E::used_at(record), E::expires_at(record), &now)))
+    }
+
+    /// 使用令牌；条件更新保证并发请求中只有一个能使用，令牌已被使用或已过期时返回 false
+    pub async fn claim<E: EmailTokenEntity, C: ConnectionTrait>(conn: &C, token: &str) -> Result<bool, DbErr> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        let claimed = E::update_many()
+            .col_expr(E::USED_AT, Expr::value(Some(now)))
+            .filter(E::TOKEN_HASH.eq(AuthService::hash_token(token)))
+            .filter(E::USED_AT.is_null())
+            .filter(E::EXPIRES_AT.gt(now))
+            .exec(conn)
+            .await?;
+
+        Ok(claimed.rows_affected > 0)
+    }
+
+    /// 令牌是否仍可使用：未被使用（或被新令牌作废）且未过期
+    fn is_usable(used_at: Option<DateTimeWithTimeZone>, expires_at: DateTimeWithTimeZone, now: &DateTime<Utc>) -> bool {
+        used_at.is_none() && expires_at.with_timezone(&Utc) > *now
+    }
+}
+
+impl EmailTokenEntity for password_reset_token::Entity {
+    const USER_ID: Self::Column = password_reset_token::Column::UserId;
+    const TOKEN_HASH: Self::Column = password_reset_token::Column::TokenHash;
+    const EXPIRES_AT: Self::Column = password_reset_token::Column::ExpiresAt;
+    const USED_AT: Self::Column = password_reset_token::Column::UsedAt;
+    const CREATED_AT: Self::Column = password_reset_token::Column::CreatedAt;
+
+    fn expires_at(record: &Self::Model) -> DateTimeWithTimeZone {
+        record.expires_at
+    }
+
+    fn used_at(record: &Self::Model) -> Option<DateTimeWithTimeZone> {
+        record.used_at
+    }
+}
+
+impl EmailTokenEntity for email_verification_token::Entity {
+    const USER_ID: Self::Column = email_verification_token::Column::UserId;
+    const TOKEN_HASH: Self::Column = email_verification_token::Column::TokenHash;
+    const EXPIRES_AT: Self::Column = email_verification_token::Column::ExpiresAt;
+    const USED_AT: Self::Column = email_verification_token::Column::UsedAt;
+    const CREATED_AT: Self::Column = email_verification_token::Column::CreatedAt;
+
+    fn expires_at(record: &Self::Model) -> DateTimeWithTimeZone {
+        record.expires_at
+    }
+
+    fn used_at(record: &Self::Model) -> Option<DateTimeWithTimeZone> {
+        record.used_at
+    }
+}
+
+impl EmailTokenEntity for magic_link_token::Entity {
+    const USER_ID: Self::Column = magic_link_token::Column::UserId;
+    const TOKEN_HASH: Self::Column = magic_link_token::Column::TokenHash;
+    const EXPIRES_AT: Self::Column = magic_link_token::Column::ExpiresAt;
+    const USED_AT: Self::Column = magic_link_token::Column::UsedAt;
+    const CREATED_AT: Self::Column = magic_link_token::Column::CreatedAt;
+
+    fn expires_at(record: &Self::Model) -> DateTimeWithTimeZone {
+        record.expires_at
+    }
+
+    fn used_at(record: &Self::Model) -> Option<DateTimeWithTimeZone> {
+        record.used_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usable(expires_at: DateTime<Utc>, used_at: Option<DateTime<Utc>>, now: &DateTime<Utc>) -> bool {
+        EmailTokenService::is_usable(used_at.map(Into::into), expires_at.into(), now)
+    }
+
+    #[test]
+    fn test_email_token_usable_business_rules() {
+        let now = Utc::now();
+
+        // 未使用且未过期的令牌可用
+        assert!(usable(now + Duration::minutes(5), None, &now));
+        // 已过期的令牌不可用
+        assert!(!usable(now - Duration::seconds(1), None, &now));
+        assert!(!usable(now, None, &now));
+        // 已使用（或被新令牌作废）的令牌不可用
+        assert!(!usable(now + Duration::minutes(5), Some(now), &now));
+    }
+
+    #[test]
+    fn test_email_token_columns() {
+        // 各令牌表的公共列指向各自的表
+        let sql = password_reset_token::Entity::find()
+            .filter(<password_reset_token::Entity as EmailTokenEntity>::TOKEN_HASH.eq("hash"))
+            .filter(<password_reset_token::Entity as EmailTokenEntity>::USED_AT.is_null())
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.contains(r#""password_reset_tokens"."token_hash" = 'hash'"#));
+        assert!(sql.contains(r#""password_reset_tokens"."used_at" IS NULL"#));
+
+        let sql = magic_link_token::Entity::find()
+            .filter(<magic_link_token::Entity as EmailTokenEntity>::EXPIRES_AT.gt(Utc::now()))
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.contains(r#""magic_link_tokens"."expires_at" >"#));
+    }
+}
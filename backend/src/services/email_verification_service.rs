@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::*;
+use std::env;
+use thiserror::Error;
+
+use crate::auth::TokenUse;
+use crate::mail::{app_link, send_in_background, MailMessage};
+use crate::models::{email_verification_token, user};
+use crate::services::EmailTokenService;
+
+#[derive(Error, Debug)]
+pub enum EmailVerificationError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("验证链接无效或已过期")]
+    InvalidToken,
+}
+
+/// 邮箱未验证的账户如何处理，通过 EMAIL_VERIFICATION 环境变量配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmailVerificationMode {
+    /// 不限制，未验证的账户与已验证账户一样使用
+    Optional,
+    /// 可以登录，但只能访问当前用户信息和退出登录（默认）
+    #[default]
+    Restricted,
+    /// 验证邮箱之前不能登录
+    Required,
+}
+
+impl EmailVerificationMode {
+    pub fn from_env() -> Self {
+        match env::var("EMAIL_VERIFICATION").as_deref() {
+            Ok("optional") => Self::Optional,
+            Ok("required") => Self::Required,
+            _ => Self::Restricted,
+        }
+    }
+
+    /// 是否拒绝邮箱未验证的用户登录
+    pub fn blocks_login(&self, user: &user::Model) -> bool {
+        *self == Self::Required && !EmailVerificationService::is_verified(user)
+    }
+
+    /// 为用户签发的会话令牌用途
+    pub fn token_use_for(&self, user: &user::Model) -> TokenUse {
+        if *self != Self::Optional && !EmailVerificationService::is_verified(user) {
+            TokenUse::EmailUnverified
+        } else {
+            TokenUse::Access
+        }
+    }
+}
+
+pub struct EmailVerificationService;
+
+impl EmailVerificationService {
+    /// 验证令牌有效期（秒），默认24小时
+    pub fn token_ttl() -> i64 {
+        env::var("EMAIL_VERIFICATION_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(86_400)
+    }
+
+    pub fn is_verified(user: &user::Model) -> bool {
+        user.email_verified_at.is_some()
+    }
+
+    /// 为用户当前邮箱生成验证令牌并发送验证邮件，之前未使用的令牌全部作废
+    ///
+    /// 邮箱已验证或请求过于频繁时不发送。
+    pub async fn send_verification(
+        db: &DatabaseConnection,
+        user: &user::Model,
+    ) -> Result<(), EmailVerificationError> {
+        if Self::is_verified(user) {
+            return Ok(());
+        }
+
+        // 令牌记录发送时的邮箱，修改邮箱后可以立即向新邮箱发送
+        let issued = EmailTokenService::issue::<email_verification_token::ActiveModel>(
+            db,
+            user.id,
+            vec![(email_verification_token::Column::Email, user.email.clone().into())],
+            Self::token_ttl(),
+        )
+        .await?;
+
+        match issued {
+            Some(issued) => send_in_background(Self::verification_message(user, &issued.token, issued.expires_at)),
+            None => tracing::info!("用户 {} 的验证邮件请求过于频繁，已忽略", user.username),
+        }
+
+        Ok(())
+    }
+
+    /// 重新发送验证邮件
+    ///
+    /// 邮箱不存在、已验证或账户被禁用时同样返回成功，不向调用方暴露邮箱是否注册。
+    pub async fn request_resend(db: &DatabaseConnection, email: &str) -> Result<(), EmailVerificationError> {
+        let user = user::Entity::find()
+            .filter(user::Column::Email.eq(email))
+            .filter(user::Column::IsActive.eq(true))
+            .one(db)
+            .await?;
+
+        match user {
+            Some(user) => Self::send_verification(db, &user).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 使用验证令牌确认邮箱
+    pub async fn verify(db: &DatabaseConnection, token: &str) -> Result<user::Model, EmailVerificationError> {
+        let record = EmailTokenService::find_usable::<email_verification_token::Entity>(db, token)
+            .await?
+            .ok_or(EmailVerificationError::InvalidToken)?;
+
+        // 发送验证邮件后又修改了邮箱时，旧令牌不能用于验证新邮箱
+        let user = user::Entity::find_by_id(record.user_id)
+            .one(db)
+            .await?
+            .filter(|u| u.is_active && u.email == record.email)
+            .ok_or(EmailVerificationError::InvalidToken)?;
+
+        let txn = db.begin().await?;
+
+        if !EmailTokenService::claim::<email_verification_token::Entity, _>(&txn, token).await? {
+            return Err(EmailVerificationError::InvalidToken);
+        }
+
+        let now: DateTimeWithTimeZone = Utc::now().into();
+        let mut active_user: user::ActiveModel = user.into();
+        active_user.email_verified_at = Set(Some(now));
+        active_user.updated_at = Set(now);
+        let user = active_user.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(user)
+    }
+
+    fn verification_message(user: &user::Model, token: &str, expires_at: DateTime<Utc>) -> MailMessage {
+        MailMessage {
+            to: user.email.clone(),
+            subject: "验证邮箱".to_string(),
+            body: format!(
+                "{}，您好：\n\n请在 {} 之前打开以下链接验证您的邮箱：\n\n{}\n\n如果您没有注册或修改过邮箱，请忽略此邮件。\n",
+                user.username,
+                expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                app_link("/verify-email", token)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_user(email_verified_at: Option<DateTime<Utc>>) -> user::Model {
+        user::Model {
+            email_verified_at: email_verified_at.map(Into::into),
+            ..user::Model::for_test(1, "alice")
+        }
+    }
+
+    #[test]
+    fn test_verification_mode_business_rules() {
+        let unverified = test_user(None);
+        let verified = test_user(Some(Utc::now()));
+
+        // 不限制：未验证的用户也签发正式访问令牌
+        let optional = EmailVerificationMode::Optional;
+        assert!(!optional.blocks_login(&unverified));
+        assert_eq!(optional.token_use_for(&unverified), TokenUse::Access);
+
+        // 受限：可以登录，但只能拿到受限令牌
+        let restricted = EmailVerificationMode::Restricted;
+        assert!(!restricted.blocks_login(&unverified));
+        assert_eq!(restricted.token_use_for(&unverified), TokenUse::EmailUnverified);
+        assert_eq!(restricted.token_use_for(&verified), TokenUse::Access);
+
+        // 强制：验证之前不能登录
+        let required = EmailVerificationMode::Required;
+        assert!(required.blocks_login(&unverified));
+        assert!(!required.blocks_login(&verified));
+        assert_eq!(required.token_use_for(&verified), TokenUse::Access);
+    }
+
+    #[test]
+    fn test_verification_message_contains_link() {
+        let user = test_user(None);
+
+        let message = EmailVerificationService::verification_message(&user, "abc123", Utc::now());
+        assert_eq!(message.to, "alice@example.com");
+        assert!(message.body.contains("/verify-email?token=abc123"));
+    }
+}
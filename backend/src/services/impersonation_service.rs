@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use sea_orm::*;
+use thiserror::Error;
+
+use crate::auth::{Actor, AuthError, AuthService, Claims};
+use crate::models::user;
+use crate::rbac::{RbacError, RbacService};
+use crate::services::RevocationService;
+
+#[derive(Error, Debug)]
+pub enum ImpersonationError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("权限检查失败: {0}")]
+    RbacError(#[from] RbacError),
+    #[error("令牌生成失败: {0}")]
+    TokenError(#[from] AuthError),
+    #[error("用户不存在")]
+    UserNotFound,
+    #[error("不能模拟自己")]
+    SelfImpersonation,
+    #[error("不能模拟服务账户")]
+    ServiceAccount,
+    #[error("不能模拟已禁用的用户")]
+    UserDisabled,
+    #[error("不能模拟权限高于自己的用户")]
+    HigherPrivileges(Vec<String>),
+    #[error("API密钥不能用于模拟登录")]
+    ApiKey,
+    #[error("模拟登录期间不能再次模拟其他用户")]
+    AlreadyImpersonating,
+    #[error("当前不在模拟登录中")]
+    NotImpersonating,
+}
+
+impl ImpersonationError {
+    /// 被拒绝的模拟登录尝试，需要记录到登录日志
+    pub fn is_forbidden(&self) -> bool {
+        matches!(
+            self,
+            ImpersonationError::SelfImpersonation
+                | ImpersonationError::ServiceAccount
+                | ImpersonationError::UserDisabled
+                | ImpersonationError::HigherPrivileges(_)
+                | ImpersonationError::ApiKey
+                | ImpersonationError::AlreadyImpersonating
+        )
+    }
+}
+
+/// 签发的模拟登录令牌
+pub struct ImpersonationGrant {
+    pub token: String,
+    pub expires_in: u64,
+    pub user: user::Model,
+    pub actor: Actor,
+}
+
+pub struct ImpersonationService;
+
+impl ImpersonationService {
+    /// 以指定用户的身份签发模拟登录令牌：sub 为被模拟的用户，act 为实际操作的管理员
+    /// 被模拟用户的权限必须是管理员权限的子集，令牌不绑定会话、没有刷新令牌
+    pub async fn start(
+        db: &DatabaseConnection,
+        claims: &Claims,
+        user_id: i32,
+    ) -> Result<ImpersonationGrant, ImpersonationError> {
+        if claims.api_key.is_some() {
+            return Err(ImpersonationError::ApiKey);
+        }
+        if claims.is_impersonating() {
+            return Err(ImpersonationError::AlreadyImpersonating);
+        }
+        if claims.sub == user_id {
+            return Err(ImpersonationError::SelfImpersonation);
+        }
+
+        let target = user::Entity::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or(ImpersonationError::UserNotFound)?;
+
+        if target.is_service_account {
+            return Err(ImpersonationError::ServiceAccount);
+        }
+        if !target.is_active {
+            return Err(ImpersonationError::UserDisabled);
+        }
+
+        let actor_permissions = RbacService::get_user_permissions(db, claims.sub).await?;
+        let target_permissions = RbacService::get_user_permissions(db, target.id).await?;
+        let missing = Self::missing_permissions(&actor_permissions, &target_permissions);
+        if !missing.is_empty() {
+            return Err(ImpersonationError::HigherPrivileges(missing));
+        }
+
+        let actor = Actor {
+            sub: claims.sub,
+            username: claims.username.clone(),
+        };
+        let expires_in = AuthService::impersonation_token_ttl();
+        let mut impersonation = Claims::new(target.id, &target.username, expires_in);
+        impersonation.act = Some(actor.clone());
+        let token = AuthService::encode_claims(&impersonation)?;
+
+        Ok(ImpersonationGrant {
+            token,
+            expires_in,
+            user: target,
+            actor,
+        })
+    }
+
+    /// 结束模拟登录：撤销当前使用的模拟登录令牌，返回实际操作的管理员
+    pub async fn stop(db: &DatabaseConnection, claims: &Claims) -> Result<Actor, ImpersonationError> {
+        let actor = claims.act.clone().ok_or(ImpersonationError::NotImpersonating)?;
+
+        RevocationService::revoke_token(db, claims, "impersonation_stopped").await?;
+
+        Ok(actor)
+    }
+
+    /// 被模拟的用户具有、而管理员没有的权限，按名称排序
+    fn missing_permissions(actor: &HashSet<String>, target: &HashSet<String>) -> Vec<String> {
+        let mut missing: Vec<String> = target.difference(actor).cloned().collect();
+        missing.sort();
+        missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_missing_permissions() {
+        let admin = permissions(&["user:read", "user:update", "user:impersonate", "role:read"]);
+
+        // 权限相同或更少的用户可以被模拟
+        assert!(ImpersonationService::missing_permissions(&admin, &permissions(&[])).is_empty());
+        assert!(ImpersonationService::missing_permissions(&admin, &permissions(&["user:read", "role:read"])).is_empty());
+        assert!(ImpersonationService::missing_permissions(&admin, &admin).is_empty());
+
+        // 被模拟的用户具有管理员没有的权限
+        let missing = ImpersonationService::missing_permissions(
+            &admin,
+            &permissions(&["user:read", "user:delete", "api_key:create"]),
+        );
+        assert_eq!(missing, vec!["api_key:create".to_string(), "user:delete".to_string()]);
+    }
+
+    #[test]
+    fn test_forbidden_errors() {
+        assert!(ImpersonationError::SelfImpersonation.is_forbidden());
+        assert!(ImpersonationError::HigherPrivileges(vec![]).is_forbidden());
+        assert!(ImpersonationError::AlreadyImpersonating.is_forbidden());
+        assert!(!ImpersonationError::UserNotFound.is_forbidden());
+        assert!(!ImpersonationError::NotImpersonating.is_forbidden());
+        assert!(!ImpersonationError::DatabaseError(DbErr::Custom("x".to_string())).is_forbidden());
+    }
+}
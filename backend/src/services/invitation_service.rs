@@ -0,0 +1,268 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use std::env;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::mail::{app_link, send_in_background, MailMessage};
+use crate::models::{user, user_invitation};
+use crate::services::{PasswordPolicy, PasswordPolicyError, PasswordPolicyService};
+
+#[derive(Error, Debug)]
+pub enum InvitationError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error(transparent)]
+    PasswordPolicy(#[from] PasswordPolicyError),
+    #[error("邀请链接无效或已过期")]
+    InvalidToken,
+    #[error("用户已设置密码，无需邀请")]
+    AlreadyAccepted,
+    #[error("服务账户不能使用密码登录，无需邀请")]
+    ServiceAccount,
+}
+
+/// 已写入数据库、尚未发送邮件的邀请
+///
+/// 调用方在自己的事务提交之后再发送，避免事务回滚时用户收到无效的邀请链接。
+#[derive(Debug, Clone)]
+pub struct NewInvitation {
+    message: MailMessage,
+    expires_at: DateTime<Utc>,
+}
+
+impl NewInvitation {
+    /// 在后台发送邀请邮件，返回邀请的过期时间
+    pub fn send(self) -> DateTime<Utc> {
+        send_in_background(self.message);
+        self.expires_at
+    }
+}
+
+pub struct InvitationService;
+
+impl InvitationService {
+    /// 邀请令牌有效期（秒），默认7天
+    pub fn invitation_ttl() -> i64 {
+        env::var("INVITATION_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(604_800)
+    }
+
+    /// 用户是否仍在等待接受邀请（尚未设置过密码）；服务账户没有密码，但不接受邀请
+    pub fn is_pending(user: &user::Model) -> bool {
+        !user.is_service_account && user.password_hash.is_empty()
+    }
+
+    /// 生成新的邀请令牌并发送邀请邮件，之前未使用的邀请全部作废；返回邀请的过期时间
+    pub async fn invite(
+        db: &DatabaseConnection,
+        user: &user::Model,
+        invited_by: i32,
+    ) -> Result<DateTime<Utc>, InvitationError> {
+        let txn = db.begin().await?;
+        let invitation = Self::create_invitation(&txn, user, invited_by).await?;
+        txn.commit().await?;
+
+        Ok(invitation.send())
+    }
+
+    /// 在调用方的事务中生成新的邀请令牌，之前未使用的邀请全部作废；事务提交后调用 send 发送邮件
+    pub async fn create_invitation<C: ConnectionTrait>(
+        conn: &C,
+        user: &user::Model,
+        invited_by: i32,
+    ) -> Result<NewInvitation, InvitationError> {
+        if user.is_service_account {
+            return Err(InvitationError::ServiceAccount);
+        }
+
+        if !Self::is_pending(user) {
+            return Err(InvitationError::AlreadyAccepted);
+        }
+
+        let token = AuthService::generate_refresh_token();
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(Self::invitation_ttl());
+
+        user_invitation::Entity::update_many()
+            .col_expr(
+                user_invitation::Column::RevokedAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(now))),
+            )
+            .filter(user_invitation::Column::UserId.eq(user.id))
+            .filter(user_invitation::Column::AcceptedAt.is_null())
+            .filter(user_invitation::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+
+        let record = user_invitation::ActiveModel {
+            user_id: Set(user.id),
+            token_hash: Set(AuthService::hash_token(&token)),
+            invited_by: Set(Some(invited_by)),
+            expires_at: Set(expires_at.into()),
+            created_at: Set(now.into()),
+            ..Default::default()
+        };
+        user_invitation::Entity::insert(record)
+            .exec_without_returning(conn)
+            .await?;
+
+        Ok(NewInvitation {
+            message: Self::invitation_message(user, &token, expires_at),
+            expires_at,
+        })
+    }
+
+    /// 接受邀请并设置密码，同时将邮箱标记为已验证
+    pub async fn accept(
+        db: &DatabaseConnection,
+        token: &str,
+        password: &str,
+    ) -> Result<user::Model, InvitationError> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        let invitation = user_invitation::Entity::find()
+            .filter(user_invitation::Column::TokenHash.eq(AuthService::hash_token(token)))
+            .one(db)
+            .await?
+            .ok_or(InvitationError::InvalidToken)?;
+
+        if !Self::is_usable(&invitation, &Utc::now()) {
+            return Err(InvitationError::InvalidToken);
+        }
+
+        let user = user::Entity::find_by_id(invitation.user_id)
+            .one(db)
+            .await?
+            .filter(|u| u.is_active && Self::is_pending(u))
+            .ok_or(InvitationError::InvalidToken)?;
+
+        // 先检查密码策略，不符合时邀请仍可继续使用
+        let policy = PasswordPolicy::from_env();
+        PasswordPolicyService::validate_new_password(
+            db,
+            &policy,
+            "password",
+            password,
+            &user.username,
+            &user.email,
+            None,
+        )
+        .await?;
+
+        let txn = db.begin().await?;
+
+        // 条件更新保证并发请求中只有一个能使用该邀请
+        let claimed = user_invitation::Entity::update_many()
+            .col_expr(user_invitation::Column::AcceptedAt, Expr::value(Some(now)))
+            .filter(user_invitation::Column::Id.eq(invitation.id))
+            .filter(user_invitation::Column::AcceptedAt.is_null())
+            .filter(user_invitation::Column::RevokedAt.is_null())
+            .filter(user_invitation::Column::ExpiresAt.gt(now))
+            .exec(&txn)
+            .await?;
+
+        if claimed.rows_affected == 0 {
+            return Err(InvitationError::InvalidToken);
+        }
+
+        let user = PasswordPolicyService::set_password(&txn, &policy, user, password).await?;
+
+        // 邀请邮件发送到用户邮箱，能够接受邀请即说明邮箱有效
+        let user = if user.email_verified_at.is_none() {
+            let mut active_user: user::ActiveModel = user.into();
+            active_user.email_verified_at = Set(Some(now));
+            active_user.update(&txn).await?
+        } else {
+            user
+        };
+
+        txn.commit().await?;
+
+        Ok(user)
+    }
+
+    /// 邀请是否仍可使用：未被接受、未被作废且未过期
+    fn is_usable(invitation: &user_invitation::Model, now: &DateTime<Utc>) -> bool {
+        invitation.accepted_at.is_none()
+            && invitation.revoked_at.is_none()
+            && invitation.expires_at.with_timezone(&Utc) > *now
+    }
+
+    fn invitation_message(user: &user::Model, token: &str, expires_at: DateTime<Utc>) -> MailMessage {
+        MailMessage {
+            to: user.email.clone(),
+            subject: "账户邀请".to_string(),
+            body: format!(
+                "{}，您好：\n\n管理员为您创建了账户。请在 {} 之前打开以下链接设置密码：\n\n{}\n",
+                user.username,
+                expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                app_link("/accept-invitation", token)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invitation(
+        expires_at: DateTime<Utc>,
+        accepted_at: Option<DateTime<Utc>>,
+        revoked_at: Option<DateTime<Utc>>,
+    ) -> user_invitation::Model {
+        user_invitation::Model {
+            id: 1,
+            user_id: 1,
+            token_hash: AuthService::hash_token("token"),
+            invited_by: Some(2),
+            expires_at: expires_at.into(),
+            accepted_at: accepted_at.map(Into::into),
+            revoked_at: revoked_at.map(Into::into),
+            created_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_invitation_usable_business_rules() {
+        let now = Utc::now();
+        let later = now + Duration::days(1);
+
+        assert!(InvitationService::is_usable(&invitation(later, None, None), &now));
+        // 已过期
+        assert!(!InvitationService::is_usable(&invitation(now - Duration::seconds(1), None, None), &now));
+        // 已接受
+        assert!(!InvitationService::is_usable(&invitation(later, Some(now), None), &now));
+        // 重新发送后旧邀请作废
+        assert!(!InvitationService::is_usable(&invitation(later, None, Some(now)), &now));
+    }
+
+    #[test]
+    fn test_invitation_pending_business_rules() {
+        let now = Utc::now();
+        let mut user = user::Model {
+            password_hash: String::new(),
+            ..user::Model::for_test(1, "invitee")
+        };
+
+        // 尚未设置密码的用户等待接受邀请
+        assert!(InvitationService::is_pending(&user));
+
+        user.password_hash = "$2b$12$hash".to_string();
+        assert!(!InvitationService::is_pending(&user));
+
+        // 服务账户没有密码，但不是等待邀请的用户
+        user.password_hash = String::new();
+        user.is_service_account = true;
+        assert!(!InvitationService::is_pending(&user));
+
+        let message = InvitationService::invitation_message(&user, "abc123", now);
+        assert_eq!(message.to, "invitee@example.com");
+        assert!(message.body.contains("/accept-invitation?token=abc123"));
+    }
+}
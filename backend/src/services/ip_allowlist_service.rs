@@ -0,0 +1,261 @@
+use chrono::Utc;
+use sea_orm::*;
+use std::net::IpAddr;
+use thiserror::Error;
+
+use crate::client_ip::parse_network;
+use crate::models::{ip_allowlist_entry, role, user, user_role, CreateIpAllowlistEntryDto};
+
+#[derive(Error, Debug)]
+pub enum IpAllowlistError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("角色不存在")]
+    RoleNotFound,
+    #[error("用户不存在")]
+    UserNotFound,
+    #[error("白名单记录不存在")]
+    EntryNotFound,
+    #[error("IP地址或CIDR格式无效: {0}")]
+    InvalidCidr(String),
+    #[error("该网段已在白名单中")]
+    Duplicate,
+    #[error("修改后当前网络将无法访问您的账户")]
+    WouldLockOut,
+}
+
+/// 白名单所属的角色或用户
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowlistOwner {
+    Role(i32),
+    User(i32),
+}
+
+pub struct IpAllowlistService;
+
+impl IpAllowlistService {
+    /// 查看角色或用户的白名单
+    pub async fn list(
+        db: &DatabaseConnection,
+        owner: AllowlistOwner,
+    ) -> Result<Vec<ip_allowlist_entry::Model>, IpAllowlistError> {
+        Self::ensure_owner_exists(db, owner).await?;
+
+        let entries = ip_allowlist_entry::Entity::find()
+            .filter(Self::owner_condition(owner))
+            .order_by_asc(ip_allowlist_entry::Column::Id)
+            .all(db)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// 添加白名单记录；添加后操作者自己当前的地址不再被允许时拒绝，避免把自己锁在外面
+    pub async fn add(
+        db: &DatabaseConnection,
+        owner: AllowlistOwner,
+        dto: CreateIpAllowlistEntryDto,
+        actor_id: i32,
+        actor_ip: Option<&str>,
+    ) -> Result<ip_allowlist_entry::Model, IpAllowlistError> {
+        Self::ensure_owner_exists(db, owner).await?;
+
+        let network = parse_network(&dto.cidr).ok_or_else(|| IpAllowlistError::InvalidCidr(dto.cidr.clone()))?;
+        let cidr = network.to_string();
+
+        let existing = ip_allowlist_entry::Entity::find()
+            .filter(Self::owner_condition(owner))
+            .filter(ip_allowlist_entry::Column::Cidr.eq(&cidr))
+            .one(db)
+            .await?;
+        if existing.is_some() {
+            return Err(IpAllowlistError::Duplicate);
+        }
+
+        if Self::applies_to(db, owner, actor_id).await? {
+            let mut cidrs: Vec<String> = Self::entries_for_user(db, actor_id)
+                .await?
+                .into_iter()
+                .map(|entry| entry.cidr)
+                .collect();
+            cidrs.push(cidr.clone());
+            if !Self::permits(&cidrs, actor_ip) {
+                return Err(IpAllowlistError::WouldLockOut);
+            }
+        }
+
+        let (role_id, user_id) = match owner {
+            AllowlistOwner::Role(id) => (Some(id), None),
+            AllowlistOwner::User(id) => (None, Some(id)),
+        };
+        let entry = ip_allowlist_entry::ActiveModel {
+            role_id: Set(role_id),
+            user_id: Set(user_id),
+            cidr: Set(cidr),
+            description: Set(dto.description.filter(|d| !d.trim().is_empty())),
+            created_by: Set(Some(actor_id)),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        };
+
+        Ok(entry.insert(db).await?)
+    }
+
+    /// 删除白名单记录；删除后操作者自己当前的地址不再被允许时拒绝
+    pub async fn remove(
+        db: &DatabaseConnection,
+        owner: AllowlistOwner,
+        entry_id: i32,
+        actor_id: i32,
+        actor_ip: Option<&str>,
+    ) -> Result<(), IpAllowlistError> {
+        let entry = ip_allowlist_entry::Entity::find_by_id(entry_id)
+            .filter(Self::owner_condition(owner))
+            .one(db)
+            .await?
+            .ok_or(IpAllowlistError::EntryNotFound)?;
+
+        if Self::applies_to(db, owner, actor_id).await? {
+            let cidrs: Vec<String> = Self::entries_for_user(db, actor_id)
+                .await?
+                .into_iter()
+                .filter(|e| e.id != entry.id)
+                .map(|e| e.cidr)
+                .collect();
+            if !Self::permits(&cidrs, actor_ip) {
+                return Err(IpAllowlistError::WouldLockOut);
+            }
+        }
+
+        ip_allowlist_entry::Entity::delete_by_id(entry.id).exec(db).await?;
+
+        Ok(())
+    }
+
+    /// 用户是否可以从该地址登录和访问：用户本人及其有效角色的白名单合并计算，任一网段包含该地址即允许
+    pub async fn is_allowed(
+        db: &DatabaseConnection,
+        user_id: i32,
+        ip_address: Option<&str>,
+    ) -> Result<bool, DbErr> {
+        let cidrs: Vec<String> = Self::entries_for_user(db, user_id)
+            .await?
+            .into_iter()
+            .map(|entry| entry.cidr)
+            .collect();
+
+        Ok(Self::permits(&cidrs, ip_address))
+    }
+
+    /// 用户本人及其有效角色的白名单记录
+    async fn entries_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<Vec<ip_allowlist_entry::Model>, DbErr> {
+        let role_ids: Vec<i32> = user_role::Entity::find()
+            .filter(user_role::Column::UserId.eq(user_id))
+            .inner_join(role::Entity)
+            .filter(role::Column::IsActive.eq(true))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|ur| ur.role_id)
+            .collect();
+
+        let mut condition = Condition::any().add(ip_allowlist_entry::Column::UserId.eq(user_id));
+        if !role_ids.is_empty() {
+            condition = condition.add(ip_allowlist_entry::Column::RoleId.is_in(role_ids));
+        }
+
+        ip_allowlist_entry::Entity::find()
+            .filter(condition)
+            .all(db)
+            .await
+    }
+
+    /// 白名单为空时不限制；否则地址必须属于其中一个网段，无法确定地址时拒绝
+    fn permits(cidrs: &[String], ip_address: Option<&str>) -> bool {
+        if cidrs.is_empty() {
+            return true;
+        }
+
+        let Some(ip) = ip_address.and_then(|ip| ip.parse::<IpAddr>().ok()) else {
+            return false;
+        };
+
+        cidrs
+            .iter()
+            .filter_map(|cidr| parse_network(cidr))
+            .any(|network| network.contains(&ip))
+    }
+
+    /// 白名单是否作用于该用户：属于该用户本人，或属于该用户的有效角色
+    async fn applies_to(db: &DatabaseConnection, owner: AllowlistOwner, user_id: i32) -> Result<bool, DbErr> {
+        match owner {
+            AllowlistOwner::User(id) => Ok(id == user_id),
+            AllowlistOwner::Role(role_id) => {
+                let count = user_role::Entity::find()
+                    .filter(user_role::Column::UserId.eq(user_id))
+                    .filter(user_role::Column::RoleId.eq(role_id))
+                    .inner_join(role::Entity)
+                    .filter(role::Column::IsActive.eq(true))
+                    .count(db)
+                    .await?;
+                Ok(count > 0)
+            }
+        }
+    }
+
+    async fn ensure_owner_exists(db: &DatabaseConnection, owner: AllowlistOwner) -> Result<(), IpAllowlistError> {
+        match owner {
+            AllowlistOwner::Role(id) => role::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .map(|_| ())
+                .ok_or(IpAllowlistError::RoleNotFound),
+            AllowlistOwner::User(id) => user::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .map(|_| ())
+                .ok_or(IpAllowlistError::UserNotFound),
+        }
+    }
+
+    fn owner_condition(owner: AllowlistOwner) -> Condition {
+        match owner {
+            AllowlistOwner::Role(id) => Condition::all().add(ip_allowlist_entry::Column::RoleId.eq(id)),
+            AllowlistOwner::User(id) => Condition::all().add(ip_allowlist_entry::Column::UserId.eq(id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidrs(items: &[&str]) -> Vec<String> {
+        items.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_permits() {
+        // 没有白名单时不限制，无法确定地址也允许
+        assert!(IpAllowlistService::permits(&[], Some("203.0.113.5")));
+        assert!(IpAllowlistService::permits(&[], None));
+
+        let office = cidrs(&["192.168.1.0/24", "10.8.0.1/32", "2001:db8::/32"]);
+        assert!(IpAllowlistService::permits(&office, Some("192.168.1.77")));
+        assert!(IpAllowlistService::permits(&office, Some("10.8.0.1")));
+        assert!(IpAllowlistService::permits(&office, Some("2001:db8::5")));
+        assert!(!IpAllowlistService::permits(&office, Some("192.168.2.1")));
+        assert!(!IpAllowlistService::permits(&office, Some("10.8.0.2")));
+
+        // 有白名单时，无法确定或无法解析的地址一律拒绝
+        assert!(!IpAllowlistService::permits(&office, None));
+        assert!(!IpAllowlistService::permits(&office, Some("unknown")));
+
+        // 数据库中无效的记录不匹配任何地址，但仍然使白名单生效
+        let invalid = cidrs(&["not-a-network"]);
+        assert!(!IpAllowlistService::permits(&invalid, Some("192.168.1.1")));
+    }
+}
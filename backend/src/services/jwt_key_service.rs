@@ -0,0 +1,292 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use std::env;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::jwt_keys::{self, generate_private_key, JwtAlgorithm, JwtKey, KeyEncryption, KeyError, KeyRing};
+use crate::models::jwt_signing_key;
+
+/// 后台检查密钥轮换、重新加载密钥的间隔（秒）
+pub const KEY_REFRESH_INTERVAL: u64 = 60;
+
+/// 新密钥先通过JWKS发布、之后才开始签名的时间（秒），保证其他实例和下游服务都已拿到新公钥
+const KEY_ACTIVATION_DELAY: i64 = 600;
+
+/// 旧密钥在最后一个令牌过期后额外保留的验签时间（秒），覆盖各实例重新加载密钥的延迟
+const KEY_EXPIRY_LEEWAY: i64 = 300;
+
+/// 多实例同时轮换时使用的PostgreSQL咨询锁
+const ROTATION_LOCK_ID: i64 = 0x6a77_6b73;
+
+#[derive(Error, Debug)]
+pub enum JwtKeyError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("{0}")]
+    KeyError(#[from] KeyError),
+}
+
+/// 签名密钥配置
+#[derive(Debug, Clone, Copy)]
+pub struct JwtKeyPolicy {
+    pub algorithm: JwtAlgorithm,
+    /// 自动轮换间隔（秒），0 表示不自动轮换
+    pub rotation_interval: i64,
+}
+
+impl JwtKeyPolicy {
+    pub fn from_env() -> Result<Self, KeyError> {
+        Ok(Self {
+            algorithm: JwtAlgorithm::from_env()?,
+            rotation_interval: env::var("JWT_KEY_ROTATION_INTERVAL")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(2_592_000),
+        })
+    }
+
+    /// 最新的密钥是否需要轮换：没有密钥、算法已变更，或启用时间超过轮换间隔
+    fn rotation_due(&self, newest: Option<&jwt_signing_key::Model>, now: &DateTime<Utc>) -> bool {
+        match newest {
+            None => true,
+            Some(key) => {
+                key.algorithm != self.algorithm.as_str()
+                    || (self.rotation_interval > 0
+                        && key.activates_at.with_timezone(&Utc)
+                            + Duration::seconds(self.rotation_interval)
+                            <= *now)
+            }
+        }
+    }
+}
+
+pub struct JwtKeyService;
+
+impl JwtKeyService {
+    /// 服务启动时初始化签名密钥，配置无效时返回错误，调用方应拒绝启动
+    ///
+    /// HS256 要求配置足够长的 JWT_SECRET；EdDSA/RS256 要求配置加密私钥的 JWT_KEY_ENCRYPTION_KEY，
+    /// 密钥对在没有时自动生成。
+    pub async fn init(db: &DatabaseConnection) -> Result<(), JwtKeyError> {
+        let policy = JwtKeyPolicy::from_env()?;
+
+        if !policy.algorithm.is_asymmetric() {
+            let secret = env::var("JWT_SECRET").map_err(|_| KeyError::MissingSecret)?;
+            let key = JwtKey::hmac(&secret)?;
+            jwt_keys::install(KeyRing::new(key.clone(), vec![key]));
+            return Ok(());
+        }
+
+        Self::refresh(db).await
+    }
+
+    /// 到期时轮换密钥，并重新加载数据库中的密钥（包括其他实例生成的密钥）
+    pub async fn refresh(db: &DatabaseConnection) -> Result<(), JwtKeyError> {
+        let policy = JwtKeyPolicy::from_env()?;
+        if !policy.algorithm.is_asymmetric() {
+            return Ok(());
+        }
+
+        let encryption = KeyEncryption::from_env()?;
+        Self::rotate_if_due(db, &policy, &encryption).await?;
+        Self::reload(db, &encryption).await
+    }
+
+    async fn rotate_if_due(
+        db: &DatabaseConnection,
+        policy: &JwtKeyPolicy,
+        encryption: &KeyEncryption,
+    ) -> Result<(), JwtKeyError> {
+        let now = Utc::now();
+
+        if !policy.rotation_due(Self::newest_key(db).await?.as_ref(), &now) {
+            return Ok(());
+        }
+
+        // 生成RSA密钥较慢，放在事务之外
+        let algorithm = policy.algorithm;
+        let private_key = tokio::task::spawn_blocking(move || generate_private_key(algorithm))
+            .await
+            .map_err(|e| KeyError::GenerateError(e.to_string()))??;
+        let key = JwtKey::from_private_der(algorithm, &private_key)?;
+        let kid = key.kid.clone().unwrap_or_default();
+        let encrypted_private_key = encryption.seal(&kid, &private_key)?;
+
+        let txn = db.begin().await?;
+
+        // 多个实例同时发现需要轮换时，只有一个实例生成新密钥
+        txn.execute(Statement::from_string(
+            DbBackend::Postgres,
+            format!("SELECT pg_advisory_xact_lock({})", ROTATION_LOCK_ID),
+        ))
+        .await?;
+
+        let newest = Self::newest_key(&txn).await?;
+        if !policy.rotation_due(newest.as_ref(), &now) {
+            return Ok(());
+        }
+
+        // 首个密钥立即启用；之后的新密钥先发布一段时间再开始签名
+        let activates_at = if newest.is_some() {
+            now + Duration::seconds(KEY_ACTIVATION_DELAY)
+        } else {
+            now
+        };
+
+        // 被取代的密钥在新密钥启用前仍会签名，此后签发的令牌全部过期前仍需验签
+        let expires_at = Self::superseded_key_expiry(activates_at);
+        jwt_signing_key::Entity::update_many()
+            .col_expr(
+                jwt_signing_key::Column::ExpiresAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(expires_at))),
+            )
+            .filter(jwt_signing_key::Column::ExpiresAt.is_null())
+            .exec(&txn)
+            .await?;
+
+        let record = jwt_signing_key::ActiveModel {
+            kid: Set(kid.clone()),
+            algorithm: Set(algorithm.as_str().to_string()),
+            encrypted_private_key: Set(encrypted_private_key),
+            activates_at: Set(activates_at.into()),
+            expires_at: Set(None),
+            created_at: Set(now.into()),
+            ..Default::default()
+        };
+        jwt_signing_key::Entity::insert(record)
+            .exec_without_returning(&txn)
+            .await?;
+
+        // 清理已过期的旧密钥
+        jwt_signing_key::Entity::delete_many()
+            .filter(jwt_signing_key::Column::ExpiresAt.lt(now))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        tracing::info!(
+            "已生成新的JWT签名密钥 {} ({})，将于 {} 启用",
+            kid,
+            algorithm.as_str(),
+            activates_at
+        );
+
+        Ok(())
+    }
+
+    /// 从数据库加载仍在有效期内的密钥并安装：已启用的最新密钥用于签名，其余只用于验签
+    async fn reload(db: &DatabaseConnection, encryption: &KeyEncryption) -> Result<(), JwtKeyError> {
+        let now = Utc::now();
+
+        let records = jwt_signing_key::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(jwt_signing_key::Column::ExpiresAt.is_null())
+                    .add(jwt_signing_key::Column::ExpiresAt.gt(now)),
+            )
+            .order_by_desc(jwt_signing_key::Column::ActivatesAt)
+            .all(db)
+            .await?;
+
+        let mut signing_key = None;
+        let mut keys = Vec::with_capacity(records.len());
+
+        for record in records {
+            let key = record.algorithm.parse::<JwtAlgorithm>().and_then(|algorithm| {
+                let private_key = encryption.open(&record.kid, &record.encrypted_private_key)?;
+                JwtKey::from_private_der(algorithm, &private_key)
+            });
+
+            match key {
+                Ok(key) => {
+                    if signing_key.is_none() && record.activates_at.with_timezone(&Utc) <= now {
+                        signing_key = Some(key.clone());
+                    }
+                    keys.push(key);
+                }
+                Err(e) => tracing::error!("加载JWT签名密钥 {} 失败: {}", record.kid, e),
+            }
+        }
+
+        let signing_key = signing_key.ok_or(KeyError::NoSigningKey)?;
+        jwt_keys::install(KeyRing::new(signing_key, keys));
+
+        Ok(())
+    }
+
+    async fn newest_key<C: ConnectionTrait>(db: &C) -> Result<Option<jwt_signing_key::Model>, DbErr> {
+        jwt_signing_key::Entity::find()
+            .order_by_desc(jwt_signing_key::Column::ActivatesAt)
+            .one(db)
+            .await
+    }
+
+    fn superseded_key_expiry(successor_activates_at: DateTime<Utc>) -> DateTime<Utc> {
+        successor_activates_at + Duration::seconds(AuthService::max_token_ttl() as i64 + KEY_EXPIRY_LEEWAY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_record(algorithm: JwtAlgorithm, activates_at: DateTime<Utc>) -> jwt_signing_key::Model {
+        jwt_signing_key::Model {
+            id: 1,
+            kid: "kid".to_string(),
+            algorithm: algorithm.as_str().to_string(),
+            encrypted_private_key: Vec::new(),
+            activates_at: activates_at.into(),
+            expires_at: None,
+            created_at: activates_at.into(),
+        }
+    }
+
+    #[test]
+    fn test_rotation_due_business_rules() {
+        let now = Utc::now();
+        let policy = JwtKeyPolicy {
+            algorithm: JwtAlgorithm::EdDSA,
+            rotation_interval: 86_400,
+        };
+
+        // 没有密钥时立即生成
+        assert!(policy.rotation_due(None, &now));
+
+        // 未到轮换时间
+        let recent = key_record(JwtAlgorithm::EdDSA, now - Duration::hours(1));
+        assert!(!policy.rotation_due(Some(&recent), &now));
+
+        // 已到轮换时间
+        let old = key_record(JwtAlgorithm::EdDSA, now - Duration::days(1));
+        assert!(policy.rotation_due(Some(&old), &now));
+
+        // 尚未启用的新密钥不会触发再次轮换
+        let pending = key_record(JwtAlgorithm::EdDSA, now + Duration::minutes(5));
+        assert!(!policy.rotation_due(Some(&pending), &now));
+
+        // 更换算法后立即轮换
+        let rsa = key_record(JwtAlgorithm::RS256, now - Duration::hours(1));
+        assert!(policy.rotation_due(Some(&rsa), &now));
+
+        // 关闭自动轮换
+        let manual = JwtKeyPolicy {
+            rotation_interval: 0,
+            ..policy
+        };
+        assert!(!manual.rotation_due(Some(&old), &now));
+    }
+
+    #[test]
+    fn test_superseded_key_outlives_last_token() {
+        let activates_at = Utc::now();
+        let expires_at = JwtKeyService::superseded_key_expiry(activates_at);
+
+        // 旧密钥在新密钥启用前签发的最后一个令牌过期之后才失效
+        assert!(expires_at >= activates_at + Duration::seconds(AuthService::max_token_ttl() as i64));
+    }
+}
@@ -0,0 +1,432 @@
+use chrono::Utc;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::*;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+use crate::ldap::{self, normalize_dn, DirectoryUser, LdapDirectory, LdapError};
+use crate::models::{
+    ldap_group_mapping, role, user, user_external_identity, user_role, CreateLdapGroupMappingDto,
+    LdapGroupMappingResponse,
+};
+use crate::permission_cache::{self, Invalidation};
+use crate::rbac::{RbacError, RbacService};
+use crate::services::RevocationService;
+
+/// 目录用户在 user_external_identities 中的 provider
+pub const LDAP_PROVIDER: &str = "ldap";
+
+#[derive(Error, Debug)]
+pub enum LdapServiceError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("{0}")]
+    Ldap(#[from] LdapError),
+    #[error("权限检查失败: {0}")]
+    RbacError(#[from] RbacError),
+    #[error("未启用LDAP认证")]
+    NotConfigured,
+    #[error("用户名 {0} 已被本地账户使用")]
+    UsernameTaken(String),
+    #[error("邮箱 {0} 已被其他账户使用")]
+    EmailTaken(String),
+    #[error("角色不存在")]
+    RoleNotFound,
+    #[error("该组已映射到这个角色")]
+    MappingExists,
+    #[error("组映射不存在")]
+    MappingNotFound,
+}
+
+/// 登录时目录认证的结果
+#[derive(Debug)]
+pub enum LdapAuthOutcome {
+    /// 不是目录用户（或未启用LDAP），继续使用本地密码验证
+    NotDirectoryUser,
+    /// 目录认证通过，已同步用户信息和角色
+    Authenticated(user::Model),
+    /// 目录用户的密码错误
+    InvalidCredentials,
+}
+
+/// 一次目录同步的结果
+#[derive(Debug, Default, serde::Serialize)]
+pub struct LdapSyncReport {
+    pub directory_users: usize,
+    pub updated: usize,
+    pub deactivated: usize,
+}
+
+pub struct LdapService;
+
+impl LdapService {
+    /// 登录时的目录认证
+    ///
+    /// 已关联目录的用户只能使用目录密码；本地不存在的用户在目录中认证通过后自动创建。
+    /// 本地已有的非目录用户不受影响，避免目录中的同名账户接管本地账户。
+    pub async fn authenticate(
+        db: &DatabaseConnection,
+        username: &str,
+        password: &str,
+        local_user: Option<&user::Model>,
+    ) -> Result<LdapAuthOutcome, LdapServiceError> {
+        let Some(directory) = ldap::directory() else {
+            return Ok(LdapAuthOutcome::NotDirectoryUser);
+        };
+
+        let identity = match local_user {
+            Some(user) => match Self::identity_of(db, user.id).await? {
+                Some(identity) => Some(identity),
+                None => return Ok(LdapAuthOutcome::NotDirectoryUser),
+            },
+            None => None,
+        };
+
+        let directory_username = identity.as_ref().map_or(username, |identity| identity.subject.as_str());
+        let Some(entry) = directory.authenticate(directory_username, password).await? else {
+            return Ok(LdapAuthOutcome::InvalidCredentials);
+        };
+
+        let user = Self::upsert_user(db, &entry).await?;
+        Ok(LdapAuthOutcome::Authenticated(user))
+    }
+
+    /// 同步目录中的所有用户：更新邮箱和角色，停用已从目录中移除的用户
+    pub async fn sync(db: &DatabaseConnection) -> Result<LdapSyncReport, LdapServiceError> {
+        let directory = ldap::directory().ok_or(LdapServiceError::NotConfigured)?;
+        Self::sync_with(db, directory).await
+    }
+
+    async fn sync_with(db: &DatabaseConnection, directory: &LdapDirectory) -> Result<LdapSyncReport, LdapServiceError> {
+        let entries = directory.list_users().await?;
+        let mut report = LdapSyncReport {
+            directory_users: entries.len(),
+            ..Default::default()
+        };
+
+        // 目录查询返回空结果多半是配置错误，不能因此停用所有目录用户
+        if entries.is_empty() {
+            tracing::warn!("目录中没有查询到用户，跳过本次同步");
+            return Ok(report);
+        }
+
+        let entries: HashMap<String, DirectoryUser> = entries
+            .into_iter()
+            .map(|entry| (entry.username.to_lowercase(), entry))
+            .collect();
+
+        let linked = user_external_identity::Entity::find()
+            .filter(user_external_identity::Column::Provider.eq(LDAP_PROVIDER))
+            .find_also_related(user::Entity)
+            .all(db)
+            .await?;
+
+        for (identity, user) in linked {
+            let Some(user) = user else { continue };
+
+            match entries.get(&identity.subject) {
+                Some(entry) => {
+                    if let Err(e) = Self::update_user(db, user, entry).await {
+                        tracing::warn!("同步目录用户 {} 失败: {}", identity.subject, e);
+                        continue;
+                    }
+                    report.updated += 1;
+                }
+                None if user.is_active => {
+                    let mut active_user: user::ActiveModel = user.into();
+                    active_user.is_active = Set(false);
+                    let user = active_user.update(db).await?;
+                    RevocationService::revoke_all_for_user(db, user.id, "ldap_removed").await?;
+
+                    tracing::info!("目录中已不存在用户 {}，已停用", user.username);
+                    report.deactivated += 1;
+                }
+                None => {}
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn identity_of(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<Option<user_external_identity::Model>, DbErr> {
+        user_external_identity::Entity::find()
+            .filter(user_external_identity::Column::Provider.eq(LDAP_PROVIDER))
+            .filter(user_external_identity::Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 按目录用户名查找已关联的用户并更新，不存在时创建
+    async fn upsert_user(db: &DatabaseConnection, entry: &DirectoryUser) -> Result<user::Model, LdapServiceError> {
+        let subject = entry.username.to_lowercase();
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        let linked = user_external_identity::Entity::find()
+            .filter(user_external_identity::Column::Provider.eq(LDAP_PROVIDER))
+            .filter(user_external_identity::Column::Subject.eq(&subject))
+            .find_also_related(user::Entity)
+            .one(db)
+            .await?;
+
+        if let Some((identity, Some(user))) = linked {
+            let mut identity: user_external_identity::ActiveModel = identity.into();
+            identity.email = Set(entry.email.clone());
+            identity.last_login_at = Set(Some(now));
+            identity.update(db).await?;
+
+            return Self::update_user(db, user, entry).await;
+        }
+
+        let username_taken = user::Entity::find()
+            .filter(user::Column::Username.eq(&entry.username))
+            .one(db)
+            .await?
+            .is_some();
+        if username_taken {
+            return Err(LdapServiceError::UsernameTaken(entry.username.clone()));
+        }
+
+        let email = entry
+            .email
+            .clone()
+            .unwrap_or_else(|| format!("{}@{}", subject, DIRECTORY_EMAIL_DOMAIN));
+        if Self::email_taken(db, &email, None).await? {
+            return Err(LdapServiceError::EmailTaken(email));
+        }
+
+        let txn = db.begin().await?;
+        // 密码由目录管理，本地不保存密码
+        let user = user::ActiveModel {
+            username: Set(entry.username.clone()),
+            email: Set(email),
+            password_hash: Set(String::new()),
+            is_active: Set(true),
+            must_change_password: Set(false),
+            email_verified_at: Set(Some(now)),
+            ..Default::default()
+        };
+        let user = user.insert(&txn).await?;
+
+        let identity = user_external_identity::ActiveModel {
+            user_id: Set(user.id),
+            provider: Set(LDAP_PROVIDER.to_string()),
+            subject: Set(subject),
+            email: Set(entry.email.clone()),
+            created_at: Set(now),
+            last_login_at: Set(Some(now)),
+            ..Default::default()
+        };
+        identity.insert(&txn).await?;
+        txn.commit().await?;
+
+        tracing::info!("通过LDAP自动创建了用户 {}", user.username);
+
+        Self::sync_roles(db, user.id, &entry.groups).await?;
+        Ok(user)
+    }
+
+    /// 用目录中的信息更新本地用户：邮箱（未被其他账户使用时）和映射的角色
+    ///
+    /// 不会重新启用被管理员禁用的账户。
+    async fn update_user(
+        db: &DatabaseConnection,
+        user: user::Model,
+        entry: &DirectoryUser,
+    ) -> Result<user::Model, LdapServiceError> {
+        let user = match &entry.email {
+            Some(email) if *email != user.email => {
+                if Self::email_taken(db, email, Some(user.id)).await? {
+                    tracing::warn!("目录用户 {} 的邮箱 {} 已被其他账户使用，未更新", entry.username, email);
+                    user
+                } else {
+                    let mut active_user: user::ActiveModel = user.into();
+                    active_user.email = Set(email.clone());
+                    active_user.update(db).await?
+                }
+            }
+            _ => user,
+        };
+
+        Self::sync_roles(db, user.id, &entry.groups).await?;
+        Ok(user)
+    }
+
+    async fn email_taken(db: &DatabaseConnection, email: &str, except_user_id: Option<i32>) -> Result<bool, DbErr> {
+        let mut query = user::Entity::find().filter(user::Column::Email.eq(email));
+        if let Some(user_id) = except_user_id {
+            query = query.filter(user::Column::Id.ne(user_id));
+        }
+        Ok(query.one(db).await?.is_some())
+    }
+
+    /// 按组映射调整用户的角色，只增删出现在映射中的角色
+    async fn sync_roles(db: &DatabaseConnection, user_id: i32, groups: &[String]) -> Result<(), LdapServiceError> {
+        let mappings = ldap_group_mapping::Entity::find().all(db).await?;
+        if mappings.is_empty() {
+            return Ok(());
+        }
+
+        let current: HashSet<i32> = user_role::Entity::find()
+            .filter(user_role::Column::UserId.eq(user_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|user_role| user_role.role_id)
+            .collect();
+
+        let (to_add, to_remove) = Self::role_changes(&mappings, groups, &current);
+
+        for role_id in to_add {
+            RbacService::assign_role_to_user(db, user_id, role_id).await?;
+        }
+        if !to_remove.is_empty() {
+            user_role::Entity::delete_many()
+                .filter(user_role::Column::UserId.eq(user_id))
+                .filter(user_role::Column::RoleId.is_in(to_remove))
+                .exec(db)
+                .await?;
+            permission_cache::cache().invalidate(Invalidation::User(user_id));
+        }
+
+        Ok(())
+    }
+
+    /// 计算需要增加和移除的角色
+    fn role_changes(
+        mappings: &[ldap_group_mapping::Model],
+        groups: &[String],
+        current: &HashSet<i32>,
+    ) -> (Vec<i32>, Vec<i32>) {
+        let groups: HashSet<String> = groups.iter().map(|group| normalize_dn(group)).collect();
+
+        let managed: HashSet<i32> = mappings.iter().map(|m| m.role_id).collect();
+        let wanted: HashSet<i32> = mappings
+            .iter()
+            .filter(|m| groups.contains(&m.group_dn))
+            .map(|m| m.role_id)
+            .collect();
+
+        let mut to_add: Vec<i32> = wanted.difference(current).copied().collect();
+        let mut to_remove: Vec<i32> = current
+            .iter()
+            .filter(|role_id| managed.contains(role_id) && !wanted.contains(role_id))
+            .copied()
+            .collect();
+        to_add.sort();
+        to_remove.sort();
+
+        (to_add, to_remove)
+    }
+
+    pub async fn list_mappings(db: &DatabaseConnection) -> Result<Vec<LdapGroupMappingResponse>, LdapServiceError> {
+        let mappings = ldap_group_mapping::Entity::find()
+            .find_also_related(role::Entity)
+            .order_by_asc(ldap_group_mapping::Column::GroupDn)
+            .all(db)
+            .await?;
+
+        Ok(mappings
+            .into_iter()
+            .map(|(mapping, role)| LdapGroupMappingResponse {
+                id: mapping.id,
+                group_dn: mapping.group_dn,
+                role_id: mapping.role_id,
+                role_name: role.map(|role| role.name),
+                created_at: mapping.created_at,
+            })
+            .collect())
+    }
+
+    pub async fn create_mapping(
+        db: &DatabaseConnection,
+        dto: CreateLdapGroupMappingDto,
+    ) -> Result<ldap_group_mapping::Model, LdapServiceError> {
+        role::Entity::find_by_id(dto.role_id)
+            .one(db)
+            .await?
+            .ok_or(LdapServiceError::RoleNotFound)?;
+
+        let group_dn = normalize_dn(&dto.group_dn);
+        let existing = ldap_group_mapping::Entity::find()
+            .filter(ldap_group_mapping::Column::GroupDn.eq(&group_dn))
+            .filter(ldap_group_mapping::Column::RoleId.eq(dto.role_id))
+            .one(db)
+            .await?;
+        if existing.is_some() {
+            return Err(LdapServiceError::MappingExists);
+        }
+
+        let mapping = ldap_group_mapping::ActiveModel {
+            group_dn: Set(group_dn),
+            role_id: Set(dto.role_id),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        };
+
+        Ok(mapping.insert(db).await?)
+    }
+
+    /// 删除映射，已分配的角色保留，需要时由管理员手动移除
+    pub async fn delete_mapping(db: &DatabaseConnection, id: i32) -> Result<(), LdapServiceError> {
+        let result = ldap_group_mapping::Entity::delete_by_id(id).exec(db).await?;
+        if result.rows_affected == 0 {
+            return Err(LdapServiceError::MappingNotFound);
+        }
+        Ok(())
+    }
+}
+
+/// 目录中没有邮箱的用户使用的占位邮箱域名，.invalid 是保留的顶级域名
+const DIRECTORY_EMAIL_DOMAIN: &str = "directory.invalid";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(group_dn: &str, role_id: i32) -> ldap_group_mapping::Model {
+        ldap_group_mapping::Model {
+            id: role_id,
+            group_dn: group_dn.to_string(),
+            role_id,
+            created_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_role_changes_only_touch_mapped_roles() {
+        let mappings = vec![
+            mapping("cn=admins,ou=groups,dc=example,dc=org", 1),
+            mapping("cn=developers,ou=groups,dc=example,dc=org", 2),
+        ];
+        // 当前有角色1（映射）和角色3（手动分配）
+        let current: HashSet<i32> = [1, 3].into_iter().collect();
+
+        let (to_add, to_remove) = LdapService::role_changes(
+            &mappings,
+            &["CN=Developers, OU=Groups,DC=example,DC=org".to_string()],
+            &current,
+        );
+
+        assert_eq!(to_add, vec![2]);
+        // 不再属于 admins 组，移除角色1；手动分配的角色3保留
+        assert_eq!(to_remove, vec![1]);
+    }
+
+    #[test]
+    fn test_role_changes_no_change() {
+        let mappings = vec![mapping("cn=admins,ou=groups,dc=example,dc=org", 1)];
+        let current: HashSet<i32> = [1].into_iter().collect();
+
+        let (to_add, to_remove) = LdapService::role_changes(
+            &mappings,
+            &["cn=admins,ou=groups,dc=example,dc=org".to_string()],
+            &current,
+        );
+
+        assert!(to_add.is_empty());
+        assert!(to_remove.is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use std::env;
+
+use crate::models::{login_lockout, login_log};
+
+/// 登录失败计数的维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockScope {
+    /// 按用户名计数
+    User,
+    /// 按客户端IP计数
+    Ip,
+}
+
+impl LockScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockScope::User => "user",
+            LockScope::Ip => "ip",
+        }
+    }
+}
+
+/// 登录锁定策略
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    /// 同一账户在计数窗口内允许的最大失败次数
+    pub max_failed_attempts: i32,
+    /// 同一IP在计数窗口内允许的最大失败次数
+    pub max_failed_attempts_per_ip: i32,
+    /// 失败次数计数窗口（秒）
+    pub failure_window: i64,
+    /// 达到阈值后的锁定时长（秒）
+    pub lockout_duration: i64,
+    /// 首次失败后的响应延迟（毫秒），之后每次失败翻倍
+    pub failure_delay_ms: u64,
+    /// 响应延迟上限（毫秒）
+    pub max_failure_delay_ms: u64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: 5,
+            max_failed_attempts_per_ip: 20,
+            failure_window: 900,
+            lockout_duration: 900,
+            failure_delay_ms: 500,
+            max_failure_delay_ms: 8000,
+        }
+    }
+}
+
+impl LockoutPolicy {
+    /// 从环境变量读取策略，未配置的项使用默认值
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            max_failed_attempts: env_or("LOGIN_MAX_FAILED_ATTEMPTS", default.max_failed_attempts),
+            max_failed_attempts_per_ip: env_or(
+                "LOGIN_MAX_FAILED_ATTEMPTS_PER_IP",
+                default.max_failed_attempts_per_ip,
+            ),
+            failure_window: env_or("LOGIN_FAILURE_WINDOW", default.failure_window),
+            lockout_duration: env_or("LOGIN_LOCKOUT_DURATION", default.lockout_duration),
+            failure_delay_ms: env_or("LOGIN_FAILURE_DELAY_MS", default.failure_delay_ms),
+            max_failure_delay_ms: env_or(
+                "LOGIN_MAX_FAILURE_DELAY_MS",
+                default.max_failure_delay_ms,
+            ),
+        }
+    }
+
+    pub fn threshold(&self, scope: LockScope) -> i32 {
+        match scope {
+            LockScope::User => self.max_failed_attempts,
+            LockScope::Ip => self.max_failed_attempts_per_ip,
+        }
+    }
+
+    /// 第 failed_count 次连续失败后的响应延迟
+    pub fn failure_delay(&self, failed_count: i32) -> std::time::Duration {
+        if failed_count <= 0 {
+            return std::time::Duration::ZERO;
+        }
+
+        let exponent = (failed_count - 1).min(16) as u32;
+        let delay = self
+            .failure_delay_ms
+            .saturating_mul(1u64 << exponent)
+            .min(self.max_failure_delay_ms);
+
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+/// 一次登录失败记录后的状态
+#[derive(Debug, Clone, Default)]
+pub struct FailureOutcome {
+    /// 账户在当前窗口内的连续失败次数
+    pub failed_count: i32,
+    /// 本次失败触发锁定时的解锁时间
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// 管理员解锁账户的结果
+#[derive(Debug, Clone, Default)]
+pub struct UnlockOutcome {
+    /// 解锁前该用户是否处于锁定状态（账户被锁定，或最近一次登录失败的IP被锁定）
+    pub was_locked: bool,
+    /// 同时解除锁定的IP：该用户最近一次登录失败的地址
+    pub unlocked_ip: Option<String>,
+}
+
+pub struct LockoutService;
+
+impl LockoutService {
+    /// 检查账户或IP是否处于锁定状态，返回最晚的解锁时间
+    pub async fn check_lockout(
+        db: &DatabaseConnection,
+        username: &str,
+        ip_address: Option<&str>,
+    ) -> Result<Option<DateTime<Utc>>, DbErr> {
+        let mut condition = Condition::any().add(Self::scope_condition(LockScope::User, username));
+        if let Some(ip) = ip_address {
+            condition = condition.add(Self::scope_condition(LockScope::Ip, ip));
+        }
+
+        let locks = login_lockout::Entity::find()
+            .filter(condition)
+            .filter(login_lockout::Column::LockedUntil.gt(Utc::now()))
+            .all(db)
+            .await?;
+
+        Ok(locks
+            .into_iter()
+            .filter_map(|lock| lock.locked_until)
+            .map(|until| until.with_timezone(&Utc))
+            .max())
+    }
+
+    /// 记录一次登录失败，达到阈值时锁定对应的账户或IP
+    ///
+    /// username 为已存在用户的用户名；用户名不存在时传 None，只按IP计数，
+    /// 避免攻击者用随机用户名无限制地写入计数记录。
+    pub async fn record_failure(
+        db: &DatabaseConnection,
+        policy: &LockoutPolicy,
+        username: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<FailureOutcome, DbErr> {
+        // 不存在的用户名按首次失败计算延迟，与刚开始失败的已有账户无法区分
+        let mut outcome = FailureOutcome {
+            failed_count: 1,
+            locked_until: None,
+        };
+
+        if let Some(username) = username {
+            let user_lock = Self::increment(db, policy, LockScope::User, username).await?;
+            outcome.failed_count = user_lock.failed_count;
+            outcome.locked_until = Self::lock_if_exceeded(db, policy, LockScope::User, &user_lock).await?;
+        }
+
+        if let Some(ip) = ip_address {
+            let ip_lock = Self::increment(db, policy, LockScope::Ip, ip).await?;
+            let ip_locked_until = Self::lock_if_exceeded(db, policy, LockScope::Ip, &ip_lock).await?;
+            outcome.locked_until = outcome.locked_until.max(ip_locked_until);
+        }
+
+        Ok(outcome)
+    }
+
+    /// 登录成功后清除账户的失败计数（IP计数保留，避免攻击者用自己的账户重置）
+    pub async fn record_success(db: &DatabaseConnection, username: &str) -> Result<(), DbErr> {
+        login_lockout::Entity::delete_many()
+            .filter(Self::scope_condition(LockScope::User, username))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 管理员解锁账户：清除账户的失败计数，并解除该用户最近一次登录失败的IP上的锁定
+    ///
+    /// 只解除该IP已生效的锁定，未达到阈值的IP计数保留。
+    pub async fn unlock_user(db: &DatabaseConnection, username: &str) -> Result<UnlockOutcome, DbErr> {
+        let last_failure = login_log::Entity::find()
+            .filter(login_log::Column::Username.eq(username))
+            .filter(login_log::Column::Success.eq(false))
+            .filter(login_log::Column::IpAddress.is_not_null())
+            .order_by_desc(login_log::Column::CreatedAt)
+            .order_by_desc(login_log::Column::Id)
+            .one(db)
+            .await?;
+        let last_ip = last_failure.and_then(|log| log.ip_address);
+
+        let was_locked = Self::check_lockout(db, username, last_ip.as_deref()).await?.is_some();
+
+        login_lockout::Entity::delete_many()
+            .filter(Self::scope_condition(LockScope::User, username))
+            .exec(db)
+            .await?;
+
+        let mut unlocked_ip = None;
+        if let Some(ip) = last_ip {
+            let result = login_lockout::Entity::delete_many()
+                .filter(Self::scope_condition(LockScope::Ip, &ip))
+                .filter(login_lockout::Column::LockedUntil.gt(Utc::now()))
+                .exec(db)
+                .await?;
+
+            if result.rows_affected > 0 {
+                unlocked_ip = Some(ip);
+            }
+        }
+
+        Ok(UnlockOutcome {
+            was_locked,
+            unlocked_ip,
+        })
+    }
+
+    fn scope_condition(scope: LockScope, key: &str) -> Condition {
+        Condition::all()
+            .add(login_lockout::Column::Scope.eq(scope.as_str()))
+            .add(login_lockout::Column::LockKey.eq(key))
+    }
+
+    /// 原子地增加失败计数；计数窗口过期后从1重新开始
+    async fn increment(
+        db: &DatabaseConnection,
+        policy: &LockoutPolicy,
+        scope: LockScope,
+        key: &str,
+    ) -> Result<login_lockout::Model, DbErr> {
+        let window_start: DateTimeWithTimeZone =
+            (Utc::now() - Duration::seconds(policy.failure_window)).into();
+
+        let statement = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            INSERT INTO login_lockouts (scope, lock_key, failed_count, window_started_at, updated_at)
+            VALUES ($1, $2, 1, NOW(), NOW())
+            ON CONFLICT (scope, lock_key) DO UPDATE SET
+                failed_count = CASE
+                    WHEN login_lockouts.window_started_at < $3 THEN 1
+                    ELSE login_lockouts.failed_count + 1
+                END,
+                window_started_at = CASE
+                    WHEN login_lockouts.window_started_at < $3 THEN NOW()
+                    ELSE login_lockouts.window_started_at
+                END,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+            [scope.as_str().into(), key.into(), window_start.into()],
+        );
+
+        login_lockout::Entity::find()
+            .from_raw_sql(statement)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound("login_lockouts".to_string()))
+    }
+
+    async fn lock_if_exceeded(
+        db: &DatabaseConnection,
+        policy: &LockoutPolicy,
+        scope: LockScope,
+        lock: &login_lockout::Model,
+    ) -> Result<Option<DateTime<Utc>>, DbErr> {
+        if !Self::exceeds_threshold(policy, scope, lock.failed_count) {
+            return Ok(None);
+        }
+
+        let locked_until = Utc::now() + Duration::seconds(policy.lockout_duration);
+
+        login_lockout::Entity::update_many()
+            .col_expr(
+                login_lockout::Column::LockedUntil,
+                Expr::value(Some(DateTimeWithTimeZone::from(locked_until))),
+            )
+            .filter(login_lockout::Column::Id.eq(lock.id))
+            .exec(db)
+            .await?;
+
+        tracing::warn!(
+            "登录失败次数过多，已锁定 {} {} 至 {}",
+            scope.as_str(),
+            lock.lock_key,
+            locked_until
+        );
+
+        Ok(Some(locked_until))
+    }
+
+    fn exceeds_threshold(policy: &LockoutPolicy, scope: LockScope, failed_count: i32) -> bool {
+        let threshold = policy.threshold(scope);
+        threshold > 0 && failed_count >= threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_threshold_business_rules() {
+        let policy = LockoutPolicy::default();
+
+        // 账户阈值：第5次失败触发锁定
+        assert!(!LockoutService::exceeds_threshold(&policy, LockScope::User, 4));
+        assert!(LockoutService::exceeds_threshold(&policy, LockScope::User, 5));
+
+        // IP阈值更宽松，避免误伤同一出口IP下的其他用户
+        assert!(!LockoutService::exceeds_threshold(&policy, LockScope::Ip, 5));
+        assert!(LockoutService::exceeds_threshold(&policy, LockScope::Ip, 20));
+
+        // 阈值配置为0表示关闭该维度的锁定
+        let disabled = LockoutPolicy {
+            max_failed_attempts_per_ip: 0,
+            ..LockoutPolicy::default()
+        };
+        assert!(!LockoutService::exceeds_threshold(&disabled, LockScope::Ip, 1000));
+    }
+
+    #[test]
+    fn test_progressive_delay_business_rules() {
+        let policy = LockoutPolicy::default();
+
+        assert_eq!(policy.failure_delay(0), std::time::Duration::ZERO);
+        assert_eq!(policy.failure_delay(1), std::time::Duration::from_millis(500));
+        assert_eq!(policy.failure_delay(2), std::time::Duration::from_millis(1000));
+        assert_eq!(policy.failure_delay(3), std::time::Duration::from_millis(2000));
+        // 延迟不超过上限
+        assert_eq!(policy.failure_delay(10), std::time::Duration::from_millis(8000));
+        assert_eq!(policy.failure_delay(i32::MAX), std::time::Duration::from_millis(8000));
+    }
+
+    #[test]
+    fn test_lock_scope_names() {
+        assert_eq!(LockScope::User.as_str(), "user");
+        assert_eq!(LockScope::Ip.as_str(), "ip");
+    }
+}
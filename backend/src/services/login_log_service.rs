@@ -0,0 +1,257 @@
+use sea_orm::*;
+
+use crate::extractors::ClientInfo;
+use crate::models::login_log::{self, LoginLogFilter};
+
+/// 登录失败原因
+pub const LOGIN_FAILURE_INVALID_REQUEST: &str = "invalid_request";
+pub const LOGIN_FAILURE_USER_NOT_FOUND: &str = "user_not_found";
+pub const LOGIN_FAILURE_INVALID_PASSWORD: &str = "invalid_password";
+pub const LOGIN_FAILURE_USER_DISABLED: &str = "user_disabled";
+pub const LOGIN_FAILURE_ACCOUNT_LOCKED: &str = "account_locked";
+/// 密码正确，等待二次验证
+pub const LOGIN_FAILURE_MFA_PENDING: &str = "mfa_pending";
+/// 密码正确，所属角色要求先绑定二次验证
+pub const LOGIN_FAILURE_MFA_SETUP_REQUIRED: &str = "mfa_setup_required";
+pub const LOGIN_FAILURE_INVALID_MFA_TOKEN: &str = "invalid_mfa_token";
+pub const LOGIN_FAILURE_INVALID_MFA_CODE: &str = "invalid_mfa_code";
+/// 身份验证通过，但需要先修改管理员设置的初始密码
+pub const LOGIN_FAILURE_MUST_CHANGE_PASSWORD: &str = "must_change_password";
+/// 身份验证通过，但密码已过期需要先修改
+pub const LOGIN_FAILURE_PASSWORD_EXPIRED: &str = "password_expired";
+/// 密码正确，但邮箱尚未验证（EMAIL_VERIFICATION=required）
+pub const LOGIN_FAILURE_EMAIL_UNVERIFIED: &str = "email_unverified";
+/// 服务账户不能使用密码登录
+pub const LOGIN_FAILURE_SERVICE_ACCOUNT: &str = "service_account";
+/// 客户端凭据无效
+pub const LOGIN_FAILURE_INVALID_CLIENT: &str = "invalid_client";
+/// OIDC登录请求无效或已过期
+pub const LOGIN_FAILURE_OIDC_INVALID_STATE: &str = "oidc_invalid_state";
+/// 身份提供方返回错误或ID令牌无效
+pub const LOGIN_FAILURE_OIDC_PROVIDER_ERROR: &str = "oidc_provider_error";
+/// 外部账户没有关联本地用户，且无法自动创建
+pub const LOGIN_FAILURE_OIDC_NOT_LINKED: &str = "oidc_not_linked";
+/// 目录服务不可用，无法验证目录用户
+pub const LOGIN_FAILURE_DIRECTORY_UNAVAILABLE: &str = "directory_unavailable";
+/// 接入应用提交的授权码无效、已使用、已过期或PKCE校验失败
+pub const LOGIN_FAILURE_OAUTH_INVALID_GRANT: &str = "oauth_invalid_grant";
+/// 管理员试图模拟自己、服务账户、已禁用或权限高于自己的用户
+pub const LOGIN_FAILURE_IMPERSONATION_FORBIDDEN: &str = "impersonation_forbidden";
+/// 登录链接无效、已使用或已过期
+pub const LOGIN_FAILURE_MAGIC_LINK_INVALID: &str = "magic_link_invalid";
+/// 所属角色不允许使用邮件链接登录
+pub const LOGIN_FAILURE_MAGIC_LINK_NOT_ALLOWED: &str = "magic_link_not_allowed";
+/// 客户端地址不在用户或所属角色的IP白名单中
+pub const LOGIN_FAILURE_IP_NOT_ALLOWED: &str = "ip_not_allowed";
+
+/// 登录日志的事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginEvent {
+    /// 用户名密码登录
+    Login,
+    /// 使用刷新令牌换取新令牌
+    Refresh,
+    /// 二次验证
+    Mfa,
+    /// 服务账户使用客户端凭据换取令牌
+    ClientCredentials,
+    /// 通过外部身份提供方（OpenID Connect）登录
+    Oidc,
+    /// 接入应用通过OAuth2令牌端点换取令牌
+    OAuth,
+    /// 管理员开始模拟其他用户登录
+    ImpersonationStart,
+    /// 管理员结束模拟登录
+    ImpersonationStop,
+    /// 使用邮件中的登录链接登录
+    MagicLink,
+    /// 使用已签发的令牌或API密钥访问接口（只记录被IP白名单拒绝的请求）
+    Access,
+}
+
+impl LoginEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginEvent::Login => "login",
+            LoginEvent::Refresh => "refresh",
+            LoginEvent::Mfa => "mfa",
+            LoginEvent::ClientCredentials => "client_credentials",
+            LoginEvent::Oidc => "oidc",
+            LoginEvent::OAuth => "oauth",
+            LoginEvent::ImpersonationStart => "impersonation_start",
+            LoginEvent::ImpersonationStop => "impersonation_stop",
+            LoginEvent::MagicLink => "magic_link",
+            LoginEvent::Access => "access",
+        }
+    }
+}
+
+pub struct LoginLogService;
+
+impl LoginLogService {
+    /// 记录一次登录或刷新，failure_reason 为空表示成功
+    pub async fn record(
+        db: &DatabaseConnection,
+        event: LoginEvent,
+        username: Option<&str>,
+        user_id: Option<i32>,
+        client: &ClientInfo,
+        failure_reason: Option<&str>,
+    ) -> Result<(), DbErr> {
+        Self::insert(db, event, username, user_id, None, client, failure_reason).await
+    }
+
+    /// 记录模拟登录的开始或结束，username / user_id 为被模拟的用户
+    pub async fn record_impersonation(
+        db: &DatabaseConnection,
+        event: LoginEvent,
+        impersonator_id: i32,
+        username: Option<&str>,
+        user_id: Option<i32>,
+        client: &ClientInfo,
+        failure_reason: Option<&str>,
+    ) -> Result<(), DbErr> {
+        Self::insert(db, event, username, user_id, Some(impersonator_id), client, failure_reason).await
+    }
+
+    async fn insert(
+        db: &DatabaseConnection,
+        event: LoginEvent,
+        username: Option<&str>,
+        user_id: Option<i32>,
+        impersonator_id: Option<i32>,
+        client: &ClientInfo,
+        failure_reason: Option<&str>,
+    ) -> Result<(), DbErr> {
+        let log = login_log::ActiveModel {
+            event_type: Set(event.as_str().to_string()),
+            username: Set(username.map(|u| u.chars().take(255).collect())),
+            user_id: Set(user_id),
+            ip_address: Set(client.ip_address.clone()),
+            user_agent: Set(client.user_agent.clone()),
+            success: Set(failure_reason.is_none()),
+            failure_reason: Set(failure_reason.map(str::to_string)),
+            impersonator_id: Set(impersonator_id),
+            ..Default::default()
+        };
+
+        login_log::Entity::insert(log).exec_without_returning(db).await?;
+
+        Ok(())
+    }
+
+    /// 分页查询登录日志，按时间倒序，返回当前页数据和总数
+    pub async fn list(
+        db: &DatabaseConnection,
+        filter: &LoginLogFilter,
+        page: u32,
+        per_page: u32,
+    ) -> Result<(Vec<login_log::Model>, u64), DbErr> {
+        let condition = Self::filter_condition(filter);
+
+        let total = login_log::Entity::find()
+            .filter(condition.clone())
+            .count(db)
+            .await?;
+
+        let logs = login_log::Entity::find()
+            .filter(condition)
+            .order_by_desc(login_log::Column::CreatedAt)
+            .order_by_desc(login_log::Column::Id)
+            .limit(per_page as u64)
+            .offset(((page - 1) * per_page) as u64)
+            .all(db)
+            .await?;
+
+        Ok((logs, total))
+    }
+
+    fn filter_condition(filter: &LoginLogFilter) -> Condition {
+        let mut condition = Condition::all();
+
+        if let Some(username) = filter.username.as_deref().filter(|u| !u.is_empty()) {
+            condition = condition.add(login_log::Column::Username.contains(username));
+        }
+        if let Some(user_id) = filter.user_id {
+            condition = condition.add(login_log::Column::UserId.eq(user_id));
+        }
+        if let Some(impersonator_id) = filter.impersonator_id {
+            condition = condition.add(login_log::Column::ImpersonatorId.eq(impersonator_id));
+        }
+        if let Some(ip_address) = filter.ip_address.as_deref().filter(|ip| !ip.is_empty()) {
+            condition = condition.add(login_log::Column::IpAddress.eq(ip_address));
+        }
+        if let Some(event_type) = filter.event_type.as_deref().filter(|t| !t.is_empty()) {
+            condition = condition.add(login_log::Column::EventType.eq(event_type));
+        }
+        if let Some(success) = filter.success {
+            condition = condition.add(login_log::Column::Success.eq(success));
+        }
+        if let Some(start_time) = filter.start_time {
+            condition = condition.add(login_log::Column::CreatedAt.gte(start_time));
+        }
+        if let Some(end_time) = filter.end_time {
+            condition = condition.add(login_log::Column::CreatedAt.lt(end_time));
+        }
+
+        condition
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::sea_query::PostgresQueryBuilder;
+
+    fn where_clause(filter: &LoginLogFilter) -> String {
+        login_log::Entity::find()
+            .filter(LoginLogService::filter_condition(filter))
+            .into_query()
+            .to_string(PostgresQueryBuilder)
+    }
+
+    #[test]
+    fn test_login_log_filter_business_rules() {
+        // 无筛选条件时不限制任何列
+        let unfiltered = where_clause(&LoginLogFilter::default());
+        assert!(!unfiltered.contains(r#""username" LIKE"#));
+
+        // 空字符串视为未筛选
+        let sql = where_clause(&LoginLogFilter {
+            username: Some(String::new()),
+            ..Default::default()
+        });
+        assert_eq!(sql, unfiltered);
+
+        // 用户名模糊匹配，其他条件精确匹配
+        let sql = where_clause(&LoginLogFilter {
+            username: Some("adm".to_string()),
+            success: Some(false),
+            event_type: Some("refresh".to_string()),
+            ..Default::default()
+        });
+        assert!(sql.contains(r#""username" LIKE '%adm%'"#));
+        assert!(sql.contains(r#""success" = FALSE"#));
+        assert!(sql.contains(r#""event_type" = 'refresh'"#));
+
+        // 按模拟登录的管理员筛选
+        let sql = where_clause(&LoginLogFilter {
+            impersonator_id: Some(3),
+            ..Default::default()
+        });
+        assert!(sql.contains(r#""impersonator_id" = 3"#));
+    }
+
+    #[test]
+    fn test_login_event_names() {
+        assert_eq!(LoginEvent::Login.as_str(), "login");
+        assert_eq!(LoginEvent::Refresh.as_str(), "refresh");
+        assert_eq!(LoginEvent::Mfa.as_str(), "mfa");
+        assert_eq!(LoginEvent::ClientCredentials.as_str(), "client_credentials");
+        assert_eq!(LoginEvent::Oidc.as_str(), "oidc");
+        assert_eq!(LoginEvent::ImpersonationStart.as_str(), "impersonation_start");
+        assert_eq!(LoginEvent::ImpersonationStop.as_str(), "impersonation_stop");
+        assert_eq!(LoginEvent::MagicLink.as_str(), "magic_link");
+        assert_eq!(LoginEvent::Access.as_str(), "access");
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+use std::env;
+use thiserror::Error;
+
+use crate::mail::{app_link, send_in_background, MailMessage};
+use crate::models::{magic_link_token, user};
+use crate::rbac::{RbacError, RbacService};
+use crate::services::{EmailTokenService, EmailVerificationService};
+
+#[derive(Error, Debug)]
+pub enum MagicLinkError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("权限检查失败: {0}")]
+    RbacError(#[from] RbacError),
+    #[error("未启用邮件链接登录")]
+    Disabled,
+    #[error("登录链接无效或已过期")]
+    InvalidToken,
+}
+
+pub struct MagicLinkService;
+
+impl MagicLinkService {
+    /// 是否启用邮件链接登录，默认不启用
+    pub fn is_enabled() -> bool {
+        env::var("MAGIC_LINK_LOGIN")
+            .map(|v| v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+    }
+
+    /// 登录链接有效期（秒），默认10分钟
+    pub fn token_ttl() -> i64 {
+        env::var("MAGIC_LINK_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(600)
+    }
+
+    /// 为邮箱对应的用户生成登录令牌并发送登录链接
+    ///
+    /// 邮箱不存在或未验证、账户被禁用、所属角色不允许或请求过于频繁时同样返回成功，不向调用方暴露邮箱是否注册。
+    pub async fn request_link(db: &DatabaseConnection, email: &str) -> Result<(), MagicLinkError> {
+        if !Self::is_enabled() {
+            return Err(MagicLinkError::Disabled);
+        }
+
+        let user = user::Entity::find()
+            .filter(user::Column::Email.eq(email))
+            .filter(user::Column::IsActive.eq(true))
+            .filter(user::Column::IsServiceAccount.eq(false))
+            .one(db)
+            .await?;
+
+        // 只向已验证的邮箱发送，否则收到链接的人不一定是账户的主人
+        let Some(user) = user.filter(EmailVerificationService::is_verified) else {
+            return Ok(());
+        };
+
+        if !RbacService::is_magic_link_allowed(db, user.id).await? {
+            tracing::info!("用户 {} 所属角色不允许使用邮件链接登录，已忽略", user.username);
+            return Ok(());
+        }
+
+        let issued = EmailTokenService::issue::<magic_link_token::ActiveModel>(
+            db,
+            user.id,
+            Vec::new(),
+            Self::token_ttl(),
+        )
+        .await?;
+
+        match issued {
+            Some(issued) => send_in_background(Self::link_message(&user, &issued.token, issued.expires_at)),
+            None => tracing::info!("用户 {} 的登录链接请求过于频繁，已忽略", user.username),
+        }
+
+        Ok(())
+    }
+
+    /// 使用登录令牌，返回令牌所属的用户；令牌只能使用一次
+    ///
+    /// 用户状态和所属角色由调用方检查，检查不通过时令牌同样作废。
+    pub async fn consume(db: &DatabaseConnection, token: &str) -> Result<user::Model, MagicLinkError> {
+        if !Self::is_enabled() {
+            return Err(MagicLinkError::Disabled);
+        }
+
+        let record = EmailTokenService::find_usable::<magic_link_token::Entity>(db, token)
+            .await?
+            .ok_or(MagicLinkError::InvalidToken)?;
+
+        if !EmailTokenService::claim::<magic_link_token::Entity, _>(db, token).await? {
+            return Err(MagicLinkError::InvalidToken);
+        }
+
+        user::Entity::find_by_id(record.user_id)
+            .one(db)
+            .await?
+            .ok_or(MagicLinkError::InvalidToken)
+    }
+
+    fn link_message(user: &user::Model, token: &str, expires_at: DateTime<Utc>) -> MailMessage {
+        MailMessage {
+            to: user.email.clone(),
+            subject: "登录链接".to_string(),
+            body: format!(
+                "{}，您好：\n\n我们收到了使用邮件链接登录的请求。请在 {} 之前打开以下链接登录，链接只能使用一次：\n\n{}\n\n如果这不是您本人的操作，请忽略此邮件，请勿将链接转发给他人。\n",
+                user.username,
+                expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                app_link("/magic-link", token)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_message_contains_link() {
+        let now = Utc::now();
+        let user = user::Model {
+            password_hash: String::new(),
+            email_verified_at: Some(now.into()),
+            ..user::Model::for_test(1, "alice")
+        };
+
+        let message = MagicLinkService::link_message(&user, "abc123", now);
+        assert_eq!(message.to, "alice@example.com");
+        assert_eq!(message.subject, "登录链接");
+        assert!(message.body.contains("/magic-link?token=abc123"));
+    }
+}
@@ -0,0 +1,400 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::*;
+use sha1::Sha1;
+use std::env;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::models::{mfa_recovery_code, user_mfa};
+
+/// TOTP时间步长（秒）
+const TOTP_STEP: u64 = 30;
+/// TOTP验证码位数
+const TOTP_DIGITS: u32 = 6;
+/// 允许的时钟偏差（前后各几个时间步）
+const TOTP_SKEW: i64 = 1;
+/// 每次生成的恢复码数量
+const RECOVERY_CODE_COUNT: usize = 10;
+/// 恢复码字符集（去掉了容易混淆的 0/o、1/l/i）
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Error, Debug)]
+pub enum MfaError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("尚未绑定二次验证")]
+    NotEnrolled,
+    #[error("已启用二次验证")]
+    AlreadyEnabled,
+    #[error("验证码无效")]
+    InvalidCode,
+}
+
+pub struct MfaService;
+
+impl MfaService {
+    /// 发起绑定：生成新的密钥（覆盖尚未激活的旧密钥），返回密钥和 otpauth URI
+    pub async fn start_enrollment(
+        db: &DatabaseConnection,
+        user_id: i32,
+        username: &str,
+    ) -> Result<(String, String), MfaError> {
+        if Self::is_enabled(db, user_id).await? {
+            return Err(MfaError::AlreadyEnabled);
+        }
+
+        let secret = Self::generate_secret();
+
+        let record = user_mfa::ActiveModel {
+            user_id: Set(user_id),
+            secret: Set(secret.clone()),
+            enabled: Set(false),
+            last_used_step: Set(None),
+            created_at: Set(Utc::now().into()),
+            enabled_at: Set(None),
+        };
+
+        user_mfa::Entity::insert(record)
+            .on_conflict(
+                OnConflict::column(user_mfa::Column::UserId)
+                    .update_columns([
+                        user_mfa::Column::Secret,
+                        user_mfa::Column::LastUsedStep,
+                        user_mfa::Column::CreatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+
+        let uri = Self::otpauth_uri(&secret, username);
+        Ok((secret, uri))
+    }
+
+    /// 用验证码确认绑定并启用二次验证，返回新生成的恢复码（明文只返回这一次）
+    pub async fn activate(
+        db: &DatabaseConnection,
+        user_id: i32,
+        code: &str,
+    ) -> Result<Vec<String>, MfaError> {
+        let mfa = user_mfa::Entity::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or(MfaError::NotEnrolled)?;
+
+        if mfa.enabled {
+            return Err(MfaError::AlreadyEnabled);
+        }
+
+        let step = Self::verify_totp(&mfa.secret, code, Self::unix_now(), mfa.last_used_step)
+            .ok_or(MfaError::InvalidCode)?;
+
+        let txn = db.begin().await?;
+
+        let result = user_mfa::Entity::update_many()
+            .col_expr(user_mfa::Column::Enabled, Expr::value(true))
+            .col_expr(user_mfa::Column::LastUsedStep, Expr::value(Some(step)))
+            .col_expr(
+                user_mfa::Column::EnabledAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+            )
+            .filter(user_mfa::Column::UserId.eq(user_id))
+            .filter(user_mfa::Column::Enabled.eq(false))
+            .exec(&txn)
+            .await?;
+
+        if result.rows_affected == 0 {
+            return Err(MfaError::AlreadyEnabled);
+        }
+
+        let codes = Self::replace_recovery_codes(&txn, user_id).await?;
+        txn.commit().await?;
+
+        Ok(codes)
+    }
+
+    /// 用户是否已启用二次验证
+    pub async fn is_enabled(db: &DatabaseConnection, user_id: i32) -> Result<bool, DbErr> {
+        let mfa = user_mfa::Entity::find_by_id(user_id).one(db).await?;
+        Ok(mfa.map(|m| m.enabled).unwrap_or(false))
+    }
+
+    /// 校验登录时提交的TOTP验证码或恢复码，通过后该验证码/恢复码不能再次使用
+    pub async fn verify_code(
+        db: &DatabaseConnection,
+        user_id: i32,
+        code: &str,
+    ) -> Result<(), MfaError> {
+        let mfa = user_mfa::Entity::find_by_id(user_id)
+            .one(db)
+            .await?
+            .filter(|m| m.enabled)
+            .ok_or(MfaError::NotEnrolled)?;
+
+        if let Some(step) = Self::verify_totp(&mfa.secret, code, Self::unix_now(), mfa.last_used_step) {
+            // 条件更新保证同一时间步的验证码只能使用一次
+            let result = user_mfa::Entity::update_many()
+                .col_expr(user_mfa::Column::LastUsedStep, Expr::value(Some(step)))
+                .filter(user_mfa::Column::UserId.eq(user_id))
+                .filter(
+                    Condition::any()
+                        .add(user_mfa::Column::LastUsedStep.is_null())
+                        .add(user_mfa::Column::LastUsedStep.lt(step)),
+                )
+                .exec(db)
+                .await?;
+
+            if result.rows_affected == 1 {
+                return Ok(());
+            }
+            return Err(MfaError::InvalidCode);
+        }
+
+        Self::consume_recovery_code(db, user_id, code).await
+    }
+
+    /// 重置用户的二次验证（删除密钥和恢复码）
+    pub async fn reset(db: &DatabaseConnection, user_id: i32) -> Result<(), DbErr> {
+        mfa_recovery_code::Entity::delete_many()
+            .filter(mfa_recovery_code::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+
+        user_mfa::Entity::delete_by_id(user_id).exec(db).await?;
+
+        Ok(())
+    }
+
+    async fn consume_recovery_code(
+        db: &DatabaseConnection,
+        user_id: i32,
+        code: &str,
+    ) -> Result<(), MfaError> {
+        let code_hash = AuthService::hash_token(&Self::normalize_recovery_code(code));
+
+        let result = mfa_recovery_code::Entity::update_many()
+            .col_expr(
+                mfa_recovery_code::Column::UsedAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+            )
+            .filter(mfa_recovery_code::Column::UserId.eq(user_id))
+            .filter(mfa_recovery_code::Column::CodeHash.eq(code_hash))
+            .filter(mfa_recovery_code::Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+
+        if result.rows_affected == 0 {
+            return Err(MfaError::InvalidCode);
+        }
+
+        tracing::info!("用户 {} 使用恢复码完成二次验证", user_id);
+        Ok(())
+    }
+
+    async fn replace_recovery_codes<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+    ) -> Result<Vec<String>, DbErr> {
+        mfa_recovery_code::Entity::delete_many()
+            .filter(mfa_recovery_code::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| Self::generate_recovery_code())
+            .collect();
+
+        let records = codes.iter().map(|code| mfa_recovery_code::ActiveModel {
+            user_id: Set(user_id),
+            code_hash: Set(AuthService::hash_token(&Self::normalize_recovery_code(code))),
+            ..Default::default()
+        });
+
+        mfa_recovery_code::Entity::insert_many(records)
+            .exec_without_returning(db)
+            .await?;
+
+        Ok(codes)
+    }
+
+    /// 生成20字节随机密钥，使用Base32编码（与常见认证器App兼容）
+    fn generate_secret() -> String {
+        let mut bytes = [0u8; 20];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        BASE32_NOPAD.encode(&bytes)
+    }
+
+    fn otpauth_uri(secret: &str, username: &str) -> String {
+        let issuer = env::var("MFA_ISSUER").unwrap_or_else(|_| "Web Admin".to_string());
+        let label = format!("{}:{}", issuer, username);
+
+        format!(
+            "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            urlencoding::encode(&label),
+            secret,
+            urlencoding::encode(&issuer),
+            TOTP_DIGITS,
+            TOTP_STEP
+        )
+    }
+
+    /// 校验TOTP验证码，返回匹配的时间步；不晚于 last_used_step 的时间步视为重放
+    fn verify_totp(secret: &str, code: &str, now: u64, last_used_step: Option<i64>) -> Option<i64> {
+        let code = code.trim();
+        if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+        let current_step = (now / TOTP_STEP) as i64;
+
+        (-TOTP_SKEW..=TOTP_SKEW)
+            .map(|offset| current_step + offset)
+            .filter(|step| *step >= 0 && last_used_step.is_none_or(|last| *step > last))
+            .find(|step| {
+                let expected = format!(
+                    "{:0width$}",
+                    Self::hotp(&key, *step as u64),
+                    width = TOTP_DIGITS as usize
+                );
+                constant_time_eq(expected.as_bytes(), code.as_bytes())
+            })
+    }
+
+    /// RFC 4226 HOTP
+    fn hotp(key: &[u8], counter: u64) -> u32 {
+        let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC可以接受任意长度的密钥");
+        mac.update(&counter.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            digest[offset] & 0x7f,
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ]);
+
+        binary % 10u32.pow(TOTP_DIGITS)
+    }
+
+    fn generate_recovery_code() -> String {
+        let mut rng = rand::thread_rng();
+        let chars: String = (0..12)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect();
+
+        format!("{}-{}-{}", &chars[0..4], &chars[4..8], &chars[8..12])
+    }
+
+    /// 恢复码比较时忽略大小写、空格和分隔符
+    fn normalize_recovery_code(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    }
+
+    fn unix_now() -> u64 {
+        Utc::now().timestamp().max(0) as u64
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 附录B的测试密钥 "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_hotp_rfc4226_vectors() {
+        let key = b"12345678901234567890";
+        let expected = [755224, 287082, 359152, 969429, 338314];
+
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(MfaService::hotp(key, counter as u64), *code);
+        }
+    }
+
+    #[test]
+    fn test_totp_rfc6238_vectors() {
+        // RFC中为8位验证码，取后6位即为6位验证码
+        let cases = [(59, "287082"), (1111111109, "081804"), (1234567890, "005924")];
+
+        for (time, code) in cases {
+            assert!(
+                MfaService::verify_totp(RFC_SECRET, code, time, None).is_some(),
+                "时间 {} 的验证码应为 {}",
+                time,
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_totp_verification_business_rules() {
+        let now = 1_111_111_109;
+        let step = (now / TOTP_STEP) as i64;
+
+        // 允许前后一个时间步的时钟偏差
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "081804", now + 30, None), Some(step));
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "081804", now - 30, None), Some(step));
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "081804", now + 90, None), None);
+
+        // 已使用过的时间步不能再次使用
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "081804", now, Some(step)), None);
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "081804", now, Some(step - 1)), Some(step));
+
+        // 格式不正确的验证码直接拒绝
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "08180", now, None), None);
+        assert_eq!(MfaService::verify_totp(RFC_SECRET, "08180a", now, None), None);
+        assert_eq!(MfaService::verify_totp("not base32!", "081804", now, None), None);
+    }
+
+    #[test]
+    fn test_secret_generation_business_rules() {
+        let secret1 = MfaService::generate_secret();
+        let secret2 = MfaService::generate_secret();
+
+        assert_ne!(secret1, secret2);
+        assert_eq!(BASE32_NOPAD.decode(secret1.as_bytes()).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn test_otpauth_uri_format() {
+        let uri = MfaService::otpauth_uri("JBSWY3DPEHPK3PXP", "alice");
+
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains("alice"));
+        assert!(uri.contains("secret=JBSWY3DPEHPK3PXP"));
+        assert!(uri.contains("digits=6"));
+        assert!(uri.contains("period=30"));
+        // 标签中的空格和冒号需要转义
+        assert!(!uri["otpauth://totp/".len()..].contains(' '));
+    }
+
+    #[test]
+    fn test_recovery_code_business_rules() {
+        let code = MfaService::generate_recovery_code();
+        assert_eq!(code.len(), 14);
+        assert_eq!(code.matches('-').count(), 2);
+
+        // 用户输入时的大小写和分隔符不影响匹配
+        let normalized = MfaService::normalize_recovery_code(&code);
+        assert_eq!(normalized.len(), 12);
+        assert_eq!(
+            MfaService::normalize_recovery_code(&format!(" {} ", code.to_uppercase().replace('-', " "))),
+            normalized
+        );
+    }
+}
@@ -1,7 +1,51 @@
 pub mod department_service;
 pub mod user_department_service;
 pub mod user_service;
+pub mod token_service;
+pub mod revocation_service;
+pub mod session_service;
+pub mod lockout_service;
+pub mod login_log_service;
+pub mod mfa_service;
+pub mod email_token_service;
+pub mod password_reset_service;
+pub mod magic_link_service;
+pub mod password_policy_service;
+pub mod invitation_service;
+pub mod email_verification_service;
+pub mod registration_service;
+pub mod jwt_key_service;
+pub mod api_key_service;
+pub mod service_account_service;
+pub mod oidc_service;
+pub mod ldap_service;
+pub mod oauth_client_service;
+pub mod oauth_service;
+pub mod impersonation_service;
+pub mod ip_allowlist_service;
 
 pub use department_service::*;
 pub use user_department_service::*;
 pub use user_service::*;
+pub use token_service::*;
+pub use revocation_service::*;
+pub use session_service::*;
+pub use lockout_service::*;
+pub use login_log_service::*;
+pub use mfa_service::*;
+pub use email_token_service::*;
+pub use password_reset_service::*;
+pub use magic_link_service::*;
+pub use password_policy_service::*;
+pub use invitation_service::*;
+pub use email_verification_service::*;
+pub use registration_service::*;
+pub use jwt_key_service::*;
+pub use api_key_service::*;
+pub use service_account_service::*;
+pub use oidc_service::*;
+pub use ldap_service::*;
+pub use oauth_client_service::*;
+pub use oauth_service::*;
+pub use impersonation_service::*;
+pub use ip_allowlist_service::*;
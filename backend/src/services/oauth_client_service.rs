@@ -0,0 +1,355 @@
+use chrono::Utc;
+use reqwest::Url;
+use sea_orm::*;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::models::{oauth_client, user, CreateOAuthClientDto, UpdateOAuthClientDto};
+use crate::services::{GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, SUPPORTED_SCOPES};
+
+/// 客户端密钥的固定前缀，便于识别和密钥扫描
+pub const OAUTH_CLIENT_SECRET_PREFIX: &str = "ocs_";
+
+/// 列表中显示的密钥开头长度（包含前缀）
+const SECRET_PREFIX_DISPLAY_LEN: usize = 12;
+
+/// client_id 的长度（十六进制字符）
+const CLIENT_ID_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum OAuthClientError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("接入应用不存在")]
+    NotFound,
+    #[error("回调地址无效: {0}")]
+    InvalidRedirectUri(String),
+    #[error("不支持的授权范围: {0}")]
+    InvalidScope(String),
+    #[error("不支持的授权类型: {0}")]
+    InvalidGrantType(String),
+    #[error("使用授权码的应用至少需要一个回调地址")]
+    RedirectUriRequired,
+    #[error("客户端凭据授权只能用于机密客户端，且需要指定服务账户")]
+    ServiceAccountRequired,
+    #[error("服务账户不存在")]
+    ServiceAccountNotFound,
+    #[error("公开客户端没有客户端密钥")]
+    PublicClient,
+}
+
+pub struct OAuthClientService;
+
+impl OAuthClientService {
+    /// 注册接入应用，返回应用和明文客户端密钥（公开客户端没有密钥，明文只在创建时返回一次）
+    pub async fn create(
+        db: &DatabaseConnection,
+        dto: CreateOAuthClientDto,
+        created_by: i32,
+    ) -> Result<(oauth_client::Model, Option<String>), OAuthClientError> {
+        let redirect_uris = Self::normalize_redirect_uris(dto.redirect_uris)?;
+        let allowed_scopes = Self::normalize_scopes(dto.allowed_scopes)?;
+        let grant_types = Self::normalize_grant_types(dto.grant_types)?;
+        Self::check_grants(&grant_types, &redirect_uris, dto.confidential, dto.service_account_id)?;
+        if let Some(account_id) = dto.service_account_id {
+            Self::check_service_account(db, account_id).await?;
+        }
+
+        let secret = dto.confidential.then(Self::generate_secret);
+        let now = Utc::now();
+        let client = oauth_client::ActiveModel {
+            client_id: Set(AuthService::generate_refresh_token()[..CLIENT_ID_LEN].to_string()),
+            name: Set(dto.name),
+            description: Set(dto.description),
+            secret_prefix: Set(secret.as_ref().map(|s| s[..SECRET_PREFIX_DISPLAY_LEN].to_string())),
+            secret_hash: Set(secret.as_deref().map(AuthService::hash_token)),
+            redirect_uris: Set(redirect_uris),
+            allowed_scopes: Set(allowed_scopes),
+            grant_types: Set(grant_types),
+            service_account_id: Set(dto.service_account_id),
+            skip_consent: Set(dto.skip_consent),
+            is_active: Set(true),
+            created_by: Set(Some(created_by)),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        };
+
+        let client = client.insert(db).await?;
+        Ok((client, secret))
+    }
+
+    pub async fn list(db: &DatabaseConnection) -> Result<Vec<oauth_client::Model>, OAuthClientError> {
+        let clients = oauth_client::Entity::find()
+            .order_by_asc(oauth_client::Column::Id)
+            .all(db)
+            .await?;
+
+        Ok(clients)
+    }
+
+    pub async fn find(db: &DatabaseConnection, id: i32) -> Result<oauth_client::Model, OAuthClientError> {
+        oauth_client::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(OAuthClientError::NotFound)
+    }
+
+    /// 按 client_id 查找启用的应用
+    pub async fn find_active(
+        db: &DatabaseConnection,
+        client_id: &str,
+    ) -> Result<Option<oauth_client::Model>, DbErr> {
+        oauth_client::Entity::find()
+            .filter(oauth_client::Column::ClientId.eq(client_id))
+            .filter(oauth_client::Column::IsActive.eq(true))
+            .one(db)
+            .await
+    }
+
+    pub async fn update(
+        db: &DatabaseConnection,
+        id: i32,
+        dto: UpdateOAuthClientDto,
+    ) -> Result<oauth_client::Model, OAuthClientError> {
+        let client = Self::find(db, id).await?;
+        let confidential = client.is_confidential();
+
+        let redirect_uris = match dto.redirect_uris {
+            Some(uris) => Self::normalize_redirect_uris(uris)?,
+            None => client.redirect_uris.clone(),
+        };
+        let grant_types = match dto.grant_types {
+            Some(grant_types) => Self::normalize_grant_types(grant_types)?,
+            None => client.grant_types.clone(),
+        };
+        let service_account_id = dto.service_account_id.or(client.service_account_id);
+        Self::check_grants(&grant_types, &redirect_uris, confidential, service_account_id)?;
+        if let Some(account_id) = dto.service_account_id {
+            Self::check_service_account(db, account_id).await?;
+        }
+
+        let mut active_client: oauth_client::ActiveModel = client.into();
+        if let Some(name) = dto.name {
+            active_client.name = Set(name);
+        }
+        if let Some(description) = dto.description {
+            active_client.description = Set(Some(description));
+        }
+        if let Some(scopes) = dto.allowed_scopes {
+            active_client.allowed_scopes = Set(Self::normalize_scopes(scopes)?);
+        }
+        if let Some(skip_consent) = dto.skip_consent {
+            active_client.skip_consent = Set(skip_consent);
+        }
+        if let Some(is_active) = dto.is_active {
+            active_client.is_active = Set(is_active);
+        }
+        active_client.redirect_uris = Set(redirect_uris);
+        active_client.grant_types = Set(grant_types);
+        active_client.service_account_id = Set(service_account_id);
+        active_client.updated_at = Set(Utc::now().into());
+
+        Ok(active_client.update(db).await?)
+    }
+
+    /// 删除应用，授权码和用户的授权记录一并删除，已签发的令牌随即无法通过内省
+    pub async fn delete(db: &DatabaseConnection, id: i32) -> Result<(), OAuthClientError> {
+        let result = oauth_client::Entity::delete_by_id(id).exec(db).await?;
+        if result.rows_affected == 0 {
+            return Err(OAuthClientError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// 重新生成客户端密钥，旧密钥立即失效
+    pub async fn rotate_secret(
+        db: &DatabaseConnection,
+        id: i32,
+    ) -> Result<(oauth_client::Model, String), OAuthClientError> {
+        let client = Self::find(db, id).await?;
+        if !client.is_confidential() {
+            return Err(OAuthClientError::PublicClient);
+        }
+
+        let secret = Self::generate_secret();
+        let mut active_client: oauth_client::ActiveModel = client.into();
+        active_client.secret_prefix = Set(Some(secret[..SECRET_PREFIX_DISPLAY_LEN].to_string()));
+        active_client.secret_hash = Set(Some(AuthService::hash_token(&secret)));
+        active_client.updated_at = Set(Utc::now().into());
+
+        Ok((active_client.update(db).await?, secret))
+    }
+
+    /// 校验客户端凭据：机密客户端必须提供正确的密钥，公开客户端不能提供密钥
+    pub fn verify_secret(client: &oauth_client::Model, secret: Option<&str>) -> bool {
+        match (&client.secret_hash, secret) {
+            (Some(hash), Some(secret)) => *hash == AuthService::hash_token(secret),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    async fn check_service_account(db: &DatabaseConnection, account_id: i32) -> Result<(), OAuthClientError> {
+        user::Entity::find_by_id(account_id)
+            .filter(user::Column::IsServiceAccount.eq(true))
+            .one(db)
+            .await?
+            .ok_or(OAuthClientError::ServiceAccountNotFound)?;
+        Ok(())
+    }
+
+    fn check_grants(
+        grant_types: &[String],
+        redirect_uris: &[String],
+        confidential: bool,
+        service_account_id: Option<i32>,
+    ) -> Result<(), OAuthClientError> {
+        let has = |grant: &str| grant_types.iter().any(|g| g == grant);
+
+        if has(GRANT_AUTHORIZATION_CODE) && redirect_uris.is_empty() {
+            return Err(OAuthClientError::RedirectUriRequired);
+        }
+        if has(GRANT_CLIENT_CREDENTIALS) && (!confidential || service_account_id.is_none()) {
+            return Err(OAuthClientError::ServiceAccountRequired);
+        }
+        Ok(())
+    }
+
+    /// 回调地址必须是完整的 https 地址（本机调试可以使用 http），不能带 fragment
+    fn normalize_redirect_uris(uris: Vec<String>) -> Result<Vec<String>, OAuthClientError> {
+        let mut normalized = Vec::with_capacity(uris.len());
+        for uri in uris {
+            let uri = uri.trim().to_string();
+            let parsed = Url::parse(&uri).map_err(|_| OAuthClientError::InvalidRedirectUri(uri.clone()))?;
+            let loopback = matches!(parsed.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+            let secure = parsed.scheme() == "https" || (parsed.scheme() == "http" && loopback);
+            if !secure || parsed.fragment().is_some() || uri.len() > 500 {
+                return Err(OAuthClientError::InvalidRedirectUri(uri));
+            }
+            if !normalized.contains(&uri) {
+                normalized.push(uri);
+            }
+        }
+        Ok(normalized)
+    }
+
+    fn normalize_scopes(scopes: Vec<String>) -> Result<Vec<String>, OAuthClientError> {
+        let mut normalized: Vec<String> = Vec::new();
+        for scope in scopes {
+            let scope = scope.trim().to_string();
+            if !SUPPORTED_SCOPES.iter().any(|(name, _)| *name == scope) {
+                return Err(OAuthClientError::InvalidScope(scope));
+            }
+            if !normalized.contains(&scope) {
+                normalized.push(scope);
+            }
+        }
+        Ok(normalized)
+    }
+
+    fn normalize_grant_types(grant_types: Vec<String>) -> Result<Vec<String>, OAuthClientError> {
+        let mut normalized: Vec<String> = Vec::new();
+        for grant_type in grant_types {
+            let grant_type = grant_type.trim().to_string();
+            if grant_type != GRANT_AUTHORIZATION_CODE && grant_type != GRANT_CLIENT_CREDENTIALS {
+                return Err(OAuthClientError::InvalidGrantType(grant_type));
+            }
+            if !normalized.contains(&grant_type) {
+                normalized.push(grant_type);
+            }
+        }
+        Ok(normalized)
+    }
+
+    fn generate_secret() -> String {
+        format!("{}{}", OAUTH_CLIENT_SECRET_PREFIX, AuthService::generate_refresh_token())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(secret: Option<&str>) -> oauth_client::Model {
+        let now = Utc::now();
+        oauth_client::Model {
+            id: 1,
+            client_id: "client".to_string(),
+            name: "工单系统".to_string(),
+            description: None,
+            secret_prefix: None,
+            secret_hash: secret.map(AuthService::hash_token),
+            redirect_uris: vec!["https://tickets.example.com/callback".to_string()],
+            allowed_scopes: vec!["openid".to_string()],
+            grant_types: vec![GRANT_AUTHORIZATION_CODE.to_string()],
+            service_account_id: None,
+            skip_consent: false,
+            is_active: true,
+            created_by: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+        }
+    }
+
+    #[test]
+    fn test_verify_client_secret() {
+        let confidential = client(Some("ocs_secret"));
+        assert!(OAuthClientService::verify_secret(&confidential, Some("ocs_secret")));
+        assert!(!OAuthClientService::verify_secret(&confidential, Some("ocs_other")));
+        assert!(!OAuthClientService::verify_secret(&confidential, None));
+
+        // 公开客户端不能提供密钥
+        let public = client(None);
+        assert!(OAuthClientService::verify_secret(&public, None));
+        assert!(!OAuthClientService::verify_secret(&public, Some("ocs_secret")));
+    }
+
+    #[test]
+    fn test_redirect_uri_rules() {
+        let valid = OAuthClientService::normalize_redirect_uris(vec![
+            "https://tickets.example.com/callback".to_string(),
+            " http://localhost:8080/callback ".to_string(),
+            "https://tickets.example.com/callback".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            valid,
+            vec!["https://tickets.example.com/callback", "http://localhost:8080/callback"]
+        );
+
+        for uri in [
+            "http://tickets.example.com/callback",
+            "https://tickets.example.com/callback#token",
+            "/callback",
+            "javascript:alert(1)",
+        ] {
+            assert!(
+                OAuthClientService::normalize_redirect_uris(vec![uri.to_string()]).is_err(),
+                "回调地址 {} 应该被拒绝",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn test_grant_rules() {
+        let uris = vec!["https://tickets.example.com/callback".to_string()];
+        let code = vec![GRANT_AUTHORIZATION_CODE.to_string()];
+        let credentials = vec![GRANT_CLIENT_CREDENTIALS.to_string()];
+
+        assert!(OAuthClientService::check_grants(&code, &uris, false, None).is_ok());
+        assert!(matches!(
+            OAuthClientService::check_grants(&code, &[], true, None),
+            Err(OAuthClientError::RedirectUriRequired)
+        ));
+
+        // 客户端凭据授权需要机密客户端和服务账户
+        assert!(OAuthClientService::check_grants(&credentials, &[], true, Some(2)).is_ok());
+        assert!(OAuthClientService::check_grants(&credentials, &[], true, None).is_err());
+        assert!(OAuthClientService::check_grants(&credentials, &[], false, Some(2)).is_err());
+
+        assert!(OAuthClientService::normalize_grant_types(vec!["password".to_string()]).is_err());
+        assert!(OAuthClientService::normalize_scopes(vec!["admin".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,687 @@
+use chrono::{Duration, Utc};
+use reqwest::Url;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use std::env;
+use thiserror::Error;
+
+use crate::auth::{AuthError, AuthService, Claims, TokenUse};
+use crate::models::{
+    oauth_authorization_code, oauth_client, oauth_consent, user, AuthorizationRequest, OAuthConsentResponse,
+};
+use crate::oidc::{random_token, Pkce};
+use crate::rbac::{RbacError, RbacService};
+use crate::services::{OAuthClientService, RevocationService};
+
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_PROFILE: &str = "profile";
+pub const SCOPE_EMAIL: &str = "email";
+pub const SCOPE_ROLES: &str = "roles";
+
+/// 支持的授权范围及在授权页面上的说明
+pub const SUPPORTED_SCOPES: &[(&str, &str)] = &[
+    (SCOPE_OPENID, "确认你的身份"),
+    (SCOPE_PROFILE, "读取你的用户名"),
+    (SCOPE_EMAIL, "读取你的邮箱地址"),
+    (SCOPE_ROLES, "读取你的角色和权限"),
+];
+
+/// 授权码有效期（秒）
+const AUTHORIZATION_CODE_TTL: i64 = 60;
+
+#[derive(Error, Debug)]
+pub enum OAuthError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("生成令牌失败: {0}")]
+    AuthError(#[from] AuthError),
+    #[error("权限检查失败: {0}")]
+    RbacError(#[from] RbacError),
+    #[error("{0}")]
+    InvalidRequest(&'static str),
+    #[error("客户端认证失败")]
+    InvalidClient,
+    #[error("{0}")]
+    InvalidGrant(&'static str),
+    #[error("该应用不允许使用此授权类型")]
+    UnauthorizedClient,
+    #[error("不支持的授权类型")]
+    UnsupportedGrantType,
+    #[error("只支持授权码模式（response_type=code）")]
+    UnsupportedResponseType,
+    #[error("请求的授权范围无效")]
+    InvalidScope,
+    #[error("授权记录不存在")]
+    ConsentNotFound,
+}
+
+impl OAuthError {
+    /// RFC 6749 5.2 定义的错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidGrant(_) => "invalid_grant",
+            OAuthError::UnauthorizedClient => "unauthorized_client",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::UnsupportedResponseType => "unsupported_response_type",
+            OAuthError::InvalidScope => "invalid_scope",
+            OAuthError::ConsentNotFound => "not_found",
+            OAuthError::DatabaseError(_) | OAuthError::AuthError(_) | OAuthError::RbacError(_) => "server_error",
+        }
+    }
+}
+
+/// 签发给接入应用的ID令牌，角色和权限在用户授权 roles 范围时包含
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct OAuthIdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: u64,
+    pub iat: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<String>>,
+}
+
+/// 令牌端点签发的令牌
+#[derive(Debug)]
+pub struct OAuthTokenGrant {
+    pub user: user::Model,
+    pub access_token: String,
+    pub expires_in: u64,
+    pub scope: String,
+    pub id_token: Option<String>,
+}
+
+pub struct OAuthService;
+
+impl OAuthService {
+    /// 本系统作为授权服务时的 issuer，默认为 APP_BASE_URL
+    pub fn issuer() -> String {
+        env::var("OAUTH_ISSUER")
+            .or_else(|_| env::var("APP_BASE_URL"))
+            .unwrap_or_else(|_| "http://localhost:4200".to_string())
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// 查找授权请求中的应用并校验回调地址；这一步失败时不能重定向回应用
+    pub async fn find_authorization_client(
+        db: &DatabaseConnection,
+        request: &AuthorizationRequest,
+    ) -> Result<oauth_client::Model, OAuthError> {
+        let client = OAuthClientService::find_active(db, &request.client_id)
+            .await?
+            .ok_or(OAuthError::InvalidClient)?;
+
+        if !client.redirect_uris.contains(&request.redirect_uri) {
+            return Err(OAuthError::InvalidRequest("回调地址与应用注册的不一致"));
+        }
+
+        Ok(client)
+    }
+
+    /// 校验授权请求的其余参数，返回请求的授权范围
+    pub fn check_authorization_request(
+        client: &oauth_client::Model,
+        request: &AuthorizationRequest,
+    ) -> Result<Vec<String>, OAuthError> {
+        if request.response_type != "code" {
+            return Err(OAuthError::UnsupportedResponseType);
+        }
+        if !client.allows_grant(GRANT_AUTHORIZATION_CODE) {
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        // 所有应用都必须使用 PKCE，只支持 S256
+        if request.code_challenge.is_none() {
+            return Err(OAuthError::InvalidRequest("缺少 code_challenge，必须使用 PKCE"));
+        }
+        if request.code_challenge_method.as_deref() != Some("S256") {
+            return Err(OAuthError::InvalidRequest("code_challenge_method 必须为 S256"));
+        }
+
+        let scopes = Self::parse_scopes(request.scope.as_deref());
+        if scopes.is_empty() || !scopes.iter().all(|s| client.allowed_scopes.contains(s)) {
+            return Err(OAuthError::InvalidScope);
+        }
+
+        Ok(scopes)
+    }
+
+    /// 用户是否需要确认授权：受信任的应用不需要，之前已同意过相同范围的也不需要
+    pub async fn consent_required(
+        db: &DatabaseConnection,
+        client: &oauth_client::Model,
+        user_id: i32,
+        scopes: &[String],
+    ) -> Result<bool, OAuthError> {
+        if client.skip_consent {
+            return Ok(false);
+        }
+
+        let consent = oauth_consent::Entity::find()
+            .filter(oauth_consent::Column::UserId.eq(user_id))
+            .filter(oauth_consent::Column::ClientId.eq(client.id))
+            .one(db)
+            .await?;
+
+        Ok(!consent.is_some_and(|c| scopes.iter().all(|s| c.scopes.contains(s))))
+    }
+
+    /// 用户同意授权：记录授权范围并生成授权码，返回带授权码的回调地址
+    pub async fn approve(
+        db: &DatabaseConnection,
+        client: &oauth_client::Model,
+        user_id: i32,
+        request: &AuthorizationRequest,
+        scopes: Vec<String>,
+    ) -> Result<String, OAuthError> {
+        let now = Utc::now();
+
+        if !client.skip_consent {
+            Self::record_consent(db, client.id, user_id, &scopes).await?;
+        }
+
+        let code = random_token();
+        let record = oauth_authorization_code::ActiveModel {
+            code_hash: Set(AuthService::hash_token(&code)),
+            client_id: Set(client.id),
+            user_id: Set(user_id),
+            redirect_uri: Set(request.redirect_uri.clone()),
+            scopes: Set(scopes),
+            code_challenge: Set(request.code_challenge.clone().unwrap_or_default()),
+            nonce: Set(request.nonce.clone()),
+            expires_at: Set((now + Duration::seconds(AUTHORIZATION_CODE_TTL)).into()),
+            created_at: Set(now.into()),
+            ..Default::default()
+        };
+        record.insert(db).await?;
+
+        Ok(Self::redirect_with(&request.redirect_uri, &[("code", &code)], request.state.as_deref()))
+    }
+
+    /// 在回调地址上附加参数和原样返回的 state
+    pub fn redirect_with(redirect_uri: &str, params: &[(&str, &str)], state: Option<&str>) -> String {
+        let Ok(mut url) = Url::parse(redirect_uri) else {
+            return redirect_uri.to_string();
+        };
+        {
+            let mut query = url.query_pairs_mut();
+            for (key, value) in params {
+                query.append_pair(key, value);
+            }
+            if let Some(state) = state {
+                query.append_pair("state", state);
+            }
+        }
+        url.to_string()
+    }
+
+    /// 校验令牌端点的客户端凭据
+    pub async fn authenticate_client(
+        db: &DatabaseConnection,
+        client_id: &str,
+        client_secret: Option<&str>,
+    ) -> Result<oauth_client::Model, OAuthError> {
+        OAuthClientService::find_active(db, client_id)
+            .await?
+            .filter(|client| OAuthClientService::verify_secret(client, client_secret))
+            .ok_or(OAuthError::InvalidClient)
+    }
+
+    /// 使用授权码换取令牌，授权码只能使用一次
+    pub async fn exchange_code(
+        db: &DatabaseConnection,
+        client: &oauth_client::Model,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthTokenGrant, OAuthError> {
+        if !client.allows_grant(GRANT_AUTHORIZATION_CODE) {
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        let record = oauth_authorization_code::Entity::find()
+            .filter(oauth_authorization_code::Column::CodeHash.eq(AuthService::hash_token(code)))
+            .one(db)
+            .await?
+            .ok_or(OAuthError::InvalidGrant("授权码无效、已使用或已过期"))?;
+
+        let deleted = oauth_authorization_code::Entity::delete_by_id(record.id).exec(db).await?;
+        if deleted.rows_affected == 0 || record.expires_at.with_timezone(&Utc) <= Utc::now() {
+            return Err(OAuthError::InvalidGrant("授权码无效、已使用或已过期"));
+        }
+        if record.client_id != client.id {
+            return Err(OAuthError::InvalidGrant("授权码不是签发给该应用的"));
+        }
+        if record.redirect_uri != redirect_uri {
+            return Err(OAuthError::InvalidGrant("回调地址与授权请求的不一致"));
+        }
+        if Pkce::challenge_for(code_verifier) != record.code_challenge {
+            return Err(OAuthError::InvalidGrant("PKCE 校验失败"));
+        }
+
+        let user = user::Entity::find_by_id(record.user_id)
+            .one(db)
+            .await?
+            .filter(|user| user.is_active && !user.is_service_account)
+            .ok_or(OAuthError::InvalidGrant("用户不存在或已被禁用"))?;
+
+        let access_token = Self::access_token(client, &user, &record.scopes)?;
+
+        let id_token = if record.scopes.iter().any(|s| s == SCOPE_OPENID) {
+            let (roles, permissions) = if record.scopes.iter().any(|s| s == SCOPE_ROLES) {
+                let roles = RbacService::get_user_roles(db, user.id).await?;
+                let mut permissions: Vec<String> =
+                    RbacService::get_user_permissions(db, user.id).await?.into_iter().collect();
+                permissions.sort();
+                (roles, permissions)
+            } else {
+                (Vec::new(), Vec::new())
+            };
+
+            let claims = Self::id_token_claims(
+                &user,
+                &client.client_id,
+                &record.scopes,
+                record.nonce.clone(),
+                roles,
+                permissions,
+                Utc::now().timestamp() as u64,
+            );
+            Some(AuthService::encode_claims(&claims)?)
+        } else {
+            None
+        };
+
+        Ok(OAuthTokenGrant {
+            user,
+            access_token,
+            expires_in: AuthService::access_token_ttl(),
+            scope: record.scopes.join(" "),
+            id_token,
+        })
+    }
+
+    /// 客户端凭据授权：令牌代表应用关联的服务账户，不签发ID令牌
+    pub async fn client_credentials(
+        db: &DatabaseConnection,
+        client: &oauth_client::Model,
+        scope: Option<&str>,
+    ) -> Result<OAuthTokenGrant, OAuthError> {
+        if !client.allows_grant(GRANT_CLIENT_CREDENTIALS) || !client.is_confidential() {
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        let scopes = match scope {
+            Some(scope) => Self::parse_scopes(Some(scope)),
+            None => client
+                .allowed_scopes
+                .iter()
+                .filter(|s| *s != SCOPE_OPENID)
+                .cloned()
+                .collect(),
+        };
+        if scopes.iter().any(|s| s == SCOPE_OPENID || !client.allowed_scopes.contains(s)) {
+            return Err(OAuthError::InvalidScope);
+        }
+
+        let account_id = client.service_account_id.ok_or(OAuthError::UnauthorizedClient)?;
+        let account = user::Entity::find_by_id(account_id)
+            .one(db)
+            .await?
+            .filter(|account| account.is_active && account.is_service_account)
+            .ok_or(OAuthError::UnauthorizedClient)?;
+
+        let access_token = Self::access_token(client, &account, &scopes)?;
+
+        Ok(OAuthTokenGrant {
+            user: account,
+            access_token,
+            expires_in: AuthService::access_token_ttl(),
+            scope: scopes.join(" "),
+            id_token: None,
+        })
+    }
+
+    /// 令牌内省：返回仍然有效的接入应用令牌，其他令牌一律视为无效
+    pub async fn introspect(db: &DatabaseConnection, token: &str) -> Result<Option<Claims>, OAuthError> {
+        let Ok(claims) = AuthService::verify_token(token) else {
+            return Ok(None);
+        };
+        if claims.token_use != TokenUse::OAuth || RevocationService::is_revoked(db, &claims).await? {
+            return Ok(None);
+        }
+
+        // 应用已删除或停用、用户已禁用后，令牌随即失效
+        let Some(client_id) = claims.client_id.as_deref() else {
+            return Ok(None);
+        };
+        if OAuthClientService::find_active(db, client_id).await?.is_none() {
+            return Ok(None);
+        }
+        let user_active = user::Entity::find_by_id(claims.sub)
+            .one(db)
+            .await?
+            .is_some_and(|user| user.is_active);
+
+        Ok(user_active.then_some(claims))
+    }
+
+    /// 撤销令牌（RFC 7009）：只能撤销签发给该应用的令牌，无效的令牌直接忽略
+    pub async fn revoke(db: &DatabaseConnection, client: &oauth_client::Model, token: &str) -> Result<(), OAuthError> {
+        let Ok(claims) = AuthService::verify_token(token) else {
+            return Ok(());
+        };
+
+        if claims.token_use == TokenUse::OAuth && claims.client_id.as_deref() == Some(client.client_id.as_str()) {
+            RevocationService::revoke_token(db, &claims, "oauth_revoked").await?;
+        }
+
+        Ok(())
+    }
+
+    /// 用户已授权的应用
+    pub async fn list_consents(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<Vec<OAuthConsentResponse>, OAuthError> {
+        let consents = oauth_consent::Entity::find()
+            .filter(oauth_consent::Column::UserId.eq(user_id))
+            .find_also_related(oauth_client::Entity)
+            .order_by_desc(oauth_consent::Column::UpdatedAt)
+            .all(db)
+            .await?;
+
+        Ok(consents
+            .into_iter()
+            .filter_map(|(consent, client)| {
+                client.map(|client| OAuthConsentResponse {
+                    client_id: client.client_id,
+                    client_name: client.name,
+                    scopes: consent.scopes,
+                    created_at: consent.created_at,
+                    updated_at: consent.updated_at,
+                })
+            })
+            .collect())
+    }
+
+    /// 撤销对应用的授权，下次使用该应用时需要重新确认
+    pub async fn revoke_consent(db: &DatabaseConnection, user_id: i32, client_id: &str) -> Result<(), OAuthError> {
+        let client = oauth_client::Entity::find()
+            .filter(oauth_client::Column::ClientId.eq(client_id))
+            .one(db)
+            .await?
+            .ok_or(OAuthError::ConsentNotFound)?;
+
+        let result = oauth_consent::Entity::delete_many()
+            .filter(oauth_consent::Column::UserId.eq(user_id))
+            .filter(oauth_consent::Column::ClientId.eq(client.id))
+            .exec(db)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(OAuthError::ConsentNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// 合并之前已同意的范围
+    async fn record_consent(
+        db: &DatabaseConnection,
+        client_id: i32,
+        user_id: i32,
+        scopes: &[String],
+    ) -> Result<(), DbErr> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+        let existing = oauth_consent::Entity::find()
+            .filter(oauth_consent::Column::UserId.eq(user_id))
+            .filter(oauth_consent::Column::ClientId.eq(client_id))
+            .one(db)
+            .await?;
+
+        let mut merged = existing.map(|c| c.scopes).unwrap_or_default();
+        for scope in scopes {
+            if !merged.contains(scope) {
+                merged.push(scope.clone());
+            }
+        }
+
+        let consent = oauth_consent::ActiveModel {
+            user_id: Set(user_id),
+            client_id: Set(client_id),
+            scopes: Set(merged),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        oauth_consent::Entity::insert(consent)
+            .on_conflict(
+                OnConflict::columns([oauth_consent::Column::UserId, oauth_consent::Column::ClientId])
+                    .update_columns([oauth_consent::Column::Scopes, oauth_consent::Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+
+        Ok(())
+    }
+
+    fn access_token(client: &oauth_client::Model, user: &user::Model, scopes: &[String]) -> Result<String, AuthError> {
+        let mut claims = Claims::new(user.id, &user.username, AuthService::access_token_ttl());
+        claims.token_use = TokenUse::OAuth;
+        claims.client_id = Some(client.client_id.clone());
+        claims.scope = Some(scopes.join(" "));
+        AuthService::encode_claims(&claims)
+    }
+
+    fn id_token_claims(
+        user: &user::Model,
+        client_id: &str,
+        scopes: &[String],
+        nonce: Option<String>,
+        roles: Vec<String>,
+        permissions: Vec<String>,
+        now: u64,
+    ) -> OAuthIdTokenClaims {
+        let has = |scope: &str| scopes.iter().any(|s| s == scope);
+
+        OAuthIdTokenClaims {
+            iss: Self::issuer(),
+            sub: user.id.to_string(),
+            aud: client_id.to_string(),
+            exp: now + AuthService::access_token_ttl(),
+            iat: now,
+            nonce,
+            preferred_username: has(SCOPE_PROFILE).then(|| user.username.clone()),
+            email: has(SCOPE_EMAIL).then(|| user.email.clone()),
+            email_verified: has(SCOPE_EMAIL).then_some(user.email_verified_at.is_some()),
+            roles: has(SCOPE_ROLES).then_some(roles),
+            permissions: has(SCOPE_ROLES).then_some(permissions),
+        }
+    }
+
+    /// 解析空格分隔的授权范围，去掉重复项
+    fn parse_scopes(scope: Option<&str>) -> Vec<String> {
+        let mut scopes: Vec<String> = Vec::new();
+        for scope in scope.unwrap_or_default().split_whitespace() {
+            if !scopes.iter().any(|s| s == scope) {
+                scopes.push(scope.to_string());
+            }
+        }
+        scopes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> oauth_client::Model {
+        let now = Utc::now();
+        oauth_client::Model {
+            id: 1,
+            client_id: "client".to_string(),
+            name: "工单系统".to_string(),
+            description: None,
+            secret_prefix: None,
+            secret_hash: None,
+            redirect_uris: vec!["https://tickets.example.com/callback".to_string()],
+            allowed_scopes: vec![SCOPE_OPENID.to_string(), SCOPE_PROFILE.to_string(), SCOPE_ROLES.to_string()],
+            grant_types: vec![GRANT_AUTHORIZATION_CODE.to_string()],
+            service_account_id: None,
+            skip_consent: false,
+            is_active: true,
+            created_by: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+        }
+    }
+
+    fn request() -> AuthorizationRequest {
+        AuthorizationRequest {
+            response_type: "code".to_string(),
+            client_id: "client".to_string(),
+            redirect_uri: "https://tickets.example.com/callback".to_string(),
+            scope: Some("openid profile openid".to_string()),
+            state: Some("xyz".to_string()),
+            code_challenge: Some(Pkce::challenge_for("verifier")),
+            code_challenge_method: Some("S256".to_string()),
+            nonce: None,
+        }
+    }
+
+    fn user() -> user::Model {
+        user::Model {
+            email_verified_at: Some(Utc::now().into()),
+            ..user::Model::for_test(42, "alice")
+        }
+    }
+
+    #[test]
+    fn test_check_authorization_request() {
+        let scopes = OAuthService::check_authorization_request(&client(), &request()).unwrap();
+        assert_eq!(scopes, vec!["openid", "profile"]);
+
+        // 必须使用 PKCE S256
+        let plain = AuthorizationRequest {
+            code_challenge_method: Some("plain".to_string()),
+            ..request()
+        };
+        assert_eq!(
+            OAuthService::check_authorization_request(&client(), &plain).unwrap_err().code(),
+            "invalid_request"
+        );
+        let no_pkce = AuthorizationRequest {
+            code_challenge: None,
+            ..request()
+        };
+        assert!(OAuthService::check_authorization_request(&client(), &no_pkce).is_err());
+
+        // 超出应用允许的范围
+        let email = AuthorizationRequest {
+            scope: Some("openid email".to_string()),
+            ..request()
+        };
+        assert_eq!(
+            OAuthService::check_authorization_request(&client(), &email).unwrap_err().code(),
+            "invalid_scope"
+        );
+
+        let implicit = AuthorizationRequest {
+            response_type: "token".to_string(),
+            ..request()
+        };
+        assert_eq!(
+            OAuthService::check_authorization_request(&client(), &implicit).unwrap_err().code(),
+            "unsupported_response_type"
+        );
+    }
+
+    #[test]
+    fn test_redirect_with_keeps_existing_query() {
+        let url = OAuthService::redirect_with(
+            "https://tickets.example.com/callback?tenant=1",
+            &[("code", "abc")],
+            Some("a b"),
+        );
+        assert_eq!(url, "https://tickets.example.com/callback?tenant=1&code=abc&state=a+b");
+
+        let url = OAuthService::redirect_with("https://tickets.example.com/callback", &[("error", "access_denied")], None);
+        assert_eq!(url, "https://tickets.example.com/callback?error=access_denied");
+    }
+
+    #[test]
+    fn test_id_token_claims_follow_scopes() {
+        let roles = vec!["管理员".to_string()];
+        let permissions = vec!["user:read".to_string()];
+
+        let claims = OAuthService::id_token_claims(
+            &user(),
+            "client",
+            &[SCOPE_OPENID.to_string()],
+            Some("n-0S6".to_string()),
+            roles.clone(),
+            permissions.clone(),
+            1_000,
+        );
+        assert_eq!(claims.sub, "42");
+        assert_eq!(claims.aud, "client");
+        assert_eq!(claims.nonce.as_deref(), Some("n-0S6"));
+        assert!(claims.preferred_username.is_none());
+        assert!(claims.email.is_none());
+        assert!(claims.roles.is_none());
+
+        let claims = OAuthService::id_token_claims(
+            &user(),
+            "client",
+            &[SCOPE_OPENID.to_string(), SCOPE_PROFILE.to_string(), SCOPE_EMAIL.to_string(), SCOPE_ROLES.to_string()],
+            None,
+            roles.clone(),
+            permissions.clone(),
+            1_000,
+        );
+        assert_eq!(claims.preferred_username.as_deref(), Some("alice"));
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(claims.email_verified, Some(true));
+        assert_eq!(claims.roles, Some(roles));
+        assert_eq!(claims.permissions, Some(permissions));
+
+        let json = serde_json::to_value(&claims).unwrap();
+        assert!(json.get("nonce").is_none());
+    }
+
+    #[test]
+    fn test_oauth_access_token_claims() {
+        let token = OAuthService::access_token(&client(), &user(), &[SCOPE_OPENID.to_string(), SCOPE_ROLES.to_string()]).unwrap();
+        // 不校验签名，避免与其他测试共享 JWT_SECRET 造成干扰
+        let mut validation = jsonwebtoken::Validation::default();
+        validation.insecure_disable_signature_validation();
+        let claims = jsonwebtoken::decode::<Claims>(&token, &jsonwebtoken::DecodingKey::from_secret(&[]), &validation)
+            .unwrap()
+            .claims;
+
+        // 接入应用的令牌不能用于访问本系统的接口
+        assert_eq!(claims.token_use, TokenUse::OAuth);
+        assert_eq!(claims.client_id.as_deref(), Some("client"));
+        assert_eq!(claims.scope.as_deref(), Some("openid roles"));
+    }
+}
@@ -0,0 +1,383 @@
+use chrono::{Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::*;
+use std::collections::HashSet;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::models::user_department::CreateUserDepartmentDto;
+use crate::models::{department, oidc_login_state, user, user_external_identity, OidcCallbackDto};
+use crate::oidc::{self, IdTokenClaims, OidcClient, OidcError, OidcProvider, Pkce};
+use crate::rbac::RbacService;
+use crate::services::UserDepartmentService;
+
+/// 发起登录到回调之间的最长时间（秒）
+const LOGIN_STATE_TTL: i64 = 600;
+
+/// 自动创建用户时用户名的最大长度，为重名时追加的序号留出空间
+const USERNAME_BASE_MAX_LEN: usize = 40;
+
+#[derive(Error, Debug)]
+pub enum OidcLoginError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("{0}")]
+    Oidc(#[from] OidcError),
+    #[error("登录请求无效或已过期，请重新登录")]
+    InvalidState,
+    #[error("该外部账户尚未关联本系统的用户，请联系管理员")]
+    AccountNotLinked,
+    #[error("身份提供方没有提供邮箱，无法创建用户")]
+    EmailRequired,
+    #[error("邮箱已被其他账户使用")]
+    EmailTaken,
+}
+
+/// 外部身份提供方登录的结果
+pub struct OidcLogin {
+    pub user: user::Model,
+    /// 身份提供方已完成多因素认证且该提供方配置为信任，不再要求本地二次验证
+    pub mfa_satisfied: bool,
+}
+
+pub struct OidcService;
+
+impl OidcService {
+    /// 发起登录：保存 state、nonce 和 PKCE 校验码，返回跳转到身份提供方的授权地址
+    pub async fn begin(db: &DatabaseConnection, provider_name: &str) -> Result<String, OidcLoginError> {
+        let provider = OidcProvider::find(provider_name)?;
+        let metadata = OidcClient::discover(&provider).await?;
+
+        let now = Utc::now();
+        let state = oidc::random_token();
+        let nonce = oidc::random_token();
+        let pkce = Pkce::generate();
+        let redirect_uri = oidc::redirect_uri();
+
+        // 顺便清理已过期的登录状态
+        oidc_login_state::Entity::delete_many()
+            .filter(oidc_login_state::Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await?;
+
+        let record = oidc_login_state::ActiveModel {
+            state_hash: Set(AuthService::hash_token(&state)),
+            provider: Set(provider.name.clone()),
+            nonce: Set(nonce.clone()),
+            code_verifier: Set(pkce.verifier),
+            redirect_uri: Set(redirect_uri.clone()),
+            expires_at: Set((now + Duration::seconds(LOGIN_STATE_TTL)).into()),
+            created_at: Set(now.into()),
+            ..Default::default()
+        };
+        record.insert(db).await?;
+
+        Ok(OidcClient::authorization_url(
+            &metadata,
+            &provider,
+            &state,
+            &nonce,
+            &pkce.challenge,
+            &redirect_uri,
+        ))
+    }
+
+    /// 完成登录：校验 state，用授权码换取并校验ID令牌，返回对应的本地用户
+    pub async fn complete(db: &DatabaseConnection, dto: OidcCallbackDto) -> Result<OidcLogin, OidcLoginError> {
+        let state = Self::claim_state(db, &dto.state).await?;
+        let provider = OidcProvider::find(&state.provider)?;
+        let metadata = OidcClient::discover(&provider).await?;
+
+        let id_token =
+            OidcClient::exchange_code(&metadata, &provider, &dto.code, &state.code_verifier, &state.redirect_uri).await?;
+        let claims = OidcClient::verify_id_token(&metadata, &provider, &id_token, &state.nonce).await?;
+
+        let user = Self::resolve_user(db, &provider, &claims).await?;
+
+        Ok(OidcLogin {
+            user,
+            mfa_satisfied: provider.trusts_mfa(&claims),
+        })
+    }
+
+    /// 取回并删除登录状态，并发的回调中只有一个能成功
+    async fn claim_state(db: &DatabaseConnection, state: &str) -> Result<oidc_login_state::Model, OidcLoginError> {
+        let record = oidc_login_state::Entity::find()
+            .filter(oidc_login_state::Column::StateHash.eq(AuthService::hash_token(state)))
+            .one(db)
+            .await?
+            .ok_or(OidcLoginError::InvalidState)?;
+
+        let deleted = oidc_login_state::Entity::delete_by_id(record.id).exec(db).await?;
+        if deleted.rows_affected == 0 || record.expires_at.with_timezone(&Utc) <= Utc::now() {
+            return Err(OidcLoginError::InvalidState);
+        }
+
+        Ok(record)
+    }
+
+    /// 查找外部身份对应的本地用户：已关联的身份、按已验证的邮箱关联、自动创建，依次尝试
+    async fn resolve_user(
+        db: &DatabaseConnection,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+    ) -> Result<user::Model, OidcLoginError> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        let linked = user_external_identity::Entity::find()
+            .filter(user_external_identity::Column::Provider.eq(&provider.name))
+            .filter(user_external_identity::Column::Subject.eq(&claims.sub))
+            .find_also_related(user::Entity)
+            .one(db)
+            .await?;
+
+        if let Some((identity, Some(user))) = linked {
+            let mut identity: user_external_identity::ActiveModel = identity.into();
+            identity.email = Set(claims.email.clone());
+            identity.last_login_at = Set(Some(now));
+            identity.update(db).await?;
+            return Ok(user);
+        }
+
+        let verified_email = claims.email.as_deref().filter(|_| claims.email_verified);
+
+        if let (true, Some(email)) = (provider.link_by_email, verified_email) {
+            let existing = user::Entity::find()
+                .filter(user::Column::Email.eq(email))
+                .filter(user::Column::IsServiceAccount.eq(false))
+                .one(db)
+                .await?;
+
+            if let Some(user) = existing {
+                let txn = db.begin().await?;
+                Self::link_identity(&txn, user.id, provider, claims).await?;
+
+                // 身份提供方已验证该邮箱
+                let user = if user.email_verified_at.is_none() {
+                    let mut active_user: user::ActiveModel = user.into();
+                    active_user.email_verified_at = Set(Some(now));
+                    active_user.update(&txn).await?
+                } else {
+                    user
+                };
+                txn.commit().await?;
+
+                tracing::info!("用户 {} 已关联 {} 的外部账户 {}", user.username, provider.name, claims.sub);
+                return Ok(user);
+            }
+        }
+
+        if !provider.jit_provisioning {
+            return Err(OidcLoginError::AccountNotLinked);
+        }
+
+        Self::provision_user(db, provider, claims).await
+    }
+
+    /// 自动创建用户并关联外部身份，然后分配默认角色和部门
+    async fn provision_user(
+        db: &DatabaseConnection,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+    ) -> Result<user::Model, OidcLoginError> {
+        let email = claims.email.clone().ok_or(OidcLoginError::EmailRequired)?;
+
+        let email_taken = user::Entity::find()
+            .filter(user::Column::Email.eq(&email))
+            .one(db)
+            .await?
+            .is_some();
+        if email_taken {
+            return Err(OidcLoginError::EmailTaken);
+        }
+
+        let username = Self::unique_username(db, &Self::username_candidate(provider, claims)).await?;
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        let txn = db.begin().await?;
+        // 没有本地密码，只能通过身份提供方登录，直到用户通过找回密码设置密码
+        let user = user::ActiveModel {
+            username: Set(username),
+            email: Set(email),
+            password_hash: Set(String::new()),
+            is_active: Set(true),
+            must_change_password: Set(false),
+            email_verified_at: Set(claims.email_verified.then_some(now)),
+            ..Default::default()
+        };
+        let user = user.insert(&txn).await?;
+        Self::link_identity(&txn, user.id, provider, claims).await?;
+        txn.commit().await?;
+
+        tracing::info!("通过 {} 自动创建了用户 {}", provider.name, user.username);
+
+        // 分配默认角色和部门，失败不影响登录，管理员可以稍后手动分配
+        if let Some(role_name) = &provider.default_role {
+            match RbacService::assign_role_by_name(db, user.id, role_name).await {
+                Ok(true) => {}
+                Ok(false) => tracing::warn!("OIDC默认角色 {} 不存在或已禁用，未分配角色", role_name),
+                Err(e) => tracing::error!("为新用户 {} 分配默认角色失败: {}", user.username, e),
+            }
+        }
+
+        if let Some(code) = &provider.default_department {
+            if let Err(e) = Self::assign_default_department(db, user.id, code).await {
+                tracing::error!("为新用户 {} 分配默认部门失败: {}", user.username, e);
+            }
+        }
+
+        Ok(user)
+    }
+
+    async fn link_identity<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+    ) -> Result<(), DbErr> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+        let identity = user_external_identity::ActiveModel {
+            user_id: Set(user_id),
+            provider: Set(provider.name.clone()),
+            subject: Set(claims.sub.clone()),
+            email: Set(claims.email.clone()),
+            created_at: Set(now),
+            last_login_at: Set(Some(now)),
+            ..Default::default()
+        };
+        identity.insert(db).await?;
+        Ok(())
+    }
+
+    async fn assign_default_department(db: &DatabaseConnection, user_id: i32, code: &str) -> anyhow::Result<()> {
+        let department = department::Entity::find()
+            .filter(department::Column::Code.eq(code))
+            .filter(department::Column::IsActive.eq(true))
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("部门 {} 不存在或已禁用", code))?;
+
+        UserDepartmentService::assign_user_to_department(CreateUserDepartmentDto {
+            user_id,
+            department_id: department.id,
+            position: None,
+            is_primary: true,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// 自动创建用户时的用户名：优先使用 preferred_username，其次是邮箱的用户名部分
+    fn username_candidate(provider: &OidcProvider, claims: &IdTokenClaims) -> String {
+        let raw = claims
+            .preferred_username
+            .as_deref()
+            .or_else(|| claims.email.as_deref().and_then(|email| email.split('@').next()))
+            .unwrap_or_default();
+
+        let username: String = raw
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            .take(USERNAME_BASE_MAX_LEN)
+            .collect();
+
+        if username.len() < 3 {
+            format!("{}_{}", provider.name, username).chars().take(USERNAME_BASE_MAX_LEN).collect()
+        } else {
+            username
+        }
+    }
+
+    /// 用户名已存在时追加序号
+    async fn unique_username(db: &DatabaseConnection, base: &str) -> Result<String, DbErr> {
+        let taken: HashSet<String> = user::Entity::find()
+            .filter(user::Column::Username.starts_with(base))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|u| u.username)
+            .collect();
+
+        Ok(Self::first_free_username(base, &taken))
+    }
+
+    fn first_free_username(base: &str, taken: &HashSet<String>) -> String {
+        if !taken.contains(base) {
+            return base.to_string();
+        }
+
+        (2..)
+            .map(|n| format!("{}{}", base, n))
+            .find(|candidate| !taken.contains(candidate))
+            .expect("序号不会用尽")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> OidcProvider {
+        OidcProvider {
+            name: "company".to_string(),
+            display_name: "公司账号".to_string(),
+            issuer: "https://idp.example.com".to_string(),
+            client_id: "web-admin".to_string(),
+            client_secret: None,
+            scopes: vec!["openid".to_string()],
+            link_by_email: true,
+            jit_provisioning: true,
+            default_role: None,
+            default_department: None,
+            trust_idp_mfa: false,
+            mfa_acr_values: Vec::new(),
+        }
+    }
+
+    fn claims(preferred_username: Option<&str>, email: Option<&str>) -> IdTokenClaims {
+        IdTokenClaims {
+            sub: "employee-42".to_string(),
+            nonce: None,
+            email: email.map(str::to_string),
+            email_verified: true,
+            preferred_username: preferred_username.map(str::to_string),
+            amr: Vec::new(),
+            acr: None,
+        }
+    }
+
+    #[test]
+    fn test_username_candidate() {
+        let provider = provider();
+
+        assert_eq!(OidcService::username_candidate(&provider, &claims(Some("alice"), None)), "alice");
+        // 没有 preferred_username 时使用邮箱的用户名部分
+        assert_eq!(
+            OidcService::username_candidate(&provider, &claims(None, Some("bob.smith@example.com"))),
+            "bob.smith"
+        );
+        // 去掉不允许的字符
+        assert_eq!(
+            OidcService::username_candidate(&provider, &claims(Some("张三 zhang+san"), None)),
+            "zhangsan"
+        );
+        // 太短时加上提供方名称
+        assert_eq!(OidcService::username_candidate(&provider, &claims(Some("li"), None)), "company_li");
+        assert_eq!(OidcService::username_candidate(&provider, &claims(None, None)), "company_");
+
+        let long = "a".repeat(100);
+        assert_eq!(
+            OidcService::username_candidate(&provider, &claims(Some(&long), None)).len(),
+            USERNAME_BASE_MAX_LEN
+        );
+    }
+
+    #[test]
+    fn test_first_free_username() {
+        let taken: HashSet<String> = ["alice", "alice2", "alice3"].iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(OidcService::first_free_username("bob", &taken), "bob");
+        assert_eq!(OidcService::first_free_username("alice", &taken), "alice4");
+    }
+}
@@ -0,0 +1,456 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use std::borrow::Cow;
+use std::env;
+use thiserror::Error;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::auth::{AuthError, AuthService};
+use crate::models::{password_history, user};
+use crate::password_hash::Argon2Config;
+
+/// 常见弱密码，比较时忽略大小写
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "1234567", "12345678", "123456789", "1234567890", "123123", "111111",
+    "000000", "654321", "666666", "888888", "112233", "121212", "123321", "147258369",
+    "password", "password1", "password12", "password123", "passw0rd", "p@ssw0rd",
+    "p@ssword", "qwerty", "qwerty123", "qwertyuiop", "1q2w3e4r", "1qaz2wsx", "qazwsx",
+    "zaq12wsx", "asdfghjkl", "abc123", "abc12345", "abcd1234", "a1b2c3d4", "aa123456",
+    "admin", "admin123", "admin@123", "administrator", "root", "root123", "toor",
+    "welcome", "welcome1", "welcome123", "letmein", "iloveyou", "monkey", "dragon",
+    "sunshine", "princess", "football", "baseball", "superman", "trustno1", "master",
+    "changeme", "default", "secret", "test", "test123", "test1234", "guest", "user",
+    "login", "woaini1314", "5201314",
+];
+
+/// 各密码哈希算法的账户数量
+#[derive(Debug, Default, serde::Serialize)]
+pub struct PasswordHashStats {
+    pub total: u64,
+    /// 使用 Argon2id 且参数与当前配置相同
+    pub argon2id: u64,
+    /// 使用 Argon2id 但参数与当前配置不同，下次登录时重新计算
+    pub argon2id_outdated: u64,
+    /// 仍在使用旧的 bcrypt 哈希，下次登录时升级为 Argon2id
+    pub bcrypt: u64,
+    /// 没有本地密码的账户（服务账户、目录用户和外部身份提供方创建的用户）
+    pub without_password: u64,
+}
+
+/// 密码策略
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// 最小长度（按字符计）
+    pub min_length: usize,
+    /// 至少包含的字符类别数（小写字母、大写字母、数字、其他符号）
+    pub min_char_classes: usize,
+    /// 是否拒绝常见弱密码
+    pub deny_common: bool,
+    /// 是否拒绝包含用户名或邮箱名的密码
+    pub deny_user_info: bool,
+    /// 密码有效期（天），0 表示永不过期
+    pub max_age_days: i64,
+    /// 不允许重复使用最近几次的密码，0 表示不限制
+    pub history_size: usize,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            min_char_classes: 3,
+            deny_common: true,
+            deny_user_info: true,
+            max_age_days: 0,
+            history_size: 5,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// 从环境变量读取策略，未配置的项使用默认值
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            min_length: env_or("PASSWORD_MIN_LENGTH", default.min_length),
+            min_char_classes: env_or("PASSWORD_MIN_CHAR_CLASSES", default.min_char_classes),
+            deny_common: env_or("PASSWORD_DENY_COMMON", default.deny_common),
+            deny_user_info: env_or("PASSWORD_DENY_USER_INFO", default.deny_user_info),
+            max_age_days: env_or("PASSWORD_MAX_AGE_DAYS", default.max_age_days),
+            history_size: env_or("PASSWORD_HISTORY_SIZE", default.history_size),
+        }
+    }
+
+    /// 检查密码本身是否符合策略（不含历史密码检查），返回所有不满足的规则
+    pub fn check(&self, password: &str, username: &str, email: &str) -> Vec<ValidationError> {
+        let mut violations = Vec::new();
+
+        let length = password.chars().count();
+        if length < self.min_length {
+            let mut error = violation(
+                "password_too_short",
+                format!("密码长度不能少于{}个字符", self.min_length),
+            );
+            error.add_param(Cow::Borrowed("min"), &self.min_length);
+            violations.push(error);
+        }
+
+        let classes = char_classes(password);
+        if classes < self.min_char_classes {
+            let mut error = violation(
+                "password_too_simple",
+                format!(
+                    "密码需要包含小写字母、大写字母、数字、符号中的至少{}类",
+                    self.min_char_classes
+                ),
+            );
+            error.add_param(Cow::Borrowed("min_char_classes"), &self.min_char_classes);
+            violations.push(error);
+        }
+
+        let lowercase = password.to_lowercase();
+        if self.deny_common && COMMON_PASSWORDS.contains(&lowercase.as_str()) {
+            violations.push(violation("password_common", "密码过于常见".to_string()));
+        }
+
+        if self.deny_user_info && contains_user_info(&lowercase, username, email) {
+            violations.push(violation(
+                "password_contains_user_info",
+                "密码不能包含用户名或邮箱".to_string(),
+            ));
+        }
+
+        violations
+    }
+
+    /// 按字段返回结构化的验证错误，格式与DTO验证失败时一致
+    pub fn validate(
+        &self,
+        field: &'static str,
+        password: &str,
+        username: &str,
+        email: &str,
+    ) -> Result<(), ValidationErrors> {
+        let violations = self.check(password, username, email);
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        let mut errors = ValidationErrors::new();
+        for violation in violations {
+            errors.add(field, violation);
+        }
+        Err(errors)
+    }
+
+    /// 密码是否已超过有效期
+    pub fn is_expired(&self, changed_at: &DateTime<Utc>, now: &DateTime<Utc>) -> bool {
+        self.max_age_days > 0 && *now - *changed_at >= Duration::days(self.max_age_days)
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+fn violation(code: &'static str, message: String) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::Owned(message));
+    error
+}
+
+fn char_classes(password: &str) -> usize {
+    let checks: [fn(&char) -> bool; 3] = [
+        char::is_ascii_lowercase,
+        char::is_ascii_uppercase,
+        char::is_ascii_digit,
+    ];
+
+    let mut classes = checks
+        .iter()
+        .filter(|check| password.chars().any(|c| check(&c)))
+        .count();
+
+    if password.chars().any(|c| !c.is_ascii_alphanumeric()) {
+        classes += 1;
+    }
+
+    classes
+}
+
+/// 密码（已转小写）是否包含用户名或邮箱@前的部分；过短的名字不检查，避免误伤
+fn contains_user_info(lowercase_password: &str, username: &str, email: &str) -> bool {
+    let email_name = email.split('@').next().unwrap_or_default();
+
+    [username, email_name]
+        .iter()
+        .map(|s| s.to_lowercase())
+        .filter(|s| s.chars().count() >= 3)
+        .any(|s| lowercase_password.contains(&s))
+}
+
+#[derive(Error, Debug)]
+pub enum PasswordPolicyError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("认证错误: {0}")]
+    AuthError(#[from] AuthError),
+    #[error("密码不符合安全策略")]
+    Violation(ValidationErrors),
+}
+
+pub struct PasswordPolicyService;
+
+impl PasswordPolicyService {
+    /// 检查新密码是否符合策略；传入已有用户时，同时检查是否与当前密码或最近使用过的密码相同
+    pub async fn validate_new_password<C: ConnectionTrait>(
+        db: &C,
+        policy: &PasswordPolicy,
+        field: &'static str,
+        password: &str,
+        username: &str,
+        email: &str,
+        existing_user: Option<&user::Model>,
+    ) -> Result<(), PasswordPolicyError> {
+        let mut errors = match policy.validate(field, password, username, email) {
+            Ok(()) => ValidationErrors::new(),
+            Err(errors) => errors,
+        };
+
+        if let Some(user) = existing_user {
+            if Self::is_reused(db, policy, user, password).await? {
+                let mut error = violation(
+                    "password_reused",
+                    format!("不能使用最近{}次用过的密码", policy.history_size),
+                );
+                error.add_param(Cow::Borrowed("history_size"), &policy.history_size);
+                errors.add(field, error);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(PasswordPolicyError::Violation(errors))
+        }
+    }
+
+    /// 更新用户密码：旧密码写入历史记录，刷新密码修改时间并清除首次登录修改密码的要求
+    pub async fn set_password<C: ConnectionTrait>(
+        db: &C,
+        policy: &PasswordPolicy,
+        user: user::Model,
+        password: &str,
+    ) -> Result<user::Model, PasswordPolicyError> {
+        let password_hash = AuthService::hash_password(password)?;
+        let now = Utc::now();
+
+        if policy.history_size > 0 && !user.password_hash.is_empty() {
+            let record = password_history::ActiveModel {
+                user_id: Set(user.id),
+                password_hash: Set(user.password_hash.clone()),
+                created_at: Set(now.into()),
+                ..Default::default()
+            };
+            password_history::Entity::insert(record)
+                .exec_without_returning(db)
+                .await?;
+
+            Self::prune_history(db, policy, user.id).await?;
+        }
+
+        let mut active: user::ActiveModel = user.into();
+        active.password_hash = Set(password_hash);
+        active.password_changed_at = Set(now.into());
+        active.must_change_password = Set(false);
+        active.updated_at = Set(now.into());
+
+        Ok(active.update(db).await?)
+    }
+
+    /// 登录验证通过后，密码哈希使用旧算法或旧参数时用明文密码重新计算
+    /// 密码本身没有变化，不写入历史记录，也不更新密码修改时间
+    pub async fn rehash_if_needed<C: ConnectionTrait>(
+        db: &C,
+        user: user::Model,
+        password: &str,
+    ) -> Result<user::Model, PasswordPolicyError> {
+        if !AuthService::password_needs_rehash(&user.password_hash) {
+            return Ok(user);
+        }
+
+        let password_hash = AuthService::hash_password(password)?;
+        let mut active: user::ActiveModel = user.into();
+        active.password_hash = Set(password_hash);
+
+        Ok(active.update(db).await?)
+    }
+
+    /// 统计各密码哈希算法的账户数量，用于确认旧哈希的升级进度
+    pub async fn hash_stats<C: ConnectionTrait>(db: &C) -> Result<PasswordHashStats, DbErr> {
+        let count = |condition: Option<Condition>| async move {
+            let mut query = user::Entity::find();
+            if let Some(condition) = condition {
+                query = query.filter(condition);
+            }
+            query.count(db).await
+        };
+
+        let current_prefix = Argon2Config::from_env().hash_prefix();
+        let total = count(None).await?;
+        let argon2id_all = count(Some(Condition::all().add(user::Column::PasswordHash.starts_with("$argon2id$")))).await?;
+        let argon2id = count(Some(Condition::all().add(user::Column::PasswordHash.starts_with(&current_prefix)))).await?;
+        let bcrypt = count(Some(Condition::all().add(user::Column::PasswordHash.starts_with("$2")))).await?;
+        let without_password = count(Some(Condition::all().add(user::Column::PasswordHash.eq("")))).await?;
+
+        Ok(PasswordHashStats {
+            total,
+            argon2id,
+            argon2id_outdated: argon2id_all - argon2id,
+            bcrypt,
+            without_password,
+        })
+    }
+
+    /// 新密码是否与当前密码或最近的历史密码相同
+    async fn is_reused<C: ConnectionTrait>(
+        db: &C,
+        policy: &PasswordPolicy,
+        user: &user::Model,
+        password: &str,
+    ) -> Result<bool, DbErr> {
+        if policy.history_size == 0 {
+            return Ok(false);
+        }
+
+        if AuthService::verify_password(password, &user.password_hash).is_ok() {
+            return Ok(true);
+        }
+
+        // 当前密码也计入历史次数
+        let history = password_history::Entity::find()
+            .filter(password_history::Column::UserId.eq(user.id))
+            .order_by_desc(password_history::Column::Id)
+            .limit(policy.history_size.saturating_sub(1) as u64)
+            .all(db)
+            .await?;
+
+        Ok(history
+            .iter()
+            .any(|h| AuthService::verify_password(password, &h.password_hash).is_ok()))
+    }
+
+    /// 只保留最近的历史记录
+    async fn prune_history<C: ConnectionTrait>(
+        db: &C,
+        policy: &PasswordPolicy,
+        user_id: i32,
+    ) -> Result<(), DbErr> {
+        let keep: Vec<i32> = password_history::Entity::find()
+            .select_only()
+            .column(password_history::Column::Id)
+            .filter(password_history::Column::UserId.eq(user_id))
+            .order_by_desc(password_history::Column::Id)
+            .limit(policy.history_size as u64)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        password_history::Entity::delete_many()
+            .filter(password_history::Column::UserId.eq(user_id))
+            .filter(password_history::Column::Id.is_not_in(keep))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(policy: &PasswordPolicy, password: &str) -> Vec<String> {
+        policy
+            .check(password, "alice", "alice.w@example.com")
+            .into_iter()
+            .map(|e| e.code.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_password_policy_business_rules() {
+        let policy = PasswordPolicy::default();
+
+        // 符合所有规则
+        assert!(codes(&policy, "Tr0ub4dor&3").is_empty());
+
+        // 长度不足
+        assert!(codes(&policy, "Ab1!").contains(&"password_too_short".to_string()));
+
+        // 字符类别不足：只有小写字母和数字
+        assert!(codes(&policy, "horsebattery42").contains(&"password_too_simple".to_string()));
+
+        // 常见密码忽略大小写
+        assert!(codes(&policy, "P@ssw0rd").contains(&"password_common".to_string()));
+
+        // 包含用户名或邮箱名
+        assert!(codes(&policy, "Alice#2024!").contains(&"password_contains_user_info".to_string()));
+        assert!(codes(&policy, "X9!alice.w").contains(&"password_contains_user_info".to_string()));
+
+        // 同时违反多条规则时全部返回
+        assert_eq!(codes(&policy, "admin").len(), 3);
+    }
+
+    #[test]
+    fn test_password_policy_can_be_relaxed() {
+        let relaxed = PasswordPolicy {
+            min_length: 6,
+            min_char_classes: 1,
+            deny_common: false,
+            deny_user_info: false,
+            ..PasswordPolicy::default()
+        };
+
+        assert!(codes(&relaxed, "password").is_empty());
+        assert!(codes(&relaxed, "alice1").is_empty());
+    }
+
+    #[test]
+    fn test_password_policy_validation_errors() {
+        let policy = PasswordPolicy::default();
+
+        let errors = policy
+            .validate("new_password", "short", "bob", "bob@example.com")
+            .unwrap_err();
+        let field_errors = errors.field_errors();
+        let password_errors = field_errors.get("new_password").unwrap();
+
+        let too_short = password_errors
+            .iter()
+            .find(|e| e.code == "password_too_short")
+            .unwrap();
+        assert_eq!(too_short.params.get("min").unwrap(), 8);
+        assert!(too_short.message.is_some());
+    }
+
+    #[test]
+    fn test_password_expiry_business_rules() {
+        let now = Utc::now();
+
+        // 默认不过期
+        let policy = PasswordPolicy::default();
+        assert!(!policy.is_expired(&(now - Duration::days(3650)), &now));
+
+        let policy = PasswordPolicy {
+            max_age_days: 90,
+            ..PasswordPolicy::default()
+        };
+        assert!(!policy.is_expired(&(now - Duration::days(89)), &now));
+        assert!(policy.is_expired(&(now - Duration::days(90)), &now));
+    }
+}
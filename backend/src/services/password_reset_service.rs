@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+use std::env;
+use thiserror::Error;
+
+use crate::mail::{app_link, send_in_background, MailMessage};
+use crate::models::{password_reset_token, user};
+use crate::services::{
+    EmailTokenService, LockoutService, PasswordPolicy, PasswordPolicyError, PasswordPolicyService, RevocationService,
+};
+
+#[derive(Error, Debug)]
+pub enum PasswordResetError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error(transparent)]
+    PasswordPolicy(#[from] PasswordPolicyError),
+    #[error("重置链接无效或已过期")]
+    InvalidToken,
+}
+
+pub struct PasswordResetService;
+
+impl PasswordResetService {
+    /// 重置令牌有效期（秒）
+    pub fn reset_token_ttl() -> i64 {
+        env::var("PASSWORD_RESET_TOKEN_EXPIRATION")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(3600)
+    }
+
+    /// 发起找回密码：为邮箱对应的用户生成重置令牌并发送邮件
+    ///
+    /// 邮箱不存在、账户被禁用或请求过于频繁时同样返回成功，不向调用方暴露邮箱是否注册。
+    pub async fn request_reset(db: &DatabaseConnection, email: &str) -> Result<(), PasswordResetError> {
+        let user = user::Entity::find()
+            .filter(user::Column::Email.eq(email))
+            .filter(user::Column::IsActive.eq(true))
+            .filter(user::Column::IsServiceAccount.eq(false))
+            .one(db)
+            .await?;
+
+        let Some(user) = user else {
+            return Ok(());
+        };
+
+        let issued = EmailTokenService::issue::<password_reset_token::ActiveModel>(
+            db,
+            user.id,
+            Vec::new(),
+            Self::reset_token_ttl(),
+        )
+        .await?;
+
+        match issued {
+            Some(issued) => send_in_background(Self::reset_message(&user, &issued.token, issued.expires_at)),
+            None => tracing::info!("用户 {} 的重置密码请求过于频繁，已忽略", user.username),
+        }
+
+        Ok(())
+    }
+
+    /// 使用重置令牌设置新密码，成功后撤销该用户的全部令牌和会话
+    pub async fn reset_password(
+        db: &DatabaseConnection,
+        token: &str,
+        new_password: &str,
+    ) -> Result<user::Model, PasswordResetError> {
+        let record = EmailTokenService::find_usable::<password_reset_token::Entity>(db, token)
+            .await?
+            .ok_or(PasswordResetError::InvalidToken)?;
+
+        let user = user::Entity::find_by_id(record.user_id)
+            .one(db)
+            .await?
+            .filter(|u| u.is_active)
+            .ok_or(PasswordResetError::InvalidToken)?;
+
+        // 先检查密码策略，不符合时令牌仍可继续使用
+        let policy = PasswordPolicy::from_env();
+        PasswordPolicyService::validate_new_password(
+            db,
+            &policy,
+            "new_password",
+            new_password,
+            &user.username,
+            &user.email,
+            Some(&user),
+        )
+        .await?;
+
+        let txn = db.begin().await?;
+
+        if !EmailTokenService::claim::<password_reset_token::Entity, _>(&txn, token).await? {
+            return Err(PasswordResetError::InvalidToken);
+        }
+
+        let user = PasswordPolicyService::set_password(&txn, &policy, user, new_password).await?;
+
+        txn.commit().await?;
+
+        RevocationService::revoke_all_for_user(db, user.id, "password_reset").await?;
+        LockoutService::record_success(db, &user.username).await?;
+
+        Ok(user)
+    }
+
+    fn reset_message(user: &user::Model, token: &str, expires_at: DateTime<Utc>) -> MailMessage {
+        MailMessage {
+            to: user.email.clone(),
+            subject: "重置密码".to_string(),
+            body: format!(
+                "{}，您好：\n\n我们收到了重置您账户密码的请求。请在 {} 之前打开以下链接设置新密码：\n\n{}\n\n如果这不是您本人的操作，请忽略此邮件，您的密码不会被修改。\n",
+                user.username,
+                expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                app_link("/reset-password", token)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_message_contains_link() {
+        let now = Utc::now();
+        let user = user::Model::for_test(1, "alice");
+
+        let message = PasswordResetService::reset_message(&user, "abc123", now);
+        assert_eq!(message.to, "alice@example.com");
+        assert!(message.body.contains("/reset-password?token=abc123"));
+    }
+}
@@ -0,0 +1,412 @@
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use std::env;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::models::{registration_code, CreateRegistrationCodeDto};
+use crate::rbac::{RbacError, RbacService};
+
+#[derive(Error, Debug)]
+pub enum RegistrationError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("注册功能已关闭")]
+    Disabled,
+    #[error("注册需要邀请码")]
+    InvitationCodeRequired,
+    #[error("邀请码无效或已过期")]
+    InvalidInvitationCode,
+    #[error("该邮箱域名不允许注册")]
+    EmailDomainNotAllowed,
+    #[error("邀请码不存在")]
+    CodeNotFound,
+    #[error("注册配置无效: {0}")]
+    InvalidConfig(String),
+}
+
+/// 自助注册方式，通过 REGISTRATION_MODE 环境变量配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegistrationMode {
+    /// 关闭自助注册，只能由管理员创建用户
+    Disabled,
+    /// 任何人都可以注册（默认）
+    #[default]
+    Open,
+    /// 注册时必须提供有效的邀请码
+    InviteOnly,
+    /// 只允许指定域名的邮箱注册
+    DomainRestricted,
+}
+
+impl RegistrationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegistrationMode::Disabled => "disabled",
+            RegistrationMode::Open => "open",
+            RegistrationMode::InviteOnly => "invite_only",
+            RegistrationMode::DomainRestricted => "domain_restricted",
+        }
+    }
+
+    /// 解析配置值，不区分大小写，无法识别时返回 None
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "open" => Some(RegistrationMode::Open),
+            "disabled" => Some(RegistrationMode::Disabled),
+            "invite_only" => Some(RegistrationMode::InviteOnly),
+            "domain_restricted" => Some(RegistrationMode::DomainRestricted),
+            _ => None,
+        }
+    }
+}
+
+/// 注册策略
+#[derive(Debug, Clone, Default)]
+pub struct RegistrationPolicy {
+    pub mode: RegistrationMode,
+    /// DomainRestricted 模式下允许注册的邮箱域名（小写）
+    pub allowed_domains: Vec<String>,
+    /// 新注册用户自动获得的角色名称
+    pub default_role: Option<String>,
+}
+
+impl RegistrationPolicy {
+    /// 从环境变量读取注册策略
+    ///
+    /// REGISTRATION_MODE 可选 open（默认）、disabled、invite_only、domain_restricted；
+    /// REGISTRATION_ALLOWED_DOMAINS 为逗号分隔的域名列表；REGISTRATION_DEFAULT_ROLE 为角色名称。
+    /// 配置无法识别时返回错误，避免拼写错误导致意外开放注册。
+    pub fn from_env() -> Result<Self, RegistrationError> {
+        Self::from_values(
+            env::var("REGISTRATION_MODE").ok().as_deref(),
+            env::var("REGISTRATION_ALLOWED_DOMAINS").ok().as_deref(),
+            env::var("REGISTRATION_DEFAULT_ROLE").ok().as_deref(),
+        )
+    }
+
+    fn from_values(
+        mode: Option<&str>,
+        allowed_domains: Option<&str>,
+        default_role: Option<&str>,
+    ) -> Result<Self, RegistrationError> {
+        // 未配置或留空时使用默认的 open
+        let mode = match mode.map(str::trim).filter(|v| !v.is_empty()) {
+            None => RegistrationMode::default(),
+            Some(value) => RegistrationMode::parse(value).ok_or_else(|| {
+                RegistrationError::InvalidConfig(format!(
+                    "REGISTRATION_MODE 取值 {} 无效，可选 open、disabled、invite_only、domain_restricted",
+                    value
+                ))
+            })?,
+        };
+
+        let allowed_domains = allowed_domains.map(Self::parse_domains).unwrap_or_default();
+        if mode == RegistrationMode::DomainRestricted && allowed_domains.is_empty() {
+            return Err(RegistrationError::InvalidConfig(
+                "domain_restricted 模式需要配置 REGISTRATION_ALLOWED_DOMAINS".to_string(),
+            ));
+        }
+
+        let default_role = default_role
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+
+        Ok(Self {
+            mode,
+            allowed_domains,
+            default_role,
+        })
+    }
+
+    fn parse_domains(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(|d| d.trim().trim_start_matches('@').to_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect()
+    }
+
+    /// 邮箱域名是否在允许列表中
+    pub fn is_email_allowed(&self, email: &str) -> bool {
+        let Some((_, domain)) = email.rsplit_once('@') else {
+            return false;
+        };
+        let domain = domain.to_lowercase();
+        self.allowed_domains.contains(&domain)
+    }
+
+    /// 检查不依赖数据库的注册条件
+    pub fn check(&self, email: &str, invitation_code: Option<&str>) -> Result<(), RegistrationError> {
+        match self.mode {
+            RegistrationMode::Disabled => Err(RegistrationError::Disabled),
+            RegistrationMode::Open => Ok(()),
+            RegistrationMode::InviteOnly => match invitation_code.map(str::trim) {
+                Some(code) if !code.is_empty() => Ok(()),
+                _ => Err(RegistrationError::InvitationCodeRequired),
+            },
+            RegistrationMode::DomainRestricted => {
+                if self.is_email_allowed(email) {
+                    Ok(())
+                } else {
+                    Err(RegistrationError::EmailDomainNotAllowed)
+                }
+            }
+        }
+    }
+}
+
+pub struct RegistrationService;
+
+impl RegistrationService {
+    /// 查找可用的邀请码（不消耗使用次数），不区分大小写
+    pub async fn find_usable_code(
+        db: &DatabaseConnection,
+        code: &str,
+    ) -> Result<registration_code::Model, RegistrationError> {
+        let record = registration_code::Entity::find()
+            .filter(registration_code::Column::CodeHash.eq(AuthService::hash_token(&code.trim().to_uppercase())))
+            .one(db)
+            .await?
+            .ok_or(RegistrationError::InvalidInvitationCode)?;
+
+        if !Self::is_usable(&record, &Utc::now()) {
+            return Err(RegistrationError::InvalidInvitationCode);
+        }
+
+        Ok(record)
+    }
+
+    /// 消耗一次邀请码，与创建用户在同一事务中执行
+    pub async fn claim_code<C: ConnectionTrait>(
+        db: &C,
+        code_id: i32,
+    ) -> Result<(), RegistrationError> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+
+        // 条件更新保证并发注册时不会超出使用次数
+        let claimed = registration_code::Entity::update_many()
+            .col_expr(
+                registration_code::Column::UsedCount,
+                Expr::col(registration_code::Column::UsedCount).add(1),
+            )
+            .filter(registration_code::Column::Id.eq(code_id))
+            .filter(registration_code::Column::RevokedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(registration_code::Column::ExpiresAt.is_null())
+                    .add(registration_code::Column::ExpiresAt.gt(now)),
+            )
+            .filter(
+                Condition::any()
+                    .add(registration_code::Column::MaxUses.is_null())
+                    .add(
+                        Expr::col(registration_code::Column::UsedCount)
+                            .lt(Expr::col(registration_code::Column::MaxUses)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+
+        if claimed.rows_affected == 0 {
+            return Err(RegistrationError::InvalidInvitationCode);
+        }
+
+        Ok(())
+    }
+
+    /// 为新注册的用户分配默认角色，角色不存在或已禁用时只记录警告
+    pub async fn assign_default_role(
+        db: &DatabaseConnection,
+        policy: &RegistrationPolicy,
+        user_id: i32,
+    ) -> Result<(), RbacError> {
+        let Some(role_name) = &policy.default_role else {
+            return Ok(());
+        };
+
+        if !RbacService::assign_role_by_name(db, user_id, role_name).await? {
+            tracing::warn!("注册默认角色 {} 不存在或已禁用，未分配角色", role_name);
+        }
+
+        Ok(())
+    }
+
+    /// 创建邀请码，返回记录和邀请码明文（明文只在此时返回一次）
+    pub async fn create_code(
+        db: &DatabaseConnection,
+        dto: CreateRegistrationCodeDto,
+        created_by: i32,
+    ) -> Result<(registration_code::Model, String), RegistrationError> {
+        let code = Self::generate_code();
+
+        let record = registration_code::ActiveModel {
+            code_hash: Set(AuthService::hash_token(&code)),
+            description: Set(dto.description),
+            max_uses: Set(dto.max_uses),
+            used_count: Set(0),
+            expires_at: Set(dto.expires_at.map(Into::into)),
+            created_by: Set(Some(created_by)),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        };
+        let record = record.insert(db).await?;
+
+        Ok((record, code))
+    }
+
+    pub async fn list_codes(db: &DatabaseConnection) -> Result<Vec<registration_code::Model>, RegistrationError> {
+        let codes = registration_code::Entity::find()
+            .order_by_desc(registration_code::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        Ok(codes)
+    }
+
+    /// 作废邀请码，已使用该邀请码注册的用户不受影响
+    pub async fn revoke_code(db: &DatabaseConnection, id: i32) -> Result<(), RegistrationError> {
+        let record = registration_code::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(RegistrationError::CodeNotFound)?;
+
+        if record.revoked_at.is_none() {
+            let mut active: registration_code::ActiveModel = record.into();
+            active.revoked_at = Set(Some(Utc::now().into()));
+            active.update(db).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 邀请码是否仍可使用：未作废、未过期且未达到使用次数上限
+    pub fn is_usable(record: &registration_code::Model, now: &DateTime<Utc>) -> bool {
+        record.revoked_at.is_none()
+            && record
+                .expires_at
+                .is_none_or(|expires_at| expires_at.with_timezone(&Utc) > *now)
+            && record.max_uses.is_none_or(|max| record.used_count < max)
+    }
+
+    /// 生成便于手动输入的邀请码：16个大写字母和数字，每4位以短横线分隔
+    fn generate_code() -> String {
+        let raw = AuthService::generate_refresh_token().to_uppercase();
+        raw.as_bytes()[..16]
+            .chunks(4)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn policy(mode: RegistrationMode) -> RegistrationPolicy {
+        RegistrationPolicy {
+            mode,
+            allowed_domains: RegistrationPolicy::parse_domains("example.com, @Corp.Example.org ,"),
+            default_role: None,
+        }
+    }
+
+    fn code_record(
+        max_uses: Option<i32>,
+        used_count: i32,
+        expires_at: Option<DateTime<Utc>>,
+        revoked_at: Option<DateTime<Utc>>,
+    ) -> registration_code::Model {
+        registration_code::Model {
+            id: 1,
+            code_hash: AuthService::hash_token("code"),
+            description: None,
+            max_uses,
+            used_count,
+            expires_at: expires_at.map(Into::into),
+            revoked_at: revoked_at.map(Into::into),
+            created_by: Some(1),
+            created_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_registration_mode_business_rules() {
+        assert!(policy(RegistrationMode::Open).check("a@other.com", None).is_ok());
+        assert!(matches!(
+            policy(RegistrationMode::Disabled).check("a@example.com", Some("CODE")),
+            Err(RegistrationError::Disabled)
+        ));
+
+        // 邀请制：必须提供邀请码，有效性在查询数据库时检查
+        let invite_only = policy(RegistrationMode::InviteOnly);
+        assert!(invite_only.check("a@other.com", Some("ABCD-EFGH")).is_ok());
+        assert!(matches!(invite_only.check("a@other.com", None), Err(RegistrationError::InvitationCodeRequired)));
+        assert!(matches!(invite_only.check("a@other.com", Some("  ")), Err(RegistrationError::InvitationCodeRequired)));
+
+        // 域名限制：大小写不敏感，不匹配子域名
+        let restricted = policy(RegistrationMode::DomainRestricted);
+        assert!(restricted.check("a@EXAMPLE.com", None).is_ok());
+        assert!(restricted.check("a@corp.example.org", None).is_ok());
+        assert!(matches!(restricted.check("a@evil-example.com", None), Err(RegistrationError::EmailDomainNotAllowed)));
+        assert!(matches!(restricted.check("a@sub.example.com", None), Err(RegistrationError::EmailDomainNotAllowed)));
+        assert!(matches!(restricted.check("no-at-sign", None), Err(RegistrationError::EmailDomainNotAllowed)));
+    }
+
+    #[test]
+    fn test_registration_policy_config() {
+        let mode = |value: Option<&str>| RegistrationPolicy::from_values(value, Some("example.com"), None).map(|p| p.mode);
+
+        // 未配置或留空时默认开放注册
+        assert_eq!(mode(None).unwrap(), RegistrationMode::Open);
+        assert_eq!(mode(Some(" ")).unwrap(), RegistrationMode::Open);
+        // 不区分大小写
+        assert_eq!(mode(Some("Disabled")).unwrap(), RegistrationMode::Disabled);
+        assert_eq!(mode(Some("INVITE_ONLY")).unwrap(), RegistrationMode::InviteOnly);
+        assert_eq!(mode(Some("domain_restricted")).unwrap(), RegistrationMode::DomainRestricted);
+
+        // 拼写错误不能被当作开放注册
+        assert!(matches!(mode(Some("invite-only")), Err(RegistrationError::InvalidConfig(_))));
+        assert!(matches!(mode(Some("closed")), Err(RegistrationError::InvalidConfig(_))));
+
+        // 域名限制模式必须配置允许的域名
+        assert!(matches!(
+            RegistrationPolicy::from_values(Some("domain_restricted"), None, None),
+            Err(RegistrationError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            RegistrationPolicy::from_values(Some("domain_restricted"), Some(" , "), None),
+            Err(RegistrationError::InvalidConfig(_))
+        ));
+        assert!(RegistrationPolicy::from_values(Some("open"), None, None).is_ok());
+    }
+
+    #[test]
+    fn test_registration_code_usable_business_rules() {
+        let now = Utc::now();
+
+        // 不限次数、永不过期
+        assert!(RegistrationService::is_usable(&code_record(None, 100, None, None), &now));
+        // 未达到使用次数上限
+        assert!(RegistrationService::is_usable(&code_record(Some(2), 1, Some(now + Duration::days(1)), None), &now));
+        // 已达到使用次数上限
+        assert!(!RegistrationService::is_usable(&code_record(Some(2), 2, None, None), &now));
+        // 已过期
+        assert!(!RegistrationService::is_usable(&code_record(None, 0, Some(now - Duration::seconds(1)), None), &now));
+        // 已作废
+        assert!(!RegistrationService::is_usable(&code_record(None, 0, None, Some(now)), &now));
+    }
+
+    #[test]
+    fn test_generate_code_format() {
+        let code = RegistrationService::generate_code();
+        assert_eq!(code.len(), 19);
+        assert_eq!(code.split('-').count(), 4);
+        assert!(code.chars().all(|c| c == '-' || c.is_ascii_digit() || c.is_ascii_uppercase()));
+        assert_ne!(code, RegistrationService::generate_code());
+    }
+}
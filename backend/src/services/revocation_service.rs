@@ -0,0 +1,147 @@
+use chrono::{DateTime, SubsecRound, TimeZone, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::*;
+
+use crate::auth::Claims;
+use crate::models::{refresh_token, revoked_token, user_token_revocation};
+use crate::services::SessionService;
+
+pub struct RevocationService;
+
+impl RevocationService {
+    /// 撤销单个访问令牌（按jti记录，直到令牌原本的过期时间）
+    pub async fn revoke_token(
+        db: &DatabaseConnection,
+        claims: &Claims,
+        reason: &str,
+    ) -> Result<(), DbErr> {
+        let expires_at = Utc
+            .timestamp_opt(claims.exp as i64, 0)
+            .single()
+            .unwrap_or_else(Utc::now);
+
+        let record = revoked_token::ActiveModel {
+            jti: Set(claims.jti.clone()),
+            user_id: Set(claims.sub),
+            reason: Set(reason.to_string()),
+            expires_at: Set(expires_at.into()),
+            ..Default::default()
+        };
+
+        revoked_token::Entity::insert(record)
+            .on_conflict(
+                OnConflict::column(revoked_token::Column::Jti)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 撤销用户的所有令牌：已签发的访问令牌和全部刷新令牌
+    pub async fn revoke_all_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+        reason: &str,
+    ) -> Result<(), DbErr> {
+        let now: DateTimeWithTimeZone = Utc::now().into();
+        // JWT的iat精确到秒，撤销时间同样取整到秒
+        let revoked_before = now.trunc_subsecs(0);
+
+        let record = user_token_revocation::ActiveModel {
+            user_id: Set(user_id),
+            revoked_before: Set(revoked_before),
+            reason: Set(reason.to_string()),
+            updated_at: Set(now),
+        };
+
+        user_token_revocation::Entity::insert(record)
+            .on_conflict(
+                OnConflict::column(user_token_revocation::Column::UserId)
+                    .update_columns([
+                        user_token_revocation::Column::RevokedBefore,
+                        user_token_revocation::Column::Reason,
+                        user_token_revocation::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+
+        refresh_token::Entity::update_many()
+            .col_expr(refresh_token::Column::RevokedAt, Expr::value(Some(now)))
+            .filter(refresh_token::Column::UserId.eq(user_id))
+            .filter(refresh_token::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+
+        SessionService::revoke_all_sessions(db, user_id, reason).await?;
+
+        Ok(())
+    }
+
+    /// 检查访问令牌是否已被撤销
+    pub async fn is_revoked(db: &DatabaseConnection, claims: &Claims) -> Result<bool, DbErr> {
+        let revoked = revoked_token::Entity::find()
+            .filter(revoked_token::Column::Jti.eq(&claims.jti))
+            .one(db)
+            .await?;
+
+        if revoked.is_some() {
+            return Ok(true);
+        }
+
+        // 模拟登录令牌在被模拟的用户或实际操作的管理员被强制下线、禁用后都会失效
+        let mut user_ids = vec![claims.sub];
+        if let Some(actor) = &claims.act {
+            user_ids.push(actor.sub);
+        }
+
+        let user_revocations = user_token_revocation::Entity::find()
+            .filter(user_token_revocation::Column::UserId.is_in(user_ids))
+            .all(db)
+            .await?;
+
+        Ok(user_revocations
+            .iter()
+            .any(|r| Self::issued_before(claims.iat, &r.revoked_before.with_timezone(&Utc))))
+    }
+
+    /// 清理已过期的撤销记录，返回删除的条数
+    pub async fn purge_expired(db: &DatabaseConnection) -> Result<u64, DbErr> {
+        let result = revoked_token::Entity::delete_many()
+            .filter(revoked_token::Column::ExpiresAt.lt(Utc::now()))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// 令牌签发时间是否早于撤销时间
+    ///
+    /// JWT的iat精确到秒，撤销时间按秒取整后比较：与撤销发生在同一秒内签发的令牌视为有效，
+    /// 否则撤销后立即重新登录（例如重置密码后）拿到的新令牌会被误判为已撤销。
+    fn issued_before(iat: u64, revoked_before: &DateTime<Utc>) -> bool {
+        (iat as i64) < revoked_before.timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_revocation_cutoff_business_rules() {
+        let revoked_before = Utc.timestamp_opt(1_700_000_000, 500_000_000).unwrap();
+
+        // 撤销之前签发的令牌失效
+        assert!(RevocationService::issued_before(1_699_999_000, &revoked_before));
+        // 同一秒内签发的令牌视为撤销之后签发（例如重置密码后立即登录），仍然有效
+        assert!(!RevocationService::issued_before(1_700_000_000, &revoked_before));
+        // 撤销之后重新登录签发的令牌有效
+        assert!(!RevocationService::issued_before(1_700_000_001, &revoked_before));
+    }
+}
@@ -0,0 +1,246 @@
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use thiserror::Error;
+
+use crate::auth::AuthService;
+use crate::models::{service_account_secret, user, CreateServiceAccountDto, CreateServiceAccountSecretDto};
+
+/// 客户端密钥的固定前缀，便于识别和密钥扫描
+pub const CLIENT_SECRET_PREFIX: &str = "sas_";
+
+/// 列表中显示的密钥开头长度（包含前缀）
+const SECRET_PREFIX_DISPLAY_LEN: usize = 12;
+
+/// 服务账户占位邮箱的域名，.invalid 是保留的顶级域名，邮件不会被投递
+const SERVICE_ACCOUNT_EMAIL_DOMAIN: &str = "service-accounts.invalid";
+
+#[derive(Error, Debug)]
+pub enum ServiceAccountError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("用户名已存在")]
+    UsernameTaken,
+    #[error("服务账户不存在")]
+    NotFound,
+    #[error("客户端密钥不存在")]
+    SecretNotFound,
+    #[error("过期时间必须晚于当前时间")]
+    InvalidExpiry,
+    #[error("客户端凭据无效")]
+    InvalidClient,
+}
+
+pub struct ServiceAccountService;
+
+impl ServiceAccountService {
+    /// 创建服务账户：没有密码、邮箱为占位地址，角色通过 user_roles 分配
+    pub async fn create(db: &DatabaseConnection, dto: CreateServiceAccountDto) -> Result<user::Model, ServiceAccountError> {
+        let email = Self::placeholder_email(&dto.username);
+
+        let existing = user::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(user::Column::Username.eq(&dto.username))
+                    .add(user::Column::Email.eq(&email)),
+            )
+            .one(db)
+            .await?;
+
+        if existing.is_some() {
+            return Err(ServiceAccountError::UsernameTaken);
+        }
+
+        let now: DateTimeWithTimeZone = Utc::now().into();
+        let account = user::ActiveModel {
+            username: Set(dto.username),
+            email: Set(email),
+            password_hash: Set(String::new()),
+            is_active: Set(true),
+            must_change_password: Set(false),
+            email_verified_at: Set(Some(now)),
+            is_service_account: Set(true),
+            ..Default::default()
+        };
+
+        Ok(account.insert(db).await?)
+    }
+
+    pub async fn list(db: &DatabaseConnection) -> Result<Vec<user::Model>, ServiceAccountError> {
+        let accounts = user::Entity::find()
+            .filter(user::Column::IsServiceAccount.eq(true))
+            .order_by_asc(user::Column::Id)
+            .all(db)
+            .await?;
+
+        Ok(accounts)
+    }
+
+    pub async fn find(db: &DatabaseConnection, id: i32) -> Result<user::Model, ServiceAccountError> {
+        user::Entity::find_by_id(id)
+            .filter(user::Column::IsServiceAccount.eq(true))
+            .one(db)
+            .await?
+            .ok_or(ServiceAccountError::NotFound)
+    }
+
+    /// 为服务账户创建客户端密钥，返回密钥记录和明文密钥（明文只在创建时返回一次）
+    pub async fn create_secret(
+        db: &DatabaseConnection,
+        account: &user::Model,
+        dto: CreateServiceAccountSecretDto,
+    ) -> Result<(service_account_secret::Model, String), ServiceAccountError> {
+        let now = Utc::now();
+
+        if dto.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(ServiceAccountError::InvalidExpiry);
+        }
+
+        let secret = Self::generate_secret();
+        let record = service_account_secret::ActiveModel {
+            user_id: Set(account.id),
+            description: Set(dto.description),
+            secret_prefix: Set(secret[..SECRET_PREFIX_DISPLAY_LEN].to_string()),
+            secret_hash: Set(AuthService::hash_token(&secret)),
+            expires_at: Set(dto.expires_at.map(Into::into)),
+            last_used_at: Set(None),
+            revoked_at: Set(None),
+            created_at: Set(now.into()),
+            ..Default::default()
+        };
+
+        let record = record.insert(db).await?;
+        Ok((record, secret))
+    }
+
+    pub async fn list_secrets(
+        db: &DatabaseConnection,
+        account_id: i32,
+    ) -> Result<Vec<service_account_secret::Model>, ServiceAccountError> {
+        let secrets = service_account_secret::Entity::find()
+            .filter(service_account_secret::Column::UserId.eq(account_id))
+            .order_by_desc(service_account_secret::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        Ok(secrets)
+    }
+
+    /// 撤销客户端密钥，已签发的访问令牌在过期前仍然有效
+    pub async fn revoke_secret(db: &DatabaseConnection, account_id: i32, secret_id: i32) -> Result<(), ServiceAccountError> {
+        let record = service_account_secret::Entity::find_by_id(secret_id)
+            .filter(service_account_secret::Column::UserId.eq(account_id))
+            .one(db)
+            .await?
+            .ok_or(ServiceAccountError::SecretNotFound)?;
+
+        if record.revoked_at.is_none() {
+            let mut active_record: service_account_secret::ActiveModel = record.into();
+            active_record.revoked_at = Set(Some(Utc::now().into()));
+            active_record.update(db).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 校验客户端凭据，client_id 为服务账户的用户名
+    pub async fn authenticate_client(
+        db: &DatabaseConnection,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<user::Model, ServiceAccountError> {
+        let now = Utc::now();
+
+        let (record, account) = service_account_secret::Entity::find()
+            .filter(service_account_secret::Column::SecretHash.eq(AuthService::hash_token(client_secret)))
+            .find_also_related(user::Entity)
+            .one(db)
+            .await?
+            .ok_or(ServiceAccountError::InvalidClient)?;
+
+        let account = account
+            .filter(|u| u.is_service_account && u.is_active && u.username == client_id)
+            .ok_or(ServiceAccountError::InvalidClient)?;
+
+        if !Self::is_usable(&record, &now) {
+            return Err(ServiceAccountError::InvalidClient);
+        }
+
+        service_account_secret::Entity::update_many()
+            .col_expr(
+                service_account_secret::Column::LastUsedAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(now))),
+            )
+            .filter(service_account_secret::Column::Id.eq(record.id))
+            .exec(db)
+            .await?;
+
+        Ok(account)
+    }
+
+    /// 密钥是否仍可使用：未撤销且未过期
+    fn is_usable(record: &service_account_secret::Model, now: &DateTime<Utc>) -> bool {
+        record.revoked_at.is_none()
+            && record
+                .expires_at
+                .is_none_or(|expires_at| expires_at.with_timezone(&Utc) > *now)
+    }
+
+    fn placeholder_email(username: &str) -> String {
+        format!("{}@{}", username.to_lowercase(), SERVICE_ACCOUNT_EMAIL_DOMAIN)
+    }
+
+    fn generate_secret() -> String {
+        format!("{}{}", CLIENT_SECRET_PREFIX, AuthService::generate_refresh_token())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn secret_record(expires_at: Option<DateTime<Utc>>, revoked_at: Option<DateTime<Utc>>) -> service_account_secret::Model {
+        service_account_secret::Model {
+            id: 1,
+            user_id: 1,
+            description: None,
+            secret_prefix: "sas_12345678".to_string(),
+            secret_hash: AuthService::hash_token("sas_secret"),
+            expires_at: expires_at.map(Into::into),
+            last_used_at: None,
+            revoked_at: revoked_at.map(Into::into),
+            created_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_client_secret_usable_business_rules() {
+        let now = Utc::now();
+
+        assert!(ServiceAccountService::is_usable(&secret_record(None, None), &now));
+        assert!(ServiceAccountService::is_usable(&secret_record(Some(now + Duration::days(1)), None), &now));
+        // 已过期
+        assert!(!ServiceAccountService::is_usable(&secret_record(Some(now - Duration::seconds(1)), None), &now));
+        // 已撤销
+        assert!(!ServiceAccountService::is_usable(&secret_record(None, Some(now)), &now));
+    }
+
+    #[test]
+    fn test_generate_secret_format() {
+        let secret = ServiceAccountService::generate_secret();
+
+        assert!(secret.starts_with(CLIENT_SECRET_PREFIX));
+        assert_eq!(secret.len(), CLIENT_SECRET_PREFIX.len() + 64);
+        assert_ne!(secret, ServiceAccountService::generate_secret());
+    }
+
+    #[test]
+    fn test_placeholder_email_is_not_deliverable() {
+        let email = ServiceAccountService::placeholder_email("CI-Deployer");
+
+        assert_eq!(email, "ci-deployer@service-accounts.invalid");
+        assert!(email.ends_with(".invalid"));
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::extractors::ClientInfo;
+use crate::models::{refresh_token, user_session};
+
+/// 会话最近活跃时间的最小更新间隔（秒），避免每个请求都写数据库
+const LAST_SEEN_UPDATE_INTERVAL: i64 = 60;
+
+pub struct SessionService;
+
+impl SessionService {
+    /// 为一次成功登录创建会话
+    pub async fn create_session(
+        db: &DatabaseConnection,
+        user_id: i32,
+        client: &ClientInfo,
+        ttl: u64,
+    ) -> Result<user_session::Model, DbErr> {
+        let now = Utc::now();
+
+        let session = user_session::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            user_agent: Set(client.user_agent.clone()),
+            ip_address: Set(client.ip_address.clone()),
+            created_at: Set(now.into()),
+            last_seen_at: Set(now.into()),
+            expires_at: Set((now + Duration::seconds(ttl as i64)).into()),
+            ..Default::default()
+        };
+
+        session.insert(db).await
+    }
+
+    /// 延长会话有效期（刷新令牌轮换时调用）
+    pub async fn extend_session<C: ConnectionTrait>(
+        db: &C,
+        session_id: Uuid,
+        ttl: u64,
+    ) -> Result<(), DbErr> {
+        let now = Utc::now();
+
+        user_session::Entity::update_many()
+            .col_expr(
+                user_session::Column::ExpiresAt,
+                Expr::value(DateTimeWithTimeZone::from(now + Duration::seconds(ttl as i64))),
+            )
+            .col_expr(
+                user_session::Column::LastSeenAt,
+                Expr::value(DateTimeWithTimeZone::from(now)),
+            )
+            .filter(user_session::Column::Id.eq(session_id))
+            .filter(user_session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 校验会话仍然有效，并更新最近活跃时间
+    pub async fn touch_session(
+        db: &DatabaseConnection,
+        session_id: Uuid,
+        user_id: i32,
+    ) -> Result<bool, DbErr> {
+        let session = user_session::Entity::find_by_id(session_id).one(db).await?;
+
+        let session = match session {
+            Some(session) if Self::is_active(&session) && session.user_id == user_id => session,
+            _ => return Ok(false),
+        };
+
+        let now = Utc::now();
+        if now - session.last_seen_at.with_timezone(&Utc)
+            > Duration::seconds(LAST_SEEN_UPDATE_INTERVAL)
+        {
+            user_session::Entity::update_many()
+                .col_expr(
+                    user_session::Column::LastSeenAt,
+                    Expr::value(DateTimeWithTimeZone::from(now)),
+                )
+                .filter(user_session::Column::Id.eq(session_id))
+                .exec(db)
+                .await?;
+        }
+
+        Ok(true)
+    }
+
+    /// 获取用户的所有有效会话，按最近活跃时间倒序
+    pub async fn list_active_sessions(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<Vec<user_session::Model>, DbErr> {
+        user_session::Entity::find()
+            .filter(user_session::Column::UserId.eq(user_id))
+            .filter(user_session::Column::RevokedAt.is_null())
+            .filter(user_session::Column::ExpiresAt.gt(Utc::now()))
+            .order_by_desc(user_session::Column::LastSeenAt)
+            .all(db)
+            .await
+    }
+
+    /// 查找属于指定用户的会话
+    pub async fn find_user_session(
+        db: &DatabaseConnection,
+        user_id: i32,
+        session_id: Uuid,
+    ) -> Result<Option<user_session::Model>, DbErr> {
+        user_session::Entity::find_by_id(session_id)
+            .filter(user_session::Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 撤销会话及其刷新令牌族
+    pub async fn revoke_session(
+        db: &DatabaseConnection,
+        session_id: Uuid,
+        reason: &str,
+    ) -> Result<(), DbErr> {
+        let now = DateTimeWithTimeZone::from(Utc::now());
+
+        user_session::Entity::update_many()
+            .col_expr(user_session::Column::RevokedAt, Expr::value(Some(now)))
+            .col_expr(
+                user_session::Column::RevokedReason,
+                Expr::value(Some(reason.to_string())),
+            )
+            .filter(user_session::Column::Id.eq(session_id))
+            .filter(user_session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+
+        refresh_token::Entity::update_many()
+            .col_expr(refresh_token::Column::RevokedAt, Expr::value(Some(now)))
+            .filter(refresh_token::Column::FamilyId.eq(session_id))
+            .filter(refresh_token::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 撤销用户的所有会话
+    pub async fn revoke_all_sessions(
+        db: &DatabaseConnection,
+        user_id: i32,
+        reason: &str,
+    ) -> Result<(), DbErr> {
+        let now = DateTimeWithTimeZone::from(Utc::now());
+
+        user_session::Entity::update_many()
+            .col_expr(user_session::Column::RevokedAt, Expr::value(Some(now)))
+            .col_expr(
+                user_session::Column::RevokedReason,
+                Expr::value(Some(reason.to_string())),
+            )
+            .filter(user_session::Column::UserId.eq(user_id))
+            .filter(user_session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 撤销用户除指定会话外的所有会话及其刷新令牌，未指定时全部撤销
+    pub async fn revoke_other_sessions(
+        db: &DatabaseConnection,
+        user_id: i32,
+        keep_session_id: Option<Uuid>,
+        reason: &str,
+    ) -> Result<(), DbErr> {
+        let now = DateTimeWithTimeZone::from(Utc::now());
+
+        let mut sessions = user_session::Entity::update_many()
+            .col_expr(user_session::Column::RevokedAt, Expr::value(Some(now)))
+            .col_expr(
+                user_session::Column::RevokedReason,
+                Expr::value(Some(reason.to_string())),
+            )
+            .filter(user_session::Column::UserId.eq(user_id))
+            .filter(user_session::Column::RevokedAt.is_null());
+        let mut refresh_tokens = refresh_token::Entity::update_many()
+            .col_expr(refresh_token::Column::RevokedAt, Expr::value(Some(now)))
+            .filter(refresh_token::Column::UserId.eq(user_id))
+            .filter(refresh_token::Column::RevokedAt.is_null());
+
+        if let Some(keep_session_id) = keep_session_id {
+            sessions = sessions.filter(user_session::Column::Id.ne(keep_session_id));
+            refresh_tokens = refresh_tokens.filter(refresh_token::Column::FamilyId.ne(keep_session_id));
+        }
+
+        sessions.exec(db).await?;
+        refresh_tokens.exec(db).await?;
+
+        Ok(())
+    }
+
+    fn is_active(session: &user_session::Model) -> bool {
+        session.revoked_at.is_none() && session.expires_at > Utc::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_model(
+        expires_at: chrono::DateTime<Utc>,
+        revoked_at: Option<chrono::DateTime<Utc>>,
+    ) -> user_session::Model {
+        user_session::Model {
+            id: Uuid::new_v4(),
+            user_id: 1,
+            user_agent: None,
+            ip_address: None,
+            created_at: Utc::now().into(),
+            last_seen_at: Utc::now().into(),
+            expires_at: expires_at.into(),
+            revoked_at: revoked_at.map(Into::into),
+            revoked_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_session_activity_business_rules() {
+        let future = Utc::now() + Duration::hours(1);
+        let past = Utc::now() - Duration::hours(1);
+
+        // 未过期且未撤销的会话有效
+        assert!(SessionService::is_active(&session_model(future, None)));
+        // 已过期的会话无效
+        assert!(!SessionService::is_active(&session_model(past, None)));
+        // 已撤销的会话无效
+        assert!(!SessionService::is_active(&session_model(future, Some(Utc::now()))));
+    }
+}
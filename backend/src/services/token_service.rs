@@ -0,0 +1,216 @@
+use chrono::{Duration, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::auth::{AuthError, AuthResponse, AuthService};
+use crate::extractors::ClientInfo;
+use crate::models::{refresh_token, user};
+use crate::services::{EmailVerificationMode, SessionService};
+
+#[derive(Error, Debug)]
+pub enum TokenError {
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] DbErr),
+    #[error("{0}")]
+    AuthError(#[from] AuthError),
+    #[error("无效的刷新令牌")]
+    InvalidRefreshToken,
+    #[error("刷新令牌已过期")]
+    RefreshTokenExpired,
+    #[error("刷新令牌已被使用，该登录会话的所有令牌已被撤销")]
+    RefreshTokenReused,
+    #[error("用户不存在")]
+    UserNotFound,
+    #[error("账户已被禁用")]
+    UserDisabled,
+}
+
+impl TokenError {
+    /// 记录到登录日志中的失败原因
+    pub fn log_reason(&self) -> &'static str {
+        match self {
+            TokenError::InvalidRefreshToken => "invalid_refresh_token",
+            TokenError::RefreshTokenExpired => "refresh_token_expired",
+            TokenError::RefreshTokenReused => "refresh_token_reused",
+            TokenError::UserNotFound => "user_not_found",
+            TokenError::UserDisabled => "user_disabled",
+            TokenError::DatabaseError(_) | TokenError::AuthError(_) => "internal_error",
+        }
+    }
+}
+
+pub struct TokenService;
+
+impl TokenService {
+    /// 登录成功后创建会话并签发令牌，会话ID即新刷新令牌族的ID
+    pub async fn issue_tokens(
+        db: &DatabaseConnection,
+        user: &user::Model,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, TokenError> {
+        let session =
+            SessionService::create_session(db, user.id, client, AuthService::refresh_token_ttl())
+                .await?;
+
+        Self::issue_in_family(db, user, session.id, None).await
+    }
+
+    /// 使用刷新令牌换取新的令牌对
+    ///
+    /// 每个刷新令牌只能使用一次；已使用的令牌被再次提交时，
+    /// 视为令牌被盗用，整个令牌族都会被撤销。
+    pub async fn rotate_refresh_token(
+        db: &DatabaseConnection,
+        raw_token: &str,
+    ) -> Result<(user::Model, AuthResponse), TokenError> {
+        let token_hash = AuthService::hash_token(raw_token);
+
+        let stored = refresh_token::Entity::find()
+            .filter(refresh_token::Column::TokenHash.eq(&token_hash))
+            .one(db)
+            .await?
+            .ok_or(TokenError::InvalidRefreshToken)?;
+
+        if stored.revoked_at.is_some() {
+            return Err(TokenError::InvalidRefreshToken);
+        }
+
+        if stored.used_at.is_some() {
+            SessionService::revoke_session(db, stored.family_id, "refresh_token_reused").await?;
+            tracing::warn!(
+                "检测到刷新令牌重放，已撤销令牌族 {} (用户ID: {})",
+                stored.family_id,
+                stored.user_id
+            );
+            return Err(TokenError::RefreshTokenReused);
+        }
+
+        if stored.expires_at < Utc::now() {
+            return Err(TokenError::RefreshTokenExpired);
+        }
+
+        // 标记旧令牌、延长会话和写入新令牌在同一事务中完成，
+        // 任何一步失败都会回滚，客户端用同一刷新令牌重试时不会被误判为重放
+        let txn = db.begin().await?;
+
+        // 条件更新保证并发请求中只有一个能够成功轮换
+        let result = refresh_token::Entity::update_many()
+            .col_expr(
+                refresh_token::Column::UsedAt,
+                Expr::value(Some(DateTimeWithTimeZone::from(Utc::now()))),
+            )
+            .filter(refresh_token::Column::Id.eq(stored.id))
+            .filter(refresh_token::Column::UsedAt.is_null())
+            .exec(&txn)
+            .await?;
+
+        if result.rows_affected == 0 {
+            txn.rollback().await?;
+            SessionService::revoke_session(db, stored.family_id, "refresh_token_reused").await?;
+            return Err(TokenError::RefreshTokenReused);
+        }
+
+        let user = user::Entity::find_by_id(stored.user_id)
+            .one(&txn)
+            .await?
+            .ok_or(TokenError::UserNotFound)?;
+
+        if !user.is_active {
+            txn.rollback().await?;
+            SessionService::revoke_session(db, stored.family_id, "user_disabled").await?;
+            return Err(TokenError::UserDisabled);
+        }
+
+        SessionService::extend_session(&txn, stored.family_id, AuthService::refresh_token_ttl())
+            .await?;
+
+        let auth_response =
+            Self::issue_in_family(&txn, &user, stored.family_id, Some(stored.id)).await?;
+
+        txn.commit().await?;
+
+        Ok((user, auth_response))
+    }
+
+    /// 查找刷新令牌所属的用户（无论令牌是否仍然有效）
+    pub async fn find_refresh_token_owner(
+        db: &DatabaseConnection,
+        raw_token: &str,
+    ) -> Result<Option<user::Model>, DbErr> {
+        let owner = refresh_token::Entity::find()
+            .filter(refresh_token::Column::TokenHash.eq(AuthService::hash_token(raw_token)))
+            .find_also_related(user::Entity)
+            .one(db)
+            .await?;
+
+        Ok(owner.and_then(|(_, user)| user))
+    }
+
+    async fn issue_in_family<C: ConnectionTrait>(
+        db: &C,
+        user: &user::Model,
+        family_id: Uuid,
+        parent_id: Option<i32>,
+    ) -> Result<AuthResponse, TokenError> {
+        // 邮箱验证状态在每次签发时重新判断，验证完成后刷新令牌即可拿到正式访问令牌
+        let token_use = EmailVerificationMode::from_env().token_use_for(user);
+        let access_token =
+            AuthService::generate_session_token(user.id, &user.username, family_id, token_use)?;
+        let refresh_token = AuthService::generate_refresh_token();
+        let refresh_ttl = AuthService::refresh_token_ttl();
+
+        let record = refresh_token::ActiveModel {
+            user_id: Set(user.id),
+            token_hash: Set(AuthService::hash_token(&refresh_token)),
+            family_id: Set(family_id),
+            parent_id: Set(parent_id),
+            expires_at: Set((Utc::now() + Duration::seconds(refresh_ttl as i64)).into()),
+            ..Default::default()
+        };
+        record.insert(db).await?;
+
+        Ok(AuthResponse {
+            token: access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: AuthService::access_token_ttl(),
+            refresh_token,
+            refresh_expires_in: refresh_ttl,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_error_business_context() {
+        let error_scenarios = vec![
+            (TokenError::InvalidRefreshToken, "无效的刷新令牌"),
+            (TokenError::RefreshTokenExpired, "刷新令牌已过期"),
+            (TokenError::RefreshTokenReused, "已被撤销"),
+            (TokenError::UserDisabled, "账户已被禁用"),
+        ];
+
+        for (error, expected_context) in error_scenarios {
+            let error_message = format!("{}", error);
+            assert!(error_message.contains(expected_context),
+                "错误消息应该包含业务上下文: '{}'", expected_context);
+        }
+    }
+
+    #[test]
+    fn test_token_error_log_reasons() {
+        assert_eq!(TokenError::InvalidRefreshToken.log_reason(), "invalid_refresh_token");
+        assert_eq!(TokenError::RefreshTokenExpired.log_reason(), "refresh_token_expired");
+        assert_eq!(TokenError::RefreshTokenReused.log_reason(), "refresh_token_reused");
+        assert_eq!(TokenError::UserDisabled.log_reason(), "user_disabled");
+        assert_eq!(
+            TokenError::DatabaseError(DbErr::Custom("连接失败".to_string())).log_reason(),
+            "internal_error"
+        );
+    }
+}
@@ -23,7 +23,6 @@ mod tests {
         // 测试UserService结构体创建
         let _service = UserService;
         // 如果编译通过，说明结构体创建成功
-        assert!(true);
     }
 
     #[test]
@@ -37,6 +36,11 @@ mod tests {
             is_active: true,
             created_at: chrono::Utc::now().into(),
             updated_at: chrono::Utc::now().into(),
+            password_changed_at: chrono::Utc::now().into(),
+            must_change_password: false,
+            email_verified_at: None,
+            registration_code_id: None,
+            is_service_account: false,
         };
 
         assert_eq!(user.id, 1);
@@ -56,13 +60,18 @@ mod tests {
             is_active: false,
             created_at: chrono::Utc::now().into(),
             updated_at: chrono::Utc::now().into(),
+            password_changed_at: chrono::Utc::now().into(),
+            must_change_password: false,
+            email_verified_at: None,
+            registration_code_id: None,
+            is_service_account: false,
         };
 
         // 验证字段类型和值
         assert_eq!(user.id, 123);
-        assert!(user.username.len() > 0);
+        assert!(!user.username.is_empty());
         assert!(user.email.contains("@"));
-        assert!(user.password_hash.len() > 0);
+        assert!(!user.password_hash.is_empty());
         assert!(!user.is_active); // 测试false状态
     }
 
@@ -77,6 +86,11 @@ mod tests {
             is_active: true,
             created_at: chrono::Utc::now().into(),
             updated_at: chrono::Utc::now().into(),
+            password_changed_at: chrono::Utc::now().into(),
+            must_change_password: false,
+            email_verified_at: None,
+            registration_code_id: None,
+            is_service_account: false,
         };
 
         let inactive_user = Model {
@@ -87,6 +101,11 @@ mod tests {
             is_active: false,
             created_at: chrono::Utc::now().into(),
             updated_at: chrono::Utc::now().into(),
+            password_changed_at: chrono::Utc::now().into(),
+            must_change_password: false,
+            email_verified_at: None,
+            registration_code_id: None,
+            is_service_account: false,
         };
 
         assert!(active_user.is_active);
@@ -99,7 +118,7 @@ mod tests {
     #[test]
     fn test_password_hash_variations() {
         // 测试不同的密码哈希格式
-        let hash_formats = vec![
+        let hash_formats = [
             "$2b$12$example_bcrypt_hash",
             "$argon2id$v=19$m=4096,t=3,p=1$example",
             "sha256_example_hash",
@@ -115,10 +134,15 @@ mod tests {
                 is_active: true,
                 created_at: chrono::Utc::now().into(),
                 updated_at: chrono::Utc::now().into(),
+                password_changed_at: chrono::Utc::now().into(),
+                must_change_password: false,
+                email_verified_at: None,
+                registration_code_id: None,
+                is_service_account: false,
             };
 
             assert_eq!(user.password_hash, *hash);
-            assert!(user.password_hash.len() > 0);
+            assert!(!user.password_hash.is_empty());
         }
     }
 
@@ -136,6 +160,11 @@ mod tests {
                 is_active: true,
                 created_at: chrono::Utc::now().into(),
                 updated_at: chrono::Utc::now().into(),
+                password_changed_at: chrono::Utc::now().into(),
+                must_change_password: false,
+                email_verified_at: None,
+                registration_code_id: None,
+                is_service_account: false,
             };
 
             assert_eq!(user.id, id);
@@ -146,7 +175,7 @@ mod tests {
     #[test]
     fn test_email_formats() {
         // 测试不同邮箱格式
-        let email_formats = vec![
+        let email_formats = [
             "simple@example.com",
             "user.name@domain.co.uk",
             "user+tag@subdomain.example.org",
@@ -163,6 +192,11 @@ mod tests {
                 is_active: true,
                 created_at: chrono::Utc::now().into(),
                 updated_at: chrono::Utc::now().into(),
+                password_changed_at: chrono::Utc::now().into(),
+                must_change_password: false,
+                email_verified_at: None,
+                registration_code_id: None,
+                is_service_account: false,
             };
 
             assert_eq!(user.email, *email);
@@ -175,7 +209,7 @@ mod tests {
     fn test_username_variations() {
         // 测试不同用户名格式
         let long_username = "a".repeat(50);
-        let usernames = vec![
+        let usernames = [
             "abc",                    // 最短有效用户名
             long_username.as_str(),  // 最长用户名
             "user_123",              // 包含下划线和数字
@@ -193,6 +227,11 @@ mod tests {
                 is_active: true,
                 created_at: chrono::Utc::now().into(),
                 updated_at: chrono::Utc::now().into(),
+                password_changed_at: chrono::Utc::now().into(),
+                must_change_password: false,
+                email_verified_at: None,
+                registration_code_id: None,
+                is_service_account: false,
             };
 
             assert_eq!(user.username, *username);
@@ -16,6 +16,7 @@ impl<T> ApiResponse<T> {
         }
     }
 
+    #[allow(dead_code)]
     pub fn success_with_message(data: T, message: &str) -> Self {
         Self {
             success: true,
@@ -36,7 +37,6 @@ impl<T> ApiResponse<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json;
 
     #[test]
     fn test_api_response_success_business_logic() {
@@ -135,7 +135,7 @@ mod tests {
         
         // 布尔数据
         let bool_response = ApiResponse::success(true);
-        assert_eq!(bool_response.data.unwrap(), true);
+        assert!(bool_response.data.unwrap());
         
         // 数组数据
         let array_response = ApiResponse::success(vec!["a", "b", "c"]);
@@ -14,6 +14,8 @@ export interface LoginResponse {
     expires_in: number;
     token: string;
     token_type: string;
+    refresh_token: string;
+    refresh_expires_in: number;
   };
   message?: string;
   error?: string;
@@ -76,6 +78,7 @@ export class AuthService {
               // 保存token和过期时间到本地存储
               localStorage.setItem('auth_token', response.auth.token);
               localStorage.setItem('auth_token_expiry', expiryTime.toString());
+              localStorage.setItem('refresh_token', response.auth.refresh_token);
               
               this.tokenSubject.next(response.auth.token);
               this.isAuthenticatedSubject.next(true);
@@ -97,6 +100,15 @@ export class AuthService {
   }
 
   logout(): void {
+    // 通知服务端撤销当前访问令牌和刷新令牌
+    const refreshToken = localStorage.getItem('refresh_token');
+    if (this.getToken()) {
+      this.http.post(`${this.apiUrl}/logout`, { refresh_token: refreshToken })
+        .subscribe({
+          error: (error) => console.log('服务端注销失败:', error)
+        });
+    }
+
     this.clearTokenStorage();
     this.clearRefreshTimer();
     this.tokenSubject.next(null);
@@ -150,14 +162,15 @@ export class AuthService {
   private refreshToken(): void {
     console.log('开始刷新token...');
     
-    const currentToken = this.getToken();
-    if (!currentToken) {
-      console.log('没有当前token，无法刷新');
+    const refreshToken = localStorage.getItem('refresh_token');
+    if (!refreshToken) {
+      console.log('没有刷新令牌，无法刷新');
+      this.logout();
       return;
     }
 
-    // 调用refresh接口获取新token
-    this.http.post<LoginResponse>(`${this.apiUrl}/refresh`, {})
+    // 调用refresh接口获取新token（刷新令牌每次使用后都会轮换）
+    this.http.post<LoginResponse>(`${this.apiUrl}/refresh`, { refresh_token: refreshToken })
       .subscribe({
         next: (response) => {
           if (response.auth && response.auth.token) {
@@ -169,6 +182,7 @@ export class AuthService {
             // 更新token和过期时间
             localStorage.setItem('auth_token', response.auth.token);
             localStorage.setItem('auth_token_expiry', expiryTime.toString());
+            localStorage.setItem('refresh_token', response.auth.refresh_token);
             
             this.tokenSubject.next(response.auth.token);
             this.isAuthenticatedSubject.next(true);
@@ -203,6 +217,7 @@ export class AuthService {
   private clearTokenStorage(): void {
     localStorage.removeItem('auth_token');
     localStorage.removeItem('auth_token_expiry');
+    localStorage.removeItem('refresh_token');
   }
 
   /**
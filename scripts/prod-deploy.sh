@@ -97,9 +97,14 @@ check_environment() {
     fi
 
     # 检查必要的环境变量
-    if ! grep -q "JWT_SECRET" .env || ! grep -q "POSTGRES_PASSWORD" .env; then
+    if ! grep -q "POSTGRES_PASSWORD" .env; then
         print_error "缺少必要的环境变量配置"
-        print_info "请确保 .env 文件包含 JWT_SECRET 和 POSTGRES_PASSWORD"
+        print_info "请确保 .env 文件包含 POSTGRES_PASSWORD"
+        exit 1
+    fi
+
+    if grep -q "^JWT_ALGORITHM=HS256" .env && ! grep -q "^JWT_SECRET=" .env; then
+        print_error "使用 HS256 签名时必须配置 JWT_SECRET"
         exit 1
     fi
 